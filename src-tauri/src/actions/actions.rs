@@ -5,6 +5,7 @@ use crate::actions::action_ledger::{update_action_status, ActionLedgerStatus};
 use crate::ai::ai_provider::SmartAiRouter;
 use crate::data::timeline::{record_timeline_event, TimelineEntryType, TimelineStatus};
 use crate::mcp::browser::BrowserMcpServer;
+use crate::mcp::{McpServer, ToolRequest};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -85,14 +86,44 @@ lazy_static::lazy_static! {
 }
 
 /// Execute an action using the appropriate handler
+///
+/// Actions without a handler that name an MCP tool (calls held for approval by
+/// a guardrail rule) are sent back to the MCP server.
 async fn execute_with_handler(ctx: HandlerContext) -> Result<serde_json::Value, String> {
     let handler = HANDLER_REGISTRY.get(&ctx.action_type);
     match handler {
         Some(h) => h(ctx).await,
+        None if is_mcp_tool(&ctx.mcp_server, &ctx.action_type) => handle_mcp_tool(ctx).await,
         None => Err(format!("Unknown action type: {}", ctx.action_type)),
     }
 }
 
+fn is_mcp_tool(mcp_server: &BrowserMcpServer, name: &str) -> bool {
+    mcp_server
+        .discover_tools(None)
+        .iter()
+        .any(|tool| tool.name == name)
+}
+
+/// Handler for approved MCP tool calls: invoke the stored call
+async fn handle_mcp_tool(ctx: HandlerContext) -> Result<serde_json::Value, String> {
+    let response = ctx
+        .mcp_server
+        .invoke_tool(ToolRequest {
+            tool_name: ctx.action_type.clone(),
+            arguments: ctx.args,
+            request_id: format!("action-{}", ctx.action_id),
+        })
+        .await;
+    if response.success {
+        Ok(response.data)
+    } else {
+        Err(response
+            .error
+            .unwrap_or_else(|| format!("{} failed", ctx.action_type)))
+    }
+}
+
 // ============================================================================
 // Action Handler Implementations
 // ============================================================================
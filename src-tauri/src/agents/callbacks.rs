@@ -11,13 +11,25 @@
 //! - Block execution by returning an override
 //! - Apply guardrails and safety policies
 //!
+//! Tool and model requests are checked against the declarative guardrail
+//! policy (`policy.rs`) before any callback runs. The router checks every model
+//! request with `check_model_request`; inside `with_model_callbacks` the
+//! invocation's before_model callbacks run too.
+//!
 //! Reference: Google ADK Callbacks documentation
 
+use super::policy;
 use super::traits::{AgentContext, AgentOutput};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
+use tokio::sync::Mutex as AsyncMutex;
+
+tokio::task_local! {
+    static MODEL_SCOPE: (Arc<AsyncMutex<CallbackRegistry>>, CallbackContext);
+}
 
 // =============================================================================
 // Callback Context
@@ -126,6 +138,16 @@ pub struct TokenUsage {
     pub total_tokens: usize,
 }
 
+impl TokenUsage {
+    pub fn new(prompt_tokens: usize, completion_tokens: usize) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+}
+
 // =============================================================================
 // Tool Callback Types
 // =============================================================================
@@ -340,12 +362,16 @@ impl CallbackRegistry {
         None
     }
 
-    /// Run before_model callbacks, return first override if any
+    /// Check the guardrail policy, then run before_model callbacks;
+    /// return the first override if any
     pub async fn run_before_model(
         &self,
         ctx: &CallbackContext,
         request: &LlmRequest,
     ) -> Option<LlmResponse> {
+        if let Some(response) = policy::enforce_model(ctx, request) {
+            return Some(response);
+        }
         for callback in &self.model_callbacks {
             if let Some(response) = callback.before_model(ctx, request).await {
                 return Some(response);
@@ -369,12 +395,16 @@ impl CallbackRegistry {
         None
     }
 
-    /// Run before_tool callbacks, return first override if any
+    /// Check the guardrail policy, then run before_tool callbacks;
+    /// return the first override if any
     pub async fn run_before_tool(
         &self,
         ctx: &CallbackContext,
         tool_call: &ToolCall,
     ) -> Option<ToolResult> {
+        if let Some(result) = policy::enforce_tool(ctx, tool_call) {
+            return Some(result);
+        }
         for callback in &self.tool_callbacks {
             if let Some(result) = callback.before_tool(ctx, tool_call).await {
                 return Some(result);
@@ -399,6 +429,29 @@ impl CallbackRegistry {
     }
 }
 
+/// Run `future` with its model requests passed to `registry`'s before_model
+/// callbacks in `ctx`
+pub async fn with_model_callbacks<F: Future>(
+    registry: Arc<AsyncMutex<CallbackRegistry>>,
+    ctx: CallbackContext,
+    future: F,
+) -> F::Output {
+    MODEL_SCOPE.scope((registry, ctx), future).await
+}
+
+/// Check a model request before it is sent; `Some` overrides the request
+///
+/// Outside `with_model_callbacks` only the guardrail policy applies.
+pub async fn check_model_request(request: &LlmRequest) -> Option<LlmResponse> {
+    match MODEL_SCOPE.try_with(|(registry, ctx)| (registry.clone(), ctx.clone())) {
+        Ok((registry, ctx)) => registry.lock().await.run_before_model(&ctx, request).await,
+        Err(_) => {
+            let ctx = CallbackContext::new(AgentContext::default(), "Router", "");
+            policy::enforce_model(&ctx, request)
+        }
+    }
+}
+
 pub struct ExtensionToolCallback {
     tool_name: String,
     extension_id: String,
@@ -0,0 +1,679 @@
+//! Declarative Agents - user-defined agents from AGENTS.md or agents.toml
+//!
+//! Users declare agents in TOML, either in `agents.toml` or in a `+++`
+//! front-matter block at the top of the workspace `AGENTS.md`:
+//!
+//! ```toml
+//! [[agents]]
+//! name = "Librarian"
+//! system_prompt = "Recommend further reading about the current page."
+//! provider = "ollama"
+//! model = "llama3.2"
+//! tools = ["browser.get_content"]
+//! autonomy = "suggester"
+//! after = "Observer"
+//! ```
+//!
+//! Each declaration becomes a `UserAgent`, an `Agent` that runs in the puzzle
+//! pipeline right after the built-in agent named by `after`, and can be named
+//! as a `transfer_to` target. Both files are watched and the agents reloaded
+//! when they change.
+
+use super::traits::{Agent, AgentContext, AgentError, AgentOutput, AgentResult, NextAction};
+use crate::ai::ai_provider::SmartAiRouter;
+use crate::ai::prompts::{self, PromptValue};
+use crate::ai::providers::{discovery, CompletionOptions, ProviderKind};
+use crate::ai::structured;
+use crate::config::privacy::{AutonomyLevel, PrivacySettings};
+use crate::config::toml_config::{CoreConfig, ProviderEntry};
+use crate::data::workspace_context::{self, AGENTS_MD};
+use async_trait::async_trait;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Agent declarations outside AGENTS.md
+pub const AGENTS_TOML: &str = "agents.toml";
+
+/// Names taken by the built-in agents
+const BUILTIN_AGENTS: &[&str] = &[
+    "Observer",
+    "Verifier",
+    "Narrator",
+    "Planner",
+    "Critic",
+    "Guardrail",
+    "Watchdog",
+    "Operator",
+    "Orchestrator",
+];
+/// Built-in pipeline agents a user agent can run after
+const PIPELINE_AGENTS: &[&str] = &["Planner", "Observer", "Verifier", "Narrator"];
+/// Output keys that steer the orchestrator, which the model may not set
+const CONTROL_KEYS: &[&str] = &[
+    "transfer_to",
+    "escalate",
+    "escalation_reason",
+    "terminate",
+    "state_delta",
+];
+/// Characters of page content included in the prompt
+const MAX_PAGE_CONTENT: usize = 2000;
+
+static WATCH_STARTED: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    static ref REGISTRY: RwLock<Registry> = RwLock::new(Registry::default());
+}
+
+/// A user-defined agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub system_prompt: String,
+    /// Provider kind or discovered server name (router chain when unset)
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    /// MCP tools the agent may request
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Most autonomy the agent gets, whatever the global setting
+    #[serde(default)]
+    pub autonomy: AutonomyLevel,
+    /// JSON Schema of the reply; without one the reply is plain text
+    #[serde(default)]
+    pub output_schema: Option<serde_json::Value>,
+    /// Built-in pipeline agent this agent runs after
+    #[serde(default)]
+    pub after: Option<String>,
+    /// Agent that takes over once this one has replied
+    #[serde(default)]
+    pub transfer_to: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AgentsFile {
+    #[serde(default)]
+    agents: Vec<AgentSpec>,
+}
+
+impl AgentSpec {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("agent name is empty".to_string());
+        }
+        if BUILTIN_AGENTS
+            .iter()
+            .any(|builtin| builtin.eq_ignore_ascii_case(&self.name))
+        {
+            return Err(format!("{} is a built-in agent", self.name));
+        }
+        if self.system_prompt.trim().is_empty() {
+            return Err(format!("{} has no system_prompt", self.name));
+        }
+        if let Some(provider) = &self.provider {
+            if ProviderKind::from_str(provider).is_none()
+                && !discovery::is_discovered_name(provider)
+            {
+                return Err(format!("{} uses unknown provider {}", self.name, provider));
+            }
+        }
+        if let Some(schema) = &self.output_schema {
+            if !schema.is_object() {
+                return Err(format!("{} output_schema must be a table", self.name));
+            }
+        }
+        if let Some(after) = &self.after {
+            if !PIPELINE_AGENTS
+                .iter()
+                .any(|agent| agent.eq_ignore_ascii_case(after))
+            {
+                return Err(format!(
+                    "{} runs after {}, which is not a pipeline agent",
+                    self.name, after
+                ));
+            }
+        }
+        if let Some(target) = &self.transfer_to {
+            if ["Operator", "Orchestrator", self.name.as_str()]
+                .iter()
+                .any(|reserved| reserved.eq_ignore_ascii_case(target))
+            {
+                return Err(format!("{} can't transfer to {}", self.name, target));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lower of two autonomy levels
+fn capped(level: AutonomyLevel, ceiling: AutonomyLevel) -> AutonomyLevel {
+    if (level as u8) <= (ceiling as u8) {
+        level
+    } else {
+        ceiling
+    }
+}
+
+/// Agent built from an `AgentSpec`
+pub struct UserAgent {
+    spec: AgentSpec,
+    ai_router: Arc<SmartAiRouter>,
+}
+
+impl UserAgent {
+    /// Create the agent, pinning it to its provider/model if it names one
+    pub fn new(spec: AgentSpec, ai_router: &Arc<SmartAiRouter>, core: &CoreConfig) -> Self {
+        let ai_router = if spec.provider.is_some() || spec.model.is_some() {
+            let provider = spec
+                .provider
+                .clone()
+                .or_else(|| core.default_provider.clone())
+                .unwrap_or_else(|| ProviderKind::Ollama.to_string());
+            Arc::new(ai_router.pinned(
+                core,
+                ProviderEntry {
+                    provider,
+                    model: spec.model.clone(),
+                    base_url: None,
+                },
+            ))
+        } else {
+            ai_router.clone()
+        };
+        Self { spec, ai_router }
+    }
+
+    pub fn spec(&self) -> &AgentSpec {
+        &self.spec
+    }
+
+    fn prompt(&self, context: &AgentContext) -> AgentResult<String> {
+        let page_content: String = context
+            .page_content
+            .chars()
+            .take(MAX_PAGE_CONTENT)
+            .collect();
+        let or_none = |items: Vec<String>| {
+            if items.is_empty() {
+                vec!["(none)".to_string()]
+            } else {
+                items
+            }
+        };
+
+        prompts::render(
+            "agent.user",
+            &[
+                ("system_prompt", self.spec.system_prompt.as_str().into()),
+                ("agent_name", self.spec.name.as_str().into()),
+                (
+                    "title",
+                    crate::config::privacy::redact_with_settings(&context.current_title).into(),
+                ),
+                ("url", context.current_url.as_str().into()),
+                (
+                    "page_content",
+                    crate::config::privacy::redact_with_settings(&page_content).into(),
+                ),
+                ("puzzle_clue", context.puzzle_clue.as_str().into()),
+                ("proximity", PromptValue::percent(context.proximity)),
+                (
+                    "previous_outputs",
+                    or_none(context.previous_outputs.clone()).into(),
+                ),
+                ("tools", or_none(self.spec.tools.clone()).into()),
+            ],
+        )
+        .map_err(AgentError::ConfigError)
+    }
+
+    /// Replace control keys in the model's reply with the spec's
+    ///
+    /// Page content reaches the prompt, so the model's reply can't be trusted
+    /// to steer the orchestrator.
+    fn set_controls(&self, data: &mut HashMap<String, serde_json::Value>) {
+        for key in CONTROL_KEYS {
+            if data.remove(*key).is_some() {
+                tracing::debug!("Dropped {} from {}'s reply", key, self.spec.name);
+            }
+        }
+        if let Some(target) = &self.spec.transfer_to {
+            data.insert("transfer_to".to_string(), target.as_str().into());
+        }
+    }
+
+    /// Keep a requested tool call only if the agent may make it
+    ///
+    /// Calls to tools outside `tools` are dropped. Below `supervised` autonomy
+    /// the call becomes a `suggested_tool_call` awaiting confirmation.
+    fn gate_tool_call(
+        &self,
+        data: &mut HashMap<String, serde_json::Value>,
+        autonomy: AutonomyLevel,
+    ) -> Option<NextAction> {
+        let call = data.remove("tool_call")?;
+        let tool = call
+            .get("tool")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let autonomy = capped(autonomy, self.spec.autonomy);
+
+        if !self.spec.tools.iter().any(|allowed| allowed == tool) {
+            tracing::warn!("{} requested tool {} it may not use", self.spec.name, tool);
+            None
+        } else if !autonomy.allows_actions() {
+            tracing::debug!("{} may not call tools at {:?}", self.spec.name, autonomy);
+            None
+        } else if autonomy.requires_confirmation(false) {
+            data.insert("suggested_tool_call".to_string(), call);
+            Some(NextAction::PauseForConfirmation)
+        } else {
+            data.insert("tool_call".to_string(), call);
+            None
+        }
+    }
+}
+
+#[async_trait]
+impl Agent for UserAgent {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn description(&self) -> &str {
+        &self.spec.description
+    }
+
+    async fn process(&self, context: &AgentContext) -> AgentResult<AgentOutput> {
+        let prompt = self.prompt(context)?;
+        let options = CompletionOptions {
+            temperature: self.spec.temperature.or(Some(0.7)),
+            response_schema: self.spec.output_schema.clone(),
+            ..Default::default()
+        };
+
+        let Some(schema) = &self.spec.output_schema else {
+            let reply = self
+                .ai_router
+                .complete_with_options(&prompt, options)
+                .await
+                .map_err(|e| AgentError::ServiceError(e.to_string()))?;
+            let mut data = HashMap::new();
+            self.set_controls(&mut data);
+            return Ok(AgentOutput {
+                agent_name: self.spec.name.clone(),
+                result: reply.trim().to_string(),
+                confidence: 0.0,
+                next_action: None,
+                data,
+            });
+        };
+
+        let reply: serde_json::Value = structured::request_with_repair(
+            &prompt,
+            schema,
+            structured::DEFAULT_REPAIR_ATTEMPTS,
+            |request| {
+                let options = options.clone();
+                async move {
+                    self.ai_router
+                        .complete_with_options(&request, options)
+                        .await
+                }
+            },
+        )
+        .await
+        .map_err(|e| AgentError::ServiceError(format!("{}: {}", self.spec.name, e)))?;
+
+        let mut data: HashMap<String, serde_json::Value> = match reply {
+            serde_json::Value::Object(fields) => fields.into_iter().collect(),
+            other => HashMap::from([("output".to_string(), other)]),
+        };
+        self.set_controls(&mut data);
+        let next_action = self.gate_tool_call(&mut data, PrivacySettings::load().autonomy_level);
+        let result = data
+            .get("message")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| serde_json::to_string(&data).unwrap_or_default());
+        let confidence = data
+            .get("confidence")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0) as f32;
+
+        Ok(AgentOutput {
+            agent_name: self.spec.name.clone(),
+            result,
+            confidence,
+            next_action,
+            data,
+        })
+    }
+}
+
+/// Read agent declarations from AGENTS.md front matter and agents.toml
+///
+/// Returns the valid specs and one message per rejected file or declaration.
+pub fn load_specs(data_dir: &Path) -> (Vec<AgentSpec>, Vec<String>) {
+    let mut declared = Vec::new();
+    let mut errors = Vec::new();
+
+    let sources = [(AGENTS_MD, true), (AGENTS_TOML, false)];
+    for (file, front_matter) in sources {
+        let Ok(content) = std::fs::read_to_string(data_dir.join(file)) else {
+            continue;
+        };
+        let toml_text = if front_matter {
+            match workspace_context::split_front_matter(&content).0 {
+                Some(text) => text,
+                None => continue,
+            }
+        } else {
+            content.as_str()
+        };
+        match toml::from_str::<AgentsFile>(toml_text) {
+            Ok(parsed) => declared.extend(parsed.agents),
+            Err(e) => errors.push(format!("{}: {}", file, e)),
+        }
+    }
+
+    let mut specs: Vec<AgentSpec> = Vec::new();
+    for spec in declared {
+        if let Err(e) = spec.validate() {
+            errors.push(e);
+        } else if specs
+            .iter()
+            .any(|s| s.name.eq_ignore_ascii_case(&spec.name))
+        {
+            errors.push(format!("{} is declared more than once", spec.name));
+        } else {
+            specs.push(spec);
+        }
+    }
+    (specs, errors)
+}
+
+#[derive(Default)]
+struct Registry {
+    ai_router: Option<Arc<SmartAiRouter>>,
+    core: CoreConfig,
+    agents: Vec<Arc<UserAgent>>,
+    errors: Vec<String>,
+}
+
+/// Loaded user agents and declaration errors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserAgentStatus {
+    pub agents: Vec<AgentSpec>,
+    pub errors: Vec<String>,
+}
+
+/// Load user agents on `ai_router` and reload them when their files change
+pub fn init_user_agents(ai_router: Arc<SmartAiRouter>, core: CoreConfig) {
+    if let Ok(mut registry) = REGISTRY.write() {
+        registry.ai_router = Some(ai_router);
+        registry.core = core;
+    }
+    reload_user_agents();
+    ensure_watcher();
+}
+
+/// Re-read the declarations, replacing the loaded agents
+pub fn reload_user_agents() -> UserAgentStatus {
+    let (specs, errors) = load_specs(&workspace_context::get_data_dir());
+    for error in &errors {
+        tracing::warn!("Skipping user agent declaration: {}", error);
+    }
+
+    let Ok(mut registry) = REGISTRY.write() else {
+        return UserAgentStatus {
+            agents: Vec::new(),
+            errors,
+        };
+    };
+    registry.agents = match registry.ai_router.clone() {
+        Some(router) => specs
+            .iter()
+            .map(|spec| Arc::new(UserAgent::new(spec.clone(), &router, &registry.core)))
+            .collect(),
+        None => Vec::new(),
+    };
+    registry.errors = errors.clone();
+    tracing::info!("Loaded {} user agent(s)", registry.agents.len());
+
+    UserAgentStatus {
+        agents: specs,
+        errors,
+    }
+}
+
+/// A loaded user agent by name (case-insensitive)
+pub fn find_user_agent(name: &str) -> Option<Arc<UserAgent>> {
+    REGISTRY
+        .read()
+        .ok()?
+        .agents
+        .iter()
+        .find(|agent| agent.spec.name.eq_ignore_ascii_case(name))
+        .cloned()
+}
+
+/// Loaded user agents that run after the pipeline agent `name`
+pub fn agents_after(name: &str) -> Vec<Arc<UserAgent>> {
+    user_agents()
+        .into_iter()
+        .filter(|agent| {
+            agent
+                .spec
+                .after
+                .as_deref()
+                .is_some_and(|after| after.eq_ignore_ascii_case(name))
+        })
+        .collect()
+}
+
+/// All loaded user agents
+pub fn user_agents() -> Vec<Arc<UserAgent>> {
+    REGISTRY
+        .read()
+        .map(|registry| registry.agents.clone())
+        .unwrap_or_default()
+}
+
+fn ensure_watcher() {
+    if WATCH_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let data_dir = workspace_context::get_data_dir();
+
+    std::thread::spawn(move || {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher: RecommendedWatcher = match notify::recommended_watcher(tx) {
+            Ok(w) => w,
+            Err(e) => {
+                tracing::warn!("Failed to watch user agent files: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(&data_dir, RecursiveMode::NonRecursive) {
+            tracing::warn!("Failed to watch {:?}: {}", data_dir, e);
+            return;
+        }
+
+        for event in rx {
+            let Ok(event) = event else { continue };
+            let touches_agents = event.paths.iter().any(|path| {
+                path.file_name()
+                    .is_some_and(|name| name == AGENTS_MD || name == AGENTS_TOML)
+            });
+            if touches_agents {
+                reload_user_agents();
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn list_user_agents() -> UserAgentStatus {
+    let errors = REGISTRY
+        .read()
+        .map(|registry| registry.errors.clone())
+        .unwrap_or_default();
+    UserAgentStatus {
+        agents: user_agents()
+            .iter()
+            .map(|agent| agent.spec.clone())
+            .collect(),
+        errors,
+    }
+}
+
+#[tauri::command]
+pub fn reload_user_agents_cmd() -> UserAgentStatus {
+    reload_user_agents()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::providers::fixture::{Cassette, FixtureMatch};
+
+    fn spec(name: &str, tools: &[&str], autonomy: AutonomyLevel) -> AgentSpec {
+        AgentSpec {
+            name: name.to_string(),
+            description: String::new(),
+            system_prompt: "Help the user.".to_string(),
+            provider: None,
+            model: None,
+            temperature: None,
+            tools: tools.iter().map(|t| t.to_string()).collect(),
+            autonomy,
+            output_schema: None,
+            after: None,
+            transfer_to: None,
+        }
+    }
+
+    fn replaying_router() -> Arc<SmartAiRouter> {
+        Arc::new(SmartAiRouter::replaying(
+            Arc::new(Cassette::from_entries(Vec::new())),
+            FixtureMatch::Lenient,
+        ))
+    }
+
+    #[test]
+    fn test_load_specs_from_front_matter_and_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(AGENTS_MD),
+            "+++\n[[agents]]\nname = \"Librarian\"\nsystem_prompt = \"Suggest books.\"\ntools = [\"browser.get_content\"]\nautonomy = \"suggester\"\n\n[agents.output_schema]\ntype = \"object\"\n+++\n# Instructions\nBe kind.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join(AGENTS_TOML),
+            "[[agents]]\nname = \"librarian\"\nsystem_prompt = \"Again\"\n\n[[agents]]\nname = \"Narrator\"\nsystem_prompt = \"Mine\"\n\n[[agents]]\nname = \"Scout\"\nsystem_prompt = \"Look around.\"\nprovider = \"nonsense\"\n",
+        )
+        .unwrap();
+
+        let (specs, errors) = load_specs(dir.path());
+        assert_eq!(specs.len(), 1);
+        assert_eq!(specs[0].name, "Librarian");
+        assert_eq!(specs[0].autonomy, AutonomyLevel::Suggester);
+        assert_eq!(
+            specs[0].output_schema,
+            Some(serde_json::json!({ "type": "object" }))
+        );
+        assert_eq!(errors.len(), 3);
+
+        let (_, body) = workspace_context::split_front_matter(
+            &std::fs::read_to_string(dir.path().join(AGENTS_MD)).unwrap(),
+        );
+        assert_eq!(body, "# Instructions\nBe kind.\n");
+    }
+
+    #[test]
+    fn test_tool_calls_respect_allowlist_and_autonomy() {
+        let router = replaying_router();
+        let core = CoreConfig::default();
+        let agent = UserAgent::new(
+            spec("Scout", &["browser.get_content"], AutonomyLevel::Supervised),
+            &router,
+            &core,
+        );
+        let call = |tool: &str| {
+            HashMap::from([(
+                "tool_call".to_string(),
+                serde_json::json!({ "tool": tool, "arguments": {} }),
+            )])
+        };
+
+        let mut data = call("browser.navigate");
+        assert!(agent
+            .gate_tool_call(&mut data, AutonomyLevel::Autonomous)
+            .is_none());
+        assert!(data.is_empty());
+
+        let mut data = call("browser.get_content");
+        assert!(agent
+            .gate_tool_call(&mut data, AutonomyLevel::Autonomous)
+            .is_none());
+        assert!(data.contains_key("tool_call"));
+
+        let mut data = call("browser.get_content");
+        assert!(matches!(
+            agent.gate_tool_call(&mut data, AutonomyLevel::Suggester),
+            Some(NextAction::PauseForConfirmation)
+        ));
+        assert!(data.contains_key("suggested_tool_call"));
+
+        let mut data = call("browser.get_content");
+        assert!(agent
+            .gate_tool_call(&mut data, AutonomyLevel::Observer)
+            .is_none());
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn test_control_keys_come_from_the_spec() {
+        let mut scout = spec("Scout", &[], AutonomyLevel::Autonomous);
+        let agent = UserAgent::new(scout.clone(), &replaying_router(), &CoreConfig::default());
+        let mut data: HashMap<String, serde_json::Value> =
+            serde_json::from_value(serde_json::json!({
+                "message": "Click here",
+                "transfer_to": "Operator",
+                "escalate": true,
+                "state_delta": { "solved": true }
+            }))
+            .unwrap();
+        agent.set_controls(&mut data);
+        assert_eq!(data.keys().collect::<Vec<_>>(), vec!["message"]);
+
+        scout.transfer_to = Some("Librarian".to_string());
+        let agent = UserAgent::new(scout.clone(), &replaying_router(), &CoreConfig::default());
+        let mut data = HashMap::from([("transfer_to".to_string(), "Narrator".into())]);
+        agent.set_controls(&mut data);
+        assert_eq!(data["transfer_to"], "Librarian");
+
+        scout.transfer_to = Some("operator".to_string());
+        assert!(scout.validate().is_err());
+        scout.transfer_to = None;
+        scout.after = Some("Guardrail".to_string());
+        assert!(scout.validate().is_err());
+        scout.after = Some("verifier".to_string());
+        assert!(scout.validate().is_ok());
+    }
+}
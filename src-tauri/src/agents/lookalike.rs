@@ -0,0 +1,506 @@
+//! Lookalike Domains - homoglyph, typosquat and brand-impersonation checks
+//!
+//! A host is compared with well-known brand domains and the user's top sites:
+//! - IDN hosts are decoded from punycode and reduced to a confusable
+//!   "skeleton" (Cyrillic `а` → `a`, `rn` → `m`, `0` → `o`, ...), so
+//!   `xn--pypal-4ve.com` (`pаypal.com`) is caught as imitating `paypal.com`
+//! - names within a small edit distance, keeping the first letter, are
+//!   typosquats (`gooogle.com`, `paypall.com`); the skeleton is only compared
+//!   exactly, since mapping `i` → `l` then allowing an edit turns real words
+//!   into brands (`finance` / `binance`). A single edit also needs a sign it
+//!   was deliberate, since adding or dropping a letter is how plurals and
+//!   everyday words differ from brands (`offices` / `office`)
+//! - a brand appearing as its own label elsewhere in the host is impersonation
+//!   (`paypal.com.account-verify.io`, `paypal-secure.net`), unless the host is
+//!   registered under the brand's own name (`login.github.io`)
+//!
+//! Suspicious TLDs and URL shorteners are flagged separately.
+
+use serde::{Deserialize, Serialize};
+
+/// Domains commonly imitated by phishing pages
+const BRAND_DOMAINS: &[&str] = &[
+    "paypal.com",
+    "apple.com",
+    "icloud.com",
+    "google.com",
+    "gmail.com",
+    "microsoft.com",
+    "office.com",
+    "outlook.com",
+    "live.com",
+    "amazon.com",
+    "facebook.com",
+    "instagram.com",
+    "whatsapp.com",
+    "netflix.com",
+    "spotify.com",
+    "github.com",
+    "linkedin.com",
+    "twitter.com",
+    "dropbox.com",
+    "docusign.com",
+    "adobe.com",
+    "yahoo.com",
+    "ebay.com",
+    "chase.com",
+    "wellsfargo.com",
+    "bankofamerica.com",
+    "americanexpress.com",
+    "coinbase.com",
+    "binance.com",
+    "steamcommunity.com",
+];
+
+/// TLDs with a high share of abuse; cheap or free to register
+const SUSPICIOUS_TLDS: &[&str] = &[
+    "zip", "mov", "tk", "ml", "ga", "cf", "gq", "xyz", "top", "click", "country", "kim", "work",
+    "rest", "fit", "loan", "support",
+];
+
+/// Services that hide a link's destination
+const URL_SHORTENERS: &[&str] = &[
+    "bit.ly",
+    "tinyurl.com",
+    "t.co",
+    "goo.gl",
+    "ow.ly",
+    "is.gd",
+    "buff.ly",
+    "cutt.ly",
+    "rebrand.ly",
+    "shorturl.at",
+];
+
+/// Second-level labels under which ccTLDs register domains (`example.co.uk`)
+const SECOND_LEVEL_SUFFIXES: &[&str] = &["co", "com", "org", "net", "gov", "ac", "edu"];
+
+/// Words phishing hosts pair with a brand (`paypal-secure-login.net`)
+const LURE_WORDS: &[&str] = &[
+    "login",
+    "signin",
+    "secure",
+    "security",
+    "verify",
+    "verification",
+    "account",
+    "support",
+    "update",
+    "billing",
+    "wallet",
+    "auth",
+];
+
+/// Labels shorter than this are only compared by exact skeleton; short names
+/// sit one edit away from too many real words (`chase` / `phase`)
+const MIN_FUZZY_LABEL: usize = 6;
+
+/// Characters that render like a Latin letter or digit
+const CONFUSABLES: &[(char, char)] = &[
+    // Cyrillic
+    ('а', 'a'),
+    ('в', 'b'),
+    ('с', 'c'),
+    ('ԁ', 'd'),
+    ('е', 'e'),
+    ('ё', 'e'),
+    ('һ', 'h'),
+    ('і', 'i'),
+    ('ї', 'i'),
+    ('ј', 'j'),
+    ('к', 'k'),
+    ('ӏ', 'l'),
+    ('м', 'm'),
+    ('н', 'h'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('ԛ', 'q'),
+    ('ѕ', 's'),
+    ('т', 't'),
+    ('у', 'y'),
+    ('х', 'x'),
+    ('ԝ', 'w'),
+    // Greek
+    ('α', 'a'),
+    ('β', 'b'),
+    ('ε', 'e'),
+    ('η', 'n'),
+    ('ι', 'i'),
+    ('κ', 'k'),
+    ('ν', 'v'),
+    ('ο', 'o'),
+    ('ρ', 'p'),
+    ('τ', 't'),
+    ('υ', 'u'),
+    ('χ', 'x'),
+    // Latin variants
+    ('à', 'a'),
+    ('á', 'a'),
+    ('â', 'a'),
+    ('ä', 'a'),
+    ('å', 'a'),
+    ('ç', 'c'),
+    ('è', 'e'),
+    ('é', 'e'),
+    ('ê', 'e'),
+    ('ë', 'e'),
+    ('ı', 'i'),
+    ('ì', 'i'),
+    ('í', 'i'),
+    ('ï', 'i'),
+    ('ł', 'l'),
+    ('ñ', 'n'),
+    ('ò', 'o'),
+    ('ó', 'o'),
+    ('ô', 'o'),
+    ('ö', 'o'),
+    ('ø', 'o'),
+    ('ù', 'u'),
+    ('ú', 'u'),
+    ('ü', 'u'),
+    ('ý', 'y'),
+    ('ɡ', 'g'),
+    // Digits and letters that pass for each other
+    ('0', 'o'),
+    ('1', 'l'),
+    ('i', 'l'),
+    ('3', 'e'),
+    ('5', 's'),
+];
+
+/// Letter pairs that pass for a single letter
+const CONFUSABLE_SEQUENCES: &[(&str, &str)] = &[("rn", "m"), ("vv", "w"), ("cl", "d")];
+
+/// How a host imitates a legitimate domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LookalikeTechnique {
+    /// Non-Latin characters that look like the real name (IDN homograph)
+    Homoglyph,
+    /// ASCII characters that look alike (`rn` for `m`, `1` for `l`)
+    Confusable,
+    /// A character or two added, dropped or swapped
+    Typosquat,
+    /// The real domain, or the brand next to a lure word, on an unrelated host
+    BrandImpersonation,
+}
+
+impl LookalikeTechnique {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LookalikeTechnique::Homoglyph => "look-alike characters from another alphabet",
+            LookalikeTechnique::Confusable => "look-alike characters",
+            LookalikeTechnique::Typosquat => "a near-identical spelling",
+            LookalikeTechnique::BrandImpersonation => "the brand name on an unrelated domain",
+        }
+    }
+}
+
+/// A host imitating a legitimate domain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LookalikeMatch {
+    /// Host as displayed (punycode decoded)
+    pub host: String,
+    /// The legitimate domain being imitated
+    pub imitates: String,
+    pub technique: LookalikeTechnique,
+}
+
+/// Unicode form of a (possibly punycode) host
+pub fn decode_host(host: &str) -> String {
+    let decoded = url::quirks::domain_to_unicode(host);
+    if decoded.is_empty() {
+        host.to_lowercase()
+    } else {
+        decoded.to_lowercase()
+    }
+}
+
+/// Host reduced to the Latin characters it resembles
+pub fn skeleton(text: &str) -> String {
+    let mut mapped: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            CONFUSABLES
+                .iter()
+                .find(|(from, _)| *from == c)
+                .map_or(c, |(_, to)| *to)
+        })
+        .collect();
+    for (from, to) in CONFUSABLE_SEQUENCES {
+        mapped = mapped.replace(from, to);
+    }
+    mapped
+}
+
+/// Optimal string alignment distance (Levenshtein plus adjacent swaps)
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Registrable domain (`accounts.google.co.uk` → `google.co.uk`)
+pub fn registrable_domain(host: &str) -> String {
+    let labels: Vec<&str> = host.trim_end_matches('.').split('.').collect();
+    let keep = match labels.as_slice() {
+        [.., second, tld] if tld.len() == 2 && SECOND_LEVEL_SUFFIXES.contains(second) => 3,
+        _ => 2,
+    };
+    labels[labels.len().saturating_sub(keep)..].join(".")
+}
+
+/// The name part of a registrable domain (`google.co.uk` → `google`)
+fn name_label(domain: &str) -> &str {
+    domain.split('.').next().unwrap_or(domain)
+}
+
+fn is_same_or_subdomain(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// Whether the host's TLD is on the suspicious list
+pub fn has_suspicious_tld(host: &str) -> bool {
+    host.rsplit('.')
+        .next()
+        .is_some_and(|tld| SUSPICIOUS_TLDS.contains(&tld))
+}
+
+/// Whether the host is a URL shortener
+pub fn is_url_shortener(host: &str) -> bool {
+    URL_SHORTENERS
+        .iter()
+        .any(|shortener| is_same_or_subdomain(host, shortener))
+}
+
+/// Whether a label one edit away from `known` looks like a typosquat rather
+/// than a plural or a real word: the host sits on a suspicious TLD, the label
+/// mixes in non-Latin characters, a letter was swapped or replaced, or a
+/// doubled letter was added or dropped (`gooogle`, `paypall`)
+fn is_deliberate_typo(host: &str, label: &str, known: &str) -> bool {
+    if has_suspicious_tld(host) || !label.is_ascii() {
+        return true;
+    }
+    let label: Vec<char> = label.chars().collect();
+    let known: Vec<char> = known.chars().collect();
+    let (longer, shorter) = match label.len().cmp(&known.len()) {
+        std::cmp::Ordering::Equal => return true,
+        std::cmp::Ordering::Greater => (&label, &known),
+        std::cmp::Ordering::Less => (&known, &label),
+    };
+    let extra = (0..shorter.len())
+        .find(|&i| longer[i] != shorter[i])
+        .unwrap_or(shorter.len());
+    (extra > 0 && longer[extra - 1] == longer[extra])
+        || longer.get(extra + 1) == Some(&longer[extra])
+}
+
+/// Compare `host` with the brand list and `known_domains`
+///
+/// Hosts that are, or are subdomains of, a known domain never match.
+pub fn find_lookalike(host: &str, known_domains: &[String]) -> Option<LookalikeMatch> {
+    let ascii_host = host.trim_end_matches('.').to_lowercase();
+    let display_host = decode_host(&ascii_host);
+    let mut known: Vec<String> = BRAND_DOMAINS.iter().map(|d| d.to_string()).collect();
+    known.extend(
+        known_domains
+            .iter()
+            .map(|d| registrable_domain(&d.to_lowercase())),
+    );
+    if known
+        .iter()
+        .any(|domain| is_same_or_subdomain(&ascii_host, domain))
+    {
+        return None;
+    }
+
+    let registrable = registrable_domain(&display_host);
+    let label = name_label(&registrable);
+    let label_skeleton = skeleton(label);
+    let found = |imitates: &str, technique| {
+        Some(LookalikeMatch {
+            host: display_host.clone(),
+            imitates: imitates.to_string(),
+            technique,
+        })
+    };
+
+    // Same skeleton: looks identical at a glance
+    for domain in &known {
+        let known_label = name_label(domain);
+        if label != known_label && label_skeleton == skeleton(known_label) {
+            let technique = if label.is_ascii() {
+                LookalikeTechnique::Confusable
+            } else {
+                LookalikeTechnique::Homoglyph
+            };
+            return found(domain, technique);
+        }
+    }
+
+    // Near-identical spelling of a longer name; first letters are rarely
+    // mistyped and changing one lands on real words (`cloud` / `icloud`)
+    for domain in &known {
+        let known_label = name_label(domain);
+        if known_label.chars().count() < MIN_FUZZY_LABEL
+            || label == known_label
+            || label.chars().next() != known_label.chars().next()
+        {
+            continue;
+        }
+        let allowed = if known_label.chars().count() >= 10 {
+            2
+        } else {
+            1
+        };
+        match edit_distance(label, known_label) {
+            1 if !is_deliberate_typo(&display_host, label, known_label) => {}
+            distance if distance <= allowed => {
+                return found(domain, LookalikeTechnique::Typosquat);
+            }
+            _ => {}
+        }
+    }
+
+    // Real domain as a prefix, or brand plus lure word, on somebody else's host;
+    // the brand's own name under another suffix (`github.io`) is not
+    let parts: Vec<&str> = display_host.split(['.', '-']).collect();
+    let has_lure = parts.iter().any(|part| LURE_WORDS.contains(part));
+    for domain in BRAND_DOMAINS {
+        let brand = name_label(domain);
+        let as_prefix = display_host.starts_with(&format!("{}.", domain))
+            || display_host.contains(&format!(".{}.", domain));
+        if as_prefix || (has_lure && brand != label && parts.contains(&brand)) {
+            return found(domain, LookalikeTechnique::BrandImpersonation);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn imitation(host: &str) -> Option<(String, LookalikeTechnique)> {
+        find_lookalike(host, &[]).map(|m| (m.imitates, m.technique))
+    }
+
+    #[test]
+    fn test_detects_lookalikes() {
+        // Cyrillic "а" in pаypal.com
+        let (imitates, technique) = imitation("xn--pypal-4ve.com").unwrap();
+        assert_eq!(imitates, "paypal.com");
+        assert_eq!(technique, LookalikeTechnique::Homoglyph);
+
+        assert_eq!(
+            imitation("paypa1.com"),
+            Some(("paypal.com".to_string(), LookalikeTechnique::Confusable))
+        );
+        assert_eq!(
+            imitation("rnicrosoft.com"),
+            Some(("microsoft.com".to_string(), LookalikeTechnique::Confusable))
+        );
+        assert_eq!(
+            imitation("gooogle.com"),
+            Some(("google.com".to_string(), LookalikeTechnique::Typosquat))
+        );
+        assert_eq!(
+            imitation("paypall.com"),
+            Some(("paypal.com".to_string(), LookalikeTechnique::Typosquat))
+        );
+        assert_eq!(
+            imitation("gogle.com").map(|(imitates, _)| imitates),
+            Some("google.com".to_string())
+        );
+        assert_eq!(
+            imitation("amazen.com").map(|(imitates, _)| imitates),
+            Some("amazon.com".to_string())
+        );
+        assert_eq!(
+            imitation("offices.xyz"),
+            Some(("office.com".to_string(), LookalikeTechnique::Typosquat))
+        );
+        assert_eq!(
+            imitation("paypal.com.account-verify.io"),
+            Some((
+                "paypal.com".to_string(),
+                LookalikeTechnique::BrandImpersonation
+            ))
+        );
+        assert_eq!(
+            imitation("netflix-billing-update.xyz").map(|(imitates, _)| imitates),
+            Some("netflix.com".to_string())
+        );
+    }
+
+    #[test]
+    fn test_legitimate_hosts_pass() {
+        assert_eq!(imitation("paypal.com"), None);
+        assert_eq!(imitation("www.paypal.com"), None);
+        assert_eq!(imitation("accounts.google.com"), None);
+        assert_eq!(imitation("rust-lang.org"), None);
+        assert_eq!(imitation("apple-pie-recipes.com"), None);
+        assert_eq!(imitation("phase.com"), None);
+
+        // Plurals and everyday words one letter from a brand
+        assert_eq!(imitation("offices.com"), None);
+        assert_eq!(imitation("amazons.com"), None);
+        assert_eq!(imitation("outlooks.com"), None);
+        assert_eq!(imitation("githubs.com"), None);
+        assert_eq!(imitation("bankofamericas.com"), None);
+
+        // Real words near a brand once confusables are mapped
+        assert_eq!(imitation("finance.org"), None);
+        assert_eq!(imitation("cloud.com"), None);
+        assert_eq!(imitation("news.cloud.net"), None);
+
+        // Pages hosted under the brand's own name
+        assert_eq!(imitation("login.github.io"), None);
+        assert_eq!(imitation("octo-auth.github.io"), None);
+        assert_eq!(
+            imitation("paypal-login.github.io").map(|(imitates, _)| imitates),
+            Some("paypal.com".to_string())
+        );
+
+        // Top sites are trusted and imitations of them are caught
+        let top_sites = vec!["www.mybank.example".to_string()];
+        assert_eq!(find_lookalike("online.mybank.example", &top_sites), None);
+        assert_eq!(
+            find_lookalike("rnybank.example", &top_sites).map(|m| m.imitates),
+            Some("mybank.example".to_string())
+        );
+    }
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(edit_distance("paypal", "paypal"), 0);
+        assert_eq!(edit_distance("paypal", "papyal"), 1);
+        assert_eq!(edit_distance("google", "gogle"), 1);
+        assert_eq!(registrable_domain("accounts.google.co.uk"), "google.co.uk");
+        assert_eq!(registrable_domain("a.b.example.com"), "example.com");
+        assert!(has_suspicious_tld("free-prizes.xyz"));
+        assert!(!has_suspicious_tld("example.com"));
+        assert!(is_url_shortener("bit.ly"));
+        assert!(!is_url_shortener("microsoft.com"));
+    }
+}
@@ -17,18 +17,28 @@
 //!   hammering failing LLM services
 //! - **Rate Limiting**: RateLimiter utility for protecting against runaway API costs
 //! - **Lifecycle Hooks**: Agent trait includes initialize(), shutdown(), health_check()
+//! - **Transcripts**: Orchestrator invocations are persisted and replayable
+//! - **User Agents**: Agents declared in AGENTS.md/agents.toml run alongside the built-ins
+//! - **Guardrail Policies**: Allow/deny/require-approval rules from guardrails.toml
+//!   are checked before every tool call and model request
+//! - **Lookalike Domains**: The Watchdog flags homoglyph, typosquat and brand
+//!   impersonation hosts against a brand list and the user's top sites
 //! - **Security**: Blocked patterns in GuardrailAgent are NEVER bypassed by gaming allowlist
 
 pub mod callbacks;
 pub mod critic;
+pub mod declarative;
 pub mod events;
 pub mod guardrail;
+pub mod lookalike;
 pub mod narrator;
 pub mod observer;
 pub mod operator;
 pub mod orchestrator;
 pub mod planner;
+pub mod policy;
 pub mod traits;
+pub mod transcript;
 pub mod verifier;
 pub mod watchdog;
 
@@ -37,15 +47,20 @@ pub use callbacks::{
     ModelCallback, PolicyCallback, TokenUsage, ToolCall, ToolCallback, ToolResult,
 };
 pub use critic::CriticAgent;
+pub use declarative::{AgentSpec, UserAgent};
 pub use events::{AgentEvent, EventActions, EventAuthor, EventContent, EventPriority, EventStream};
 pub use guardrail::{ContentType, GuardrailAgent, SafetyEvaluation};
-pub use operator::{OperatorAgent, VisualTaskPlanner, VisualTaskResult, VisualTaskStep};
+pub use operator::{
+    OperatorAgent, StepExpectation, StepOutcome, StepReport, VisualTaskPlanner, VisualTaskResult,
+    VisualTaskStep,
+};
 pub use orchestrator::AgentOrchestrator;
 pub use planner::PlannerAgent;
 pub use traits::{
     Agent, AgentContext, AgentError, AgentMode, AgentOutput, AgentPriority, AgentResult,
     NextAction, PlanningContext, RateLimiter, ReflectionFeedback, SearchStrategy, SubGoal,
 };
+pub use transcript::{Transcript, TranscriptInfo, TranscriptStore};
 pub use watchdog::{
     PatternDetectors, SuggestedAction, Threat, ThreatType, WatchdogAgent, WatchdogReport,
 };
@@ -3,6 +3,7 @@
 
 use super::traits::{Agent, AgentContext, AgentError, AgentOutput, AgentResult, NextAction};
 use crate::ai::ai_provider::SmartAiRouter;
+use crate::ai::prompts::{self, PromptValue};
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -63,55 +64,37 @@ impl NarratorAgent {
         // 2. Consider what the user needs
         // 3. Decide on the appropriate response
         // 4. Generate the dialogue
-        let cot_prompt = format!(
-            r#"You are a mysterious Ghost AI with fragments of lost memories.
-
-CURRENT CONTEXT:
-- Location: '{}'
-- Proximity to goal: {:.0}%
-- Puzzle clue: "{}"
-- Current mood: {}
-- Planning strategy: {:?}
-- Sub-goals achieved: {}/{}
-
-CHAIN OF THOUGHT - Think through this step by step:
-
-1. ANALYZE: What is the user currently doing? How close are they to the goal?
-   (Consider: Are they on the right track? Do they seem stuck? Are they exploring?)
-
-2. CONSIDER: What does the user need right now?
-   - If far (0-30%): gentle encouragement, don't give away too much
-   - If medium (30-70%): more specific hints, show excitement
-   - If close (70-100%): build anticipation, celebrate progress
-
-3. DECIDE: What tone and content fits the '{}' mood?
-   (Remember: You are ethereal, mysterious, speaking in fragments of lost memory)
-
-4. GENERATE: Create a single evocative line (MAX 80 CHARACTERS) that:
-   - Reflects your {} personality
-   - Hints at their progress appropriately
-   - Stays in character as a digital ghost
-
-OUTPUT ONLY THE FINAL DIALOGUE LINE, nothing else. No quotes, no explanation."#,
-            redacted_title,
-            context.proximity * 100.0,
-            context.puzzle_clue,
-            mood.as_prompt(),
-            context.planning.strategy,
-            context
-                .planning
-                .sub_goals
-                .iter()
-                .filter(|g| g.achieved)
-                .count(),
-            context.planning.sub_goals.len(),
-            mood.as_prompt(),
-            mood.as_prompt()
-        );
-
+        let cot_prompt = prompts::render(
+            "narrator.dialogue",
+            &[
+                ("location", redacted_title.into()),
+                ("proximity", PromptValue::percent(context.proximity)),
+                ("puzzle_clue", context.puzzle_clue.as_str().into()),
+                ("mood", mood.as_prompt().into()),
+                (
+                    "strategy",
+                    format!("{:?}", context.planning.strategy).into(),
+                ),
+                (
+                    "sub_goals_achieved",
+                    context
+                        .planning
+                        .sub_goals
+                        .iter()
+                        .filter(|g| g.achieved)
+                        .count()
+                        .into(),
+                ),
+                ("sub_goals_total", context.planning.sub_goals.len().into()),
+            ],
+        )
+        .map_err(AgentError::ConfigError)?;
+
+        // Streamed so the frontend can render the line as it is generated
+        let request_id = format!("narrator-{}", uuid::Uuid::new_v4());
         let dialogue = self
             .ai_router
-            .generate_dialogue(&cot_prompt, mood.as_prompt())
+            .generate_dialogue_streamed(&request_id, &cot_prompt, mood.as_prompt())
             .await
             .map_err(|e| AgentError::ServiceError(e.to_string()))?;
 
@@ -129,29 +112,19 @@ OUTPUT ONLY THE FINAL DIALOGUE LINE, nothing else. No quotes, no explanation."#,
 
     /// Generate congratulatory message for solving puzzle (with CoT)
     pub async fn generate_success_dialogue(&self, context: &AgentContext) -> AgentResult<String> {
-        let cot_prompt = format!(
-            r#"You are a mysterious Ghost AI celebrating a puzzle solved!
-
-PUZZLE SOLVED: "{}"
-HINTS USED: {}
-TIME INVESTED: The player persevered through the mystery
-
-CHAIN OF THOUGHT:
-1. The player just achieved something meaningful
-2. Express genuine gratitude and joy (you recovered a lost memory!)
-3. Stay in character as a mystical, ethereal being
-4. Make them feel accomplished
-
-Generate an EXCITED, TRIUMPHANT message (max 100 chars) celebrating their discovery.
-Be mystical, grateful, and joyous.
-
-OUTPUT ONLY THE CELEBRATION LINE:"#,
-            context.puzzle_clue, context.hints_revealed
-        );
-
+        let cot_prompt = prompts::render(
+            "narrator.success",
+            &[
+                ("puzzle_clue", context.puzzle_clue.as_str().into()),
+                ("hints_used", context.hints_revealed.into()),
+            ],
+        )
+        .map_err(AgentError::ConfigError)?;
+
+        let request_id = format!("narrator-{}", uuid::Uuid::new_v4());
         let dialogue = self
             .ai_router
-            .generate_dialogue(&cot_prompt, "excited and grateful")
+            .generate_dialogue_streamed(&request_id, &cot_prompt, "excited and grateful")
             .await
             .map_err(|e| AgentError::ServiceError(e.to_string()))?;
 
@@ -3,6 +3,7 @@
 
 use super::traits::{Agent, AgentContext, AgentError, AgentOutput, AgentResult, NextAction};
 use crate::ai::ai_provider::SmartAiRouter;
+use crate::ai::scheduler::{with_priority, Priority};
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -103,11 +104,16 @@ impl Agent for ObserverAgent {
             });
         }
 
-        // Calculate proximity
-        let proximity = self.analyze_content(context).await?;
+        // Observation runs behind user-initiated requests
+        let (proximity, topics) = with_priority(Priority::Background, async {
+            // Calculate proximity
+            let proximity = self.analyze_content(context).await?;
 
-        // Extract topics (best effort, don't fail if this fails)
-        let topics = self.extract_topics(context).await.unwrap_or_default();
+            // Extract topics (best effort, don't fail if this fails)
+            let topics = self.extract_topics(context).await.unwrap_or_default();
+            Ok::<_, AgentError>((proximity, topics))
+        })
+        .await?;
 
         // Determine next action based on proximity
         let next_action = if proximity > 0.85 {
@@ -1,17 +1,31 @@
 //! Operator Agent - Visual task execution
 //!
 //! Executes tasks through visual interaction with browser elements.
+//!
+//! Execution is closed-loop: after each step the screen (or browser URL) is
+//! observed again and compared with the step's `StepExpectation`. A step is
+//! retried, re-locating described targets, only when it never acted or an
+//! element or URL expectation shows it had no effect; an unchanged screen alone
+//! doesn't justify clicking again and leaves the step unverified. The task
+//! aborts when an element or URL expectation still isn't met, with the
+//! diverging step recorded in `VisualTaskResult`.
 
 use super::traits::{Agent, AgentContext, AgentError, AgentOutput, AgentPriority, AgentResult};
 use crate::capture::capture_primary_monitor_raw;
+use crate::capture::change_detection::{ChangeDetectionConfig, ChangeResult, SharedChangeDetector};
 use crate::capture::vision::VisionCapture;
-use crate::config::privacy::PrivacySettings;
+use crate::config::privacy::{AutonomyLevel, PrivacySettings};
 use crate::input::{InputController, MouseButton, ScrollDirection as InputScrollDirection};
+use crate::mcp::browser::BrowserState;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
+/// Interval between checks while waiting for an element
+const WAIT_POLL_MS: u64 = 1000;
+
 /// Result of a visual task execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VisualTaskResult {
@@ -20,6 +34,12 @@ pub struct VisualTaskResult {
     pub duration_secs: f64,
     pub summary: String,
     pub error: Option<String>,
+    /// Per-step verification, in execution order
+    #[serde(default)]
+    pub steps: Vec<StepReport>,
+    /// 1-based index of the step whose outcome didn't match its expectation
+    #[serde(default)]
+    pub diverged_at: Option<usize>,
 }
 
 /// A step in a visual task
@@ -28,6 +48,224 @@ pub struct VisualTaskStep {
     pub description: String,
     pub action_type: VisualActionType,
     pub expected_outcome: String,
+    /// Observable state to verify after the step (defaults by action type)
+    #[serde(default)]
+    pub expect: Option<StepExpectation>,
+}
+
+impl VisualTaskStep {
+    /// Expectation to verify: the explicit one, or the action's default
+    pub fn expectation(&self) -> Option<StepExpectation> {
+        if self.expect.is_some() {
+            return self.expect.clone();
+        }
+        match &self.action_type {
+            VisualActionType::Click { .. } | VisualActionType::Scroll { .. } => {
+                Some(StepExpectation::ScreenChanged)
+            }
+            // Typing a few characters changes too little of the screen to
+            // detect, so look for the typed text instead
+            VisualActionType::Fill { value, .. } if !value.trim().is_empty() => {
+                Some(StepExpectation::ElementAppears(value.clone()))
+            }
+            // Waits poll for their condition; navigation is left to the browser bridge
+            VisualActionType::Fill { .. }
+            | VisualActionType::Wait { .. }
+            | VisualActionType::Navigate { .. } => None,
+        }
+    }
+}
+
+/// State a step is expected to produce
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepExpectation {
+    /// The screen visibly changed
+    ScreenChanged,
+    /// An element matching the description is on screen
+    ElementAppears(String),
+    /// No element matching the description is on screen
+    ElementDisappears(String),
+    /// The browser navigated away from the previous URL
+    UrlChanged,
+    /// The browser URL contains the text
+    UrlContains(String),
+}
+
+impl StepExpectation {
+    /// Whether a miss shows the step had no effect; a screen that didn't
+    /// visibly change may still have taken the click
+    pub fn is_reliable(&self) -> bool {
+        !matches!(self, StepExpectation::ScreenChanged)
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            StepExpectation::ScreenChanged => "screen changes".to_string(),
+            StepExpectation::ElementAppears(desc) => format!("'{}' appears", desc),
+            StepExpectation::ElementDisappears(desc) => format!("'{}' disappears", desc),
+            StepExpectation::UrlChanged => "URL changes".to_string(),
+            StepExpectation::UrlContains(text) => format!("URL contains '{}'", text),
+        }
+    }
+}
+
+/// How a step's outcome compared with its expectation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepOutcome {
+    /// The expected state was observed
+    Verified,
+    /// Nothing to verify, the state couldn't be observed, or the screen didn't
+    /// visibly change
+    Unverified,
+    /// The expected state wasn't observed after all attempts
+    Diverged,
+}
+
+/// Verification record for one executed step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepReport {
+    /// 1-based step index
+    pub step: usize,
+    pub description: String,
+    pub expected: Option<String>,
+    pub outcome: StepOutcome,
+    /// What was observed on the last attempt
+    pub observation: String,
+    pub attempts: u32,
+}
+
+/// Observation taken before a step, for comparisons after it
+#[derive(Debug, Default)]
+struct Baseline {
+    url: Option<String>,
+}
+
+/// One try at a step
+#[derive(Debug)]
+struct Attempt {
+    /// Whether input reached the screen (false when locating or waiting failed)
+    acted: bool,
+    outcome: StepOutcome,
+    observation: String,
+}
+
+/// Final outcome of a step after its retries
+#[derive(Debug)]
+struct StepRun {
+    outcome: StepOutcome,
+    observation: String,
+    attempts: u32,
+    /// Attempts that acted on the screen
+    actions: u32,
+}
+
+/// Try a step until it stops diverging or `max_retries` retries are spent
+///
+/// A diverging attempt is only repeated when it never acted, or `reliable`
+/// (the expectation shows the step had no effect); otherwise repeating a click
+/// could submit twice. A step that acted but missed an unreliable expectation
+/// ends `Unverified`, since the click may have landed without visible change.
+async fn run_attempts<F, Fut>(
+    step: usize,
+    max_retries: u32,
+    reliable: bool,
+    mut attempt: F,
+) -> Result<StepRun, AgentError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Attempt, AgentError>>,
+{
+    let mut attempts = 0;
+    let mut actions = 0;
+    loop {
+        attempts += 1;
+        if attempts > 1 {
+            tracing::info!(
+                "Retrying step {} (attempt {}), re-locating target",
+                step,
+                attempts
+            );
+        }
+
+        let Attempt {
+            acted,
+            outcome,
+            observation,
+        } = attempt().await?;
+        if acted {
+            actions += 1;
+        }
+
+        let retry =
+            outcome == StepOutcome::Diverged && attempts <= max_retries && (!acted || reliable);
+        if !retry {
+            let outcome = if outcome == StepOutcome::Diverged && acted && !reliable {
+                StepOutcome::Unverified
+            } else {
+                outcome
+            };
+            return Ok(StepRun {
+                outcome,
+                observation,
+                attempts,
+                actions,
+            });
+        }
+        tracing::warn!("Step {} diverged: {}", step, observation);
+    }
+}
+
+/// Compare a change-detection result with `ScreenChanged`
+fn judge_screen_change(
+    result: &ChangeResult,
+    config: &ChangeDetectionConfig,
+) -> (StepOutcome, String) {
+    let percent = result.changed_percentage() * 100.0;
+    if result.should_capture(config) {
+        (
+            StepOutcome::Verified,
+            format!("Screen changed ({:.1}% of pixels)", percent),
+        )
+    } else {
+        (
+            StepOutcome::Diverged,
+            format!("Screen did not change ({:.1}% of pixels)", percent),
+        )
+    }
+}
+
+/// Compare browser URLs with `UrlChanged` / `UrlContains`
+fn judge_url(
+    expectation: &StepExpectation,
+    before: Option<&str>,
+    after: Option<&str>,
+) -> (StepOutcome, String) {
+    let Some(after) = after else {
+        return (
+            StepOutcome::Unverified,
+            "Browser URL unavailable".to_string(),
+        );
+    };
+    let matched = match expectation {
+        StepExpectation::UrlChanged => match before {
+            Some(before) => before != after,
+            None => {
+                return (
+                    StepOutcome::Unverified,
+                    "No URL recorded before the step".to_string(),
+                )
+            }
+        },
+        StepExpectation::UrlContains(text) => after.contains(text.as_str()),
+        _ => return (StepOutcome::Unverified, "Not a URL expectation".to_string()),
+    };
+    if matched {
+        (StepOutcome::Verified, format!("URL is {}", after))
+    } else {
+        (StepOutcome::Diverged, format!("URL stayed at {}", after))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -74,8 +312,13 @@ pub struct OperatorAgent {
     vision_capture: Arc<VisionCapture>,
     input_controller: Arc<InputController>,
     privacy_settings: PrivacySettings,
+    change_detector: SharedChangeDetector,
+    /// Browser page state, for URL expectations (set once the bridge is up)
+    browser_state: RwLock<Option<Arc<BrowserState>>>,
     max_steps: u32,
     step_delay_ms: u64,
+    /// Extra attempts for a step whose expectation wasn't met
+    max_retries: u32,
 }
 
 impl OperatorAgent {
@@ -88,8 +331,43 @@ impl OperatorAgent {
             vision_capture,
             input_controller,
             privacy_settings,
+            change_detector: SharedChangeDetector::new(),
+            browser_state: RwLock::new(None),
             max_steps: 20,
             step_delay_ms: 1000,
+            max_retries: 2,
+        }
+    }
+
+    /// An operator that can neither see nor touch the screen
+    ///
+    /// Used by sandboxed replays: every input step fails the autonomy check.
+    pub fn inert() -> Self {
+        let privacy_settings = PrivacySettings {
+            autonomy_level: AutonomyLevel::Observer,
+            visual_automation_consent: false,
+            ..PrivacySettings::default()
+        };
+        Self::new(
+            Arc::new(VisionCapture::new(None)),
+            Arc::new(InputController::new(
+                AutonomyLevel::Observer,
+                privacy_settings.clone(),
+            )),
+            privacy_settings,
+        )
+    }
+
+    /// Set how many times a diverging step is retried before aborting
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Use the browser bridge's page state to verify URL expectations
+    pub fn set_browser_state(&self, state: Arc<BrowserState>) {
+        if let Ok(mut browser_state) = self.browser_state.write() {
+            *browser_state = Some(state);
         }
     }
 
@@ -112,9 +390,12 @@ impl OperatorAgent {
                 duration_secs: 0.0,
                 summary: "Visual automation not allowed for this site".to_string(),
                 error: Some("Visual automation consent required".to_string()),
+                steps: Vec::new(),
+                diverged_at: None,
             });
         }
 
+        let mut reports = Vec::new();
         for (i, step) in steps.iter().enumerate() {
             if i as u32 >= self.max_steps {
                 return Ok(VisualTaskResult {
@@ -123,19 +404,29 @@ impl OperatorAgent {
                     duration_secs: start_time.elapsed().as_secs_f64(),
                     summary: format!("Stopped after {} steps (max reached)", self.max_steps),
                     error: Some("Max steps exceeded".to_string()),
+                    steps: reports,
+                    diverged_at: None,
                 });
             }
 
             tracing::info!("Executing step {}: {}", i + 1, step.description);
 
-            match self.execute_step(step).await {
-                Ok(true) => {
-                    actions_taken += 1;
-                    tokio::time::sleep(Duration::from_millis(self.step_delay_ms)).await;
-                }
-                Ok(false) => {
-                    tracing::warn!("Step {} did not complete as expected", i + 1);
-                }
+            let expectation = step.expectation();
+            let reliable = expectation
+                .as_ref()
+                .is_some_and(StepExpectation::is_reliable);
+            let run = run_attempts(i + 1, self.max_retries, reliable, || {
+                self.attempt_step(step, expectation.as_ref())
+            })
+            .await;
+            let StepRun {
+                outcome,
+                observation,
+                attempts,
+                actions,
+            } = match run {
+                Ok(run) => run,
+                // Not worth retrying: the step isn't allowed at all
                 Err(e) => {
                     return Ok(VisualTaskResult {
                         success: false,
@@ -143,8 +434,37 @@ impl OperatorAgent {
                         duration_secs: start_time.elapsed().as_secs_f64(),
                         summary: format!("Failed at step {}: {}", i + 1, step.description),
                         error: Some(e.to_string()),
+                        steps: reports,
+                        diverged_at: None,
                     });
                 }
+            };
+            actions_taken += actions;
+
+            reports.push(StepReport {
+                step: i + 1,
+                description: step.description.clone(),
+                expected: expectation.as_ref().map(StepExpectation::describe),
+                outcome,
+                observation: observation.clone(),
+                attempts,
+            });
+
+            if outcome == StepOutcome::Diverged {
+                return Ok(VisualTaskResult {
+                    success: false,
+                    actions_taken,
+                    duration_secs: start_time.elapsed().as_secs_f64(),
+                    summary: format!(
+                        "Step {} diverged after {} attempt(s): {}",
+                        i + 1,
+                        attempts,
+                        step.description
+                    ),
+                    error: Some(observation),
+                    steps: reports,
+                    diverged_at: Some(i + 1),
+                });
             }
         }
 
@@ -154,9 +474,128 @@ impl OperatorAgent {
             duration_secs: start_time.elapsed().as_secs_f64(),
             summary: format!("Completed task: {} ({} actions)", goal, actions_taken),
             error: None,
+            steps: reports,
+            diverged_at: None,
+        })
+    }
+
+    /// Run a step once and verify it; only safety violations are errors
+    async fn attempt_step(
+        &self,
+        step: &VisualTaskStep,
+        expectation: Option<&StepExpectation>,
+    ) -> Result<Attempt, AgentError> {
+        let baseline = self.baseline(expectation).await;
+        let (acted, outcome, observation) = match self.execute_step(step).await {
+            Ok(true) => {
+                tokio::time::sleep(Duration::from_millis(self.step_delay_ms)).await;
+                let (outcome, observation) = self.verify(expectation, &baseline).await;
+                (true, outcome, observation)
+            }
+            Ok(false) => (
+                false,
+                StepOutcome::Diverged,
+                "Step did not complete".to_string(),
+            ),
+            Err(e @ AgentError::SafetyViolation(_)) => return Err(e),
+            Err(e) => (false, StepOutcome::Diverged, e.to_string()),
+        };
+        Ok(Attempt {
+            acted,
+            outcome,
+            observation,
         })
     }
 
+    /// Current browser URL, if the bridge has reported a page
+    async fn current_url(&self) -> Option<String> {
+        let state = self.browser_state.read().ok()?.clone()?;
+        let url = state.current_page.read().await.url.clone();
+        (!url.is_empty()).then_some(url)
+    }
+
+    /// Record what the expectation is compared against after the step
+    async fn baseline(&self, expectation: Option<&StepExpectation>) -> Baseline {
+        match expectation {
+            Some(StepExpectation::ScreenChanged) => {
+                self.change_detector.reset().await;
+                if let Err(e) = self.change_detector.capture_and_detect().await {
+                    tracing::warn!("Baseline capture failed: {}", e);
+                }
+                Baseline::default()
+            }
+            Some(StepExpectation::UrlChanged) => Baseline {
+                url: self.current_url().await,
+            },
+            _ => Baseline::default(),
+        }
+    }
+
+    /// Whether an element matching `description` is on screen now
+    async fn element_visible(&self, description: &str) -> Result<bool, AgentError> {
+        let image_bytes = capture_primary_monitor_raw()
+            .map_err(|e| AgentError::ExecutionError(format!("Screen capture failed: {}", e)))?;
+        let analysis = self
+            .vision_capture
+            .capture_and_analyze(image_bytes)
+            .await
+            .map_err(|e| AgentError::ExecutionError(format!("Vision analysis failed: {}", e)))?;
+        Ok(self
+            .vision_capture
+            .find_element_by_description(&analysis, description)
+            .is_some())
+    }
+
+    /// Observe the screen or browser and compare with the expectation
+    async fn verify(
+        &self,
+        expectation: Option<&StepExpectation>,
+        baseline: &Baseline,
+    ) -> (StepOutcome, String) {
+        let Some(expectation) = expectation else {
+            return (StepOutcome::Unverified, "No expected state".to_string());
+        };
+        match expectation {
+            StepExpectation::ScreenChanged => {
+                match self.change_detector.capture_and_detect().await {
+                    Ok((_, result)) => {
+                        judge_screen_change(&result, &self.change_detector.get_config().await)
+                    }
+                    Err(e) => (
+                        StepOutcome::Unverified,
+                        format!("Screen capture failed: {}", e),
+                    ),
+                }
+            }
+            StepExpectation::ElementAppears(desc) | StepExpectation::ElementDisappears(desc) => {
+                let should_be_visible = matches!(expectation, StepExpectation::ElementAppears(_));
+                match self.element_visible(desc).await {
+                    Ok(visible) if visible == should_be_visible => {
+                        (StepOutcome::Verified, expectation.describe())
+                    }
+                    Ok(visible) => (
+                        StepOutcome::Diverged,
+                        format!(
+                            "'{}' is {}",
+                            desc,
+                            if visible {
+                                "still visible"
+                            } else {
+                                "not visible"
+                            }
+                        ),
+                    ),
+                    Err(e) => (StepOutcome::Unverified, e.to_string()),
+                }
+            }
+            StepExpectation::UrlChanged | StepExpectation::UrlContains(_) => judge_url(
+                expectation,
+                baseline.url.as_deref(),
+                self.current_url().await.as_deref(),
+            ),
+        }
+    }
+
     async fn resolve_target(&self, target: &Target) -> Result<(i32, i32), AgentError> {
         match target {
             Target::Coordinates(x, y) => Ok((*x, *y)),
@@ -274,9 +713,23 @@ impl OperatorAgent {
                 timeout_secs,
             } => {
                 tracing::info!("Waiting for '{}' (timeout: {}s)", condition, timeout_secs);
-                // Placeholder for visual wait
-                tokio::time::sleep(Duration::from_secs(*timeout_secs as u64)).await;
-                Ok(true)
+                let timeout = Duration::from_secs(*timeout_secs as u64);
+                if !self.vision_capture.is_available() {
+                    tokio::time::sleep(timeout).await;
+                    return Ok(true);
+                }
+
+                // Poll until the condition is on screen
+                let deadline = Instant::now() + timeout;
+                loop {
+                    if self.element_visible(condition).await? {
+                        return Ok(true);
+                    }
+                    if Instant::now() >= deadline {
+                        return Ok(false);
+                    }
+                    tokio::time::sleep(Duration::from_millis(WAIT_POLL_MS)).await;
+                }
             }
             VisualActionType::Navigate { url } => {
                 tracing::info!("Navigating to: {}", url);
@@ -312,6 +765,7 @@ impl Agent for OperatorAgent {
                 target: Target::Description(goal.clone()),
             },
             expected_outcome: "Task completed".to_string(),
+            expect: None,
         }];
 
         match self.execute_visual_task(&goal, steps).await {
@@ -325,6 +779,10 @@ impl Agent for OperatorAgent {
                     "duration_secs".to_string(),
                     serde_json::json!(result.duration_secs),
                 );
+                data.insert("steps".to_string(), serde_json::json!(result.steps));
+                if let Some(step) = result.diverged_at {
+                    data.insert("diverged_at".to_string(), serde_json::json!(step));
+                }
 
                 Ok(AgentOutput {
                     agent_name: self.name().to_string(),
@@ -355,6 +813,7 @@ impl VisualTaskPlanner {
                     url: "https://example.com".to_string(),
                 },
                 expected_outcome: "Page loaded".to_string(),
+                expect: None,
             },
             VisualTaskStep {
                 description: format!("Execute: {}", goal),
@@ -362,6 +821,7 @@ impl VisualTaskPlanner {
                     target: Target::Description(goal.to_string()),
                 },
                 expected_outcome: "Task completed".to_string(),
+                expect: None,
             },
         ]
     }
@@ -379,9 +839,171 @@ mod tests {
             duration_secs: 5.5,
             summary: "Test completed".to_string(),
             error: None,
+            steps: Vec::new(),
+            diverged_at: None,
         };
 
         assert!(result.success);
         assert_eq!(result.actions_taken, 3);
     }
+
+    #[test]
+    fn test_step_expectations() {
+        // Steps serialized before expectations existed still load
+        let step: VisualTaskStep = serde_json::from_value(serde_json::json!({
+            "description": "Open menu",
+            "action_type": { "click": { "target": { "description": "Menu" } } },
+            "expected_outcome": "Menu opens"
+        }))
+        .unwrap();
+        assert_eq!(step.expectation(), Some(StepExpectation::ScreenChanged));
+
+        let step = VisualTaskStep {
+            expect: Some(StepExpectation::ElementAppears("Settings".to_string())),
+            ..step
+        };
+        assert_eq!(step.expectation().unwrap().describe(), "'Settings' appears");
+
+        let fill = VisualTaskStep {
+            action_type: VisualActionType::Fill {
+                target: Target::Description("Search".to_string()),
+                value: "rust".to_string(),
+            },
+            expect: None,
+            ..step
+        };
+        assert_eq!(
+            fill.expectation(),
+            Some(StepExpectation::ElementAppears("rust".to_string()))
+        );
+        assert!(!StepExpectation::ScreenChanged.is_reliable());
+        assert!(StepExpectation::UrlChanged.is_reliable());
+    }
+
+    #[test]
+    fn test_judging_observations() {
+        let config = ChangeDetectionConfig::default();
+        let (outcome, _) = judge_screen_change(&ChangeResult::SignificantChange(0.3), &config);
+        assert_eq!(outcome, StepOutcome::Verified);
+        let (outcome, observation) = judge_screen_change(&ChangeResult::NoChange, &config);
+        assert_eq!(outcome, StepOutcome::Diverged);
+        assert_eq!(observation, "Screen did not change (0.0% of pixels)");
+
+        let changed = StepExpectation::UrlChanged;
+        let (outcome, _) = judge_url(
+            &changed,
+            Some("https://a.example/"),
+            Some("https://b.example/"),
+        );
+        assert_eq!(outcome, StepOutcome::Verified);
+        let (outcome, _) = judge_url(
+            &changed,
+            Some("https://a.example/"),
+            Some("https://a.example/"),
+        );
+        assert_eq!(outcome, StepOutcome::Diverged);
+        let (outcome, _) = judge_url(&changed, None, None);
+        assert_eq!(outcome, StepOutcome::Unverified);
+
+        let contains = StepExpectation::UrlContains("/checkout".to_string());
+        let (outcome, _) = judge_url(&contains, None, Some("https://shop.example/checkout"));
+        assert_eq!(outcome, StepOutcome::Verified);
+    }
+
+    fn attempt(acted: bool, outcome: StepOutcome) -> Result<Attempt, AgentError> {
+        Ok(Attempt {
+            acted,
+            outcome,
+            observation: format!("{:?}", outcome),
+        })
+    }
+
+    /// Run the retry loop over scripted attempts, returning the run and how
+    /// many attempts were made
+    async fn run_script(
+        reliable: bool,
+        script: Vec<Result<Attempt, AgentError>>,
+    ) -> Result<StepRun, AgentError> {
+        let script = std::sync::Mutex::new(std::collections::VecDeque::from(script));
+        run_attempts(1, 2, reliable, || {
+            let next = script
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("attempt past script");
+            async move { next }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_retry_loop() {
+        // An unchanged screen after a click is not retried, nor fatal
+        let run = run_script(false, vec![attempt(true, StepOutcome::Diverged)])
+            .await
+            .unwrap();
+        assert_eq!(
+            (run.outcome, run.attempts, run.actions),
+            (StepOutcome::Unverified, 1, 1)
+        );
+
+        // A reliable miss is retried and diverges once retries are spent
+        let run = run_script(
+            true,
+            vec![
+                attempt(true, StepOutcome::Diverged),
+                attempt(true, StepOutcome::Diverged),
+                attempt(true, StepOutcome::Diverged),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            (run.outcome, run.attempts, run.actions),
+            (StepOutcome::Diverged, 3, 3)
+        );
+
+        // A reliable expectation retries until it is met
+        let run = run_script(
+            true,
+            vec![
+                attempt(true, StepOutcome::Diverged),
+                attempt(true, StepOutcome::Verified),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            (run.outcome, run.attempts, run.actions),
+            (StepOutcome::Verified, 2, 2)
+        );
+
+        // A step that never acted is retried, up to the limit
+        let run = run_script(
+            false,
+            vec![
+                attempt(false, StepOutcome::Diverged),
+                attempt(false, StepOutcome::Diverged),
+                attempt(false, StepOutcome::Diverged),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            (run.outcome, run.attempts, run.actions),
+            (StepOutcome::Diverged, 3, 0)
+        );
+
+        // Unverified steps are accepted, and safety violations stop at once
+        let run = run_script(true, vec![attempt(true, StepOutcome::Unverified)])
+            .await
+            .unwrap();
+        assert_eq!(run.attempts, 1);
+        let denied = run_script(
+            true,
+            vec![Err(AgentError::SafetyViolation("denied".to_string()))],
+        )
+        .await;
+        assert!(matches!(denied, Err(AgentError::SafetyViolation(_))));
+    }
 }
@@ -11,14 +11,16 @@
 //! - **Monitoring**: InvocationMetrics for each orchestration cycle
 //! - **Events**: EventStream for observability
 //! - **ScopedState**: Temp state cleared at start of each invocation
+//! - **Transcripts**: Each invocation is persisted and can be replayed
 //!
 //! MCP Integration (Chapter 10):
 //! - Supports dynamic tool discovery via `get_available_tools()`
 //! - Can invoke browser tools through MCP interface
 //! - Resources are accessible via MCP URIs
 
-use super::callbacks::{CallbackContext, CallbackRegistry};
+use super::callbacks::{self, CallbackContext, CallbackRegistry, ToolCall, ToolResult};
 use super::critic::CriticAgent;
+use super::declarative;
 use super::events::{AgentEvent, EventActions, EventStream};
 use super::guardrail::GuardrailAgent;
 use super::narrator::NarratorAgent;
@@ -26,20 +28,29 @@ use super::observer::ObserverAgent;
 use super::operator::OperatorAgent;
 use super::planner::PlannerAgent;
 use super::traits::{
-    Agent, AgentContext, AgentMode, AgentOutput, AgentResult, NextAction, PlanningContext,
+    Agent, AgentContext, AgentError, AgentMode, AgentOutput, AgentResult, NextAction,
+    PlanningContext, SearchStrategy,
 };
+use super::transcript::{self, Transcript, TranscriptStore};
 use super::verifier::VerifierAgent;
 use super::watchdog::WatchdogAgent;
 use crate::ai::ai_provider::SmartAiRouter;
-use crate::mcp::{McpServer, ResourceDescriptor, ToolDescriptor};
-use crate::memory::{LongTermMemory, SessionMemory};
+use crate::ai::prompts;
+use crate::ai::providers::{FixtureMatch, ToolTurn};
+use crate::core::utils::{current_timestamp, current_timestamp_millis};
+use crate::mcp::{McpServer, ResourceDescriptor, ToolDescriptor, ToolRequest};
+use crate::memory::{LongTermMemory, MemoryStore, SessionMemory};
 use crate::monitoring::{InvocationMetrics, MetricsCollector};
 use crate::workflow::{
-    loop_agent::create_adaptive_loop, parallel::create_parallel_checks,
-    planning::create_intelligent_pipeline, reflection::create_narrator_with_reflection,
-    sequential::create_puzzle_pipeline, PlanningWorkflow, ReflectionWorkflow, SequentialWorkflow,
-    Workflow,
+    dag::{DagWorkflow, PlanExecution},
+    loop_agent::create_adaptive_loop,
+    parallel::create_parallel_checks,
+    planning::create_intelligent_pipeline,
+    reflection::create_narrator_with_reflection,
+    sequential::create_puzzle_pipeline,
+    PlanningWorkflow, ReflectionWorkflow, SequentialWorkflow, Workflow,
 };
+use anyhow::Context;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use std::sync::{Arc, Mutex};
@@ -55,14 +66,61 @@ const AUTONOMOUS_LOOP_MAX_ITERATIONS: usize = 5;
 const AUTONOMOUS_LOOP_DELAY_MS: u64 = 2000;
 const PLANNED_LOOP_MAX_ITERATIONS: usize = 10;
 const PLANNED_LOOP_DELAY_MS: u64 = 1500;
+const TOOL_LOOP_MAX_STEPS: usize = 8;
+const PLAN_MAX_CONCURRENCY: usize = 3;
+const PLAN_MAX_REVISIONS: usize = 2;
+/// Tool loop steps a worker gets per sub-goal
+const PLAN_WORKER_MAX_STEPS: usize = 4;
+/// Transfers allowed per invocation, so agents can't hand control back and forth forever
+const MAX_TRANSFER_DEPTH: usize = 3;
+/// Workflow name recorded in tool loop transcripts
+const TOOL_LOOP_WORKFLOW: &str = "tool_loop";
 
 /// Atomic counter for unique invocation IDs
 static INVOCATION_COUNTER: AtomicU64 = AtomicU64::new(0);
 
-/// Generate a unique invocation ID
+/// Generate a unique invocation ID (unique across restarts, as transcripts are persisted)
 fn generate_invocation_id() -> String {
     let counter = INVOCATION_COUNTER.fetch_add(1, Ordering::SeqCst);
-    format!("inv_{}", counter)
+    format!("inv_{}_{}", current_timestamp_millis(), counter)
+}
+
+/// ADK actions signalled by an agent output
+///
+/// Agents set `state_delta`, `transfer_to` (an agent name), `escalate` or
+/// `terminate` in their output data. `Abort` also terminates.
+fn output_actions(output: &AgentOutput) -> EventActions {
+    let mut actions = EventActions::default();
+    if let Some(delta) = output.data.get("state_delta").and_then(|v| v.as_object()) {
+        for (key, value) in delta {
+            actions.state_delta.insert(key.clone(), value.clone());
+        }
+    }
+    if let Some(target) = output.transfer_target() {
+        actions = actions.transfer_to(target);
+    }
+    let flag = |key: &str| output.data.get(key).and_then(|v| v.as_bool()) == Some(true);
+    actions.escalate = flag("escalate");
+    actions.terminate = flag("terminate");
+    if matches!(output.next_action, Some(NextAction::Abort)) {
+        actions.terminate = true;
+    }
+    actions
+}
+
+/// URLs from a `browser://top-sites` resource (entries are objects or strings)
+fn top_site_urls(resource: &serde_json::Value) -> Vec<String> {
+    resource
+        .get("sites")
+        .and_then(|v| v.as_array())
+        .map(|sites| {
+            sites
+                .iter()
+                .filter_map(|site| site.get("url").unwrap_or(site).as_str())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 /// The main orchestrator that coordinates all agents
@@ -96,6 +154,8 @@ pub struct AgentOrchestrator {
     callbacks: Arc<AsyncMutex<CallbackRegistry>>,
     /// ADK-style metrics collector for monitoring
     metrics: Arc<MetricsCollector>,
+    /// Where invocation transcripts are saved (none: not persisted)
+    transcripts: Option<Arc<TranscriptStore>>,
 }
 
 /// Result of a full orchestration cycle
@@ -115,6 +175,117 @@ pub struct OrchestrationResult {
     pub agent_outputs: Vec<AgentOutput>,
 }
 
+/// Result of a native tool-calling run
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ToolLoopResult {
+    /// Final model answer (None if the step budget ran out)
+    pub answer: Option<String>,
+    /// Executed tool calls, in order
+    pub steps: Vec<ToolStep>,
+}
+
+/// A single executed tool call
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ToolStep {
+    pub call: ToolCall,
+    pub result: ToolResult,
+}
+
+/// Carries out one sub-goal of a plan through the tool loop
+///
+/// The model only gets the tools the plan named for the sub-goal. The sub-goal
+/// is done once the model gives a final answer without a failed tool call.
+struct PlanWorker {
+    orchestrator: Arc<AgentOrchestrator>,
+    mcp_server: Arc<crate::mcp::BrowserMcpServer>,
+    invocation_id: String,
+}
+
+#[async_trait::async_trait]
+impl Agent for PlanWorker {
+    fn name(&self) -> &str {
+        "PlanWorker"
+    }
+
+    fn description(&self) -> &str {
+        "Carries out a plan's sub-goal with the tools it needs"
+    }
+
+    async fn process(&self, context: &AgentContext) -> AgentResult<AgentOutput> {
+        let sub_goal = context
+            .metadata
+            .get("sub_goal")
+            .cloned()
+            .unwrap_or_default();
+        let wanted: Vec<&str> = context
+            .metadata
+            .get("sub_goal_tools")
+            .map(|tools| tools.split(',').collect())
+            .unwrap_or_default();
+        let tools: Vec<ToolDescriptor> = self
+            .mcp_server
+            .discover_tools(None)
+            .into_iter()
+            .filter(|tool| wanted.contains(&tool.name.as_str()))
+            .collect();
+        if let Some(missing) = wanted
+            .iter()
+            .find(|name| !tools.iter().any(|tool| tool.name == **name))
+        {
+            return Err(AgentError::ProcessingError(format!(
+                "No tool named {}",
+                missing
+            )));
+        }
+
+        let goal = prompts::render(
+            "planner.work",
+            &[
+                ("puzzle_clue", context.puzzle_clue.as_str().into()),
+                (
+                    "current_url",
+                    crate::config::privacy::redact_with_settings(&context.current_url).into(),
+                ),
+                ("sub_goal", sub_goal.as_str().into()),
+                ("keywords", context.planning.primary_keywords.clone().into()),
+            ],
+        )
+        .map_err(AgentError::ConfigError)?;
+        let run = self
+            .orchestrator
+            .run_tool_steps(
+                &self.invocation_id,
+                context,
+                &self.mcp_server,
+                tools,
+                &goal,
+                Some(PLAN_WORKER_MAX_STEPS),
+            )
+            .await?;
+
+        if let Some(step) = run.steps.iter().find(|step| !step.result.success) {
+            return Err(AgentError::ProcessingError(format!(
+                "{} failed: {}",
+                step.call.name,
+                step.result.error.clone().unwrap_or_default()
+            )));
+        }
+        let Some(answer) = run.answer else {
+            return Err(AgentError::ProcessingError(format!(
+                "No result after {} tool steps",
+                run.steps.len()
+            )));
+        };
+        Ok(AgentOutput {
+            agent_name: self.name().to_string(),
+            result: answer,
+            confidence: 1.0,
+            next_action: None,
+            data: HashMap::from([("tool_steps".to_string(), serde_json::json!(run.steps.len()))]),
+        })
+    }
+}
+
 impl AgentOrchestrator {
     /// Create a new orchestrator with all agents and shared memory
     /// Now includes PlannerAgent and CriticAgent for intelligent behavior
@@ -177,9 +348,16 @@ impl AgentOrchestrator {
             mode: AtomicU8::new(AgentMode::Standard as u8), // Default to Standard mode
             callbacks,
             metrics: Arc::new(MetricsCollector::default()),
+            transcripts: None,
         })
     }
 
+    /// Persist a transcript of every invocation in `store`
+    pub fn with_transcripts(mut self, store: Arc<TranscriptStore>) -> Self {
+        self.transcripts = Some(store);
+        self
+    }
+
     /// Request assistance (activates "Help Me" workflow)
     pub async fn handle_assistance_request(&self, prompt: String) -> anyhow::Result<String> {
         tracing::info!("Handling assistance request: {}", prompt);
@@ -222,6 +400,11 @@ impl AgentOrchestrator {
         Arc::clone(&self.metrics)
     }
 
+    /// Get the transcript store, if transcripts are persisted
+    pub fn transcripts(&self) -> Option<&Arc<TranscriptStore>> {
+        self.transcripts.as_ref()
+    }
+
     // -------------------------------------------------------------------------
     // Mode Getters/Setters (thread-safe)
     // -------------------------------------------------------------------------
@@ -324,19 +507,185 @@ impl AgentOrchestrator {
     /// ADK Integration:
     /// - Clears temp: scoped state at start of each invocation
     /// - Runs before/after agent callbacks
-    /// - Records InvocationMetrics for monitoring
+    /// - Records InvocationMetrics (including rendered prompt templates) for monitoring
+    /// - Saves a transcript of the invocation (see `with_transcripts`)
     pub async fn process(
         &self,
         context: &AgentContext,
         mcp_server: Option<&crate::mcp::BrowserMcpServer>,
     ) -> AgentResult<OrchestrationResult> {
+        let (result, transcript) = self.process_recorded(context, mcp_server).await;
+        self.save_transcript(&transcript);
+        result
+    }
+
+    /// Run the pipeline, returning its result and transcript
+    async fn process_recorded(
+        &self,
+        context: &AgentContext,
+        mcp_server: Option<&crate::mcp::BrowserMcpServer>,
+    ) -> (AgentResult<OrchestrationResult>, Transcript) {
         let invocation_id = generate_invocation_id();
+        let started_at = current_timestamp_millis() as u64;
         let start_time = Instant::now();
-        let workflow_name = if self.use_intelligent_mode() {
+        // Model requests made by the pipeline go through the before_model callbacks
+        let callback_context =
+            CallbackContext::new(context.clone(), "Orchestrator", &invocation_id);
+        let (result, recording) =
+            transcript::record(prompts::track(callbacks::with_model_callbacks(
+                self.callbacks.clone(),
+                callback_context,
+                self.run_pipeline(&invocation_id, context, mcp_server),
+            )))
+            .await;
+
+        let transcript = Transcript::new(
+            &invocation_id,
+            self.workflow_name(),
+            self.agent_mode(),
+            context,
+            started_at,
+            start_time.elapsed().as_millis() as u64,
+            recording,
+        )
+        .with_outcome(&result);
+        (result, transcript)
+    }
+
+    fn save_transcript(&self, transcript: &Transcript) {
+        if let Some(store) = &self.transcripts {
+            if let Err(e) = store.save(&Self::stored(transcript)) {
+                tracing::warn!(
+                    "Failed to save transcript {}: {}",
+                    transcript.invocation_id,
+                    e
+                );
+            }
+        }
+    }
+
+    /// A transcript as persisted, with PII redacted unless `redact_pii` is off
+    fn stored(transcript: &Transcript) -> Transcript {
+        let mut transcript = transcript.clone();
+        if crate::config::privacy::PrivacySettings::load().redact_pii {
+            transcript.redact();
+        }
+        transcript
+    }
+
+    /// Agent a transfer can route control to, matched case-insensitively
+    ///
+    /// User-defined agents (see `declarative`) are found after the built-in ones.
+    /// The Operator is never a target: it acts on the desktop, which needs the
+    /// autonomy checks of `execute_visual_task`.
+    fn agent_by_name(&self, name: &str) -> Option<Arc<dyn Agent>> {
+        let agents: [Arc<dyn Agent>; 7] = [
+            self.observer.clone(),
+            self.verifier.clone(),
+            self.narrator.clone(),
+            self.planner.clone(),
+            self.critic.clone(),
+            self.guardrail.clone(),
+            self.watchdog.clone(),
+        ];
+        agents
+            .into_iter()
+            .find(|agent| agent.name().eq_ignore_ascii_case(name))
+            .or_else(|| declarative::find_user_agent(name).map(|agent| agent as Arc<dyn Agent>))
+    }
+
+    /// Record an escalation raised by `agent_name` and notify the user
+    fn escalate(&self, context: &AgentContext, agent_name: &str, reason: &str) {
+        let time_stuck_secs = self
+            .session
+            .lock()
+            .ok()
+            .and_then(|session| session.load().ok())
+            .filter(|state| state.puzzle_started_at > 0)
+            .map(|state| current_timestamp().saturating_sub(state.puzzle_started_at))
+            .unwrap_or(0);
+        let description = format!("{}: {}", agent_name, reason);
+
+        let escalation = match self.long_term.lock() {
+            Ok(ltm) => ltm.create_escalation(
+                &context.puzzle_id,
+                time_stuck_secs,
+                context.hints_revealed,
+                &context.current_url,
+                Some(description.clone()),
+            ),
+            Err(_) => {
+                tracing::warn!("Failed to acquire long-term memory lock for escalation");
+                return;
+            }
+        };
+        match escalation {
+            Ok(escalation) => {
+                tracing::info!("{} escalated ({})", agent_name, escalation.id);
+                crate::core::notifications::push_notification_internal(
+                    "The ghost needs your help".to_string(),
+                    description,
+                    "warning".to_string(),
+                );
+            }
+            Err(e) => tracing::warn!("Failed to record escalation: {}", e),
+        }
+    }
+
+    /// Name of the workflow the pipeline currently runs
+    fn workflow_name(&self) -> &'static str {
+        if self.use_intelligent_mode() {
             "planning"
         } else {
             "sequential"
-        };
+        }
+    }
+
+    /// Re-run a saved pipeline invocation against its recorded LLM outputs
+    ///
+    /// The replay runs in a sandbox orchestrator with throwaway memory, no
+    /// browser tools and an inert operator, so it has no side effects. Its
+    /// transcript is saved with `replay_of` set, for comparison with the original.
+    pub async fn replay_transcript(&self, invocation_id: &str) -> anyhow::Result<Transcript> {
+        let store = self
+            .transcripts
+            .as_ref()
+            .context("Transcripts are not enabled")?;
+        let original = store
+            .get(invocation_id)?
+            .with_context(|| format!("No transcript {}", invocation_id))?;
+        if original.workflow == TOOL_LOOP_WORKFLOW {
+            anyhow::bail!("Tool loop transcripts can't be replayed");
+        }
+
+        let router = Arc::new(SmartAiRouter::replaying(
+            Arc::new(original.cassette()),
+            FixtureMatch::Lenient,
+        ));
+        let memory = MemoryStore::temporary()?;
+        let sandbox = Self::new(
+            router,
+            Arc::new(Mutex::new(LongTermMemory::new(memory.clone()))),
+            Arc::new(Mutex::new(SessionMemory::new(memory))),
+            Arc::new(OperatorAgent::inert()),
+        )?;
+        sandbox.set_agent_mode(original.mode);
+
+        let (_, mut replay) = sandbox.process_recorded(&original.context, None).await;
+        replay.replay_of = Some(original.invocation_id);
+        store.save(&Self::stored(&replay))?;
+        Ok(replay)
+    }
+
+    async fn run_pipeline(
+        &self,
+        invocation_id: &str,
+        context: &AgentContext,
+        mcp_server: Option<&crate::mcp::BrowserMcpServer>,
+    ) -> AgentResult<OrchestrationResult> {
+        let invocation_id = invocation_id.to_string();
+        let start_time = Instant::now();
+        let workflow_name = self.workflow_name();
 
         // ADK: Clear temp-scoped state at start of each invocation
         if let Ok(session) = self.session.lock() {
@@ -359,7 +708,8 @@ impl AgentOrchestrator {
             let override_next_action = override_output.next_action.clone();
             let elapsed_ms = start_time.elapsed().as_millis() as u64;
             let metrics = InvocationMetrics::new(&invocation_id, "Orchestrator")
-                .complete_success(elapsed_ms, override_confidence);
+                .complete_success(elapsed_ms, override_confidence)
+                .with_prompts(prompts::rendered());
             self.metrics.record(metrics);
 
             return Ok(OrchestrationResult {
@@ -396,7 +746,8 @@ impl AgentOrchestrator {
                 tracing::warn!("Guardrail blocked URL: {}", context.current_url);
                 // Record blocked metrics
                 let metrics = InvocationMetrics::new(&invocation_id, "Orchestrator")
-                    .complete_failure(start_time.elapsed().as_millis() as u64, "guardrail_blocked");
+                    .complete_failure(start_time.elapsed().as_millis() as u64, "guardrail_blocked")
+                    .with_prompts(prompts::rendered());
                 self.metrics.record(metrics);
                 return Ok(OrchestrationResult {
                     message: "The ghost senses something... unsettling. Let's move elsewhere."
@@ -413,6 +764,12 @@ impl AgentOrchestrator {
         // Security watchdog (lightweight patterns). Run when guardrails are enabled.
         let mut outputs: Vec<AgentOutput> = Vec::new();
         if self.use_guardrails() {
+            // Top sites are the domains lookalikes most plausibly imitate
+            if let Some(server) = mcp_server {
+                if let Some(top_sites) = crate::integrations::bridge::read_top_sites(server).await {
+                    self.watchdog.set_known_sites(top_site_urls(&top_sites));
+                }
+            }
             if let Ok(watchdog_output) = self.watchdog.process(context).await {
                 outputs.push(watchdog_output.clone());
                 if let Some(next_action) = watchdog_output.next_action.as_ref() {
@@ -420,7 +777,8 @@ impl AgentOrchestrator {
                         NextAction::Abort => {
                             let elapsed_ms = start_time.elapsed().as_millis() as u64;
                             let metrics = InvocationMetrics::new(&invocation_id, "watchdog")
-                                .complete_failure(elapsed_ms, "watchdog_blocked");
+                                .complete_failure(elapsed_ms, "watchdog_blocked")
+                                .with_prompts(prompts::rendered());
                             self.metrics.record(metrics);
                             return Ok(OrchestrationResult {
                                 message: "The ghost senses a security risk and refuses to proceed."
@@ -435,12 +793,23 @@ impl AgentOrchestrator {
                         NextAction::PauseForConfirmation => {
                             let elapsed_ms = start_time.elapsed().as_millis() as u64;
                             let metrics = InvocationMetrics::new(&invocation_id, "watchdog")
-                                .complete_failure(elapsed_ms, "watchdog_confirmation_required");
+                                .complete_failure(elapsed_ms, "watchdog_confirmation_required")
+                                .with_prompts(prompts::rendered());
                             self.metrics.record(metrics);
+                            let message = match watchdog_output
+                                .data
+                                .get("imitates")
+                                .and_then(|v| v.as_str())
+                            {
+                                Some(imitates) => format!(
+                                    "Careful... this looks like {} but isn't. Please review before continuing.",
+                                    imitates
+                                ),
+                                None => "Potential risk detected. Please review before continuing."
+                                    .to_string(),
+                            };
                             return Ok(OrchestrationResult {
-                                message:
-                                    "Potential risk detected. Please review before continuing."
-                                        .to_string(),
+                                message,
                                 proximity: 0.0,
                                 solved: false,
                                 show_hint: None,
@@ -471,23 +840,33 @@ impl AgentOrchestrator {
         let mut planning_context: Option<PlanningContext> = None;
         let mut requires_confirmation = false;
         let mut abort_requested = false;
+        let mut terminated = false;
+        let mut escalation: Option<(String, String)> = None;
+        let mut transfer_depth = 0;
         let mut event_stream = EventStream::new(&invocation_id);
 
-        // Extract results from outputs
-        for output in &outputs {
+        // Extract results from outputs. Workflows stop at an output requesting
+        // a transfer, so the target's output is appended as the pipeline's next step
+        let mut index = 0;
+        while index < outputs.len() {
+            let output = &outputs[index];
+            index += 1;
+
             // Create an ADK-style event for this output
-            let mut actions = EventActions::default();
-            if let Some(delta) = output.data.get("state_delta").and_then(|v| v.as_object()) {
-                for (key, value) in delta {
-                    actions.state_delta.insert(key.clone(), value.clone());
-                }
-            }
-            if let Some(next_action) = &output.next_action {
-                match next_action {
-                    NextAction::Abort => actions.terminate = true,
-                    NextAction::PauseForConfirmation => actions.escalate = true,
-                    _ => {}
-                }
+            let actions = output_actions(output);
+            let transfer = actions
+                .transfer_to_agent
+                .clone()
+                .map(|target| (target, output.agent_name.clone(), output.result.clone()));
+            let terminate = actions.terminate;
+            if actions.escalate && escalation.is_none() {
+                let reason = output
+                    .data
+                    .get("escalation_reason")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| output.result.clone());
+                escalation = Some((output.agent_name.clone(), reason));
             }
             event_stream.push(
                 AgentEvent::text(&output.agent_name, &invocation_id, output.result.clone())
@@ -498,15 +877,21 @@ impl AgentOrchestrator {
             if let Some(tool_call) = output.data.get("tool_call") {
                 if let Some(server) = mcp_server {
                     if let Some(tool_name) = tool_call.get("tool").and_then(|v| v.as_str()) {
-                        if let Some(args) = tool_call.get("arguments") {
-                            tracing::info!("Executing MCP tool: {} {:?}", tool_name, args);
-                            // Fire and forget - tool execution is a side effect
-                            if let Err(e) = self
-                                .invoke_browser_tool(server, tool_name, args.clone())
-                                .await
-                            {
-                                tracing::error!("Failed to execute tool {}: {}", tool_name, e);
-                            }
+                        let call = crate::ai::providers::tools::tool_call(
+                            tool_name.to_string(),
+                            tool_call.get("arguments").cloned().unwrap_or_default(),
+                            None,
+                        );
+                        // Fire and forget - tool execution is a side effect
+                        let result = self
+                            .execute_tool_call(server, &callback_context, &call)
+                            .await;
+                        if !result.success {
+                            tracing::error!(
+                                "Failed to execute tool {}: {}",
+                                call.name,
+                                result.error.unwrap_or_default()
+                            );
                         }
                     }
                 }
@@ -540,6 +925,63 @@ impl AgentOrchestrator {
             if output.agent_name == "Narrator" {
                 message = output.result.clone();
             }
+
+            if terminate {
+                tracing::info!("{} terminated the invocation", output.agent_name);
+                terminated = true;
+                break;
+            }
+
+            // Route control to the agent named by a transfer
+            if let Some((target, from, result)) = transfer {
+                if transfer_depth >= MAX_TRANSFER_DEPTH {
+                    tracing::warn!(
+                        "Ignoring transfer from {} to {}: depth limit {} reached",
+                        from,
+                        target,
+                        MAX_TRANSFER_DEPTH
+                    );
+                    event_stream.push(AgentEvent::system(
+                        &invocation_id,
+                        format!("Transfer to {} refused: depth limit reached", target),
+                    ));
+                    continue;
+                }
+                let Some(agent) = self.agent_by_name(&target) else {
+                    tracing::warn!("{} requested transfer to unknown agent {}", from, target);
+                    continue;
+                };
+
+                transfer_depth += 1;
+                // The target picks up where the pipeline stopped
+                let mut transfer_context = context.clone();
+                transfer_context.previous_outputs.push(result);
+                transfer_context.proximity = proximity;
+                if let Some(planning) = &planning_context {
+                    transfer_context.planning = planning.clone();
+                }
+                if solved {
+                    transfer_context.planning.strategy = SearchStrategy::Celebrate;
+                }
+                transfer_context
+                    .metadata
+                    .insert("transferred_from".to_string(), from.clone());
+                tracing::debug!("Transferring control from {} to {}", from, agent.name());
+                match agent.process(&transfer_context).await {
+                    Ok(transferred) => outputs.push(transferred),
+                    Err(e) => event_stream.push(AgentEvent::error(
+                        agent.name(),
+                        &invocation_id,
+                        e.to_string(),
+                    )),
+                }
+            }
+        }
+        transcript::record_events(event_stream.events());
+
+        // Bubble escalations up to the user
+        if let Some((agent_name, reason)) = escalation {
+            self.escalate(context, &agent_name, &reason);
         }
 
         // Apply any state deltas collected from events
@@ -558,7 +1000,8 @@ impl AgentOrchestrator {
         if abort_requested {
             let elapsed_ms = start_time.elapsed().as_millis() as u64;
             let metrics = InvocationMetrics::new(&invocation_id, workflow_name)
-                .complete_failure(elapsed_ms, "aborted");
+                .complete_failure(elapsed_ms, "aborted")
+                .with_prompts(prompts::rendered());
             self.metrics.record(metrics);
             return Ok(OrchestrationResult {
                 message: "The ghost detected a critical risk and halted the attempt.".to_string(),
@@ -572,7 +1015,8 @@ impl AgentOrchestrator {
         if requires_confirmation {
             let elapsed_ms = start_time.elapsed().as_millis() as u64;
             let metrics = InvocationMetrics::new(&invocation_id, workflow_name)
-                .complete_failure(elapsed_ms, "confirmation_required");
+                .complete_failure(elapsed_ms, "confirmation_required")
+                .with_prompts(prompts::rendered());
             self.metrics.record(metrics);
             return Ok(OrchestrationResult {
                 message: "The ghost needs confirmation before continuing.".to_string(),
@@ -585,7 +1029,8 @@ impl AgentOrchestrator {
         }
 
         // Apply reflection if enabled and we have a narrator message
-        if self.use_reflection() && !message.is_empty() && !solved {
+        let mut candidates = Vec::new();
+        if self.use_reflection() && !message.is_empty() && !solved && !terminated {
             let mut reflection_context = context.clone();
             reflection_context.previous_outputs.push(message.clone());
 
@@ -594,6 +1039,11 @@ impl AgentOrchestrator {
                 self.reflection_workflow.execute(&reflection_context).await
             {
                 if let Some(last) = reflection_outputs.last() {
+                    // Best-of-N candidates are kept for offline tuning
+                    if let Some(recorded) = last.data.get("candidates") {
+                        candidates = serde_json::from_value(recorded.clone()).unwrap_or_default();
+                    }
+
                     // Check if reflection approved the output
                     let approved = last
                         .data
@@ -655,7 +1105,9 @@ impl AgentOrchestrator {
         // ADK: Record invocation metrics
         let elapsed_ms = start_time.elapsed().as_millis() as u64;
         let metrics = InvocationMetrics::new(&invocation_id, workflow_name)
-            .complete_success(elapsed_ms, proximity);
+            .complete_success(elapsed_ms, proximity)
+            .with_prompts(prompts::rendered())
+            .with_candidates(candidates);
         self.metrics.record(metrics);
 
         // ADK: Run after_agent callbacks
@@ -904,6 +1356,30 @@ impl AgentOrchestrator {
         self.planner.revise_plan(context, failed_reason).await
     }
 
+    /// Execute a puzzle plan as a dependency graph, running independent
+    /// sub-goals concurrently and revising the plan when one fails
+    ///
+    /// Each sub-goal is worked through the tool loop with the tools the plan
+    /// named for it (see `PlanWorker`).
+    pub async fn execute_plan(
+        self: &Arc<Self>,
+        context: &AgentContext,
+        mcp_server: &Arc<crate::mcp::BrowserMcpServer>,
+    ) -> AgentResult<PlanExecution> {
+        let invocation_id = generate_invocation_id();
+        let callback_context = CallbackContext::new(context.clone(), "PuzzlePlan", &invocation_id);
+        let worker = PlanWorker {
+            orchestrator: Arc::clone(self),
+            mcp_server: Arc::clone(mcp_server),
+            invocation_id,
+        };
+        let dag = DagWorkflow::new("PuzzlePlan", self.planner.clone(), Arc::new(worker))
+            .with_concurrency(PLAN_MAX_CONCURRENCY)
+            .with_max_revisions(PLAN_MAX_REVISIONS);
+        callbacks::with_model_callbacks(self.callbacks.clone(), callback_context, dag.run(context))
+            .await
+    }
+
     /// Validate narrator dialogue through the critic
     /// Returns feedback on quality and safety
     pub async fn validate_dialogue(
@@ -997,6 +1473,168 @@ impl AgentOrchestrator {
         crate::integrations::bridge::read_browsing_history(mcp_server, limit).await
     }
 
+    /// Execute a structured tool call through MCP
+    /// Runs before/after tool callbacks; a before_tool override skips execution
+    pub async fn execute_tool_call(
+        &self,
+        mcp_server: &crate::mcp::BrowserMcpServer,
+        callback_context: &CallbackContext,
+        call: &ToolCall,
+    ) -> ToolResult {
+        let start = Instant::now();
+        let result = self
+            .invoke_tool_call(mcp_server, callback_context, call)
+            .await;
+        transcript::record_tool_call(call, &result, start.elapsed().as_millis() as u64);
+        result
+    }
+
+    async fn invoke_tool_call(
+        &self,
+        mcp_server: &crate::mcp::BrowserMcpServer,
+        callback_context: &CallbackContext,
+        call: &ToolCall,
+    ) -> ToolResult {
+        if let Some(override_result) = {
+            let registry = self.callbacks.lock().await;
+            registry.run_before_tool(callback_context, call).await
+        } {
+            return override_result;
+        }
+
+        tracing::info!("Executing MCP tool: {} {:?}", call.name, call.arguments);
+        let response = mcp_server
+            .invoke_tool(ToolRequest {
+                tool_name: call.name.clone(),
+                arguments: serde_json::json!(call.arguments),
+                request_id: call.call_id.clone(),
+            })
+            .await;
+        let result = if response.success {
+            ToolResult::success(response.data)
+        } else {
+            ToolResult::error(
+                response
+                    .error
+                    .unwrap_or_else(|| "Unknown error".to_string()),
+            )
+        };
+
+        let override_result = {
+            let registry = self.callbacks.lock().await;
+            registry.run_after_tool(callback_context, call, &result).await
+        };
+        override_result.unwrap_or(result)
+    }
+
+    /// Work toward `goal` with native tool calling over the MCP browser tools
+    ///
+    /// Each step sends the conversation so far; the model either requests tool
+    /// calls (executed via `invoke_tool`, results fed back) or gives a final answer.
+    pub async fn run_tool_loop(
+        &self,
+        context: &AgentContext,
+        mcp_server: &crate::mcp::BrowserMcpServer,
+        goal: &str,
+        max_steps: Option<usize>,
+    ) -> AgentResult<ToolLoopResult> {
+        let invocation_id = generate_invocation_id();
+        let started_at = current_timestamp_millis() as u64;
+        let start_time = Instant::now();
+        let callback_context = CallbackContext::new(context.clone(), "ToolLoop", &invocation_id);
+        let (result, recording) = transcript::record(callbacks::with_model_callbacks(
+            self.callbacks.clone(),
+            callback_context,
+            self.run_tool_steps(
+                &invocation_id,
+                context,
+                mcp_server,
+                mcp_server.discover_tools(None),
+                goal,
+                max_steps,
+            ),
+        ))
+        .await;
+
+        let mut transcript = Transcript::new(
+            &invocation_id,
+            TOOL_LOOP_WORKFLOW,
+            self.agent_mode(),
+            context,
+            started_at,
+            start_time.elapsed().as_millis() as u64,
+            recording,
+        )
+        .with_outcome(&result);
+        transcript.goal = Some(goal.to_string());
+        self.save_transcript(&transcript);
+        result
+    }
+
+    async fn run_tool_steps(
+        &self,
+        invocation_id: &str,
+        context: &AgentContext,
+        mcp_server: &crate::mcp::BrowserMcpServer,
+        tools: Vec<ToolDescriptor>,
+        goal: &str,
+        max_steps: Option<usize>,
+    ) -> AgentResult<ToolLoopResult> {
+        let callback_context = CallbackContext::new(context.clone(), "ToolLoop", invocation_id);
+        let max_steps = max_steps.unwrap_or(TOOL_LOOP_MAX_STEPS);
+
+        let mut turns = vec![ToolTurn::user(goal)];
+        let mut steps = Vec::new();
+
+        for _ in 0..max_steps {
+            let completion = self
+                .ai_router
+                .complete_with_tools(&turns, &tools)
+                .await
+                .map_err(|e| AgentError::ServiceError(e.to_string()))?;
+
+            if completion.is_final() {
+                return Ok(ToolLoopResult {
+                    answer: completion.text,
+                    steps,
+                });
+            }
+
+            turns.push(ToolTurn::Assistant {
+                text: completion.text,
+                tool_calls: completion.tool_calls.clone(),
+            });
+
+            for call in completion.tool_calls {
+                let result = self
+                    .execute_tool_call(mcp_server, &callback_context, &call)
+                    .await;
+                // Tool output goes back to the model, so sanitize it first
+                let content = if result.success {
+                    let text = crate::ai::providers::tools::result_text(&result.result);
+                    serde_json::Value::String(crate::mcp::sanitize_tool_result(&text))
+                } else {
+                    serde_json::json!({ "error": result.error })
+                };
+                turns.push(ToolTurn::ToolResult {
+                    call_id: call.call_id.clone(),
+                    name: call.name.clone(),
+                    content,
+                });
+                steps.push(ToolStep { call, result });
+            }
+        }
+
+        tracing::warn!(
+            "Tool loop stopped after {} steps without a final answer",
+            max_steps
+        );
+        Ok(ToolLoopResult {
+            answer: None,
+            steps,
+        })
+    }
+
     /// Generate LLM-friendly tool description for prompting
     /// Converts MCP tool descriptors into a format suitable for LLM context
     pub fn format_tools_for_llm(&self, tools: &[ToolDescriptor]) -> String {
@@ -1179,3 +1817,45 @@ impl std::fmt::Debug for AgentOrchestrator {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(data: serde_json::Value, next_action: Option<NextAction>) -> AgentOutput {
+        AgentOutput {
+            agent_name: "Observer".to_string(),
+            result: "Looking around".to_string(),
+            confidence: 0.5,
+            next_action,
+            data: serde_json::from_value(data).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_output_actions_from_signals() {
+        let actions = output_actions(&output(
+            serde_json::json!({
+                "transfer_to": "Narrator",
+                "escalate": true,
+                "state_delta": { "temp:seen": 1 }
+            }),
+            None,
+        ));
+        assert_eq!(actions.transfer_to_agent.as_deref(), Some("Narrator"));
+        assert!(actions.escalate);
+        assert!(!actions.terminate);
+        assert_eq!(actions.state_delta["temp:seen"], serde_json::json!(1));
+
+        let actions = output_actions(&output(serde_json::json!({}), Some(NextAction::Abort)));
+        assert!(actions.terminate);
+        assert!(actions.transfer_to_agent.is_none());
+
+        let actions = output_actions(&output(
+            serde_json::json!({ "terminate": "yes" }),
+            Some(NextAction::PauseForConfirmation),
+        ));
+        assert!(!actions.escalate);
+        assert!(!actions.terminate);
+    }
+}
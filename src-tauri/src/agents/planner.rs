@@ -12,10 +12,82 @@ use super::traits::{
     SearchStrategy, SubGoal,
 };
 use crate::ai::ai_provider::SmartAiRouter;
+use crate::ai::prompts::{self, PromptValue};
+use crate::ai::structured::InvalidOutput;
 use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Plan as returned by the model
+#[derive(Debug, Deserialize, JsonSchema)]
+struct PlanResponse {
+    /// 2-5 steps depending on complexity
+    sub_goals: Vec<SubGoalResponse>,
+    primary_keywords: Vec<String>,
+    #[serde(default)]
+    secondary_keywords: Vec<String>,
+    /// 0.0 (trivial) to 1.0 (very hard)
+    #[schemars(range(min = 0.0, max = 1.0))]
+    difficulty: f32,
+    strategy: StrategyResponse,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SubGoalResponse {
+    step: usize,
+    description: String,
+    #[serde(default)]
+    keywords: Vec<String>,
+    /// Steps that must be done first
+    #[serde(default)]
+    depends_on: Vec<usize>,
+    /// Tools the step needs, if any
+    #[serde(default)]
+    tools: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum StrategyResponse {
+    Explore,
+    Focus,
+    Verify,
+    Celebrate,
+}
+
+impl PlanResponse {
+    fn into_planning_context(self, context: &AgentContext) -> PlanningContext {
+        PlanningContext {
+            sub_goals: self
+                .sub_goals
+                .into_iter()
+                .map(|g| SubGoal {
+                    step: g.step,
+                    description: g.description,
+                    keywords: g.keywords,
+                    achieved: false,
+                    confidence: 0.0,
+                    depends_on: g.depends_on,
+                    tools: g.tools,
+                })
+                .collect(),
+            primary_keywords: self.primary_keywords,
+            secondary_keywords: self.secondary_keywords,
+            strategy: match self.strategy {
+                StrategyResponse::Explore => SearchStrategy::Explore,
+                StrategyResponse::Focus => SearchStrategy::Focus,
+                StrategyResponse::Verify => SearchStrategy::Verify,
+                StrategyResponse::Celebrate => SearchStrategy::Celebrate,
+            },
+            difficulty: self.difficulty.clamp(0.0, 1.0),
+            revision_count: context.planning.revision_count,
+            failed_approaches: context.planning.failed_approaches.clone(),
+        }
+    }
+}
+
 /// Planner agent for dynamic puzzle analysis and goal decomposition
 pub struct PlannerAgent {
     ai_router: Arc<SmartAiRouter>,
@@ -28,148 +100,36 @@ impl PlannerAgent {
 
     /// Analyze a puzzle and generate a planning context
     pub async fn analyze_puzzle(&self, context: &AgentContext) -> AgentResult<PlanningContext> {
-        // Build the planning prompt
-        let prompt = format!(
-            r#"You are a strategic puzzle planner. Analyze this puzzle and create a search plan.
-
-PUZZLE CLUE: "{}"
-TARGET PATTERN: "{}"
-CURRENT URL: "{}"
-CURRENT PROXIMITY: {:.0}%
-
-Your task is to decompose this puzzle into actionable steps. Think about:
-1. What is the user ultimately looking for?
-2. What intermediate steps would lead them there?
-3. What keywords should they look for on web pages?
-
-Respond in this EXACT JSON format (no markdown, just raw JSON):
-{{
-    "sub_goals": [
-        {{"step": 1, "description": "Brief action description", "keywords": ["keyword1", "keyword2"]}},
-        {{"step": 2, "description": "Next action", "keywords": ["keyword3"]}}
-    ],
-    "primary_keywords": ["most", "important", "keywords"],
-    "secondary_keywords": ["related", "alternative", "terms"],
-    "difficulty": 0.5,
-    "strategy": "explore"
-}}
-
-For "strategy", use one of: "explore" (far from goal), "focus" (getting closer), "verify" (very close), "celebrate" (solved).
-For "difficulty", use 0.0-1.0 where 0.0 is trivial and 1.0 is very hard.
-Generate 2-5 sub_goals depending on complexity."#,
-            context.puzzle_clue,
-            context.target_pattern,
-            crate::config::privacy::redact_with_settings(&context.current_url),
-            context.proximity * 100.0
-        );
-
-        let response = self
-            .ai_router
-            .generate_text_light(&prompt)
-            .await
-            .map_err(|e| AgentError::ServiceError(format!("Planning failed: {}", e)))?;
-
-        // Parse the JSON response
-        self.parse_planning_response(&response, context)
+        let prompt = prompts::render(
+            "planner.plan",
+            &[
+                ("puzzle_clue", context.puzzle_clue.as_str().into()),
+                ("target_pattern", context.target_pattern.as_str().into()),
+                (
+                    "current_url",
+                    crate::config::privacy::redact_with_settings(&context.current_url).into(),
+                ),
+                ("proximity", PromptValue::percent(context.proximity)),
+            ],
+        )
+        .map_err(AgentError::ConfigError)?;
+
+        let plan = self.request_plan(&prompt, "Planning").await?;
+        Ok(match plan {
+            Some(plan) => plan.into_planning_context(context),
+            None => self.create_fallback_plan(context),
+        })
     }
 
-    /// Parse AI response into PlanningContext
-    fn parse_planning_response(
-        &self,
-        response: &str,
-        context: &AgentContext,
-    ) -> AgentResult<PlanningContext> {
-        // Try to extract JSON from response (handle markdown code blocks)
-        let json_str = response
-            .trim()
-            .trim_start_matches("```json")
-            .trim_start_matches("```")
-            .trim_end_matches("```")
-            .trim();
-
-        match serde_json::from_str::<serde_json::Value>(json_str) {
-            Ok(json) => {
-                // Parse sub_goals
-                let sub_goals = json
-                    .get("sub_goals")
-                    .and_then(|sg| sg.as_array())
-                    .map(|arr| {
-                        arr.iter()
-                            .filter_map(|g| {
-                                Some(SubGoal {
-                                    step: g.get("step")?.as_u64()? as usize,
-                                    description: g.get("description")?.as_str()?.to_string(),
-                                    keywords: g
-                                        .get("keywords")
-                                        .and_then(|k| k.as_array())
-                                        .map(|a| {
-                                            a.iter()
-                                                .filter_map(|v| v.as_str().map(String::from))
-                                                .collect()
-                                        })
-                                        .unwrap_or_default(),
-                                    achieved: false,
-                                    confidence: 0.0,
-                                })
-                            })
-                            .collect()
-                    })
-                    .unwrap_or_default();
-
-                // Parse keywords
-                let primary_keywords = json
-                    .get("primary_keywords")
-                    .and_then(|k| k.as_array())
-                    .map(|a| {
-                        a.iter()
-                            .filter_map(|v| v.as_str().map(String::from))
-                            .collect()
-                    })
-                    .unwrap_or_default();
-
-                let secondary_keywords = json
-                    .get("secondary_keywords")
-                    .and_then(|k| k.as_array())
-                    .map(|a| {
-                        a.iter()
-                            .filter_map(|v| v.as_str().map(String::from))
-                            .collect()
-                    })
-                    .unwrap_or_default();
-
-                // Parse strategy
-                let strategy = match json
-                    .get("strategy")
-                    .and_then(|s| s.as_str())
-                    .unwrap_or("explore")
-                {
-                    "focus" => SearchStrategy::Focus,
-                    "verify" => SearchStrategy::Verify,
-                    "celebrate" => SearchStrategy::Celebrate,
-                    _ => SearchStrategy::Explore,
-                };
-
-                // Parse difficulty
-                let difficulty = json
-                    .get("difficulty")
-                    .and_then(|d| d.as_f64())
-                    .unwrap_or(0.5) as f32;
-
-                Ok(PlanningContext {
-                    sub_goals,
-                    primary_keywords,
-                    secondary_keywords,
-                    strategy,
-                    difficulty,
-                    revision_count: context.planning.revision_count,
-                    failed_approaches: context.planning.failed_approaches.clone(),
-                })
-            }
-            Err(e) => {
+    /// Ask the model for a plan; `None` if it never produced a valid one
+    async fn request_plan(&self, prompt: &str, task: &str) -> AgentResult<Option<PlanResponse>> {
+        match self.ai_router.generate_structured_light(prompt).await {
+            Ok(plan) => Ok(Some(plan)),
+            Err(e) if e.is::<InvalidOutput>() => {
                 tracing::warn!("Failed to parse planning response: {}. Using fallback.", e);
-                // Fallback: extract keywords from puzzle clue
-                Ok(self.create_fallback_plan(context))
+                Ok(None)
             }
+            Err(e) => Err(AgentError::ServiceError(format!("{} failed: {}", task, e))),
         }
     }
 
@@ -203,6 +163,8 @@ Generate 2-5 sub_goals depending on complexity."#,
                 keywords: keywords.clone(),
                 achieved: false,
                 confidence: 0.0,
+                depends_on: Vec::new(),
+                tools: Vec::new(),
             }],
             primary_keywords: keywords.clone(),
             secondary_keywords: Vec::new(),
@@ -225,35 +187,22 @@ Generate 2-5 sub_goals depending on complexity."#,
             .failed_approaches
             .push(failed_reason.to_string());
 
-        let prompt = format!(
-            r#"You are revising a puzzle search plan because the previous approach failed.
-
-PUZZLE CLUE: "{}"
-PREVIOUS FAILED APPROACHES:
-{}
-
-What went wrong and what alternative approach should we try?
-
-Respond in the same JSON format as before, but with NEW keywords and sub_goals that avoid the failed approaches.
-Generate a revised plan that takes a different angle on the puzzle."#,
-            context.puzzle_clue,
-            new_context
-                .planning
-                .failed_approaches
-                .iter()
-                .enumerate()
-                .map(|(i, f)| format!("{}. {}", i + 1, f))
-                .collect::<Vec<_>>()
-                .join("\n")
-        );
-
-        let response = self
-            .ai_router
-            .generate_text_light(&prompt)
-            .await
-            .map_err(|e| AgentError::ServiceError(format!("Plan revision failed: {}", e)))?;
-
-        let mut plan = self.parse_planning_response(&response, &new_context)?;
+        let prompt = prompts::render(
+            "planner.revise",
+            &[
+                ("puzzle_clue", context.puzzle_clue.as_str().into()),
+                (
+                    "failed_approaches",
+                    new_context.planning.failed_approaches.clone().into(),
+                ),
+            ],
+        )
+        .map_err(AgentError::ConfigError)?;
+
+        let mut plan = match self.request_plan(&prompt, "Plan revision").await? {
+            Some(plan) => plan.into_planning_context(&new_context),
+            None => self.create_fallback_plan(&new_context),
+        };
         plan.revision_count = context.planning.revision_count + 1;
         plan.failed_approaches = new_context.planning.failed_approaches;
 
@@ -2,6 +2,7 @@
 //! Core abstractions for the multi-agent system
 
 use async_trait::async_trait;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::atomic::Ordering;
@@ -64,6 +65,12 @@ pub struct SubGoal {
     pub achieved: bool,
     /// Confidence that this step is on the right track
     pub confidence: f32,
+    /// Steps that must be achieved before this one can start
+    #[serde(default)]
+    pub depends_on: Vec<usize>,
+    /// Tools this step needs
+    #[serde(default)]
+    pub tools: Vec<String>,
 }
 
 /// Search strategy determined by the planner
@@ -151,7 +158,7 @@ pub struct PlanningContext {
 }
 
 /// Reflection feedback from CriticAgent
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ReflectionFeedback {
     /// Whether the output passed validation
     pub approved: bool,
@@ -162,8 +169,10 @@ pub struct ReflectionFeedback {
     /// Suggestions for improvement
     pub suggestions: Vec<String>,
     /// Safety score (0.0 = unsafe, 1.0 = safe)
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub safety_score: f32,
     /// Quality score (0.0 = poor, 1.0 = excellent)
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub quality_score: f32,
 }
 
@@ -180,8 +189,24 @@ impl Default for ReflectionFeedback {
     }
 }
 
+/// Which of two candidates a pairwise judgment preferred
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum PairwiseWinner {
+    A,
+    B,
+    Tie,
+}
+
+/// Pairwise comparison of two candidate outputs by CriticAgent
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PairwiseJudgment {
+    pub winner: PairwiseWinner,
+    /// Why the winner was preferred
+    pub reason: String,
+}
+
 /// Context passed to agents during execution
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AgentContext {
     /// Current URL being viewed
     pub current_url: String,
@@ -232,6 +257,13 @@ pub struct AgentOutput {
     pub next_action: Option<NextAction>,
 }
 
+impl AgentOutput {
+    /// Agent this output hands control to (`transfer_to` in its data)
+    pub fn transfer_target(&self) -> Option<&str> {
+        self.data.get("transfer_to").and_then(|v| v.as_str())
+    }
+}
+
 /// Suggested next action from an agent
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NextAction {
@@ -0,0 +1,396 @@
+//! Invocation Transcripts - persisted, replayable agent runs
+//!
+//! Every orchestrator invocation is saved with its input context, ADK events,
+//! state deltas, tool calls and LLM exchanges (in cassette form, see
+//! `ai::providers::fixture`). A transcript can be exported, or replayed against
+//! its recorded LLM outputs to reproduce a run exactly.
+
+use super::callbacks::{ToolCall, ToolResult};
+use super::events::{AgentEvent, EventStream};
+use super::traits::{AgentContext, AgentMode};
+use crate::ai::providers::fixture::{self, Cassette, FixtureEntry};
+use crate::config::privacy;
+use crate::memory::MemoryStore;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+
+const TRANSCRIPT_TREE: &str = "transcripts";
+const TRANSCRIPT_INDEX_TREE: &str = "transcript_index";
+
+/// Transcripts kept before the oldest are deleted
+pub const MAX_TRANSCRIPTS: usize = 200;
+
+tokio::task_local! {
+    static RECORDING: RefCell<Recording>;
+}
+
+/// A tool call made during an invocation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedToolCall {
+    pub call: ToolCall,
+    pub result: ToolResult,
+    pub duration_ms: u64,
+}
+
+/// What an invocation did, collected by `record`
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    pub events: Vec<AgentEvent>,
+    pub tool_calls: Vec<RecordedToolCall>,
+    pub llm_calls: Vec<FixtureEntry>,
+}
+
+/// Run `future`, collecting its events, tool calls and LLM exchanges
+pub async fn record<F: Future>(future: F) -> (F::Output, Recording) {
+    RECORDING
+        .scope(RefCell::new(Recording::default()), async {
+            let (output, llm_calls) = fixture::capture(future).await;
+            let mut recording = RECORDING.with(|recording| recording.take());
+            recording.llm_calls = llm_calls;
+            (output, recording)
+        })
+        .await
+}
+
+/// Add events to the enclosing recording, if any
+pub fn record_events(events: &[AgentEvent]) {
+    let _ = RECORDING.try_with(|recording| {
+        recording.borrow_mut().events.extend_from_slice(events);
+    });
+}
+
+/// Add a tool call to the enclosing recording, if any
+pub fn record_tool_call(call: &ToolCall, result: &ToolResult, duration_ms: u64) {
+    let _ = RECORDING.try_with(|recording| {
+        recording.borrow_mut().tool_calls.push(RecordedToolCall {
+            call: call.clone(),
+            result: result.clone(),
+            duration_ms,
+        });
+    });
+}
+
+/// A persisted invocation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    pub invocation_id: String,
+    /// "planning", "sequential" or "tool_loop"
+    pub workflow: String,
+    pub mode: AgentMode,
+    /// Unix timestamp (milliseconds)
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub context: AgentContext,
+    /// Goal of a tool loop
+    #[serde(default)]
+    pub goal: Option<String>,
+    pub events: Vec<AgentEvent>,
+    pub state_delta: HashMap<String, serde_json::Value>,
+    pub tool_calls: Vec<RecordedToolCall>,
+    pub llm_calls: Vec<FixtureEntry>,
+    /// Serialized result of a successful invocation
+    #[serde(default)]
+    pub output: Option<serde_json::Value>,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Invocation this transcript is a replay of
+    #[serde(default)]
+    pub replay_of: Option<String>,
+}
+
+/// Listing entry for a transcript
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptInfo {
+    pub invocation_id: String,
+    pub workflow: String,
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub success: bool,
+    pub event_count: usize,
+    pub tool_call_count: usize,
+    pub llm_call_count: usize,
+    #[serde(default)]
+    pub replay_of: Option<String>,
+}
+
+/// Export format for `TranscriptStore::export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// The whole transcript as pretty-printed JSON
+    Json,
+    /// LLM exchanges as a JSONL cassette, usable with `[fixtures] mode = "replay"`
+    Cassette,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "cassette" | "jsonl" => Ok(ExportFormat::Cassette),
+            _ => Err(format!("Unknown export format: {}", s)),
+        }
+    }
+}
+
+/// Redact PII from every string in `value`
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) => *text = privacy::redact_pii(text),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+impl Transcript {
+    /// Build a transcript from a finished invocation
+    pub fn new(
+        invocation_id: &str,
+        workflow: &str,
+        mode: AgentMode,
+        context: &AgentContext,
+        started_at: u64,
+        duration_ms: u64,
+        recording: Recording,
+    ) -> Self {
+        let mut stream = EventStream::new(invocation_id);
+        for event in &recording.events {
+            stream.push(event.clone());
+        }
+        Self {
+            invocation_id: invocation_id.to_string(),
+            workflow: workflow.to_string(),
+            mode,
+            started_at,
+            duration_ms,
+            context: context.clone(),
+            goal: None,
+            state_delta: stream.collect_state_deltas(),
+            events: recording.events,
+            tool_calls: recording.tool_calls,
+            llm_calls: recording.llm_calls,
+            output: None,
+            error: None,
+            replay_of: None,
+        }
+    }
+
+    /// Record how the invocation ended
+    pub fn with_outcome<T: Serialize, E: std::fmt::Display>(
+        mut self,
+        result: &std::result::Result<T, E>,
+    ) -> Self {
+        match result {
+            Ok(output) => self.output = serde_json::to_value(output).ok(),
+            Err(e) => self.error = Some(e.to_string()),
+        }
+        self
+    }
+
+    /// Redact PII from the page context, tool calls and LLM exchanges
+    ///
+    /// Request keys stay hashes of the original prompts, so a run whose
+    /// prompts contained PII no longer replays exactly.
+    pub fn redact(&mut self) {
+        let context = &mut self.context;
+        context.current_url = privacy::redact_pii(&context.current_url);
+        context.current_title = privacy::redact_pii(&context.current_title);
+        context.page_content = privacy::redact_pii(&context.page_content);
+
+        for recorded in &mut self.tool_calls {
+            recorded.call.arguments.values_mut().for_each(redact_json);
+            redact_json(&mut recorded.result.result);
+        }
+        for entry in &mut self.llm_calls {
+            entry.prompt = privacy::redact_pii(&entry.prompt);
+            redact_json(&mut entry.response);
+            if let Some(request) = &mut entry.request {
+                redact_json(request);
+            }
+        }
+    }
+
+    /// Recorded LLM exchanges as a replay cassette
+    pub fn cassette(&self) -> Cassette {
+        Cassette::from_entries(self.llm_calls.clone())
+    }
+
+    pub fn info(&self) -> TranscriptInfo {
+        TranscriptInfo {
+            invocation_id: self.invocation_id.clone(),
+            workflow: self.workflow.clone(),
+            started_at: self.started_at,
+            duration_ms: self.duration_ms,
+            success: self.error.is_none(),
+            event_count: self.events.len(),
+            tool_call_count: self.tool_calls.len(),
+            llm_call_count: self.llm_calls.len(),
+            replay_of: self.replay_of.clone(),
+        }
+    }
+}
+
+/// Transcript storage
+pub struct TranscriptStore {
+    store: MemoryStore,
+}
+
+impl TranscriptStore {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+
+    /// Persist a transcript, deleting the oldest beyond `MAX_TRANSCRIPTS`
+    pub fn save(&self, transcript: &Transcript) -> Result<()> {
+        self.store
+            .set(TRANSCRIPT_TREE, &transcript.invocation_id, transcript)?;
+        self.store.set(
+            TRANSCRIPT_INDEX_TREE,
+            &transcript.invocation_id,
+            &transcript.info(),
+        )?;
+
+        for stale in self.list()?.iter().skip(MAX_TRANSCRIPTS) {
+            self.delete(&stale.invocation_id)?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, invocation_id: &str) -> Result<Option<Transcript>> {
+        self.store.get(TRANSCRIPT_TREE, invocation_id)
+    }
+
+    pub fn delete(&self, invocation_id: &str) -> Result<()> {
+        self.store.delete(TRANSCRIPT_TREE, invocation_id)?;
+        self.store.delete(TRANSCRIPT_INDEX_TREE, invocation_id)
+    }
+
+    /// All transcripts, most recent first
+    pub fn list(&self) -> Result<Vec<TranscriptInfo>> {
+        let mut transcripts: Vec<TranscriptInfo> = self.store.get_all(TRANSCRIPT_INDEX_TREE)?;
+        transcripts.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        Ok(transcripts)
+    }
+
+    /// A transcript rendered for sharing
+    pub fn export(&self, invocation_id: &str, format: ExportFormat) -> Result<String> {
+        let transcript = self
+            .get(invocation_id)?
+            .with_context(|| format!("No transcript {}", invocation_id))?;
+        match format {
+            ExportFormat::Json => Ok(serde_json::to_string_pretty(&transcript)?),
+            ExportFormat::Cassette => {
+                let mut lines = Vec::with_capacity(transcript.llm_calls.len());
+                for entry in &transcript.llm_calls {
+                    lines.push(serde_json::to_string(entry)?);
+                }
+                Ok(lines.join("\n"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::events::EventActions;
+    use tempfile::tempdir;
+
+    fn transcript(id: &str, started_at: u64) -> Transcript {
+        let recording = Recording {
+            events: vec![AgentEvent::text("Observer", id, "Looking")
+                .with_actions(EventActions::with_state("proximity", 0.4))],
+            ..Default::default()
+        };
+        Transcript::new(
+            id,
+            "sequential",
+            AgentMode::Standard,
+            &AgentContext::default(),
+            started_at,
+            12,
+            recording,
+        )
+    }
+
+    #[test]
+    fn test_store_round_trip_and_export() {
+        let dir = tempdir().unwrap();
+        let store = TranscriptStore::new(MemoryStore::open(dir.path().join("test.db")).unwrap());
+
+        store.save(&transcript("inv_old", 1)).unwrap();
+        let saved = transcript("inv_new", 2).with_outcome::<_, String>(&Ok("done"));
+        store.save(&saved).unwrap();
+
+        let list = store.list().unwrap();
+        assert_eq!(list[0].invocation_id, "inv_new");
+        assert!(list[0].success);
+        assert_eq!(list[0].event_count, 1);
+
+        let loaded = store.get("inv_new").unwrap().unwrap();
+        assert_eq!(loaded.state_delta["proximity"], serde_json::json!(0.4));
+        assert_eq!(loaded.output, Some(serde_json::json!("done")));
+
+        let format: ExportFormat = "JSON".parse().unwrap();
+        let json = store.export("inv_new", format).unwrap();
+        assert!("yaml".parse::<ExportFormat>().is_err());
+        assert!(json.contains("\"invocation_id\": \"inv_new\""));
+        assert!(store.export("missing", ExportFormat::Json).is_err());
+
+        store.delete("inv_old").unwrap();
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_record_collects_events_and_tool_calls() {
+        let (value, recording) = record(async {
+            record_events(&[AgentEvent::system("inv_1", "start")]);
+            let call = ToolCall {
+                name: "browser.get_content".to_string(),
+                arguments: HashMap::new(),
+                call_id: "call_1".to_string(),
+            };
+            record_tool_call(&call, &ToolResult::success(serde_json::json!("page")), 5);
+            42
+        })
+        .await;
+
+        assert_eq!(value, 42);
+        assert_eq!(recording.events.len(), 1);
+        assert_eq!(recording.tool_calls[0].call.name, "browser.get_content");
+        assert!(recording.llm_calls.is_empty());
+
+        // Outside `record` nothing is collected
+        record_events(&[AgentEvent::system("inv_2", "ignored")]);
+    }
+
+    #[test]
+    fn test_redact_covers_context_and_exchanges() {
+        let mut transcript = transcript("inv_pii", 1);
+        transcript.context.current_url = "https://mail.test/?to=ada@example.com".to_string();
+        transcript.context.page_content = "Call 555-123-4567".to_string();
+        transcript.llm_calls.push(FixtureEntry {
+            key: "0123456789abcdef".to_string(),
+            prompt_key: "fedcba9876543210".to_string(),
+            kind: "complete".to_string(),
+            provider: "openai".to_string(),
+            prompt: "Write to ada@example.com".to_string(),
+            response: serde_json::json!("Sent to ada@example.com"),
+            error: None,
+            request: Some(serde_json::json!({"prompt": "Write to ada@example.com"})),
+            duration_ms: None,
+        });
+
+        transcript.redact();
+        let json = serde_json::to_string(&transcript).unwrap();
+        assert!(!json.contains("ada@example.com"));
+        assert!(!json.contains("555-123-4567"));
+        assert_eq!(transcript.llm_calls[0].key, "0123456789abcdef");
+    }
+}
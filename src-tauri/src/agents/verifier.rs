@@ -162,6 +162,8 @@ impl Agent for VerifierAgent {
         );
 
         if url_matches {
+            // The Narrator takes over to celebrate
+            data.insert("transfer_to".to_string(), "Narrator".into());
             return Ok(AgentOutput {
                 agent_name: self.name().to_string(),
                 result: "PUZZLE SOLVED! Pattern matched!".to_string(),
@@ -199,6 +201,7 @@ impl Agent for VerifierAgent {
                             }
                         });
                         data.insert("tool_call".to_string(), tool_call);
+                        data.insert("transfer_to".to_string(), "Narrator".into());
 
                         return Ok(AgentOutput {
                             agent_name: self.name().to_string(),
@@ -280,4 +283,21 @@ mod tests {
             .validate_url("https://example.com", "(turing|bletchley)")
             .unwrap());
     }
+
+    #[tokio::test]
+    async fn test_solve_transfers_to_narrator() {
+        let verifier = VerifierAgent::new();
+        let mut context = AgentContext {
+            current_url: "https://example.com".to_string(),
+            target_pattern: "(turing|bletchley)".to_string(),
+            ..Default::default()
+        };
+        let output = verifier.process(&context).await.unwrap();
+        assert_eq!(output.transfer_target(), None);
+
+        context.current_url = "https://en.wikipedia.org/wiki/Alan_Turing".to_string();
+        let output = verifier.process(&context).await.unwrap();
+        assert!(matches!(output.next_action, Some(NextAction::PuzzleSolved)));
+        assert_eq!(output.transfer_target(), Some("Narrator"));
+    }
 }
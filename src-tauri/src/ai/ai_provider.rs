@@ -1,27 +1,55 @@
 //! AI Provider abstraction and intelligent routing
-//! Provides a unified interface for Gemini (cloud) and Ollama (local) LLMs
+//! Provides a unified interface over every configured LLM backend
 //!
 //! ## Routing Strategy
 //!
-//! The SmartAiRouter implements intelligent routing with these priorities:
+//! The SmartAiRouter dispatches every call to an ordered chain of
+//! `Arc<dyn Provider>` built from `[core]` in config.toml
+//! (`default_provider`/`default_model` followed by `fallback_providers`):
 //!
-//! 1. **Cost Optimization**: When both providers are available, prefer Ollama for
-//!    lightweight tasks (dialogue, similarity) to reduce API costs
-//! 2. **Quality Optimization**: Prefer Gemini for complex tasks (puzzle generation,
-//!    image analysis) that benefit from more powerful models
-//! 3. **Availability**: Automatic fallback when primary provider fails
-//! 4. **Circuit Breaker**: Track failures with time-based recovery to prevent
-//!    hammering failing services
-
-use crate::agents::traits::{AgentError, RateLimiter};
+//! 1. **Cost Optimization**: For lightweight tasks (dialogue, similarity) local
+//!    providers (Ollama) are tried first to reduce API costs
+//! 2. **Quality Optimization**: Complex tasks (puzzle generation, image analysis)
+//!    follow the configured chain order
+//! 3. **Availability**: Automatic fallback along the chain when a provider fails
+//! 4. **Circuit Breaker**: Each provider+model has an error-rate breaker (see
+//!    `circuit_breaker`) with half-open probes and exponential cool-down, so
+//!    failing services aren't hammered
+//! 5. **Spend Budgets**: Remote providers whose `[usage.budgets]` limit is used
+//!    up are skipped, so requests fall back to local models; a chain without
+//!    one falls back to the shared Ollama client
+//! 6. **Scheduling**: Calls are admitted by a priority-aware token bucket with
+//!    per-provider concurrency caps (see `scheduler`)
+
+use crate::agents::callbacks::{self, LlmRequest};
+use crate::agents::traits::AgentError;
+use crate::ai::circuit_breaker::{BreakerConfig, BreakerRegistry, BreakerSnapshot, CircuitBreaker};
 use crate::ai::gemini_client::{
     ActivityContext, AdaptivePuzzle, DynamicPuzzle, GeminiClient, VerificationResult,
 };
 use crate::ai::ollama_client::OllamaClient;
-use anyhow::Result;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::ai::prompts;
+use crate::ai::providers::discovery;
+use crate::ai::providers::fixture::{self, Cassette, FixtureMatch, FixtureMode, FixtureProvider};
+use crate::ai::providers::{chat, streaming};
+use crate::ai::providers::{
+    ChatMessage, CompletionOptions, CompletionStream, Provider, ProviderError, ProviderFactory,
+    ProviderInfo, ProviderKind, ToolCompletion, ToolTurn,
+};
+use crate::ai::scheduler::{self, RequestScheduler, SlotPermit};
+use crate::ai::structured;
+use crate::ai::usage;
+use crate::config::toml_config::{CoreConfig, FixtureConfig, ProviderEntry, SchedulerConfig};
+use crate::mcp::types::ToolDescriptor;
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio_util::sync::CancellationToken;
 
 /// Default rate limit: 60 calls per minute (1 per second on average)
 const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 60;
@@ -31,6 +59,9 @@ const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 60;
 pub enum ProviderType {
     Gemini,
     Ollama,
+    OpenAI,
+    Anthropic,
+    Custom,
     None,
 }
 
@@ -39,68 +70,358 @@ impl std::fmt::Display for ProviderType {
         match self {
             ProviderType::Gemini => write!(f, "Gemini"),
             ProviderType::Ollama => write!(f, "Ollama"),
+            ProviderType::OpenAI => write!(f, "OpenAI"),
+            ProviderType::Anthropic => write!(f, "Anthropic"),
+            ProviderType::Custom => write!(f, "Custom"),
             ProviderType::None => write!(f, "None"),
         }
     }
 }
 
-/// Circuit breaker recovery time (30 seconds)
-const CIRCUIT_BREAKER_RECOVERY_SECS: u64 = 30;
+impl From<ProviderKind> for ProviderType {
+    fn from(kind: ProviderKind) -> Self {
+        match kind {
+            ProviderKind::Gemini => ProviderType::Gemini,
+            ProviderKind::Ollama => ProviderType::Ollama,
+            ProviderKind::OpenAI => ProviderType::OpenAI,
+            ProviderKind::Anthropic => ProviderType::Anthropic,
+            ProviderKind::Custom => ProviderType::Custom,
+        }
+    }
+}
+
+/// Default sampling for general text generation
+const TEXT_TEMPERATURE: f64 = 0.7;
+const TEXT_MAX_TOKENS: usize = 500;
+
+/// Sampling for tool-calling turns (low temperature for reliable arguments)
+const TOOL_TEMPERATURE: f64 = 0.2;
+const TOOL_MAX_TOKENS: usize = 1024;
+
+/// Sampling for structured (JSON) output
+const STRUCTURED_TEMPERATURE: f64 = 0.4;
+const STRUCTURED_MAX_TOKENS: usize = 1024;
+
+/// Context window assumed when the active provider doesn't report one
+const DEFAULT_CONTEXT_WINDOW: usize = 8192;
+
+tokio::task_local! {
+    static TEMPERATURE_OFFSET: f64;
+}
+
+/// Run `future` with every sampling temperature shifted by `offset`
+///
+/// Used to spread best-of-N candidates across temperatures; the result is
+/// clamped to 0.0-2.0.
+pub async fn with_temperature_offset<F: Future>(offset: f64, future: F) -> F::Output {
+    TEMPERATURE_OFFSET.scope(offset, future).await
+}
+
+/// How the router orders the provider chain for a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Route {
+    /// Configured chain order (quality first)
+    Quality,
+    /// Local providers first, then the configured chain
+    Light,
+    /// Configured chain order, vision-capable providers only
+    Vision,
+    /// Configured chain order, providers with native tool calling only
+    Tools,
+}
+
+/// A model request as the before-model checks see it
+struct ModelCheck<'a> {
+    task: &'a str,
+    prompt: &'a str,
+    options: &'a CompletionOptions,
+}
+
+/// How a dispatched call was answered
+enum Dispatched<T> {
+    /// A provider answered; its concurrency permit is still held
+    Answered(T, SlotPermit),
+    /// A model callback answered in the provider's place
+    Replaced(String),
+}
+
+/// Call results a model callback's replacement reply can stand in for
+trait FromReply {
+    fn from_reply(reply: String) -> Self;
+}
+
+impl FromReply for String {
+    fn from_reply(reply: String) -> Self {
+        reply
+    }
+}
+
+impl FromReply for ToolCompletion {
+    fn from_reply(reply: String) -> Self {
+        ToolCompletion {
+            text: Some(reply),
+            tool_calls: Vec::new(),
+        }
+    }
+}
+
+/// A provider of the configured chain
+pub struct ChainEntry {
+    /// Configured provider name (e.g. `openai`, `custom`, `lmstudio:1234`);
+    /// usage and budgets are accounted under it
+    pub id: String,
+    pub kind: ProviderKind,
+    /// Runs on this machine: no spend budget, local breaker settings and
+    /// concurrency cap, preferred by light tasks
+    pub local: bool,
+    pub provider: Arc<dyn Provider>,
+}
+
+impl ChainEntry {
+    /// Entry identified by its provider kind
+    pub fn new(kind: ProviderKind, provider: Arc<dyn Provider>) -> Self {
+        Self {
+            id: kind.to_string(),
+            kind,
+            local: kind.is_local(),
+            provider,
+        }
+    }
+}
+
+/// A provider in the fallback chain with its own breaker and counters
+struct ProviderSlot {
+    /// Chain entry id, the account for usage and budgets
+    id: String,
+    kind: ProviderKind,
+    local: bool,
+    provider: Arc<dyn Provider>,
+    /// Circuit breaker shared by every user of this provider+model
+    breaker: Arc<CircuitBreaker>,
+    /// LLM call counter (for telemetry/cost tracking)
+    call_count: AtomicU64,
+    /// Fixture providers capture their own exchanges for transcripts
+    captures: bool,
+}
+
+impl ProviderSlot {
+    fn new(entry: ChainEntry, breakers: &BreakerRegistry) -> Self {
+        let ChainEntry {
+            id,
+            kind,
+            local,
+            provider,
+        } = entry;
+        let config = if local {
+            BreakerConfig::local()
+        } else {
+            breaker_config(kind)
+        };
+        let breaker = breakers.get(provider.name(), provider.model(), config);
+        Self {
+            id,
+            kind,
+            local,
+            provider,
+            breaker,
+            call_count: AtomicU64::new(0),
+            captures: false,
+        }
+    }
+
+    /// Slot for an entry whose provider is a fixture provider
+    fn fixture(entry: ChainEntry, breakers: &BreakerRegistry) -> Self {
+        Self {
+            captures: true,
+            ..Self::new(entry, breakers)
+        }
+    }
+
+    /// Chain entry of this slot over `provider`
+    fn entry(&self, provider: Arc<dyn Provider>) -> ChainEntry {
+        ChainEntry {
+            id: self.id.clone(),
+            kind: self.kind,
+            local: self.local,
+            provider,
+        }
+    }
+
+    /// Slot for replaying `cassette`
+    fn replay(cassette: Arc<Cassette>, matching: FixtureMatch, breakers: &BreakerRegistry) -> Self {
+        let entry = ChainEntry {
+            id: "fixture".to_string(),
+            ..ChainEntry::new(
+                ProviderKind::Custom,
+                Arc::new(FixtureProvider::replay(cassette, matching)),
+            )
+        };
+        Self::fixture(entry, breakers)
+    }
+}
+
+/// Breaker settings for a provider kind
+pub fn breaker_config(kind: ProviderKind) -> BreakerConfig {
+    if kind.is_local() {
+        BreakerConfig::local()
+    } else {
+        BreakerConfig::default()
+    }
+}
+
+/// Resolve the API key for a provider kind
+///
+/// Order: system keychain, then provider-specific environment variable, then
+/// `core.api_key` when the provider is the configured default.
+fn resolve_api_key(kind: ProviderKind, core: &CoreConfig) -> Option<String> {
+    if kind == ProviderKind::Gemini {
+        if let Some(key) = crate::core::utils::runtime_config().get_api_key() {
+            if !key.is_empty() {
+                return Some(key);
+            }
+        }
+    }
+
+    if let Ok(key) = crate::config::secrets::get_api_key(&kind.to_string()) {
+        if !key.is_empty() {
+            return Some(key);
+        }
+    }
+
+    let env_var = match kind {
+        ProviderKind::OpenAI => Some("OPENAI_API_KEY"),
+        ProviderKind::Anthropic => Some("ANTHROPIC_API_KEY"),
+        _ => None,
+    };
+    if let Some(key) = env_var.and_then(|var| std::env::var(var).ok()) {
+        if !key.is_empty() {
+            return Some(key);
+        }
+    }
+
+    let is_default = core
+        .default_provider
+        .as_deref()
+        .and_then(ProviderKind::from_str)
+        == Some(kind);
+    if is_default {
+        return core.api_key.clone().filter(|k| !k.is_empty());
+    }
+
+    None
+}
 
-/// Refresh Ollama availability periodically (seconds)
+/// Build the ordered provider chain from `[core]` configuration
 ///
-/// We treat Ollama availability as dynamic: the user might start/stop Ollama
-/// while the desktop app is already running.
-const OLLAMA_STATUS_REFRESH_SECS: u64 = 15;
+/// The shared Gemini and Ollama clients are reused when an entry doesn't pin a
+/// specific model. Entries that can't be constructed (e.g. a missing API key)
+/// are skipped with a log line.
+pub fn build_provider_chain(
+    core: &CoreConfig,
+    gemini: Option<Arc<GeminiClient>>,
+    ollama: Arc<OllamaClient>,
+) -> Vec<ChainEntry> {
+    let mut chain: Vec<ChainEntry> = Vec::new();
+
+    for entry in core.provider_chain() {
+        let id = entry.provider.to_lowercase();
+        // Discovered local servers are referenced by name, e.g. `lmstudio:1234`
+        let discovered = discovery::find_server(&entry.provider);
+        let entry = match &discovered {
+            Some(server) => server.provider_entry(entry.model.as_deref()),
+            None => entry,
+        };
+        let Some(kind) = ProviderKind::from_str(&entry.provider) else {
+            if discovery::is_discovered_name(&entry.provider) {
+                tracing::info!("Skipping {} in chain: server not found", entry.provider);
+            } else {
+                tracing::warn!("Unknown provider in chain: {}", entry.provider);
+            }
+            continue;
+        };
+
+        let provider: Arc<dyn Provider> = match (kind, entry.model.as_deref(), &gemini) {
+            (ProviderKind::Gemini, None, Some(client)) => client.clone() as Arc<dyn Provider>,
+            (ProviderKind::Ollama, None, _) => ollama.clone() as Arc<dyn Provider>,
+            (kind, model, _) => {
+                let api_key = resolve_api_key(kind, core);
+                match ProviderFactory::create(
+                    kind,
+                    api_key.as_deref(),
+                    model,
+                    entry.base_url.as_deref(),
+                ) {
+                    Ok(provider) => provider,
+                    Err(e) => {
+                        tracing::debug!("Skipping provider {} in chain: {}", kind, e);
+                        continue;
+                    }
+                }
+            }
+        };
+
+        // Make the provider visible to get_registered_providers/get_provider_info
+        if crate::ai::providers::get_provider(&kind.to_string()).is_none() {
+            crate::ai::providers::register_provider(&kind.to_string(), provider.clone());
+        }
+
+        tracing::info!(
+            "Provider chain [{}]: {} ({})",
+            chain.len(),
+            id,
+            provider.model()
+        );
+        chain.push(ChainEntry {
+            id,
+            kind,
+            local: kind.is_local() || discovered.is_some(),
+            provider,
+        });
+    }
+
+    chain
+}
 
 /// Smart AI Router that intelligently routes requests between providers
 ///
 /// ## Routing Strategy
 ///
-/// | Task Type        | Primary   | Fallback  |
-/// |------------------|-----------|-----------|
-/// | Dialogue         | Ollama*   | Gemini    |
-/// | URL Similarity   | Ollama*   | Gemini    |
-/// | Text (Light)     | Ollama*   | Gemini    |
-/// | Text (Heavy)     | Gemini    | Ollama    |
-/// | Image Analysis   | Gemini    | Ollama    |
-/// | Puzzle Gen       | Gemini    | Ollama    |
-/// | Verification     | Gemini    | Ollama    |
+/// | Task Type        | Order                              |
+/// |------------------|------------------------------------|
+/// | Dialogue         | Local first, then chain            |
+/// | URL Similarity   | Local first, then chain            |
+/// | Text (Light)     | Local first, then chain            |
+/// | Text (Heavy)     | Chain                              |
+/// | Image Analysis   | Chain (vision-capable only)        |
+/// | Puzzle Gen       | Chain                              |
+/// | Verification     | Chain (vision-capable only)        |
 ///
-/// *When both available; otherwise uses what's available
+/// Remote providers with an open circuit are skipped and retried as a last
+/// resort; local servers with an open circuit are skipped until a health probe
+/// succeeds.
 pub struct SmartAiRouter {
+    /// Ordered provider chain (primary first), rebuilt by `reload_chain`
+    chain: RwLock<Vec<Arc<ProviderSlot>>>,
+    /// Gemini client shared with the vision analyzer
     gemini: Option<Arc<GeminiClient>>,
+    /// Ollama client shared with the vision analyzer and status checks
     ollama: Arc<OllamaClient>,
-    /// Track if Gemini is currently experiencing issues (circuit breaker)
-    gemini_failing: AtomicBool,
-    /// Timestamp when Gemini started failing (for recovery)
-    gemini_fail_time: AtomicU64,
-    /// Track if Ollama was confirmed available
-    ollama_available: AtomicBool,
-    /// Timestamp of last Ollama check
-    ollama_last_check: AtomicU64,
-    /// LLM call counter for Gemini (for telemetry/cost tracking)
-    gemini_call_count: AtomicU64,
-    /// LLM call counter for Ollama (for telemetry)
-    ollama_call_count: AtomicU64,
-    /// Rate limiter to prevent runaway costs
-    rate_limiter: RateLimiter,
+    /// Breaker of the shared Ollama client (also its chain slot's, if any)
+    ollama_breaker: Arc<CircuitBreaker>,
+    /// Slot of the shared Ollama client, used once every remote slot of a
+    /// chain without local providers is over budget
+    budget_fallback: Arc<ProviderSlot>,
+    /// Where this router's breakers live
+    breakers: Arc<BreakerRegistry>,
+    /// Admits calls by priority to prevent runaway costs and starvation
+    scheduler: Arc<RequestScheduler>,
 }
 
 impl SmartAiRouter {
     /// Create a new router with optional Gemini client and Ollama client
+    /// (chain: Gemini, then Ollama)
     pub fn new(gemini: Option<Arc<GeminiClient>>, ollama: Arc<OllamaClient>) -> Self {
-        Self {
-            gemini,
-            ollama,
-            gemini_failing: AtomicBool::new(false),
-            gemini_fail_time: AtomicU64::new(0),
-            ollama_available: AtomicBool::new(false),
-            ollama_last_check: AtomicU64::new(0),
-            gemini_call_count: AtomicU64::new(0),
-            ollama_call_count: AtomicU64::new(0),
-            rate_limiter: RateLimiter::new(DEFAULT_RATE_LIMIT_PER_MINUTE),
-        }
+        Self::with_rate_limit(gemini, ollama, DEFAULT_RATE_LIMIT_PER_MINUTE)
     }
 
     /// Create a new router with a custom rate limit
@@ -109,87 +430,338 @@ impl SmartAiRouter {
         ollama: Arc<OllamaClient>,
         max_calls_per_minute: u32,
     ) -> Self {
+        let mut chain = Vec::new();
+        if let Some(ref client) = gemini {
+            chain.push(ChainEntry::new(ProviderKind::Gemini, client.clone()));
+        }
+        chain.push(ChainEntry::new(ProviderKind::Ollama, ollama.clone()));
+
+        Self::with_providers(chain, gemini, ollama, max_calls_per_minute)
+    }
+
+    /// Create a router whose chain is built from `[core]` configuration
+    ///
+    /// Breakers come from the process-wide registry, so their state is shared
+    /// with the vision analyzer and reported by `/health` and the metrics.
+    pub fn from_config(
+        core: &CoreConfig,
+        gemini: Option<Arc<GeminiClient>>,
+        ollama: Arc<OllamaClient>,
+    ) -> Self {
+        let chain = build_provider_chain(core, gemini.clone(), ollama.clone());
+        Self::with_breakers(
+            chain,
+            gemini,
+            ollama,
+            DEFAULT_RATE_LIMIT_PER_MINUTE,
+            BreakerRegistry::global(),
+        )
+    }
+
+    /// Create a router over an explicit provider chain (with private breakers)
+    pub fn with_providers(
+        chain: Vec<ChainEntry>,
+        gemini: Option<Arc<GeminiClient>>,
+        ollama: Arc<OllamaClient>,
+        max_calls_per_minute: u32,
+    ) -> Self {
+        let breakers = Arc::new(BreakerRegistry::new());
+        Self::with_breakers(chain, gemini, ollama, max_calls_per_minute, breakers)
+    }
+
+    /// Create a router over an explicit provider chain and breaker registry
+    pub fn with_breakers(
+        chain: Vec<ChainEntry>,
+        gemini: Option<Arc<GeminiClient>>,
+        ollama: Arc<OllamaClient>,
+        max_calls_per_minute: u32,
+        breakers: Arc<BreakerRegistry>,
+    ) -> Self {
+        let ollama_breaker = breakers.get(
+            ollama.name(),
+            ollama.model(),
+            breaker_config(ProviderKind::Ollama),
+        );
+        let budget_fallback = Arc::new(ProviderSlot::new(
+            ChainEntry::new(ProviderKind::Ollama, ollama.clone()),
+            &breakers,
+        ));
         Self {
+            chain: RwLock::new(
+                chain
+                    .into_iter()
+                    .map(|entry| Arc::new(ProviderSlot::new(entry, &breakers)))
+                    .collect(),
+            ),
             gemini,
             ollama,
-            gemini_failing: AtomicBool::new(false),
-            gemini_fail_time: AtomicU64::new(0),
-            ollama_available: AtomicBool::new(false),
-            ollama_last_check: AtomicU64::new(0),
-            gemini_call_count: AtomicU64::new(0),
-            ollama_call_count: AtomicU64::new(0),
-            rate_limiter: RateLimiter::new(max_calls_per_minute),
+            ollama_breaker,
+            budget_fallback,
+            breakers,
+            scheduler: Arc::new(RequestScheduler::new(max_calls_per_minute)),
         }
     }
 
-    /// Check rate limit before making an LLM call
-    /// Returns Err(AgentError::RateLimited) if limit exceeded
-    fn check_rate_limit(&self) -> Result<()> {
-        if self.rate_limiter.try_acquire() {
-            Ok(())
+    /// Record or replay provider calls as configured in `[fixtures]`
+    ///
+    /// Record wraps every provider in the chain so its exchanges are appended to
+    /// the cassette. Replay replaces the chain with a single fixture provider that
+    /// never touches the network.
+    pub fn with_fixtures(mut self, config: &FixtureConfig) -> Self {
+        let mode = match FixtureMode::from_str(&config.mode) {
+            Some(FixtureMode::Off) => return self,
+            Some(mode) => mode,
+            None => {
+                tracing::warn!("Unknown fixture mode '{}', ignoring", config.mode);
+                return self;
+            }
+        };
+        let matching = FixtureMatch::from_str(&config.matching).unwrap_or(FixtureMatch::Strict);
+
+        let path = config
+            .cassette
+            .as_ref()
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(fixture::default_cassette_path);
+        let cassette = match Cassette::load(&path) {
+            Ok(cassette) => Arc::new(cassette),
+            Err(e) => {
+                tracing::error!("Failed to load fixture cassette {:?}: {}", path, e);
+                return self;
+            }
+        };
+
+        match mode {
+            FixtureMode::Record => {
+                tracing::info!("Recording provider calls to {:?}", path);
+                let recorders = self
+                    .slots()
+                    .iter()
+                    .map(|slot| {
+                        let recorder =
+                            FixtureProvider::record(slot.provider.clone(), cassette.clone());
+                        let entry = slot.entry(Arc::new(recorder));
+                        Arc::new(ProviderSlot::fixture(entry, &self.breakers))
+                    })
+                    .collect();
+                self.set_chain(recorders);
+            }
+            FixtureMode::Replay => {
+                tracing::info!(
+                    "Replaying {} recorded provider calls from {:?} ({:?})",
+                    cassette.len(),
+                    path,
+                    matching
+                );
+                let replayer = ProviderSlot::replay(cassette, matching, &self.breakers);
+                self.set_chain(vec![Arc::new(replayer)]);
+                // Keep the vision analyzer off the network as well
+                self.gemini = None;
+            }
+            FixtureMode::Off => {}
+        }
+        self
+    }
+
+    /// Router that answers only from `cassette`, for replaying transcripts
+    pub fn replaying(cassette: Arc<Cassette>, matching: FixtureMatch) -> Self {
+        let router = Self::with_providers(
+            Vec::new(),
+            None,
+            Arc::new(OllamaClient::new()),
+            DEFAULT_RATE_LIMIT_PER_MINUTE,
+        );
+        let replayer = ProviderSlot::replay(cassette, matching, &router.breakers);
+        router.set_chain(vec![Arc::new(replayer)]);
+        router
+    }
+
+    /// Router over `entry` alone, sharing this router's breakers and scheduler
+    ///
+    /// API keys are resolved from `core`. A router recording or replaying
+    /// fixtures keeps its chain, as does one whose `entry` can't be built.
+    pub fn pinned(&self, core: &CoreConfig, entry: ProviderEntry) -> Self {
+        let pinned_core = CoreConfig {
+            default_provider: Some(entry.provider),
+            default_model: entry.model,
+            base_url: entry.base_url,
+            fallback_providers: Vec::new(),
+            ..core.clone()
+        };
+        let slots = self.slots();
+        let uses_fixtures = slots.iter().any(|slot| slot.captures);
+        let pinned_chain = if uses_fixtures {
+            Vec::new()
+        } else {
+            build_provider_chain(&pinned_core, self.gemini.clone(), self.ollama.clone())
+        };
+
+        let chain = if pinned_chain.is_empty() {
+            slots
+                .iter()
+                .map(|slot| {
+                    let entry = slot.entry(slot.provider.clone());
+                    Arc::new(ProviderSlot {
+                        captures: slot.captures,
+                        ..ProviderSlot::new(entry, &self.breakers)
+                    })
+                })
+                .collect()
         } else {
-            let wait_time = self.rate_limiter.time_until_available();
-            Err(anyhow::anyhow!(AgentError::RateLimited(format!(
-                "Rate limit exceeded. Try again in {:.1}s",
-                wait_time.as_secs_f32()
-            ))))
+            pinned_chain
+                .into_iter()
+                .map(|entry| Arc::new(ProviderSlot::new(entry, &self.breakers)))
+                .collect()
+        };
+        Self {
+            chain: RwLock::new(chain),
+            gemini: self.gemini.clone(),
+            ollama: self.ollama.clone(),
+            ollama_breaker: self.ollama_breaker.clone(),
+            budget_fallback: self.budget_fallback.clone(),
+            breakers: self.breakers.clone(),
+            scheduler: self.scheduler.clone(),
         }
     }
 
-    /// Get the current LLM call counts for telemetry
-    /// Returns (gemini_calls, ollama_calls)
-    pub fn get_call_counts(&self) -> (u64, u64) {
-        (
-            self.gemini_call_count.load(Ordering::Relaxed),
-            self.ollama_call_count.load(Ordering::Relaxed),
-        )
+    /// Schedule calls as configured in `[scheduler]`
+    ///
+    /// The scheduler is also reported in the Prometheus metrics.
+    pub fn with_scheduler(mut self, config: &SchedulerConfig) -> Self {
+        self.scheduler = Arc::new(RequestScheduler::from_config(config));
+        scheduler::set_active_scheduler(self.scheduler.clone());
+        self
     }
 
-    /// Reset call counters (e.g., at session start)
-    pub fn reset_call_counts(&self) {
-        self.gemini_call_count.store(0, Ordering::Relaxed);
-        self.ollama_call_count.store(0, Ordering::Relaxed);
+    /// Rebuild the chain from `[core]`, e.g. after a discovery scan
+    ///
+    /// Routers recording or replaying fixtures keep their chain. Breaker state
+    /// lives in the registry, so it carries over.
+    pub fn reload_chain(&self, core: &CoreConfig) {
+        if self.slots().iter().any(|slot| slot.captures) {
+            return;
+        }
+        let chain = build_provider_chain(core, self.gemini.clone(), self.ollama.clone())
+            .into_iter()
+            .map(|entry| Arc::new(ProviderSlot::new(entry, &self.breakers)))
+            .collect();
+        self.set_chain(chain);
     }
 
-    /// Increment Gemini call counter
-    fn count_gemini_call(&self) {
-        self.gemini_call_count.fetch_add(1, Ordering::Relaxed);
+    /// Snapshot of the chain; a concurrent reload doesn't affect it
+    fn slots(&self) -> Vec<Arc<ProviderSlot>> {
+        self.chain
+            .read()
+            .map(|chain| chain.clone())
+            .unwrap_or_default()
     }
 
-    /// Increment Ollama call counter
-    fn count_ollama_call(&self) {
-        self.ollama_call_count.fetch_add(1, Ordering::Relaxed);
+    /// Slots to dispatch along: the chain, plus the shared Ollama client once
+    /// every slot is a remote provider over its budget
+    fn dispatch_slots(&self) -> Vec<Arc<ProviderSlot>> {
+        let mut slots = self.slots();
+        let spent = !slots.is_empty()
+            && slots
+                .iter()
+                .all(|slot| !slot.local && usage::is_over_budget(&slot.id));
+        if spent {
+            slots.push(self.budget_fallback.clone());
+        }
+        slots
     }
 
-    /// Get current timestamp in seconds
-    fn now_secs() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0)
+    fn set_chain(&self, chain: Vec<Arc<ProviderSlot>>) {
+        if let Ok(mut current) = self.chain.write() {
+            *current = chain;
+        }
     }
 
-    /// Best-effort refresh of Ollama availability (cheap + rate-limited).
-    async fn refresh_ollama_if_stale(&self) {
-        let now = Self::now_secs();
-        let last = self.ollama_last_check.load(Ordering::SeqCst);
+    /// Wait until the scheduler admits a call at the current task's priority
+    /// Returns Err(AgentError::RateLimited) if the call can't be admitted in time
+    async fn admit(&self) -> Result<()> {
+        self.scheduler
+            .admit(scheduler::current_priority())
+            .await
+            .map_err(|e| anyhow::anyhow!(AgentError::RateLimited(e.to_string())))
+    }
 
-        if now.saturating_sub(last) < OLLAMA_STATUS_REFRESH_SECS {
-            return;
+    /// Get the current LLM call counts for telemetry
+    /// Returns (gemini_calls, ollama_calls)
+    pub fn get_call_counts(&self) -> (u64, u64) {
+        let count = |kind: ProviderKind| -> u64 {
+            self.slots()
+                .iter()
+                .filter(|slot| slot.kind == kind)
+                .map(|slot| slot.call_count.load(Ordering::Relaxed))
+                .sum()
+        };
+        (count(ProviderKind::Gemini), count(ProviderKind::Ollama))
+    }
+
+    /// Get LLM call counts for every provider kind in the chain
+    pub fn provider_call_counts(&self) -> HashMap<String, u64> {
+        let mut counts = HashMap::new();
+        for slot in &self.slots() {
+            *counts.entry(slot.kind.to_string()).or_insert(0) +=
+                slot.call_count.load(Ordering::Relaxed);
         }
+        counts
+    }
 
-        let available = self.ollama.is_available().await;
-        self.ollama_available.store(available, Ordering::SeqCst);
-        self.ollama_last_check.store(now, Ordering::SeqCst);
+    /// Reset call counters (e.g., at session start)
+    pub fn reset_call_counts(&self) {
+        for slot in &self.slots() {
+            slot.call_count.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Probe local servers whose breaker cool-down has elapsed
+    ///
+    /// Local servers come and go while the app runs and their health check is a
+    /// cheap request, so they are probed actively and skipped while open. Remote
+    /// providers are probed by the next real request once half-open.
+    pub async fn probe_health(&self) {
+        let mut probed = Vec::new();
+        let slots = self.slots();
+        let local = slots
+            .iter()
+            .filter(|slot| slot.local)
+            .map(|slot| (&slot.breaker, slot.provider.clone()))
+            .chain(std::iter::once((
+                &self.ollama_breaker,
+                self.ollama.clone() as Arc<dyn Provider>,
+            )));
+
+        for (breaker, provider) in local {
+            if probed.iter().any(|b| Arc::ptr_eq(b, breaker)) || !breaker.probe_due() {
+                continue;
+            }
+            probed.push(breaker.clone());
+            if breaker.allow_request() {
+                Self::record_health(breaker, provider.is_available().await);
+            }
+        }
+    }
+
+    /// Record a health check in a breaker
+    fn record_health(breaker: &CircuitBreaker, healthy: bool) {
+        if healthy {
+            breaker.record_success();
+        } else {
+            breaker.record_failure("health check failed");
+        }
+    }
+
+    /// Breaker states of this router's registry, sorted by key
+    pub fn breaker_snapshots(&self) -> Vec<BreakerSnapshot> {
+        self.breakers.snapshots()
     }
 
     /// Initialize and check provider availability
     pub async fn initialize(&self) {
         // Check Ollama availability
         let ollama_ok = self.ollama.is_available().await;
-        self.ollama_available.store(ollama_ok, Ordering::SeqCst);
-        self.ollama_last_check
-            .store(Self::now_secs(), Ordering::SeqCst);
+        Self::record_health(&self.ollama_breaker, ollama_ok);
 
         if ollama_ok {
             tracing::info!("Ollama server detected and available");
@@ -206,35 +778,78 @@ impl SmartAiRouter {
             tracing::debug!("Ollama server not detected");
         }
 
-        // Log Gemini status
-        if self.gemini.is_some() {
-            tracing::info!("Gemini API key configured - will be preferred for complex tasks");
+        // Log the resolved chain
+        let slots = self.slots();
+        match slots.first() {
+            Some(primary) => tracing::info!(
+                "Primary AI provider: {} ({}), {} fallback(s)",
+                primary.id,
+                primary.provider.model(),
+                slots.len() - 1
+            ),
+            None => tracing::warn!("No AI providers configured"),
+        }
+    }
+
+    /// Whether a slot can currently serve requests (local servers must not
+    /// have an open circuit, remote providers must be within their spend budget)
+    fn is_slot_available(&self, slot: &ProviderSlot) -> bool {
+        if slot.local {
+            !slot.breaker.is_open()
         } else {
-            tracing::info!("No Gemini API key - will use Ollama exclusively");
+            !usage::is_over_budget(&slot.id)
         }
     }
 
+    /// First usable slot in chain order, preferring closed circuits
+    fn active_slot(&self) -> Option<Arc<ProviderSlot>> {
+        let slots = self.dispatch_slots();
+        slots
+            .iter()
+            .find(|slot| self.is_slot_available(slot) && !slot.breaker.is_open())
+            .or_else(|| {
+                // Fall back to a provider even if its circuit is open
+                slots.iter().find(|slot| self.is_slot_available(slot))
+            })
+            .cloned()
+    }
+
     /// Get the current active provider type (for display/status)
     pub fn active_provider(&self) -> ProviderType {
-        if self.gemini.is_some() && !self.is_gemini_circuit_open() {
-            ProviderType::Gemini
-        } else if self.ollama_available.load(Ordering::SeqCst) {
-            ProviderType::Ollama
-        } else if self.gemini.is_some() {
-            ProviderType::Gemini // Fall back to Gemini even if circuit open
-        } else {
-            ProviderType::None
-        }
+        self.active_slot()
+            .map(|slot| ProviderType::from(slot.kind))
+            .unwrap_or(ProviderType::None)
+    }
+
+    /// Get info about the current active provider
+    pub fn active_provider_info(&self) -> Option<ProviderInfo> {
+        self.active_slot().map(|slot| slot.provider.info())
+    }
+
+    /// Context window of the active provider, in tokens
+    pub fn context_window(&self) -> usize {
+        self.active_provider_info()
+            .map(|info| info.context_window)
+            .filter(|window| *window > 0)
+            .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+    }
+
+    /// Get info about every provider in the chain (primary first)
+    pub fn provider_chain_info(&self) -> Vec<ProviderInfo> {
+        self.slots()
+            .iter()
+            .map(|slot| slot.provider.info())
+            .collect()
     }
 
     /// Check if any AI provider is available
     pub fn is_available(&self) -> bool {
-        self.gemini.is_some() || self.ollama_available.load(Ordering::SeqCst)
+        self.slots().iter().any(|slot| self.is_slot_available(slot))
     }
 
-    /// Check if Ollama is available
+    /// Check if Ollama is available (its circuit isn't open)
     pub fn has_ollama(&self) -> bool {
-        self.ollama_available.load(Ordering::SeqCst)
+        !self.ollama_breaker.is_open()
     }
 
     /// Check if Gemini is configured
@@ -242,377 +857,642 @@ impl SmartAiRouter {
         self.gemini.is_some()
     }
 
-    /// Refresh Ollama availability (call periodically if needed)
-    pub async fn refresh_ollama_status(&self) {
-        let available = self.ollama.is_available().await;
-        self.ollama_available.store(available, Ordering::SeqCst);
-        self.ollama_last_check
-            .store(Self::now_secs(), Ordering::SeqCst);
+    /// Check if a provider kind is part of the chain
+    pub fn has_provider(&self, kind: ProviderKind) -> bool {
+        self.slots().iter().any(|slot| slot.kind == kind)
     }
 
-    /// Check if Gemini circuit breaker is open (should skip Gemini)
-    fn is_gemini_circuit_open(&self) -> bool {
-        if !self.gemini_failing.load(Ordering::SeqCst) {
-            return false;
-        }
+    /// Check Ollama now, regardless of its breaker cool-down
+    pub async fn refresh_ollama_status(&self) {
+        Self::record_health(&self.ollama_breaker, self.ollama.is_available().await);
+    }
 
-        // Check if enough time has passed to try again
-        let fail_time = self.gemini_fail_time.load(Ordering::SeqCst);
-        let now = Self::now_secs();
+    /// Check if every provider of a kind in the chain has an open circuit
+    fn is_circuit_open(&self, kind: ProviderKind) -> bool {
+        let slots = self.slots();
+        let mut slots = slots.iter().filter(|slot| slot.kind == kind).peekable();
+        slots.peek().is_some() && slots.all(|slot| slot.breaker.is_open())
+    }
 
-        if now - fail_time > CIRCUIT_BREAKER_RECOVERY_SECS {
-            // Reset circuit breaker - allow retry
-            self.gemini_failing.store(false, Ordering::SeqCst);
-            tracing::info!("Gemini circuit breaker reset - retrying");
-            false
-        } else {
-            true
+    /// Order chain indices for a route
+    fn route_order(chain: &[Arc<ProviderSlot>], route: Route) -> Vec<usize> {
+        let indices = 0..chain.len();
+        match route {
+            Route::Quality => indices.collect(),
+            Route::Vision => indices
+                .filter(|&i| chain[i].provider.supports_vision())
+                .collect(),
+            Route::Tools => indices
+                .filter(|&i| chain[i].provider.supports_tools())
+                .collect(),
+            Route::Light => {
+                let (mut local, remote): (Vec<usize>, Vec<usize>) =
+                    indices.partition(|&i| chain[i].local);
+                local.extend(remote);
+                local
+            }
         }
     }
 
-    /// Mark Gemini as recovering (after successful request)
-    fn mark_gemini_ok(&self) {
-        self.gemini_failing.store(false, Ordering::SeqCst);
-    }
+    /// Call a single provider, updating its breaker and counters
+    /// Returns the provider's concurrency permit alongside the result
+    async fn try_slot<T, F, Fut>(
+        &self,
+        slot: &ProviderSlot,
+        task: &str,
+        call: &F,
+    ) -> std::result::Result<(T, SlotPermit), ProviderError>
+    where
+        F: Fn(Arc<dyn Provider>) -> Fut,
+        Fut: Future<Output = std::result::Result<T, ProviderError>>,
+    {
+        let permit = self
+            .scheduler
+            .acquire_slot(&slot.id, slot.local, scheduler::current_priority())
+            .await;
+        slot.call_count.fetch_add(1, Ordering::Relaxed);
+
+        // Inside a transcript, collect the exchange for replay
+        let provider = if fixture::is_capturing() && !slot.captures {
+            Arc::new(FixtureProvider::capture(slot.provider.clone())) as Arc<dyn Provider>
+        } else {
+            slot.provider.clone()
+        };
 
-    /// Mark Gemini as failing (after error)
-    fn mark_gemini_failing(&self) {
-        self.gemini_failing.store(true, Ordering::SeqCst);
-        self.gemini_fail_time
-            .store(Self::now_secs(), Ordering::SeqCst);
+        match usage::accounted_to(&slot.id, call(provider)).await {
+            Ok(result) => {
+                slot.breaker.record_success();
+                Ok((result, permit))
+            }
+            Err(e) => {
+                tracing::warn!("{} {} failed: {}", slot.id, task, e);
+                slot.breaker.record_failure(&e.to_string());
+                Err(e)
+            }
+        }
     }
 
-    /// Choose provider for "light" tasks where cost matters.
-    /// Returns (primary, has_fallback).
-    fn choose_provider(&self) -> (ProviderType, bool) {
-        let has_gemini = self.gemini.is_some() && !self.is_gemini_circuit_open();
-        let has_ollama = self.ollama_available.load(Ordering::SeqCst);
-
-        match (has_gemini, has_ollama) {
-            // Both available: prefer local for cost
-            (true, true) => (ProviderType::Ollama, true),
-            // Only one available
-            (true, false) => (ProviderType::Gemini, false),
-            (false, true) => (ProviderType::Ollama, false),
-            // Neither available
-            (false, false) => (ProviderType::None, false),
+    /// Run the before-model checks (guardrail policy and callbacks) on a request
+    /// about to be sent to `slot`
+    ///
+    /// Errs when the request is blocked; `Some` is a callback's replacement reply.
+    async fn before_model(
+        &self,
+        slot: &ProviderSlot,
+        check: &ModelCheck<'_>,
+    ) -> Result<Option<String>> {
+        let request = LlmRequest {
+            prompt: check.prompt.to_string(),
+            system_prompt: None,
+            model: slot.provider.model().to_string(),
+            temperature: check.options.temperature.map(|t| t as f32),
+            max_tokens: check.options.max_tokens,
+            params: HashMap::from([
+                ("task".to_string(), serde_json::json!(check.task)),
+                ("provider".to_string(), serde_json::json!(slot.id)),
+            ]),
+        };
+        match callbacks::check_model_request(&request).await {
+            None => Ok(None),
+            Some(response) if response.blocked => {
+                Err(anyhow::anyhow!(AgentError::SafetyViolation(
+                    response
+                        .block_reason
+                        .unwrap_or_else(|| "Model request blocked".to_string())
+                )))
+            }
+            Some(response) => Ok(Some(response.text)),
         }
     }
 
-    // ========================================================================
-    // AI Methods with Fallback Logic
-    // ========================================================================
+    /// Dispatch a call along the provider chain with fallback
+    async fn dispatch<T, F, Fut>(&self, check: &ModelCheck<'_>, route: Route, call: F) -> Result<T>
+    where
+        T: FromReply,
+        F: Fn(Arc<dyn Provider>) -> Fut,
+        Fut: Future<Output = std::result::Result<T, ProviderError>>,
+    {
+        Ok(match self.dispatch_held(check, route, call).await? {
+            Dispatched::Answered(result, _permit) => result,
+            Dispatched::Replaced(reply) => T::from_reply(reply),
+        })
+    }
 
-    /// Analyze an image with AI vision
-    pub async fn analyze_image(&self, base64_image: &str, prompt: &str) -> Result<String> {
-        // Check rate limit first
-        self.check_rate_limit()?;
-        self.refresh_ollama_if_stale().await;
-
-        // Try Gemini first if available and not failing
-        if let Some(ref gemini) = self.gemini {
-            if !self.is_gemini_circuit_open() {
-                match gemini.analyze_image(base64_image, prompt).await {
-                    Ok(result) => {
-                        self.mark_gemini_ok();
-                        self.count_gemini_call();
-                        return Ok(result);
-                    }
-                    Err(e) => {
-                        tracing::warn!("Gemini analyze_image failed, trying Ollama: {}", e);
-                        self.mark_gemini_failing();
-                    }
-                }
+    /// Like `dispatch`, keeping the concurrency permit of the provider that answered
+    ///
+    /// Each provider tried is checked first (see `before_model`), so policy
+    /// rules and callbacks see the model the request actually goes to.
+    async fn dispatch_held<T, F, Fut>(
+        &self,
+        check: &ModelCheck<'_>,
+        route: Route,
+        call: F,
+    ) -> Result<Dispatched<T>>
+    where
+        F: Fn(Arc<dyn Provider>) -> Fut,
+        Fut: Future<Output = std::result::Result<T, ProviderError>>,
+    {
+        // Wait for the scheduler first
+        self.admit().await?;
+        self.probe_health().await;
+
+        let mut last_error: Option<ProviderError> = None;
+        let mut deferred = Vec::new();
+
+        let chain = self.dispatch_slots();
+        for index in Self::route_order(&chain, route) {
+            let slot = &chain[index];
+            if !self.is_slot_available(slot) {
+                continue;
+            }
+            if !slot.breaker.allow_request() {
+                deferred.push(index);
+                continue;
             }
-        }
 
-        // Fallback to Ollama
-        if self.ollama_available.load(Ordering::SeqCst) {
-            self.count_ollama_call();
-            match self.ollama.analyze_image(base64_image, prompt).await {
-                Ok(res) => return Ok(res),
-                Err(e) => {
-                    // unexpected error (likely connection refused or timeout)
-                    // mark unavailable so we don't spam it, checking periodically
-                    tracing::warn!(
-                        "Ollama analyze_image failed: {}. Marking unavailable for cooldown.",
-                        e
-                    );
-                    self.ollama_available.store(false, Ordering::SeqCst);
-                    return Err(e);
-                }
+            if let Some(reply) = self.before_model(slot, check).await? {
+                return Ok(Dispatched::Replaced(reply));
+            }
+            match self.try_slot(slot, check.task, &call).await {
+                Ok((result, permit)) => return Ok(Dispatched::Answered(result, permit)),
+                Err(e) => last_error = Some(e),
             }
         }
 
-        // Last resort: try Gemini even if marked as failing
-        if let Some(ref gemini) = self.gemini {
-            self.count_gemini_call();
-            match gemini.analyze_image(base64_image, prompt).await {
-                Ok(res) => return Ok(res),
-                Err(e) => {
-                    return Err(anyhow::anyhow!(AgentError::CircuitOpen(format!(
-                        "All providers failed. Gemini error: {}",
-                        e
-                    ))))
-                }
+        // Last resort: providers whose circuit is open
+        for index in deferred {
+            let slot = &chain[index];
+            if let Some(reply) = self.before_model(slot, check).await? {
+                return Ok(Dispatched::Replaced(reply));
+            }
+            match self.try_slot(slot, check.task, &call).await {
+                Ok((result, permit)) => return Ok(Dispatched::Answered(result, permit)),
+                Err(e) => last_error = Some(e),
             }
         }
 
-        Err(anyhow::anyhow!(AgentError::CircuitOpen(
-            "No AI provider available".to_string()
-        )))
+        Err(anyhow::anyhow!(AgentError::CircuitOpen(match last_error {
+            Some(e) => format!("All providers failed. Last error: {}", e),
+            None => "No AI provider available".to_string(),
+        })))
     }
 
-    /// Generate text from a prompt (prefers Gemini for quality)
-    /// Use `generate_text_light()` for agent tasks that can use local LLM
-    pub async fn generate_text(&self, prompt: &str) -> Result<String> {
-        // Check rate limit first
-        self.check_rate_limit()?;
-        self.refresh_ollama_if_stale().await;
-
-        // Try Gemini first
-        if let Some(ref gemini) = self.gemini {
-            if !self.is_gemini_circuit_open() {
-                match gemini.generate_text(prompt).await {
-                    Ok(result) => {
-                        self.mark_gemini_ok();
-                        self.count_gemini_call();
-                        return Ok(result);
-                    }
-                    Err(e) => {
-                        tracing::warn!("Gemini generate_text failed, trying Ollama: {}", e);
-                        self.mark_gemini_failing();
-                    }
-                }
+    /// Complete a prompt along the chain with explicit options
+    pub async fn complete_with_options(
+        &self,
+        prompt: &str,
+        options: CompletionOptions,
+    ) -> Result<String> {
+        let check = ModelCheck {
+            task: "complete",
+            prompt,
+            options: &options,
+        };
+        self.dispatch(&check, Route::Quality, |provider| {
+            let options = options.clone();
+            async move { provider.complete_with_options(prompt, options).await }
+        })
+        .await
+    }
+
+    /// Complete a prompt preferring local providers
+    async fn complete_light(
+        &self,
+        task: &str,
+        prompt: &str,
+        options: CompletionOptions,
+    ) -> Result<String> {
+        let check = ModelCheck {
+            task,
+            prompt,
+            options: &options,
+        };
+        self.dispatch(&check, Route::Light, |provider| {
+            let options = options.clone();
+            async move { provider.complete_with_options(prompt, options).await }
+        })
+        .await
+    }
+
+    /// Complete a prompt in chain order
+    async fn complete_quality(
+        &self,
+        task: &str,
+        prompt: &str,
+        options: CompletionOptions,
+    ) -> Result<String> {
+        let check = ModelCheck {
+            task,
+            prompt,
+            options: &options,
+        };
+        self.dispatch(&check, Route::Quality, |provider| {
+            let options = options.clone();
+            async move { provider.complete_with_options(prompt, options).await }
+        })
+        .await
+    }
+
+    /// Open a stream along the chain, publishing deltas under `request_id`
+    ///
+    /// Fallback only applies while opening the stream; once deltas flow,
+    /// errors are surfaced to the consumer.
+    async fn open_stream<F, Fut>(
+        &self,
+        request_id: &str,
+        route: Route,
+        check: &ModelCheck<'_>,
+        open: F,
+    ) -> Result<CompletionStream>
+    where
+        F: Fn(Arc<dyn Provider>, CancellationToken) -> Fut,
+        Fut: Future<Output = std::result::Result<CompletionStream, ProviderError>>,
+    {
+        let registration = streaming::register_stream(request_id);
+        let cancel = registration.token.clone();
+        let opened = self
+            .dispatch_held(check, route, |provider| open(provider, cancel.clone()))
+            .await;
+
+        match opened {
+            Ok(Dispatched::Answered(stream, permit)) => {
+                // The provider's slot stays taken until the stream is dropped
+                let stream: CompletionStream = Box::pin(stream.map(move |delta| {
+                    let _held = &permit;
+                    delta
+                }));
+                Ok(streaming::publish_stream(request_id, &registration, stream))
+            }
+            // A callback's replacement reply arrives as a single delta
+            Ok(Dispatched::Replaced(reply)) => {
+                let stream: CompletionStream = Box::pin(futures::stream::iter([Ok(reply)]));
+                Ok(streaming::publish_stream(request_id, &registration, stream))
+            }
+            Err(e) => {
+                streaming::publish_failure(request_id, &registration, &e.to_string());
+                Err(e)
             }
         }
+    }
+
+    /// Open a stream of a single prompt
+    async fn open_prompt_stream(
+        &self,
+        request_id: &str,
+        route: Route,
+        prompt: &str,
+        options: CompletionOptions,
+    ) -> Result<CompletionStream> {
+        let check = ModelCheck {
+            task: "stream",
+            prompt,
+            options: &options,
+        };
+        self.open_stream(request_id, route, &check, |provider, cancel| {
+            let options = options.clone();
+            async move { provider.complete_stream(prompt, options, cancel).await }
+        })
+        .await
+    }
 
-        // Fallback to Ollama
-        if self.ollama_available.load(Ordering::SeqCst) {
-            self.count_ollama_call();
-            match self.ollama.generate_text(prompt).await {
-                Ok(res) => return Ok(res),
-                Err(e) => {
-                    tracing::warn!("Ollama generate_text failed: {}. Marking unavailable.", e);
-                    self.ollama_available.store(false, Ordering::SeqCst);
-                    return Err(e);
+    /// Complete a prompt as `T`, validated against its JSON Schema
+    ///
+    /// Each attempt goes through the chain with fallback; replies that fail
+    /// validation are re-prompted with the errors.
+    async fn complete_structured<T>(
+        &self,
+        task: &str,
+        route: Route,
+        prompt: &str,
+        options: CompletionOptions,
+    ) -> Result<T>
+    where
+        T: DeserializeOwned + JsonSchema,
+    {
+        let schema = structured::schema_for::<T>();
+        let options = CompletionOptions {
+            response_schema: Some(schema.clone()),
+            ..options
+        };
+
+        structured::request_with_repair(
+            prompt,
+            &schema,
+            structured::DEFAULT_REPAIR_ATTEMPTS,
+            |request| {
+                let options = options.clone();
+                async move {
+                    let check = ModelCheck {
+                        task,
+                        prompt: &request,
+                        options: &options,
+                    };
+                    self.dispatch(&check, route, |provider| {
+                        let options = options.clone();
+                        let request = request.clone();
+                        async move { provider.complete_with_options(&request, options).await }
+                    })
+                    .await
                 }
-            }
+            },
+        )
+        .await
+        .with_context(|| format!("{} returned invalid output", task))
+    }
+
+    /// Sampling options for a task
+    fn options(temperature: f64, max_tokens: usize) -> CompletionOptions {
+        let offset = TEMPERATURE_OFFSET.try_with(|offset| *offset).unwrap_or(0.0);
+        CompletionOptions {
+            temperature: Some((temperature + offset).clamp(0.0, 2.0)),
+            max_tokens: Some(max_tokens),
+            ..Default::default()
         }
+    }
 
-        // Last resort
-        if let Some(ref gemini) = self.gemini {
-            self.count_gemini_call();
-            match gemini.generate_text(prompt).await {
-                Ok(res) => return Ok(res),
-                Err(e) => {
-                    return Err(anyhow::anyhow!(AgentError::CircuitOpen(format!(
-                        "All providers failed. Gemini error: {}",
-                        e
-                    ))))
-                }
-            }
+    /// Like `options`, but grounded in web search on providers that support it
+    fn grounded_options(temperature: f64, max_tokens: usize) -> CompletionOptions {
+        CompletionOptions {
+            grounded: true,
+            ..Self::options(temperature, max_tokens)
         }
+    }
+
+    // ========================================================================
+    // AI Methods with Fallback Logic
+    // ========================================================================
 
-        Err(anyhow::anyhow!(AgentError::CircuitOpen(
-            "No AI provider available".to_string()
-        )))
+    /// Analyze an image with AI vision
+    pub async fn analyze_image(&self, base64_image: &str, prompt: &str) -> Result<String> {
+        let options = CompletionOptions::default();
+        let check = ModelCheck {
+            task: "analyze_image",
+            prompt,
+            options: &options,
+        };
+        self.dispatch(&check, Route::Vision, move |provider| async move {
+            provider.analyze_image(base64_image, prompt).await
+        })
+        .await
     }
 
-    /// Generate text from a prompt (prefers Ollama for cost optimization)
+    /// Generate text from a prompt (follows the configured chain for quality)
+    /// Use `generate_text_light()` for agent tasks that can use local LLM
+    pub async fn generate_text(&self, prompt: &str) -> Result<String> {
+        self.complete_quality(
+            "generate_text",
+            prompt,
+            Self::options(TEXT_TEMPERATURE, TEXT_MAX_TOKENS),
+        )
+        .await
+    }
+
+    /// Generate text from a prompt (prefers local providers for cost optimization)
     /// Use this for agent tasks (planning, critique, guardrails) that don't need
     /// the highest quality model. Routes to Ollama when available to reduce API costs.
     pub async fn generate_text_light(&self, prompt: &str) -> Result<String> {
-        // Check rate limit first
-        self.check_rate_limit()?;
-        self.refresh_ollama_if_stale().await;
-
-        let (primary, has_fallback) = self.choose_provider();
-
-        match primary {
-            ProviderType::Ollama => {
-                match self.ollama.generate_text(prompt).await {
-                    Ok(result) => {
-                        self.count_ollama_call();
-                        return Ok(result);
-                    }
-                    Err(e) if has_fallback => {
-                        tracing::warn!("Ollama generate_text_light failed, trying Gemini: {}", e);
-                    }
-                    Err(e) => {
-                        tracing::warn!(
-                            "Ollama generate_text_light failed: {}. Marking unavailable.",
-                            e
-                        );
-                        self.ollama_available.store(false, Ordering::SeqCst);
-                        return Err(e);
-                    }
-                }
+        self.complete_light(
+            "generate_text_light",
+            prompt,
+            Self::options(TEXT_TEMPERATURE, TEXT_MAX_TOKENS),
+        )
+        .await
+    }
 
-                // Fallback to Gemini
-                if let Some(ref gemini) = self.gemini {
-                    self.count_gemini_call();
-                    return gemini.generate_text(prompt).await;
-                }
-            }
-            ProviderType::Gemini => {
-                if let Some(ref gemini) = self.gemini {
-                    match gemini.generate_text(prompt).await {
-                        Ok(result) => {
-                            self.mark_gemini_ok();
-                            self.count_gemini_call();
-                            return Ok(result);
-                        }
-                        Err(e) if has_fallback => {
-                            tracing::warn!(
-                                "Gemini generate_text_light failed, trying Ollama: {}",
-                                e
-                            );
-                            self.mark_gemini_failing();
-                        }
-                        Err(e) => {
-                            self.mark_gemini_failing();
-                            return Err(e);
-                        }
-                    }
-                }
+    /// Stream text from a prompt (follows the configured chain for quality)
+    /// Cancel with `providers::cancel_stream(request_id)`
+    pub async fn stream_text(&self, request_id: &str, prompt: &str) -> Result<CompletionStream> {
+        self.open_prompt_stream(
+            request_id,
+            Route::Quality,
+            prompt,
+            Self::options(TEXT_TEMPERATURE, TEXT_MAX_TOKENS),
+        )
+        .await
+    }
+
+    /// Stream text from a prompt (prefers local providers)
+    pub async fn stream_text_light(
+        &self,
+        request_id: &str,
+        prompt: &str,
+    ) -> Result<CompletionStream> {
+        self.open_prompt_stream(
+            request_id,
+            Route::Light,
+            prompt,
+            Self::options(TEXT_TEMPERATURE, TEXT_MAX_TOKENS),
+        )
+        .await
+    }
+
+    /// Generate text while publishing deltas, returning the full completion
+    /// A cancelled stream returns the text received so far
+    pub async fn generate_text_streamed(&self, request_id: &str, prompt: &str) -> Result<String> {
+        let stream = self.stream_text(request_id, prompt).await?;
+        Ok(streaming::collect_stream(stream).await?)
+    }
 
-                // Fallback to Ollama
-                if self.ollama_available.load(Ordering::SeqCst) {
-                    self.count_ollama_call();
-                    return self.ollama.generate_text(prompt).await;
+    /// Continue a multi-turn conversation (follows the configured chain)
+    /// Callers keep `messages` within `context_window()`
+    pub async fn chat(&self, messages: &[ChatMessage]) -> Result<String> {
+        let options = Self::options(TEXT_TEMPERATURE, TEXT_MAX_TOKENS);
+        let prompt = chat::flatten(messages);
+        let check = ModelCheck {
+            task: "chat",
+            prompt: &prompt,
+            options: &options,
+        };
+        self.dispatch(&check, Route::Quality, |provider| {
+            let options = options.clone();
+            async move { provider.complete_chat(messages, options).await }
+        })
+        .await
+    }
+
+    /// Continue a conversation while publishing deltas, returning the full reply
+    /// A cancelled stream returns the text received so far
+    pub async fn chat_streamed(
+        &self,
+        request_id: &str,
+        messages: &[ChatMessage],
+    ) -> Result<String> {
+        let options = Self::options(TEXT_TEMPERATURE, TEXT_MAX_TOKENS);
+        let prompt = chat::flatten(messages);
+        let check = ModelCheck {
+            task: "chat",
+            prompt: &prompt,
+            options: &options,
+        };
+        let stream = self
+            .open_stream(request_id, Route::Quality, &check, |provider, cancel| {
+                let options = options.clone();
+                async move {
+                    provider
+                        .complete_chat_stream(messages, options, cancel)
+                        .await
                 }
-            }
-            ProviderType::None => {}
-        }
+            })
+            .await?;
+        Ok(streaming::collect_stream(stream).await?)
+    }
 
-        Err(anyhow::anyhow!("No AI provider available"))
+    /// Continue a tool-calling conversation using native function calling
+    /// Only providers that support tools are tried; calls come back structured
+    pub async fn complete_with_tools(
+        &self,
+        turns: &[ToolTurn],
+        tools: &[ToolDescriptor],
+    ) -> Result<ToolCompletion> {
+        let options = CompletionOptions {
+            tools: Some(tools.to_vec()),
+            ..Self::options(TOOL_TEMPERATURE, TOOL_MAX_TOKENS)
+        };
+        // Tool results carry page content, so they are checked along with the instructions
+        let prompt: Vec<String> = turns
+            .iter()
+            .filter_map(|turn| match turn {
+                ToolTurn::User { content } => Some(content.clone()),
+                ToolTurn::Assistant { text, .. } => text.clone(),
+                ToolTurn::ToolResult { content, .. } => Some(content.to_string()),
+            })
+            .collect();
+        let prompt = prompt.join("\n\n");
+        let check = ModelCheck {
+            task: "complete_with_tools",
+            prompt: &prompt,
+            options: &options,
+        };
+        self.dispatch(&check, Route::Tools, |provider| {
+            let options = options.clone();
+            async move { provider.complete_with_tools(turns, options).await }
+        })
+        .await
+    }
+
+    /// Generate a value of `T` as schema-validated JSON (follows the configured chain)
+    ///
+    /// Uses native JSON modes where available (Ollama `format`, OpenAI
+    /// `response_format`, Gemini JSON mime type) and re-prompts with the
+    /// validation errors when a reply doesn't match the schema.
+    pub async fn generate_structured<T>(&self, prompt: &str) -> Result<T>
+    where
+        T: DeserializeOwned + JsonSchema,
+    {
+        self.complete_structured(
+            "generate_structured",
+            Route::Quality,
+            prompt,
+            Self::options(STRUCTURED_TEMPERATURE, STRUCTURED_MAX_TOKENS),
+        )
+        .await
+    }
+
+    /// Generate a value of `T` as schema-validated JSON (prefers local providers)
+    pub async fn generate_structured_light<T>(&self, prompt: &str) -> Result<T>
+    where
+        T: DeserializeOwned + JsonSchema,
+    {
+        self.complete_structured(
+            "generate_structured_light",
+            Route::Light,
+            prompt,
+            Self::options(STRUCTURED_TEMPERATURE, STRUCTURED_MAX_TOKENS),
+        )
+        .await
+    }
+
+    /// Analyze an image, returning a value of `T` validated against its JSON Schema
+    pub async fn analyze_image_structured<T>(&self, base64_image: &str, prompt: &str) -> Result<T>
+    where
+        T: DeserializeOwned + JsonSchema,
+    {
+        let schema = structured::schema_for::<T>();
+        structured::request_with_repair(
+            prompt,
+            &schema,
+            structured::DEFAULT_REPAIR_ATTEMPTS,
+            |request| async move { self.analyze_image(base64_image, &request).await },
+        )
+        .await
+        .context("analyze_image returned invalid output")
     }
 
     /// Calculate URL similarity
-    /// Light task - prefers Ollama when both available (cost optimization)
+    /// Light task - prefers local providers (cost optimization)
     pub async fn calculate_url_similarity(&self, url1: &str, url2: &str) -> Result<f32> {
-        // Check rate limit first
-        self.check_rate_limit()?;
-        self.refresh_ollama_if_stale().await;
-
-        let (primary, has_fallback) = self.choose_provider();
-
-        match primary {
-            ProviderType::Ollama => {
-                match self.ollama.calculate_url_similarity(url1, url2).await {
-                    Ok(result) => {
-                        self.count_ollama_call();
-                        return Ok(result);
-                    }
-                    Err(e) if has_fallback => {
-                        tracing::warn!("Ollama similarity failed, trying Gemini: {}", e);
-                    }
-                    Err(e) => {
-                        tracing::warn!("Ollama similarity failed: {}. Marking unavailable.", e);
-                        self.ollama_available.store(false, Ordering::SeqCst);
-                        return Err(e);
-                    }
-                }
+        self.probe_health().await;
+        if !self.is_available() {
+            return Ok(0.0); // Return no similarity if no provider
+        }
 
-                // Fallback to Gemini
-                if let Some(ref gemini) = self.gemini {
-                    self.count_gemini_call();
-                    return gemini.calculate_url_similarity(url1, url2).await;
-                }
-            }
-            ProviderType::Gemini => {
-                if let Some(ref gemini) = self.gemini {
-                    match gemini.calculate_url_similarity(url1, url2).await {
-                        Ok(result) => {
-                            self.mark_gemini_ok();
-                            self.count_gemini_call();
-                            return Ok(result);
-                        }
-                        Err(e) if has_fallback => {
-                            tracing::warn!("Gemini similarity failed, trying Ollama: {}", e);
-                            self.mark_gemini_failing();
-                        }
-                        Err(e) => {
-                            self.mark_gemini_failing();
-                            return Err(e);
-                        }
-                    }
-                }
+        let prompt = format!(
+            "Compare these two URLs semantically. Consider the topic, domain, and content they represent.
+            Return ONLY a single number between 0.0 and 1.0 representing their similarity.
+            0.0 means completely unrelated, 1.0 means identical or very closely related.
 
-                // Fallback to Ollama
-                if self.ollama_available.load(Ordering::SeqCst) {
-                    self.count_ollama_call();
-                    return self.ollama.calculate_url_similarity(url1, url2).await;
-                }
-            }
-            ProviderType::None => {}
-        }
+            URL1: {}
+            URL2: {}
+
+            Respond with just the number, nothing else.",
+            url1, url2
+        );
 
-        Ok(0.0) // Return no similarity if no provider
+        let text = self
+            .complete_light("calculate_url_similarity", &prompt, Self::options(0.1, 10))
+            .await?;
+
+        let similarity = text.trim().parse::<f32>().unwrap_or(0.0);
+        Ok(similarity.clamp(0.0, 1.0))
     }
 
     /// Generate dialogue
-    /// Light task - prefers Ollama when both available (cost optimization)
+    /// Light task - prefers local providers (cost optimization)
     pub async fn generate_dialogue(&self, context: &str, personality: &str) -> Result<String> {
-        // Check rate limit first
-        self.check_rate_limit()?;
-        self.refresh_ollama_if_stale().await;
-
-        let (primary, has_fallback) = self.choose_provider();
-
-        match primary {
-            ProviderType::Ollama => {
-                match self.ollama.generate_dialogue(context, personality).await {
-                    Ok(result) => {
-                        self.count_ollama_call();
-                        return Ok(result);
-                    }
-                    Err(e) if has_fallback => {
-                        tracing::warn!("Ollama dialogue failed, trying Gemini: {}", e);
-                    }
-                    Err(e) => {
-                        tracing::warn!("Ollama dialogue failed: {}. Marking unavailable.", e);
-                        self.ollama_available.store(false, Ordering::SeqCst);
-                        return Err(e);
-                    }
-                }
+        self.probe_health().await;
+        if !self.is_available() {
+            return Ok("...".to_string()); // Silent fallback
+        }
 
-                // Fallback to Gemini
-                if let Some(ref gemini) = self.gemini {
-                    self.count_gemini_call();
-                    return gemini.generate_dialogue(context, personality).await;
-                }
-            }
-            ProviderType::Gemini => {
-                if let Some(ref gemini) = self.gemini {
-                    match gemini.generate_dialogue(context, personality).await {
-                        Ok(result) => {
-                            self.mark_gemini_ok();
-                            self.count_gemini_call();
-                            return Ok(result);
-                        }
-                        Err(e) if has_fallback => {
-                            tracing::warn!("Gemini dialogue failed, trying Ollama: {}", e);
-                            self.mark_gemini_failing();
-                        }
-                        Err(e) => {
-                            self.mark_gemini_failing();
-                            return Err(e);
-                        }
-                    }
-                }
+        let prompt = Self::dialogue_prompt(context, personality);
+        let text = self
+            .complete_light("generate_dialogue", &prompt, Self::options(0.9, 50))
+            .await?;
+        Ok(text.trim().to_string())
+    }
 
-                // Fallback to Ollama
-                if self.ollama_available.load(Ordering::SeqCst) {
-                    self.count_ollama_call();
-                    return self.ollama.generate_dialogue(context, personality).await;
-                }
-            }
-            ProviderType::None => {}
+    /// Generate dialogue while publishing deltas under `request_id`
+    /// Light task - prefers local providers (cost optimization)
+    pub async fn generate_dialogue_streamed(
+        &self,
+        request_id: &str,
+        context: &str,
+        personality: &str,
+    ) -> Result<String> {
+        self.probe_health().await;
+        if !self.is_available() {
+            return Ok("...".to_string()); // Silent fallback
         }
 
-        Ok("...".to_string()) // Silent fallback
+        let prompt = Self::dialogue_prompt(context, personality);
+        let stream = self
+            .open_prompt_stream(request_id, Route::Light, &prompt, Self::options(0.9, 50))
+            .await?;
+        let text = streaming::collect_stream(stream).await?;
+        Ok(text.trim().to_string())
+    }
+
+    fn dialogue_prompt(context: &str, personality: &str) -> String {
+        format!(
+            "You are a desktop companion. Your personality is: {}
+
+            Based on this context about what the user is viewing: {}
+
+            Generate a short, helpful, or intriguing comment (max 100 characters).
+            If in 'Mystery' mode, be cryptic. If in 'Companion' mode, be helpful but concise.
+            Stay in character.",
+            personality, context
+        )
     }
 
     /// Generate dynamic puzzle
@@ -623,50 +1503,38 @@ impl SmartAiRouter {
         page_content: &str,
         history_context: &str,
     ) -> Result<DynamicPuzzle> {
-        // Check rate limit first
-        self.check_rate_limit()?;
-        self.refresh_ollama_if_stale().await;
-
-        // Try Gemini first (has Google Search grounding)
-        if let Some(ref gemini) = self.gemini {
-            if !self.is_gemini_circuit_open() {
-                match gemini
-                    .generate_dynamic_puzzle(url, page_title, page_content, history_context)
-                    .await
-                {
-                    Ok(result) => {
-                        self.mark_gemini_ok();
-                        self.count_gemini_call();
-                        return Ok(result);
-                    }
-                    Err(e) => {
-                        tracing::warn!("Gemini puzzle generation failed, trying Ollama: {}", e);
-                        self.mark_gemini_failing();
-                    }
-                }
-            }
-        }
+        tracing::info!(
+            "Generating dynamic puzzle for URL: {} (title: {})",
+            url,
+            page_title
+        );
+
+        let prompt = prompts::render(
+            "puzzle.dynamic",
+            &[
+                ("url", url.into()),
+                ("title", page_title.into()),
+                (
+                    "content",
+                    page_content.chars().take(500).collect::<String>().into(),
+                ),
+                ("history", history_context.into()),
+            ],
+        )
+        .map_err(anyhow::Error::msg)?;
 
-        // Fallback to Ollama
-        if self.ollama_available.load(Ordering::SeqCst) {
-            self.count_ollama_call();
-            return self
-                .ollama
-                .generate_dynamic_puzzle(url, page_title, page_content, history_context)
-                .await;
-        }
+        let puzzle: DynamicPuzzle = self
+            .complete_structured(
+                "generate_dynamic_puzzle",
+                Route::Quality,
+                &prompt,
+                Self::grounded_options(0.8, 300),
+            )
+            .await?;
 
-        // Last resort
-        if let Some(ref gemini) = self.gemini {
-            self.count_gemini_call();
-            return gemini
-                .generate_dynamic_puzzle(url, page_title, page_content, history_context)
-                .await;
-        }
+        tracing::info!("Successfully generated puzzle: {:?}", puzzle.clue);
 
-        Err(anyhow::anyhow!(
-            "No AI provider available for puzzle generation"
-        ))
+        Ok(puzzle)
     }
 
     /// Verify screenshot clue
@@ -675,48 +1543,10 @@ impl SmartAiRouter {
         base64_image: &str,
         clue_description: &str,
     ) -> Result<VerificationResult> {
-        // Check rate limit first
-        self.check_rate_limit()?;
-        self.refresh_ollama_if_stale().await;
-
-        // Try Gemini first
-        if let Some(ref gemini) = self.gemini {
-            if !self.is_gemini_circuit_open() {
-                match gemini
-                    .verify_screenshot_clue(base64_image, clue_description)
-                    .await
-                {
-                    Ok(result) => {
-                        self.mark_gemini_ok();
-                        self.count_gemini_call();
-                        return Ok(result);
-                    }
-                    Err(e) => {
-                        tracing::warn!("Gemini verification failed, trying Ollama: {}", e);
-                        self.mark_gemini_failing();
-                    }
-                }
-            }
-        }
-
-        // Fallback to Ollama
-        if self.ollama_available.load(Ordering::SeqCst) {
-            self.count_ollama_call();
-            return self
-                .ollama
-                .verify_screenshot_clue(base64_image, clue_description)
-                .await;
-        }
-
-        // Last resort
-        if let Some(ref gemini) = self.gemini {
-            self.count_gemini_call();
-            return gemini
-                .verify_screenshot_clue(base64_image, clue_description)
-                .await;
-        }
+        let prompt = prompts::render("puzzle.verify", &[("description", clue_description.into())])
+            .map_err(anyhow::Error::msg)?;
 
-        Err(anyhow::anyhow!("No AI provider available for verification"))
+        self.analyze_image_structured(base64_image, &prompt).await
     }
 
     /// Generate adaptive puzzle based on activity
@@ -726,50 +1556,44 @@ impl SmartAiRouter {
         current_app: Option<&str>,
         current_content: Option<&str>,
     ) -> Result<AdaptivePuzzle> {
-        // Check rate limit first
-        self.check_rate_limit()?;
-        self.refresh_ollama_if_stale().await;
-
-        // Try Gemini first
-        if let Some(ref gemini) = self.gemini {
-            if !self.is_gemini_circuit_open() {
-                match gemini
-                    .generate_adaptive_puzzle(activities, current_app, current_content)
-                    .await
-                {
-                    Ok(result) => {
-                        self.mark_gemini_ok();
-                        self.count_gemini_call();
-                        return Ok(result);
-                    }
-                    Err(e) => {
-                        tracing::warn!("Gemini adaptive puzzle failed, trying Ollama: {}", e);
-                        self.mark_gemini_failing();
-                    }
-                }
-            }
-        }
-
-        // Fallback to Ollama
-        if self.ollama_available.load(Ordering::SeqCst) {
-            self.count_ollama_call();
-            return self
-                .ollama
-                .generate_adaptive_puzzle(activities, current_app, current_content)
-                .await;
-        }
-
-        // Last resort
-        if let Some(ref gemini) = self.gemini {
-            self.count_gemini_call();
-            return gemini
-                .generate_adaptive_puzzle(activities, current_app, current_content)
-                .await;
-        }
+        // Build activity context string
+        let activity_summary = activities
+            .iter()
+            .map(|a| format!("- {} ({}): {}", a.app_category, a.app_name, a.description))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let current_context = match (current_app, current_content) {
+            (Some(app), Some(content)) => format!("Currently using {} viewing {}", app, content),
+            (Some(app), None) => format!("Currently using {}", app),
+            _ => "No current context".to_string(),
+        };
 
-        Err(anyhow::anyhow!(
-            "No AI provider available for adaptive puzzle generation"
-        ))
+        let prompt = prompts::render(
+            "puzzle.adaptive",
+            &[
+                ("activity", activity_summary.into()),
+                ("current_context", current_context.into()),
+            ],
+        )
+        .map_err(anyhow::Error::msg)?;
+
+        let puzzle: AdaptivePuzzle = self
+            .complete_structured(
+                "generate_adaptive_puzzle",
+                Route::Quality,
+                &prompt,
+                Self::grounded_options(0.8, 400),
+            )
+            .await?;
+
+        tracing::info!(
+            "Generated adaptive puzzle inspired by '{}': {}",
+            puzzle.inspired_by,
+            puzzle.clue
+        );
+
+        Ok(puzzle)
     }
 
     /// Generate contextual dialogue
@@ -779,48 +1603,50 @@ impl SmartAiRouter {
         current_context: &str,
         ghost_mood: &str,
     ) -> Result<String> {
-        // Check rate limit first
-        self.check_rate_limit()?;
-        self.refresh_ollama_if_stale().await;
-
-        // Try Gemini first
-        if let Some(ref gemini) = self.gemini {
-            if !self.is_gemini_circuit_open() {
-                match gemini
-                    .generate_contextual_dialogue(recent_activities, current_context, ghost_mood)
-                    .await
-                {
-                    Ok(result) => {
-                        self.mark_gemini_ok();
-                        self.count_gemini_call();
-                        return Ok(result);
-                    }
-                    Err(e) => {
-                        tracing::warn!("Gemini contextual dialogue failed, trying Ollama: {}", e);
-                        self.mark_gemini_failing();
-                    }
-                }
-            }
+        self.probe_health().await;
+        if !self.is_available() {
+            return Ok("...".to_string());
         }
 
-        // Fallback to Ollama with activity context
-        if self.ollama_available.load(Ordering::SeqCst) {
-            self.count_ollama_call();
-            return self
-                .ollama
-                .generate_contextual_dialogue(recent_activities, current_context, ghost_mood)
-                .await;
-        }
-
-        // Last resort
-        if let Some(ref gemini) = self.gemini {
-            self.count_gemini_call();
-            return gemini
-                .generate_contextual_dialogue(recent_activities, current_context, ghost_mood)
-                .await;
-        }
-
-        Ok("...".to_string())
+        let activity_summary = recent_activities
+            .iter()
+            .take(5)
+            .map(|a| format!("{} ({})", a.description, a.app_name))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let prompt = format!(
+            r#"You are a mysterious AI companion ghost. Generate a short, contextual comment.
+
+Ghost mood: {}
+Recent user activities: {}
+Current context: {}
+
+The ghost should:
+- Reference what the user has been doing naturally
+- Be helpful yet mysterious
+- Keep it under 80 characters
+- Stay in character
+
+Respond with ONLY the dialogue text, no quotes or formatting."#,
+            ghost_mood,
+            if activity_summary.is_empty() {
+                "watching the user".to_string()
+            } else {
+                activity_summary
+            },
+            current_context
+        );
+
+        let text = self
+            .complete_quality(
+                "generate_contextual_dialogue",
+                &prompt,
+                Self::options(0.9, 50),
+            )
+            .await?;
+
+        Ok(text.trim().replace('"', ""))
     }
 
     // ========================================================================
@@ -831,15 +1657,18 @@ impl SmartAiRouter {
     /// Uses Gemini if available and not failing, falls back to Ollama
     pub fn get_vision_analyzer(&self) -> Option<crate::ai::VisionAnalyzer> {
         // Check if we have at least one vision-capable provider
-        let has_gemini = self.gemini.is_some() && !self.is_gemini_circuit_open();
-        let has_ollama = self.ollama_available.load(Ordering::SeqCst);
+        let gemini_over_budget = usage::is_over_budget("gemini");
+        let has_gemini = self.gemini.is_some()
+            && !self.is_circuit_open(ProviderKind::Gemini)
+            && !gemini_over_budget;
+        let has_ollama = self.has_ollama();
 
         if !has_gemini && !has_ollama {
             tracing::warn!("No vision provider available");
             return None;
         }
 
-        let gemini_clone = self.gemini.clone();
+        let gemini_clone = self.gemini.clone().filter(|_| !gemini_over_budget);
         let ollama_clone = if has_ollama {
             Some(Arc::clone(&self.ollama))
         } else {
@@ -855,7 +1684,7 @@ impl SmartAiRouter {
         &self,
         image_bytes: &[u8],
     ) -> Result<crate::ai::VisionAnalysis> {
-        self.check_rate_limit()?;
+        self.admit().await?;
 
         match self.get_vision_analyzer() {
             Some(analyzer) => analyzer.analyze_screenshot(image_bytes).await,
@@ -882,18 +1711,333 @@ impl SmartAiRouter {
     /// Check if vision capabilities are available
     pub fn has_vision(&self) -> bool {
         let has_gemini = self.gemini.is_some();
-        let has_ollama = self.ollama_available.load(Ordering::SeqCst);
-        has_gemini || has_ollama
+        has_gemini || self.has_ollama()
     }
 
     /// Get vision provider status for display
     pub fn vision_provider(&self) -> Option<crate::ai::VisionProvider> {
-        if self.gemini.is_some() && !self.is_gemini_circuit_open() {
+        if self.gemini.is_some()
+            && !self.is_circuit_open(ProviderKind::Gemini)
+            && !usage::is_over_budget("gemini")
+        {
             Some(crate::ai::VisionProvider::Gemini)
-        } else if self.ollama_available.load(Ordering::SeqCst) {
+        } else if self.has_ollama() {
             Some(crate::ai::VisionProvider::Ollama)
         } else {
             None
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::providers::mock::MockProvider;
+
+    fn mock(name: &'static str, fail: bool) -> Arc<dyn Provider> {
+        if fail {
+            Arc::new(MockProvider::failing(name))
+        } else {
+            Arc::new(MockProvider::new(name))
+        }
+    }
+
+    fn router(chain: Vec<(ProviderKind, Arc<dyn Provider>)>) -> SmartAiRouter {
+        let chain = chain
+            .into_iter()
+            .map(|(kind, provider)| ChainEntry::new(kind, provider))
+            .collect();
+        SmartAiRouter::with_providers(chain, None, Arc::new(OllamaClient::new()), 1000)
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_along_chain() {
+        let router = router(vec![
+            (ProviderKind::OpenAI, mock("openai", true)),
+            (ProviderKind::Anthropic, mock("anthropic", false)),
+        ]);
+
+        let text = router.generate_text("hello").await.unwrap();
+        assert_eq!(text, "anthropic: hello");
+
+        let counts = router.provider_call_counts();
+        assert_eq!(counts.get("openai"), Some(&1));
+        assert_eq!(counts.get("anthropic"), Some(&1));
+
+        // Primary circuit is now open, so the fallback is the active provider
+        assert_eq!(router.active_provider(), ProviderType::Anthropic);
+    }
+
+    #[tokio::test]
+    async fn test_spent_remote_chain_falls_back_to_ollama() {
+        use crate::config::toml_config::{BudgetConfig, UsageConfig};
+
+        // Zero budgets are used up from the start
+        let dir = tempfile::tempdir().unwrap();
+        let store = crate::memory::MemoryStore::open(dir.path().join("usage.db")).unwrap();
+        let mut config = UsageConfig::default();
+        for id in ["spent-gemini", "spent-openai"] {
+            config.budgets.insert(
+                id.to_string(),
+                BudgetConfig {
+                    daily_usd: Some(0.0),
+                    monthly_usd: None,
+                },
+            );
+        }
+        usage::init_usage_ledger(store, config);
+
+        let entry = |id: &str, kind| ChainEntry {
+            id: id.to_string(),
+            kind,
+            local: false,
+            provider: mock("remote", false),
+        };
+        let router = SmartAiRouter::with_providers(
+            vec![
+                entry("spent-gemini", ProviderKind::Gemini),
+                entry("spent-openai", ProviderKind::OpenAI),
+            ],
+            None,
+            Arc::new(OllamaClient::new()),
+            1000,
+        );
+        assert_eq!(router.active_provider(), ProviderType::Ollama);
+
+        // The remote providers are skipped and Ollama is tried
+        let _ = router.generate_text("hello").await;
+        assert_eq!(router.budget_fallback.call_count.load(Ordering::Relaxed), 1);
+        let counts = router.provider_call_counts();
+        assert_eq!(counts.get("gemini"), Some(&0));
+        assert_eq!(counts.get("openai"), Some(&0));
+    }
+
+    #[tokio::test]
+    async fn test_model_callbacks_see_every_request() {
+        use crate::agents::callbacks::{CallbackContext, CallbackRegistry, PolicyCallback};
+        use crate::agents::traits::AgentContext;
+
+        let router = router(vec![(ProviderKind::OpenAI, mock("openai", false))]);
+        let mut registry = CallbackRegistry::new();
+        registry.register_model_callback(Arc::new(PolicyCallback::new().block_prompt("secret")));
+        let registry = Arc::new(tokio::sync::Mutex::new(registry));
+        let ctx = CallbackContext::new(AgentContext::default(), "Test", "inv_1");
+
+        let blocked = callbacks::with_model_callbacks(registry.clone(), ctx.clone(), async {
+            router.generate_text_light("tell me the secret").await
+        })
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            blocked.downcast_ref::<AgentError>(),
+            Some(AgentError::SafetyViolation(_))
+        ));
+        let messages = [ChatMessage::user("the secret, please")];
+        assert!(callbacks::with_model_callbacks(
+            registry.clone(),
+            ctx.clone(),
+            router.chat(&messages)
+        )
+        .await
+        .is_err());
+        assert_eq!(router.provider_call_counts().get("openai"), Some(&0));
+
+        let allowed = callbacks::with_model_callbacks(registry, ctx, router.generate_text("hi"))
+            .await
+            .unwrap();
+        assert_eq!(allowed, "openai: hi");
+    }
+
+    #[tokio::test]
+    async fn test_model_callbacks_see_the_dispatched_provider() {
+        use crate::agents::callbacks::{
+            CallbackContext, CallbackRegistry, LlmResponse, ModelCallback,
+        };
+        use crate::agents::traits::AgentContext;
+
+        #[derive(Default)]
+        struct SeenProviders(std::sync::Mutex<Vec<String>>);
+
+        #[async_trait::async_trait]
+        impl ModelCallback for SeenProviders {
+            async fn before_model(
+                &self,
+                _ctx: &CallbackContext,
+                request: &LlmRequest,
+            ) -> Option<LlmResponse> {
+                let provider = request.params["provider"].as_str().unwrap_or_default();
+                self.0.lock().unwrap().push(provider.to_string());
+                None
+            }
+        }
+
+        let router = router(vec![
+            (ProviderKind::OpenAI, mock("openai", true)),
+            (ProviderKind::Anthropic, mock("anthropic", false)),
+        ]);
+        let seen = Arc::new(SeenProviders::default());
+        let mut registry = CallbackRegistry::new();
+        registry.register_model_callback(seen.clone());
+        let ctx = CallbackContext::new(AgentContext::default(), "Test", "inv_1");
+
+        let text = callbacks::with_model_callbacks(
+            Arc::new(tokio::sync::Mutex::new(registry)),
+            ctx,
+            router.generate_text("hello"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(text, "anthropic: hello");
+        assert_eq!(*seen.0.lock().unwrap(), vec!["openai", "anthropic"]);
+    }
+
+    #[tokio::test]
+    async fn test_discovered_servers_count_as_local() {
+        // Discovered servers are OpenAI-compatible clients of kind `custom`
+        let discovered = ChainEntry {
+            id: "lmstudio:1234".to_string(),
+            local: true,
+            ..ChainEntry::new(ProviderKind::Custom, mock("openai", false))
+        };
+        let router = SmartAiRouter::with_providers(
+            vec![
+                ChainEntry::new(ProviderKind::Anthropic, mock("anthropic", false)),
+                discovered,
+            ],
+            None,
+            Arc::new(OllamaClient::new()),
+            1000,
+        );
+
+        let text = router.generate_text_light("hello").await.unwrap();
+        assert_eq!(text, "openai: hello");
+        assert_eq!(router.provider_call_counts().get("custom"), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_vision_route_skips_text_only_providers() {
+        let router = router(vec![(ProviderKind::OpenAI, mock("openai", false))]);
+
+        assert!(router.analyze_image("aGk=", "describe").await.is_err());
+        assert_eq!(router.provider_call_counts().get("openai"), Some(&0));
+    }
+
+    #[tokio::test]
+    async fn test_stream_falls_back_when_opening() {
+        let router = router(vec![
+            (ProviderKind::OpenAI, mock("openai", true)),
+            (ProviderKind::Anthropic, mock("anthropic", false)),
+        ]);
+
+        let text = router
+            .generate_text_streamed("test-router-stream", "hello")
+            .await
+            .unwrap();
+        assert_eq!(text, "anthropic: hello");
+        // Finished streams are no longer cancellable
+        assert!(!streaming::cancel_stream("test-router-stream"));
+
+        let reply = router
+            .chat_streamed("test-router-chat", &[ChatMessage::user("hi there")])
+            .await
+            .unwrap();
+        assert!(reply.starts_with("anthropic: "));
+        assert!(reply.contains("hi there"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_holds_its_provider_slot() {
+        let router = router(vec![(ProviderKind::OpenAI, mock("openai", false))]);
+
+        let stream = router
+            .stream_text("test-router-slot", "hello there")
+            .await
+            .unwrap();
+        assert_eq!(router.scheduler.stats().in_flight["openai"], 1);
+
+        let text = streaming::collect_stream(stream).await.unwrap();
+        assert_eq!(text, "openai: hello there");
+        assert_eq!(router.scheduler.stats().in_flight["openai"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_fixture_replay_runs_without_providers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fixtures.jsonl");
+        let fixtures = FixtureConfig {
+            mode: "record".to_string(),
+            cassette: Some(path.to_string_lossy().to_string()),
+            matching: "strict".to_string(),
+        };
+
+        let recorder =
+            router(vec![(ProviderKind::OpenAI, mock("openai", false))]).with_fixtures(&fixtures);
+        let recorded = recorder.generate_text("hello").await.unwrap();
+
+        let fixtures = FixtureConfig {
+            mode: "replay".to_string(),
+            ..fixtures
+        };
+        let replayer =
+            router(vec![(ProviderKind::OpenAI, mock("openai", true))]).with_fixtures(&fixtures);
+        assert_eq!(replayer.generate_text("hello").await.unwrap(), recorded);
+        assert!(replayer.generate_text("unrecorded").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_reprompts_then_fails() {
+        #[derive(Debug, serde::Deserialize, JsonSchema)]
+        #[allow(dead_code)]
+        struct Answer {
+            value: u32,
+        }
+
+        // The mock echoes the prompt, which never validates
+        let router = router(vec![(ProviderKind::OpenAI, mock("openai", false))]);
+        let err = router
+            .generate_structured::<Answer>("answer")
+            .await
+            .unwrap_err();
+
+        assert!(err.is::<structured::InvalidOutput>());
+        let attempts = structured::DEFAULT_REPAIR_ATTEMPTS as u64 + 1;
+        assert_eq!(router.provider_call_counts().get("openai"), Some(&attempts));
+    }
+
+    #[tokio::test]
+    async fn test_tool_route_skips_providers_without_tools() {
+        let router = router(vec![(ProviderKind::OpenAI, mock("openai", false))]);
+
+        let turns = vec![ToolTurn::user("open example.com")];
+        assert!(router.complete_with_tools(&turns, &[]).await.is_err());
+        assert_eq!(router.provider_call_counts().get("openai"), Some(&0));
+    }
+
+    #[test]
+    fn test_pinned_router_uses_entry_or_falls_back() {
+        let router = router(vec![(ProviderKind::OpenAI, mock("openai", false))]);
+        let core = CoreConfig::default();
+
+        let pinned = router.pinned(&core, ProviderEntry::new("ollama"));
+        assert!(pinned.has_provider(ProviderKind::Ollama));
+        assert!(!pinned.has_provider(ProviderKind::OpenAI));
+
+        let fallback = router.pinned(&core, ProviderEntry::new("nonexistent"));
+        assert!(fallback.has_provider(ProviderKind::OpenAI));
+    }
+
+    #[tokio::test]
+    async fn test_temperature_offset_is_scoped_and_clamped() {
+        let shifted =
+            with_temperature_offset(0.3, async { SmartAiRouter::options(0.7, 10).temperature })
+                .await;
+        assert_eq!(shifted, Some(1.0));
+
+        let clamped =
+            with_temperature_offset(-1.0, async { SmartAiRouter::options(0.7, 10).temperature })
+                .await;
+        assert_eq!(clamped, Some(0.0));
+
+        assert_eq!(SmartAiRouter::options(0.7, 10).temperature, Some(0.7));
+    }
+}
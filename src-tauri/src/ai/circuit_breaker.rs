@@ -0,0 +1,463 @@
+//! Circuit breakers for AI backends
+//!
+//! Every backend (provider + model) gets a breaker that tracks the error rate
+//! over a sliding window. Once the rate crosses the threshold the breaker
+//! opens and calls are skipped until a cool-down elapses; then a single
+//! half-open probe decides whether it closes again. Each failed probe doubles
+//! the cool-down up to `max_cooldown_secs`.
+//!
+//! Breakers live in a `BreakerRegistry`. The process-wide registry is shared by
+//! the router and the vision analyzer and is reported by `poll_agent_status`,
+//! `/health` and the Prometheus metrics.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+lazy_static::lazy_static! {
+    static ref GLOBAL_REGISTRY: Arc<BreakerRegistry> = Arc::new(BreakerRegistry::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls flow normally
+    Closed,
+    /// Calls are skipped until the cool-down elapses
+    Open,
+    /// One probe call is allowed to test recovery
+    HalfOpen,
+}
+
+impl BreakerState {
+    /// Numeric value for metrics (0 closed, 1 half-open, 2 open)
+    pub fn as_gauge(&self) -> u8 {
+        match self {
+            BreakerState::Closed => 0,
+            BreakerState::HalfOpen => 1,
+            BreakerState::Open => 2,
+        }
+    }
+}
+
+/// Thresholds and timings of a breaker
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakerConfig {
+    /// Sliding window the error rate is computed over
+    pub window_secs: u64,
+    /// Calls in the window before the error rate can trip the breaker
+    pub min_requests: usize,
+    /// Error rate (0.0-1.0) at which the breaker opens
+    pub failure_rate: f64,
+    /// Cool-down after the first trip
+    pub base_cooldown_secs: u64,
+    /// Upper bound for the doubled cool-down
+    pub max_cooldown_secs: u64,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: 60,
+            min_requests: 1,
+            failure_rate: 0.5,
+            base_cooldown_secs: 30,
+            max_cooldown_secs: 600,
+        }
+    }
+}
+
+impl BreakerConfig {
+    /// Local servers are cheap to probe and often started while the app runs
+    pub fn local() -> Self {
+        Self {
+            base_cooldown_secs: 15,
+            max_cooldown_secs: 60,
+            ..Self::default()
+        }
+    }
+}
+
+/// Point-in-time view of a breaker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakerSnapshot {
+    pub key: String,
+    pub provider: String,
+    pub model: String,
+    pub state: BreakerState,
+    /// Error rate over the current window
+    pub failure_rate: f64,
+    pub requests_in_window: usize,
+    /// Current cool-down length
+    pub cooldown_secs: u64,
+    /// Seconds until the next probe is allowed (open breakers only)
+    pub retry_in_secs: u64,
+    /// Times the breaker has opened since startup
+    pub trips: u64,
+    pub last_error: Option<String>,
+}
+
+struct BreakerInner {
+    state: BreakerState,
+    /// (timestamp, succeeded) for calls in the window
+    outcomes: VecDeque<(u64, bool)>,
+    opened_at: u64,
+    cooldown_secs: u64,
+    /// Trips since the breaker last closed (drives the exponential cool-down)
+    consecutive_trips: u32,
+    /// When the in-flight half-open probe was let through
+    probe_started: u64,
+    trips: u64,
+    last_error: Option<String>,
+}
+
+/// Error-rate circuit breaker for one backend
+pub struct CircuitBreaker {
+    provider: String,
+    model: String,
+    config: BreakerConfig,
+    inner: Mutex<BreakerInner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(provider: &str, model: &str, config: BreakerConfig) -> Self {
+        Self {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            config,
+            inner: Mutex::new(BreakerInner {
+                state: BreakerState::Closed,
+                outcomes: VecDeque::new(),
+                opened_at: 0,
+                cooldown_secs: config.base_cooldown_secs,
+                consecutive_trips: 0,
+                probe_started: 0,
+                trips: 0,
+                last_error: None,
+            }),
+        }
+    }
+
+    pub fn key(&self) -> String {
+        breaker_key(&self.provider, &self.model)
+    }
+
+    /// Whether a call may go through now, moving an expired open breaker to
+    /// half-open and reserving its single probe
+    pub fn allow_request(&self) -> bool {
+        self.allow_request_at(now_secs())
+    }
+
+    /// Whether calls are currently being skipped (does not change state)
+    pub fn is_open(&self) -> bool {
+        self.is_open_at(now_secs())
+    }
+
+    /// Whether the cool-down has elapsed and a probe is due
+    pub fn probe_due(&self) -> bool {
+        let Ok(inner) = self.inner.lock() else {
+            return false;
+        };
+        inner.state == BreakerState::Open && now_secs() >= inner.opened_at + inner.cooldown_secs
+    }
+
+    pub fn record_success(&self) {
+        self.record_success_at(now_secs());
+    }
+
+    pub fn record_failure(&self, error: &str) {
+        self.record_failure_at(error, now_secs());
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.inner
+            .lock()
+            .map(|inner| inner.state)
+            .unwrap_or(BreakerState::Closed)
+    }
+
+    pub fn snapshot(&self) -> BreakerSnapshot {
+        self.snapshot_at(now_secs())
+    }
+
+    fn allow_request_at(&self, now: u64) -> bool {
+        let Ok(mut inner) = self.inner.lock() else {
+            return true;
+        };
+        match inner.state {
+            BreakerState::Closed => true,
+            BreakerState::Open if now >= inner.opened_at + inner.cooldown_secs => {
+                tracing::info!("{} circuit half-open, probing", self.key());
+                inner.state = BreakerState::HalfOpen;
+                inner.probe_started = now;
+                true
+            }
+            BreakerState::Open => false,
+            // A probe whose outcome was never recorded doesn't block forever
+            BreakerState::HalfOpen
+                if now >= inner.probe_started + self.config.base_cooldown_secs =>
+            {
+                inner.probe_started = now;
+                true
+            }
+            BreakerState::HalfOpen => false,
+        }
+    }
+
+    fn is_open_at(&self, now: u64) -> bool {
+        let Ok(inner) = self.inner.lock() else {
+            return false;
+        };
+        match inner.state {
+            BreakerState::Closed => false,
+            BreakerState::Open => now < inner.opened_at + inner.cooldown_secs,
+            BreakerState::HalfOpen => now < inner.probe_started + self.config.base_cooldown_secs,
+        }
+    }
+
+    fn record_success_at(&self, now: u64) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        if inner.state != BreakerState::Closed {
+            tracing::info!("{} circuit closed", self.key());
+            inner.state = BreakerState::Closed;
+            inner.consecutive_trips = 0;
+            inner.cooldown_secs = self.config.base_cooldown_secs;
+            // Failures from before the outage shouldn't re-trip the breaker
+            inner.outcomes.clear();
+        }
+        inner.outcomes.push_back((now, true));
+        self.prune(&mut inner, now);
+    }
+
+    fn record_failure_at(&self, error: &str, now: u64) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        inner.last_error = Some(error.to_string());
+        inner.outcomes.push_back((now, false));
+        self.prune(&mut inner, now);
+
+        match inner.state {
+            BreakerState::HalfOpen => self.trip(&mut inner, now),
+            // Last-resort calls while open extend the cool-down
+            BreakerState::Open => inner.opened_at = now,
+            BreakerState::Closed => {
+                let (requests, rate) = window_stats(&inner.outcomes);
+                if requests >= self.config.min_requests && rate >= self.config.failure_rate {
+                    self.trip(&mut inner, now);
+                }
+            }
+        }
+    }
+
+    fn trip(&self, inner: &mut BreakerInner, now: u64) {
+        let factor = 1u64 << inner.consecutive_trips.min(16);
+        inner.cooldown_secs = self
+            .config
+            .base_cooldown_secs
+            .saturating_mul(factor)
+            .min(self.config.max_cooldown_secs);
+        inner.consecutive_trips += 1;
+        inner.trips += 1;
+        inner.opened_at = now;
+        inner.state = BreakerState::Open;
+        tracing::warn!(
+            "{} circuit open for {}s: {}",
+            self.key(),
+            inner.cooldown_secs,
+            inner.last_error.as_deref().unwrap_or("unknown error")
+        );
+    }
+
+    fn prune(&self, inner: &mut BreakerInner, now: u64) {
+        let cutoff = now.saturating_sub(self.config.window_secs);
+        while inner.outcomes.front().is_some_and(|(t, _)| *t < cutoff) {
+            inner.outcomes.pop_front();
+        }
+    }
+
+    fn snapshot_at(&self, now: u64) -> BreakerSnapshot {
+        let inner = self.inner.lock().ok();
+        let (state, failure_rate, requests, cooldown, retry_in, trips, last_error) = match inner {
+            Some(inner) => {
+                let (requests, rate) = window_stats(&inner.outcomes);
+                let retry_in = match inner.state {
+                    BreakerState::Open => {
+                        (inner.opened_at + inner.cooldown_secs).saturating_sub(now)
+                    }
+                    _ => 0,
+                };
+                (
+                    inner.state,
+                    rate,
+                    requests,
+                    inner.cooldown_secs,
+                    retry_in,
+                    inner.trips,
+                    inner.last_error.clone(),
+                )
+            }
+            None => (BreakerState::Closed, 0.0, 0, 0, 0, 0, None),
+        };
+        BreakerSnapshot {
+            key: self.key(),
+            provider: self.provider.clone(),
+            model: self.model.clone(),
+            state,
+            failure_rate,
+            requests_in_window: requests,
+            cooldown_secs: cooldown,
+            retry_in_secs: retry_in,
+            trips,
+            last_error,
+        }
+    }
+}
+
+/// (calls, error rate) of a window
+fn window_stats(outcomes: &VecDeque<(u64, bool)>) -> (usize, f64) {
+    if outcomes.is_empty() {
+        return (0, 0.0);
+    }
+    let failures = outcomes.iter().filter(|(_, ok)| !ok).count();
+    (outcomes.len(), failures as f64 / outcomes.len() as f64)
+}
+
+/// Registry key for a backend
+pub fn breaker_key(provider: &str, model: &str) -> String {
+    format!("{}:{}", provider, model)
+}
+
+/// Breakers keyed by provider and model
+#[derive(Default)]
+pub struct BreakerRegistry {
+    breakers: RwLock<BTreeMap<String, Arc<CircuitBreaker>>>,
+}
+
+impl BreakerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry shared across the process
+    pub fn global() -> Arc<BreakerRegistry> {
+        GLOBAL_REGISTRY.clone()
+    }
+
+    /// Breaker for a backend, created with `config` on first use
+    pub fn get(&self, provider: &str, model: &str, config: BreakerConfig) -> Arc<CircuitBreaker> {
+        let key = breaker_key(provider, model);
+        if let Some(breaker) = self.breakers.read().ok().and_then(|b| b.get(&key).cloned()) {
+            return breaker;
+        }
+        let breaker = Arc::new(CircuitBreaker::new(provider, model, config));
+        match self.breakers.write() {
+            Ok(mut breakers) => breakers.entry(key).or_insert(breaker).clone(),
+            Err(_) => breaker,
+        }
+    }
+
+    /// Snapshots of every breaker, sorted by key
+    pub fn snapshots(&self) -> Vec<BreakerSnapshot> {
+        self.breakers
+            .read()
+            .map(|b| b.values().map(|breaker| breaker.snapshot()).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Snapshots of the process-wide registry
+pub fn breaker_snapshots() -> Vec<BreakerSnapshot> {
+    GLOBAL_REGISTRY.snapshots()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BreakerConfig {
+        BreakerConfig {
+            window_secs: 60,
+            min_requests: 4,
+            failure_rate: 0.5,
+            base_cooldown_secs: 10,
+            max_cooldown_secs: 25,
+        }
+    }
+
+    #[test]
+    fn test_opens_on_error_rate_within_window() {
+        let breaker = CircuitBreaker::new("openai", "gpt-4o", config());
+        breaker.record_success_at(100);
+        breaker.record_success_at(101);
+        breaker.record_success_at(102);
+        breaker.record_failure_at("timeout", 103);
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        // Old successes age out of the window
+        for t in 165..168 {
+            breaker.record_failure_at("timeout", t);
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        breaker.record_failure_at("timeout", 168);
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(breaker.is_open_at(175));
+        assert!(!breaker.allow_request_at(175));
+
+        let snapshot = breaker.snapshot_at(175);
+        assert_eq!(snapshot.retry_in_secs, 3);
+        assert_eq!(snapshot.last_error.as_deref(), Some("timeout"));
+    }
+
+    #[test]
+    fn test_half_open_probe_and_exponential_cooldown() {
+        let breaker = CircuitBreaker::new("ollama", "llama3.2", config());
+        for t in 0..4 {
+            breaker.record_failure_at("connection refused", t);
+        }
+        assert_eq!(breaker.snapshot_at(3).cooldown_secs, 10);
+
+        // One probe after the cool-down; a failure doubles it
+        assert!(breaker.allow_request_at(13));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(!breaker.allow_request_at(14));
+        breaker.record_failure_at("connection refused", 14);
+        assert_eq!(breaker.snapshot_at(14).cooldown_secs, 20);
+
+        // Capped at max_cooldown_secs
+        assert!(breaker.allow_request_at(34));
+        breaker.record_failure_at("connection refused", 35);
+        assert_eq!(breaker.snapshot_at(35).cooldown_secs, 25);
+
+        // A successful probe closes it and resets the cool-down
+        assert!(breaker.allow_request_at(60));
+        breaker.record_success_at(61);
+        let snapshot = breaker.snapshot_at(61);
+        assert_eq!(snapshot.state, BreakerState::Closed);
+        assert_eq!(snapshot.cooldown_secs, 10);
+        assert_eq!(snapshot.trips, 3);
+    }
+
+    #[test]
+    fn test_registry_shares_breakers_by_key() {
+        let registry = BreakerRegistry::new();
+        let a = registry.get("gemini", "gemini-2.0-flash", BreakerConfig::default());
+        let b = registry.get("gemini", "gemini-2.0-flash", BreakerConfig::local());
+        registry.get("ollama", "llama3.2", BreakerConfig::local());
+        assert!(Arc::ptr_eq(&a, &b));
+
+        let snapshots = registry.snapshots();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].key, "gemini:gemini-2.0-flash");
+    }
+}
@@ -1,14 +1,30 @@
 //! Gemini AI client for screen analysis and semantic similarity
 //! Uses Google's Gemini API for vision and text understanding
 
-use crate::core::utils::clean_json_response;
-use anyhow::{Context, Result};
+use crate::agents::callbacks::TokenUsage;
+use crate::ai::providers::chat::{self, ChatMessage, ChatRole};
+use crate::ai::providers::streaming::{self, Framing, StreamEvent};
+use crate::ai::providers::tools::{self, ToolCompletion, ToolTurn};
+use crate::ai::providers::{
+    CompletionOptions, CompletionStream, Provider, ProviderError, ProviderInfo,
+};
+use crate::ai::usage::{self, StreamUsage};
+use crate::mcp::types::ToolDescriptor;
+use anyhow::Result;
+use async_trait::async_trait;
 use reqwest::Client;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio_util::sync::CancellationToken;
+
+/// Default Gemini model used when none is configured
+pub const DEFAULT_GEMINI_MODEL: &str = "gemini-2.0-flash";
 
 pub struct GeminiClient {
     client: Client,
     api_key: String,
+    model: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -52,12 +68,31 @@ struct GenerationConfig {
     temperature: f32,
     #[serde(rename = "maxOutputTokens")]
     max_output_tokens: u32,
+    /// "application/json" for JSON mode
+    #[serde(rename = "responseMimeType", skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<&'static str>,
 }
 
 #[derive(Debug, Deserialize)]
 struct GeminiResponse {
     candidates: Option<Vec<Candidate>>,
     error: Option<GeminiError>,
+    #[serde(default, rename = "usageMetadata")]
+    usage_metadata: Option<UsageMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageMetadata {
+    #[serde(default, rename = "promptTokenCount")]
+    prompt_token_count: usize,
+    #[serde(default, rename = "candidatesTokenCount")]
+    candidates_token_count: usize,
+}
+
+impl UsageMetadata {
+    fn token_usage(&self) -> TokenUsage {
+        TokenUsage::new(self.prompt_token_count, self.candidates_token_count)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -80,203 +115,247 @@ struct GeminiError {
     message: String,
 }
 
-impl GeminiClient {
-    pub fn new(api_key: String) -> Self {
-        Self {
-            client: Client::new(),
-            api_key,
-        }
-    }
-
-    fn get_api_url(&self) -> String {
-        format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent?key={}",
-            self.api_key
-        )
-    }
+/// Streamed chunks may omit content (e.g. the final chunk carrying only finishReason)
+#[derive(Debug, Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    candidates: Vec<StreamCandidate>,
+    error: Option<GeminiError>,
+    #[serde(default, rename = "usageMetadata")]
+    usage_metadata: Option<UsageMetadata>,
+}
 
-    /// Analyze screenshot with Gemini Vision
-    pub async fn analyze_image(&self, base64_image: &str, prompt: &str) -> Result<String> {
-        if self.api_key.is_empty() {
-            return Ok("AI Analysis unavailable (No API Key)".to_string());
-        }
+#[derive(Debug, Deserialize)]
+struct StreamCandidate {
+    content: Option<StreamContent>,
+}
 
-        let request = GeminiRequest {
-            contents: vec![Content {
-                parts: vec![
-                    Part::Text {
-                        text: prompt.to_string(),
-                    },
-                    Part::Image {
-                        inline_data: InlineData {
-                            mime_type: "image/png".to_string(),
-                            data: base64_image.to_string(),
-                        },
-                    },
-                ],
-            }],
-            generation_config: Some(GenerationConfig {
-                temperature: 0.7,
-                max_output_tokens: 500,
-            }),
-            tools: None,
-        };
+#[derive(Debug, Deserialize)]
+struct StreamContent {
+    #[serde(default)]
+    parts: Vec<StreamPart>,
+}
 
-        let response = self
-            .client
-            .post(self.get_api_url())
-            .json(&request)
-            .send()
-            .await?
-            .json::<GeminiResponse>()
-            .await?;
+#[derive(Debug, Deserialize)]
+struct StreamPart {
+    text: Option<String>,
+}
 
-        if let Some(error) = response.error {
-            return Err(anyhow::anyhow!("Gemini API error: {}", error.message));
+/// Conversation in the Gemini `contents` format
+///
+/// Function responses are sent as user turns; consecutive responses share one turn.
+fn tool_contents(turns: &[ToolTurn]) -> Vec<Value> {
+    let mut contents: Vec<Value> = Vec::new();
+    for turn in turns {
+        match turn {
+            ToolTurn::User { content } => {
+                contents.push(json!({"role": "user", "parts": [{"text": content}]}));
+            }
+            ToolTurn::Assistant { text, tool_calls } => {
+                let mut parts = Vec::new();
+                if let Some(text) = text {
+                    parts.push(json!({"text": text}));
+                }
+                for call in tool_calls {
+                    parts.push(json!({
+                        "functionCall": {
+                            "name": tools::wire_name(&call.name),
+                            "args": call.arguments,
+                        }
+                    }));
+                }
+                contents.push(json!({"role": "model", "parts": parts}));
+            }
+            ToolTurn::ToolResult { name, content, .. } => {
+                let part = json!({
+                    "functionResponse": {
+                        "name": tools::wire_name(name),
+                        "response": {"content": content},
+                    }
+                });
+                let previous_is_response = contents
+                    .last()
+                    .is_some_and(|last| last["parts"][0].get("functionResponse").is_some());
+                match contents
+                    .last_mut()
+                    .and_then(|last| last["parts"].as_array_mut())
+                {
+                    Some(parts) if previous_is_response => parts.push(part),
+                    _ => contents.push(json!({"role": "user", "parts": [part]})),
+                }
+            }
         }
+    }
+    contents
+}
 
-        let candidates = response
-            .candidates
-            .ok_or_else(|| anyhow::anyhow!("No candidates in response"))?;
+/// Chat messages in the Gemini `contents` format, plus the system instruction
+fn chat_contents(messages: &[ChatMessage]) -> (Option<Value>, Vec<Value>) {
+    let (system, turns) = chat::split_system(messages);
+    let contents = turns
+        .into_iter()
+        .map(|(role, text)| {
+            let role = if role == ChatRole::Assistant {
+                "model"
+            } else {
+                "user"
+            };
+            json!({"role": role, "parts": [{"text": text}]})
+        })
+        .collect();
+    let system = system.map(|text| json!({"parts": [{"text": text}]}));
+    (system, contents)
+}
 
-        let text = candidates
-            .first()
-            .and_then(|c| c.content.parts.first().map(|p| p.text.clone()))
-            .ok_or_else(|| anyhow::anyhow!("No text in response"))?;
+/// Tool parameters as a Gemini function schema
+/// Gemini's OpenAPI subset rejects `default` on properties
+fn function_parameters(tool: &ToolDescriptor) -> Value {
+    let mut parameters = tools::parameters(tool);
+    if let Some(properties) = parameters
+        .get_mut("properties")
+        .and_then(Value::as_object_mut)
+    {
+        for property in properties.values_mut() {
+            if let Some(property) = property.as_object_mut() {
+                property.remove("default");
+            }
+        }
+    }
+    parameters
+}
 
-        Ok(text)
+/// Text and function calls from a generateContent response
+fn parse_tool_completion(response: &Value, tools: &[ToolDescriptor]) -> ToolCompletion {
+    let mut completion = ToolCompletion::default();
+    let parts = response["candidates"][0]["content"]["parts"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    for part in parts {
+        if let Some(call) = part.get("functionCall") {
+            let name = call["name"].as_str().unwrap_or_default();
+            completion.tool_calls.push(tools::tool_call(
+                tools::resolve_name(name, tools),
+                call.get("args").cloned().unwrap_or(Value::Null),
+                None,
+            ));
+        } else if let Some(text) = part["text"].as_str().filter(|t| !t.trim().is_empty()) {
+            completion.text = Some(match completion.text.take() {
+                Some(existing) => format!("{}{}", existing, text),
+                None => text.to_string(),
+            });
+        }
     }
+    completion
+}
 
-    /// Generate raw text from a prompt
-    pub async fn generate_text(&self, prompt: &str) -> Result<String> {
-        if self.api_key.is_empty() {
-            return Ok(String::new());
+/// Parse one SSE payload of a streamGenerateContent response
+fn parse_stream_chunk(payload: &str) -> StreamEvent {
+    let chunk: StreamChunk = match serde_json::from_str(payload) {
+        Ok(chunk) => chunk,
+        Err(e) => {
+            return StreamEvent::Error(ProviderError::APIError(format!(
+                "Invalid stream chunk: {}",
+                e
+            )))
         }
+    };
+    if let Some(error) = chunk.error {
+        return StreamEvent::Error(ProviderError::APIError(error.message));
+    }
+    let text: String = chunk
+        .candidates
+        .into_iter()
+        .next()
+        .and_then(|c| c.content)
+        .map(|c| c.parts.into_iter().filter_map(|p| p.text).collect())
+        .unwrap_or_default();
+    if text.is_empty() {
+        StreamEvent::Skip
+    } else {
+        StreamEvent::Delta(text)
+    }
+}
 
-        let request = GeminiRequest {
-            contents: vec![Content {
-                parts: vec![Part::Text {
-                    text: prompt.to_string(),
-                }],
-            }],
-            generation_config: Some(GenerationConfig {
-                temperature: 0.7,
-                max_output_tokens: 500,
-            }),
-            tools: None,
-        };
+/// Token usage of a streamed chunk (cumulative on each chunk)
+fn stream_usage(payload: &str) -> Option<TokenUsage> {
+    serde_json::from_str::<StreamChunk>(payload)
+        .ok()?
+        .usage_metadata
+        .map(|u| u.token_usage())
+}
 
-        let response = self
-            .client
-            .post(self.get_api_url())
-            .json(&request)
-            .send()
-            .await?
-            .json::<GeminiResponse>()
-            .await?;
+impl GeminiClient {
+    pub fn new(api_key: String) -> Self {
+        Self::with_model(api_key, DEFAULT_GEMINI_MODEL)
+    }
 
-        if let Some(error) = response.error {
-            return Err(anyhow::anyhow!("Gemini API error: {}", error.message));
+    /// Create a client bound to a specific Gemini model
+    pub fn with_model(api_key: String, model: &str) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            model: model.to_string(),
         }
+    }
 
-        let candidates = response
-            .candidates
-            .ok_or_else(|| anyhow::anyhow!("No candidates"))?;
-
-        let text = candidates
-            .first()
-            .and_then(|c| c.content.parts.first().map(|p| p.text.clone()))
-            .ok_or_else(|| anyhow::anyhow!("No text in response"))?;
+    fn track_usage(&self, usage: Option<&UsageMetadata>) {
+        if let Some(usage) = usage {
+            usage::record_usage("gemini", &self.model, usage.token_usage());
+        }
+    }
 
-        Ok(text.trim().to_string())
+    fn get_api_url(&self) -> String {
+        format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
+            self.model, self.api_key
+        )
     }
 
-    /// Calculate semantic similarity between two URLs (returns 0.0-1.0)
-    pub async fn calculate_url_similarity(&self, url1: &str, url2: &str) -> Result<f32> {
-        if self.api_key.is_empty() {
-            return Ok(0.0);
-        }
+    fn get_stream_url(&self) -> String {
+        format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:streamGenerateContent?alt=sse&key={}",
+            self.model, self.api_key
+        )
+    }
 
-        let prompt = format!(
-            "Compare these two URLs semantically. Consider the topic, domain, and content they represent.
-            Return ONLY a single number between 0.0 and 1.0 representing their similarity.
-            0.0 means completely unrelated, 1.0 means identical or very closely related.
-            
-            URL1: {}
-            URL2: {}
-            
-            Respond with just the number, nothing else.",
-            url1, url2
-        );
-
-        // We can use the internal logic here or just keep as is to avoid breaking changes if specific config needed
-        // For now, keeping as is but improved structure
-        let request = GeminiRequest {
+    fn text_request(prompt: &str, temperature: f32, max_output_tokens: u32) -> GeminiRequest {
+        GeminiRequest {
             contents: vec![Content {
-                parts: vec![Part::Text { text: prompt }],
+                parts: vec![Part::Text {
+                    text: prompt.to_string(),
+                }],
             }],
             generation_config: Some(GenerationConfig {
-                temperature: 0.1,
-                max_output_tokens: 10,
+                temperature,
+                max_output_tokens,
+                response_mime_type: None,
             }),
             tools: None,
-        };
-
-        let response = self
-            .client
-            .post(self.get_api_url())
-            .json(&request)
-            .send()
-            .await?
-            .json::<GeminiResponse>()
-            .await?;
-
-        if let Some(error) = response.error {
-            return Err(anyhow::anyhow!("Gemini API error: {}", error.message));
         }
-
-        let candidates = response
-            .candidates
-            .ok_or_else(|| anyhow::anyhow!("No candidates"))?;
-
-        let text = candidates
-            .first()
-            .and_then(|c| c.content.parts.first().map(|p| p.text.clone()))
-            .ok_or_else(|| anyhow::anyhow!("No text in response"))?;
-
-        let similarity = text.trim().parse::<f32>().unwrap_or(0.0);
-
-        Ok(similarity.clamp(0.0, 1.0))
     }
 
-    /// Generate Ghost dialogue based on context
-    pub async fn generate_dialogue(&self, context: &str, personality: &str) -> Result<String> {
-        if self.api_key.is_empty() {
-            return Ok("...".to_string());
+    /// Generate text with explicit sampling parameters, optionally in JSON mode
+    /// or grounded with Google Search
+    async fn generate_with_config(
+        &self,
+        prompt: &str,
+        temperature: f32,
+        max_output_tokens: u32,
+        json: bool,
+        grounded: bool,
+    ) -> Result<String> {
+        let mut request = Self::text_request(prompt, temperature, max_output_tokens);
+        if grounded {
+            // Search grounding can't be combined with JSON mode; the caller
+            // still parses (and repairs) the reply as JSON
+            request.tools = Some(vec![Tool {
+                google_search: GoogleSearch {},
+            }]);
+        } else if let (true, Some(config)) = (json, request.generation_config.as_mut()) {
+            config.response_mime_type = Some("application/json");
         }
 
-        let prompt = format!(
-            "You are a desktop companion. Your personality is: {}
-            
-            Based on this context about what the user is viewing: {}
-            
-            Generate a short, helpful, or intriguing comment (max 100 characters).
-            If in 'Mystery' mode, be cryptic. If in 'Companion' mode, be helpful but concise.
-            Stay in character.",
-            personality, context
-        );
-
-        let request = GeminiRequest {
-            contents: vec![Content {
-                parts: vec![Part::Text { text: prompt }],
-            }],
-            generation_config: Some(GenerationConfig {
-                temperature: 0.9,
-                max_output_tokens: 50,
-            }),
-            tools: None,
-        };
-
         let response = self
             .client
             .post(self.get_api_url())
@@ -285,6 +364,7 @@ impl GeminiClient {
             .await?
             .json::<GeminiResponse>()
             .await?;
+        self.track_usage(response.usage_metadata.as_ref());
 
         if let Some(error) = response.error {
             return Err(anyhow::anyhow!("Gemini API error: {}", error.message));
@@ -302,65 +382,34 @@ impl GeminiClient {
         Ok(text.trim().to_string())
     }
 
-    /// Generate a dynamic puzzle based on current page context
-    /// Creates unique puzzles based on what the user is currently viewing
-    pub async fn generate_dynamic_puzzle(
-        &self,
-        url: &str,
-        page_title: &str,
-        page_content: &str,
-        history_context: &str,
-    ) -> Result<DynamicPuzzle> {
-        tracing::info!(
-            "Generating dynamic puzzle for URL: {} (title: {})",
-            url,
-            page_title
-        );
-
-        let prompt = format!(
-            r#"Based on this webpage the user is viewing, generate a creative puzzle for a mystery game.
-            Use Google Search to find a connection to a real-world event, person, or historical fact related to this page's topic.
-            
-            Also consider the user's recent browsing history (provided below) to see if you can make a thematic connection to their recent interests, but prioritize the CURRENT page for the clue.
-
-URL: {}
-Title: {}
-Content snippet: {}
-
-Recent Browsing History:
-{}
-
-Generate a JSON object with these fields:
-- "clue": A mysterious, cryptic clue that relates to this page's topic but leads to a DIFFERENT related page (max 100 chars)
-- "target_description": What the player should find (a related but different topic/page)
-- "target_url_pattern": A regex pattern that would match the solution URL
-- "hints": An array of 3 progressive hints
-
-Example response format (respond ONLY with valid JSON, no markdown):
-{{"clue": "The cipher machine's nemesis worked at a park...", "target_description": "Alan Turing Bletchley Park", "target_url_pattern": "(turing|bletchley)", "hints": ["Think about who cracked the code...", "A British mathematician...", "Search for Alan Turing"]}}
-
-Make the puzzle interesting and educational. The target should be related but not the same page."#,
-            url,
-            page_title,
-            &page_content.chars().take(500).collect::<String>(),
-            history_context
-        );
+    /// Analyze screenshot with Gemini Vision
+    pub async fn analyze_image(&self, base64_image: &str, prompt: &str) -> Result<String> {
+        if self.api_key.is_empty() {
+            return Ok("AI Analysis unavailable (No API Key)".to_string());
+        }
 
         let request = GeminiRequest {
             contents: vec![Content {
-                parts: vec![Part::Text { text: prompt }],
+                parts: vec![
+                    Part::Text {
+                        text: prompt.to_string(),
+                    },
+                    Part::Image {
+                        inline_data: InlineData {
+                            mime_type: "image/png".to_string(),
+                            data: base64_image.to_string(),
+                        },
+                    },
+                ],
             }],
-            tools: Some(vec![Tool {
-                google_search: GoogleSearch {},
-            }]),
             generation_config: Some(GenerationConfig {
-                temperature: 0.8,
-                max_output_tokens: 300,
+                temperature: 0.7,
+                max_output_tokens: 500,
+                response_mime_type: None,
             }),
+            tools: None,
         };
 
-        tracing::debug!("Sending request to Gemini API...");
-
         let response = self
             .client
             .post(self.get_api_url())
@@ -369,76 +418,40 @@ Make the puzzle interesting and educational. The target should be related but no
             .await?
             .json::<GeminiResponse>()
             .await?;
-
-        tracing::debug!("Gemini response received: {:?}", response);
+        self.track_usage(response.usage_metadata.as_ref());
 
         if let Some(error) = response.error {
-            tracing::error!("Gemini API error: {}", error.message);
             return Err(anyhow::anyhow!("Gemini API error: {}", error.message));
         }
 
         let candidates = response
             .candidates
-            .ok_or_else(|| anyhow::anyhow!("No candidates"))?;
+            .ok_or_else(|| anyhow::anyhow!("No candidates in response"))?;
 
         let text = candidates
             .first()
             .and_then(|c| c.content.parts.first().map(|p| p.text.clone()))
             .ok_or_else(|| anyhow::anyhow!("No text in response"))?;
 
-        tracing::debug!("Raw AI response text: {}", text);
-
-        // Clean up markdown code blocks if present
-        let clean_text = clean_json_response(&text);
-
-        // Parse JSON response
-        let puzzle: DynamicPuzzle = serde_json::from_str(clean_text)
-            .context(format!("Failed to parse puzzle JSON. Raw: {}", text))?;
-
-        tracing::info!("Successfully generated puzzle: {:?}", puzzle.clue);
-
-        Ok(puzzle)
+        Ok(text)
     }
 
-    /// Verify if a screenshot contains the solution to a puzzle
-    pub async fn verify_screenshot_clue(
-        &self,
-        base64_image: &str,
-        clue_description: &str,
-    ) -> Result<VerificationResult> {
+    /// Generate raw text from a prompt
+    pub async fn generate_text(&self, prompt: &str) -> Result<String> {
         if self.api_key.is_empty() {
-            return Err(anyhow::anyhow!("No API Key configured"));
+            return Ok(String::new());
         }
 
-        let prompt = format!(
-            "Analyze this screenshot. Does it contain content matching this description: '{}'?
-            
-            Respond with a JSON object:
-            {{
-                \"found\": boolean,
-                \"confidence\": number (0.0-1.0),
-                \"explanation\": \"Short explanation of what was found or missing\"
-            }}
-            
-            Be strict and accurate. Only return true if the visual proof CLEARLY matches the specific target description.",
-            clue_description
-        );
-
         let request = GeminiRequest {
             contents: vec![Content {
-                parts: vec![
-                    Part::Text { text: prompt },
-                    Part::Image {
-                        inline_data: InlineData {
-                            mime_type: "image/png".to_string(),
-                            data: base64_image.to_string(),
-                        },
-                    },
-                ],
+                parts: vec![Part::Text {
+                    text: prompt.to_string(),
+                }],
             }],
             generation_config: Some(GenerationConfig {
-                temperature: 0.2, // Lower temperature for more objective analysis
-                max_output_tokens: 200,
+                temperature: 0.7,
+                max_output_tokens: 500,
+                response_mime_type: None,
             }),
             tools: None,
         };
@@ -451,6 +464,7 @@ Make the puzzle interesting and educational. The target should be related but no
             .await?
             .json::<GeminiResponse>()
             .await?;
+        self.track_usage(response.usage_metadata.as_ref());
 
         if let Some(error) = response.error {
             return Err(anyhow::anyhow!("Gemini API error: {}", error.message));
@@ -465,15 +479,12 @@ Make the puzzle interesting and educational. The target should be related but no
             .and_then(|c| c.content.parts.first().map(|p| p.text.clone()))
             .ok_or_else(|| anyhow::anyhow!("No text in response"))?;
 
-        let clean_text = clean_json_response(&text);
-
-        let result: VerificationResult = serde_json::from_str(clean_text)?;
-        Ok(result)
+        Ok(text.trim().to_string())
     }
 }
 
 /// A dynamically generated puzzle based on screen context
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DynamicPuzzle {
     pub clue: String,
     pub target_description: String,
@@ -482,7 +493,7 @@ pub struct DynamicPuzzle {
 }
 
 /// Result of screenshot verification
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct VerificationResult {
     pub found: bool,
     pub confidence: f32,
@@ -490,7 +501,7 @@ pub struct VerificationResult {
 }
 
 /// An adaptive puzzle generated from observed user activity
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AdaptivePuzzle {
     /// The puzzle clue
     pub clue: String,
@@ -508,191 +519,239 @@ pub struct AdaptivePuzzle {
     pub theme: String,
 }
 
+/// Context about a user activity for adaptive puzzle generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityContext {
+    pub app_name: String,
+    pub app_category: String,
+    pub description: String,
+    pub content_context: Option<String>,
+}
+
 // ============================================================================
-// Adaptive Behavior Methods
+// Provider Trait Implementation
 // ============================================================================
 
-impl GeminiClient {
-    /// Generate an adaptive puzzle based on user's observed activity patterns
-    /// Uses activity history to create contextually relevant puzzles
-    pub async fn generate_adaptive_puzzle(
+#[async_trait]
+impl Provider for GeminiClient {
+    fn name(&self) -> &str {
+        "gemini"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn is_available(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String, ProviderError> {
+        self.complete_with_options(prompt, CompletionOptions::default())
+            .await
+    }
+
+    async fn complete_with_options(
         &self,
-        activities: &[ActivityContext],
-        current_app: Option<&str>,
-        current_content: Option<&str>,
-    ) -> Result<AdaptivePuzzle> {
+        prompt: &str,
+        options: CompletionOptions,
+    ) -> Result<String, ProviderError> {
         if self.api_key.is_empty() {
-            return Err(anyhow::anyhow!("No API Key configured"));
+            return Err(ProviderError::NotConfigured("gemini".to_string()));
         }
 
-        // Build activity context string
-        let activity_summary = activities
-            .iter()
-            .map(|a| format!("- {} ({}): {}", a.app_category, a.app_name, a.description))
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        let current_context = match (current_app, current_content) {
-            (Some(app), Some(content)) => format!("Currently using {} viewing {}", app, content),
-            (Some(app), None) => format!("Currently using {}", app),
-            _ => "No current context".to_string(),
-        };
+        let temperature = options.temperature.unwrap_or(0.7) as f32;
+        let max_tokens = options.max_tokens.unwrap_or(500) as u32;
 
-        let prompt = format!(
-            r#"You are creating an educational puzzle for a desktop companion game. 
-The user's recent activity shows their interests. Create a puzzle that connects to what they've been doing.
-
-Recent User Activity:
-{}
-
-Current Context: {}
-
-Create a fun, educational puzzle that:
-1. Relates to topics from their activity
-2. Leads them to discover something new but related
-3. Has a clear, verifiable answer (a webpage they can find)
-
-Respond with ONLY valid JSON (no markdown):
-{{
-    "clue": "A mysterious, engaging clue (max 100 chars)",
-    "target_description": "What they should find",
-    "target_url_pattern": "regex pattern for solution URL",
-    "hints": ["hint 1", "hint 2", "hint 3"],
-    "inspired_by": "which activity inspired this",
-    "difficulty": 2,
-    "theme": "category (history, science, tech, culture, etc.)"
-}}"#,
-            activity_summary, current_context
-        );
+        let json = options.response_schema.is_some();
+        self.generate_with_config(prompt, temperature, max_tokens, json, options.grounded)
+            .await
+            .map_err(|e| ProviderError::APIError(e.to_string()))
+    }
 
-        let request = GeminiRequest {
-            contents: vec![Content {
-                parts: vec![Part::Text { text: prompt }],
-            }],
-            tools: Some(vec![Tool {
-                google_search: GoogleSearch {},
-            }]),
-            generation_config: Some(GenerationConfig {
-                temperature: 0.8,
-                max_output_tokens: 400,
-            }),
-        };
+    async fn complete_stream(
+        &self,
+        prompt: &str,
+        options: CompletionOptions,
+        cancel: CancellationToken,
+    ) -> Result<CompletionStream, ProviderError> {
+        if self.api_key.is_empty() {
+            return Err(ProviderError::NotConfigured("gemini".to_string()));
+        }
+
+        let temperature = options.temperature.unwrap_or(0.7) as f32;
+        let max_tokens = options.max_tokens.unwrap_or(500) as u32;
+        let request = Self::text_request(prompt, temperature, max_tokens);
 
         let response = self
             .client
-            .post(self.get_api_url())
+            .post(self.get_stream_url())
             .json(&request)
             .send()
-            .await?
-            .json::<GeminiResponse>()
             .await?;
 
-        if let Some(error) = response.error {
-            return Err(anyhow::anyhow!("Gemini API error: {}", error.message));
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(ProviderError::APIError(format!(
+                "Status {}: {}",
+                status, text
+            )));
         }
 
-        let candidates = response
-            .candidates
-            .ok_or_else(|| anyhow::anyhow!("No candidates"))?;
-
-        let text = candidates
-            .first()
-            .and_then(|c| c.content.parts.first().map(|p| p.text.clone()))
-            .ok_or_else(|| anyhow::anyhow!("No text in response"))?;
-
-        let clean_text = clean_json_response(&text);
-
-        let puzzle: AdaptivePuzzle = serde_json::from_str(clean_text).context(format!(
-            "Failed to parse adaptive puzzle JSON. Raw: {}",
-            clean_text
-        ))?;
-
-        tracing::info!(
-            "Generated adaptive puzzle inspired by '{}': {}",
-            puzzle.inspired_by,
-            puzzle.clue
-        );
-
-        Ok(puzzle)
+        Ok(streaming::stream_response(
+            response,
+            cancel,
+            Framing::Sse,
+            StreamUsage::new(self.name(), &self.model, stream_usage),
+            parse_stream_chunk,
+        ))
     }
 
-    /// Generate contextual dialogue based on observation history
-    pub async fn generate_contextual_dialogue(
+    async fn complete_chat(
         &self,
-        recent_activities: &[ActivityContext],
-        current_context: &str,
-        ghost_mood: &str,
-    ) -> Result<String> {
+        messages: &[ChatMessage],
+        options: CompletionOptions,
+    ) -> Result<String, ProviderError> {
         if self.api_key.is_empty() {
-            return Ok("...".to_string());
+            return Err(ProviderError::NotConfigured("gemini".to_string()));
         }
 
-        let activity_summary = recent_activities
-            .iter()
-            .take(5)
-            .map(|a| format!("{} ({})", a.description, a.app_name))
-            .collect::<Vec<_>>()
-            .join(", ");
+        let (system, contents) = chat_contents(messages);
+        let mut body = json!({
+            "contents": contents,
+            "generationConfig": {
+                "temperature": options.temperature.unwrap_or(0.7),
+                "maxOutputTokens": options.max_tokens.unwrap_or(500),
+            },
+        });
+        if let Some(system) = system {
+            body["systemInstruction"] = system;
+        }
+        if options.response_schema.is_some() {
+            body["generationConfig"]["responseMimeType"] = json!("application/json");
+        }
 
-        let prompt = format!(
-            r#"You are a mysterious AI companion ghost. Generate a short, contextual comment.
+        let response = self
+            .client
+            .post(self.get_api_url())
+            .json(&body)
+            .send()
+            .await?;
 
-Ghost mood: {}
-Recent user activities: {}
-Current context: {}
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(ProviderError::APIError(format!(
+                "Status {}: {}",
+                status, text
+            )));
+        }
 
-The ghost should:
-- Reference what the user has been doing naturally
-- Be helpful yet mysterious
-- Keep it under 80 characters
-- Stay in character
+        let response: GeminiResponse = response.json().await?;
+        self.track_usage(response.usage_metadata.as_ref());
+        if let Some(error) = response.error {
+            return Err(ProviderError::APIError(error.message));
+        }
 
-Respond with ONLY the dialogue text, no quotes or formatting."#,
-            ghost_mood, activity_summary, current_context
-        );
+        response
+            .candidates
+            .and_then(|c| c.into_iter().next())
+            .and_then(|c| c.content.parts.into_iter().next())
+            .map(|p| p.text.trim().to_string())
+            .ok_or_else(|| ProviderError::APIError("No text in response".to_string()))
+    }
 
-        let request = GeminiRequest {
-            contents: vec![Content {
-                parts: vec![Part::Text { text: prompt }],
-            }],
-            generation_config: Some(GenerationConfig {
-                temperature: 0.9,
-                max_output_tokens: 50,
-            }),
-            tools: None,
-        };
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    fn supports_grounding(&self) -> bool {
+        true
+    }
+
+    async fn complete_with_tools(
+        &self,
+        turns: &[ToolTurn],
+        options: CompletionOptions,
+    ) -> Result<ToolCompletion, ProviderError> {
+        if self.api_key.is_empty() {
+            return Err(ProviderError::NotConfigured("gemini".to_string()));
+        }
+
+        let tools = options.tools.unwrap_or_default();
+        let mut body = json!({
+            "contents": tool_contents(turns),
+            "generationConfig": {
+                "temperature": options.temperature.unwrap_or(0.7),
+                "maxOutputTokens": options.max_tokens.unwrap_or(1024),
+            },
+        });
+        if !tools.is_empty() {
+            let declarations: Vec<Value> = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tools::wire_name(&tool.name),
+                        "description": tool.description,
+                        "parameters": function_parameters(tool),
+                    })
+                })
+                .collect();
+            body["tools"] = json!([{ "functionDeclarations": declarations }]);
+        }
 
         let response = self
             .client
             .post(self.get_api_url())
-            .json(&request)
+            .json(&body)
             .send()
-            .await?
-            .json::<GeminiResponse>()
             .await?;
 
-        if let Some(error) = response.error {
-            return Err(anyhow::anyhow!("Gemini API error: {}", error.message));
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(ProviderError::APIError(format!(
+                "Status {}: {}",
+                status, text
+            )));
         }
 
-        let candidates = response
-            .candidates
-            .ok_or_else(|| anyhow::anyhow!("No candidates"))?;
+        let response: Value = response.json().await?;
+        if let Ok(usage) =
+            serde_json::from_value::<UsageMetadata>(response["usageMetadata"].clone())
+        {
+            self.track_usage(Some(&usage));
+        }
+        Ok(parse_tool_completion(&response, &tools))
+    }
 
-        let text = candidates
-            .first()
-            .and_then(|c| c.content.parts.first().map(|p| p.text.clone()))
-            .ok_or_else(|| anyhow::anyhow!("No text"))?;
+    fn supports_vision(&self) -> bool {
+        true
+    }
+
+    async fn analyze_image(
+        &self,
+        base64_image: &str,
+        prompt: &str,
+    ) -> Result<String, ProviderError> {
+        if self.api_key.is_empty() {
+            return Err(ProviderError::NotConfigured("gemini".to_string()));
+        }
 
-        Ok(text.trim().replace('"', ""))
+        GeminiClient::analyze_image(self, base64_image, prompt)
+            .await
+            .map_err(|e| ProviderError::APIError(e.to_string()))
     }
-}
 
-/// Context about a user activity for adaptive puzzle generation
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ActivityContext {
-    pub app_name: String,
-    pub app_category: String,
-    pub description: String,
-    pub content_context: Option<String>,
+    fn info(&self) -> ProviderInfo {
+        ProviderInfo {
+            name: self.name().to_string(),
+            model: self.model.clone(),
+            supports_vision: true,
+            supports_streaming: true,
+            context_window: 1_000_000,
+            max_tokens: 8192,
+        }
+    }
 }
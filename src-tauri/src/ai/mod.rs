@@ -1,10 +1,16 @@
 //! AI module - AI providers and clients
 
 pub mod ai_provider;
+pub mod circuit_breaker;
 pub mod gemini_client;
 pub mod ollama_client;
+pub mod prompts;
 pub mod providers;
+pub mod scheduler;
+pub mod structured;
+pub mod usage;
 pub mod vision;
+pub mod vision_cache;
 
 // Re-export commonly used types
 pub use ai_provider::{ProviderType, SmartAiRouter};
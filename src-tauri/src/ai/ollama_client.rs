@@ -1,16 +1,36 @@
 //! Ollama AI client for local LLM inference
 //! Communicates with Ollama server via HTTP API at localhost:11434
 
-use crate::core::utils::{clean_json_response, runtime_config};
+use crate::agents::callbacks::TokenUsage;
+use crate::ai::providers::chat::{self, ChatMessage};
+use crate::ai::providers::streaming::{self, Framing, StreamEvent};
+use crate::ai::providers::tools::{self, ToolCompletion, ToolTurn};
+use crate::ai::providers::{
+    CompletionOptions, CompletionStream, Provider, ProviderError, ProviderInfo,
+};
+use crate::ai::usage::{self, StreamUsage};
+use crate::core::utils::{
+    clean_json_response, runtime_config, DEFAULT_OLLAMA_EMBEDDING_MODEL, DEFAULT_OLLAMA_TEXT_MODEL,
+    DEFAULT_OLLAMA_VISION_MODEL,
+};
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Model downloads can take a long time on slow connections
+const PULL_TIMEOUT_SECS: u64 = 2 * 60 * 60;
 
 /// Ollama client for local LLM inference
 /// Configuration is read dynamically from RuntimeConfig to support runtime changes
 pub struct OllamaClient {
     client: Client,
+    /// Text model pinned at construction (overrides RuntimeConfig when set)
+    model: Option<String>,
+    /// Embedding model (DEFAULT_OLLAMA_EMBEDDING_MODEL when unset)
+    embedding_model: Option<String>,
 }
 
 // ============================================================================
@@ -24,8 +44,9 @@ struct OllamaGenerateRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     images: Option<Vec<String>>,
     stream: bool,
+    /// "json" or a JSON Schema
     #[serde(skip_serializing_if = "Option::is_none")]
-    format: Option<String>,
+    format: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     options: Option<OllamaOptions>,
 }
@@ -40,6 +61,65 @@ struct OllamaOptions {
 #[derive(Debug, Deserialize)]
 struct OllamaGenerateResponse {
     response: String,
+    #[serde(default)]
+    prompt_eval_count: usize,
+    #[serde(default)]
+    eval_count: usize,
+}
+
+/// One NDJSON line of a streamed /api/generate response
+#[derive(Debug, Deserialize)]
+struct OllamaStreamChunk {
+    #[serde(default)]
+    response: String,
+    #[serde(default)]
+    done: bool,
+    error: Option<String>,
+    /// Set on the final (`done`) line only
+    #[serde(default)]
+    prompt_eval_count: usize,
+    #[serde(default)]
+    eval_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaEmbeddingRequest<'a> {
+    model: &'a str,
+    prompt: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbeddingResponse {
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    message: OllamaChatMessage,
+    #[serde(default)]
+    prompt_eval_count: usize,
+    #[serde(default)]
+    eval_count: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChatMessage {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_calls: Vec<OllamaToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunctionCall,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
 }
 
 #[derive(Debug, Deserialize)]
@@ -52,6 +132,95 @@ struct OllamaModel {
     name: String,
 }
 
+#[derive(Debug, Serialize)]
+struct OllamaModelRequest<'a> {
+    model: &'a str,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+/// One NDJSON line of a streamed /api/pull response
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PullProgress {
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub digest: Option<String>,
+    /// Bytes of the layer being downloaded
+    #[serde(default)]
+    pub total: Option<u64>,
+    #[serde(default)]
+    pub completed: Option<u64>,
+    #[serde(default, skip_serializing)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaShowResponse {
+    #[serde(default)]
+    parameters: Option<String>,
+    #[serde(default)]
+    details: OllamaModelDetails,
+    #[serde(default)]
+    model_info: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    capabilities: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OllamaModelDetails {
+    #[serde(default)]
+    family: Option<String>,
+    #[serde(default)]
+    parameter_size: Option<String>,
+    #[serde(default)]
+    quantization_level: Option<String>,
+}
+
+/// Details of an installed model (from /api/show)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelDetails {
+    pub name: String,
+    pub family: Option<String>,
+    pub parameter_size: Option<String>,
+    pub quantization_level: Option<String>,
+    /// Maximum context window in tokens
+    pub context_length: Option<u64>,
+    /// e.g. "completion", "vision", "tools", "embedding"
+    pub capabilities: Vec<String>,
+    /// Modelfile parameters, one `key value` per line
+    pub parameters: Option<String>,
+}
+
+/// What a configured model is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelRole {
+    Text,
+    Vision,
+    Embedding,
+}
+
+impl ModelRole {
+    /// Model we recommend (and default to) for this role
+    pub fn recommended_model(&self) -> &'static str {
+        match self {
+            ModelRole::Text => DEFAULT_OLLAMA_TEXT_MODEL,
+            ModelRole::Vision => DEFAULT_OLLAMA_VISION_MODEL,
+            ModelRole::Embedding => DEFAULT_OLLAMA_EMBEDDING_MODEL,
+        }
+    }
+}
+
+/// Whether a configured model is installed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelStatus {
+    pub role: ModelRole,
+    pub model: String,
+    pub recommended: String,
+    pub installed: bool,
+}
+
 // ============================================================================
 // OllamaClient Implementation
 // ============================================================================
@@ -69,7 +238,25 @@ impl OllamaClient {
                 Client::new()
             });
 
-        Self { client }
+        Self {
+            client,
+            model: None,
+            embedding_model: None,
+        }
+    }
+
+    /// Create a client pinned to a specific text model
+    /// The vision model and base URL are still read from RuntimeConfig
+    pub fn with_model(model: &str) -> Self {
+        let mut client = Self::new();
+        client.model = Some(model.to_string());
+        client
+    }
+
+    /// Use a specific model for embeddings
+    pub fn with_embedding_model(mut self, model: &str) -> Self {
+        self.embedding_model = Some(model.to_string());
+        self
     }
 
     /// Get current base URL from runtime config
@@ -78,13 +265,16 @@ impl OllamaClient {
     }
 
     /// Get current vision model from runtime config
-    fn vision_model(&self) -> String {
+    pub(crate) fn vision_model(&self) -> String {
         runtime_config().get_ollama_vision_model()
     }
 
-    /// Get current text model from runtime config
+    /// Get current text model (pinned model, else runtime config)
     fn text_model(&self) -> String {
-        runtime_config().get_ollama_text_model()
+        match self.model {
+            Some(ref model) => model.clone(),
+            None => runtime_config().get_ollama_text_model(),
+        }
     }
 
     /// Check if Ollama server is running and accessible
@@ -137,15 +327,137 @@ impl OllamaClient {
         Ok(tags.models.into_iter().map(|m| m.name).collect())
     }
 
+    /// Download a model, reporting progress as Ollama streams it
+    pub async fn pull_model(
+        &self,
+        model: &str,
+        mut on_progress: impl FnMut(&PullProgress),
+    ) -> Result<()> {
+        let url = format!("{}/api/pull", self.base_url());
+        let mut response = self
+            .client
+            .post(&url)
+            .json(&OllamaModelRequest {
+                model,
+                stream: true,
+            })
+            .timeout(Duration::from_secs(PULL_TIMEOUT_SECS))
+            .send()
+            .await
+            .context("Failed to connect to Ollama server")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Ollama pull error {}: {}", status, body);
+        }
+
+        let mut buffer = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .context("Model download interrupted")?
+        {
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                handle_pull_line(&line, &mut on_progress)?;
+            }
+        }
+        handle_pull_line(&buffer, &mut on_progress)?;
+
+        tracing::info!("Pulled Ollama model {}", model);
+        Ok(())
+    }
+
+    /// Remove an installed model
+    pub async fn delete_model(&self, model: &str) -> Result<()> {
+        let url = format!("{}/api/delete", self.base_url());
+        let response = self
+            .client
+            .delete(&url)
+            .json(&OllamaModelRequest {
+                model,
+                stream: false,
+            })
+            .send()
+            .await
+            .context("Failed to connect to Ollama server")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Ollama delete error {}: {}", status, body);
+        }
+        tracing::info!("Deleted Ollama model {}", model);
+        Ok(())
+    }
+
+    /// Parameters, context length and capabilities of an installed model
+    pub async fn show_model(&self, model: &str) -> Result<ModelDetails> {
+        let url = format!("{}/api/show", self.base_url());
+        let response = self
+            .client
+            .post(&url)
+            .json(&OllamaModelRequest {
+                model,
+                stream: false,
+            })
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .context("Failed to connect to Ollama server")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Ollama show error {}: {}", status, body);
+        }
+
+        let show: OllamaShowResponse = response
+            .json()
+            .await
+            .context("Failed to parse model details")?;
+        Ok(model_details(model, show))
+    }
+
+    /// Models this client is configured to use, by role
+    pub fn configured_models(&self) -> Vec<(ModelRole, String)> {
+        let mut models = vec![
+            (ModelRole::Text, self.text_model()),
+            (ModelRole::Vision, self.vision_model()),
+        ];
+        if let Some(ref model) = self.embedding_model {
+            models.push((ModelRole::Embedding, model.clone()));
+        }
+        models
+    }
+
+    /// Check which configured models are installed
+    pub async fn check_models(&self) -> Result<Vec<ModelStatus>> {
+        let installed = self.list_models().await?;
+        Ok(self
+            .configured_models()
+            .into_iter()
+            .map(|(role, model)| ModelStatus {
+                role,
+                installed: installed.iter().any(|m| m.starts_with(&model)),
+                recommended: role.recommended_model().to_string(),
+                model,
+            })
+            .collect())
+    }
+
     /// Generate text from a prompt (text-only, uses faster model)
     pub async fn generate_text(&self, prompt: &str) -> Result<String> {
-        self.generate_internal(prompt, None, Some(0.7), Some(500), false)
+        self.generate_internal(prompt, None, Some(0.7), Some(500), None)
             .await
     }
 
     /// Generate text with JSON format enforcement
     pub async fn generate_json(&self, prompt: &str) -> Result<String> {
-        self.generate_internal(prompt, None, Some(0.7), Some(500), true)
+        let format = serde_json::Value::from("json");
+        self.generate_internal(prompt, None, Some(0.7), Some(500), Some(format))
             .await
     }
 
@@ -202,6 +514,7 @@ impl OllamaClient {
             .json()
             .await
             .context("Failed to parse Ollama response")?;
+        track_usage(&vision_model, result.prompt_eval_count, result.eval_count);
 
         Ok(result.response.trim().to_string())
     }
@@ -213,9 +526,10 @@ impl OllamaClient {
         images: Option<Vec<String>>,
         temperature: Option<f32>,
         max_tokens: Option<u32>,
-        json_format: bool,
+        format: Option<serde_json::Value>,
     ) -> Result<String> {
         let url = format!("{}/api/generate", self.base_url());
+        let json_output = format.is_some();
 
         // Use vision model if images provided, otherwise use text model
         let model = if images.is_some() {
@@ -229,11 +543,7 @@ impl OllamaClient {
             prompt: prompt.to_string(),
             images,
             stream: false,
-            format: if json_format {
-                Some("json".to_string())
-            } else {
-                None
-            },
+            format,
             options: Some(OllamaOptions {
                 temperature: temperature.unwrap_or(0.7),
                 num_predict: max_tokens,
@@ -260,212 +570,413 @@ impl OllamaClient {
             .json()
             .await
             .context("Failed to parse Ollama response")?;
+        track_usage(&model, result.prompt_eval_count, result.eval_count);
 
         let text = result.response.trim().to_string();
 
         // Clean JSON response if needed
-        if json_format {
+        if json_output {
             Ok(clean_json_response(&text).to_string())
         } else {
             Ok(text)
         }
     }
 
-    /// Calculate semantic similarity between two URLs (simplified local version)
-    pub async fn calculate_url_similarity(&self, url1: &str, url2: &str) -> Result<f32> {
-        let prompt = format!(
-            "Compare these two URLs semantically. Consider the topic, domain, and content they represent.
-            Return ONLY a single number between 0.0 and 1.0 representing their similarity.
-            0.0 means completely unrelated, 1.0 means identical or very closely related.
-            
-            URL1: {}
-            URL2: {}
-            
-            Respond with just the number, nothing else.",
-            url1, url2
-        );
+    /// Open a streaming text generation request
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+        cancel: CancellationToken,
+    ) -> Result<CompletionStream> {
+        let url = format!("{}/api/generate", self.base_url());
+        let model = self.text_model();
 
-        let text = self
-            .generate_internal(&prompt, None, Some(0.1), Some(10), false)
-            .await?;
-
-        let similarity = text.trim().parse::<f32>().unwrap_or(0.0);
-        Ok(similarity.clamp(0.0, 1.0))
-    }
-
-    /// Generate dialogue based on context
-    pub async fn generate_dialogue(&self, context: &str, personality: &str) -> Result<String> {
-        let prompt = format!(
-            "You are a desktop companion. Your personality is: {}
-            
-            Based on this context about what the user is viewing: {}
-            
-            Generate a short, helpful, or intriguing comment (max 100 characters).
-            If in 'Mystery' mode, be cryptic. If in 'Companion' mode, be helpful but concise.
-            Stay in character.",
-            personality, context
-        );
+        let request = OllamaGenerateRequest {
+            model: model.clone(),
+            prompt: prompt.to_string(),
+            images: None,
+            stream: true,
+            format: None,
+            options: Some(OllamaOptions {
+                temperature: temperature.unwrap_or(0.7),
+                num_predict: max_tokens,
+            }),
+        };
+
+        tracing::debug!("Ollama stream request to {} with model {}", url, model);
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to connect to Ollama server")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Ollama API error {}: {}", status, body));
+        }
+
+        Ok(streaming::stream_response(
+            response,
+            cancel,
+            Framing::Ndjson,
+            StreamUsage::new("ollama", &model, stream_usage),
+            parse_stream_chunk,
+        ))
+    }
+
+    /// Embed texts via /api/embeddings (one request per text)
+    pub async fn embed_texts(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/api/embeddings", self.base_url());
+        let model = self
+            .embedding_model
+            .as_deref()
+            .unwrap_or(DEFAULT_OLLAMA_EMBEDDING_MODEL);
+
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in texts {
+            let response = self
+                .client
+                .post(&url)
+                .json(&OllamaEmbeddingRequest {
+                    model,
+                    prompt: text,
+                })
+                .send()
+                .await
+                .context("Failed to connect to Ollama server")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                return Err(anyhow::anyhow!("Ollama API error {}: {}", status, body));
+            }
 
-        self.generate_internal(&prompt, None, Some(0.9), Some(50), false)
+            let result: OllamaEmbeddingResponse = response
+                .json()
+                .await
+                .context("Failed to parse Ollama embedding response")?;
+            if result.embedding.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Ollama returned an empty embedding (is {} an embedding model?)",
+                    model
+                ));
+            }
+            embeddings.push(result.embedding);
+        }
+
+        Ok(embeddings)
+    }
+
+    /// Continue a multi-turn conversation via /api/chat
+    async fn chat_messages(
+        &self,
+        messages: &[ChatMessage],
+        options: CompletionOptions,
+    ) -> Result<String> {
+        let url = format!("{}/api/chat", self.base_url());
+        let model = self.text_model();
+
+        let mut request = serde_json::json!({
+            "model": model,
+            "messages": chat::openai_messages(messages),
+            "stream": false,
+            "options": {
+                "temperature": options.temperature.unwrap_or(0.7),
+                "num_predict": options.max_tokens,
+            },
+        });
+        if let Some(schema) = options.response_schema {
+            request["format"] = schema;
+        }
+
+        tracing::debug!("Ollama chat request to {} with model {}", url, model);
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&request)
+            .send()
             .await
+            .context("Failed to connect to Ollama server")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Ollama API error {}: {}", status, body));
+        }
+
+        let result: OllamaChatResponse = response
+            .json()
+            .await
+            .context("Failed to parse Ollama chat response")?;
+        track_usage(&model, result.prompt_eval_count, result.eval_count);
+
+        Ok(result.message.content.trim().to_string())
     }
 
-    /// Generate a dynamic puzzle based on page context
-    pub async fn generate_dynamic_puzzle(
+    /// Run a tool-calling chat turn via /api/chat
+    async fn chat_with_tools(
         &self,
-        url: &str,
-        page_title: &str,
-        page_content: &str,
-        history_context: &str,
-    ) -> Result<crate::ai::gemini_client::DynamicPuzzle> {
-        tracing::info!(
-            "Ollama: Generating dynamic puzzle for URL: {} (title: {})",
-            url,
-            page_title
-        );
+        turns: &[ToolTurn],
+        options: CompletionOptions,
+    ) -> Result<ToolCompletion> {
+        let url = format!("{}/api/chat", self.base_url());
+        let model = self.text_model();
+        let tools = options.tools.unwrap_or_default();
+
+        let mut request = serde_json::json!({
+            "model": model,
+            "messages": tools::openai_messages(turns, false),
+            "stream": false,
+            "options": {
+                "temperature": options.temperature.unwrap_or(0.7),
+                "num_predict": options.max_tokens,
+            },
+        });
+        if !tools.is_empty() {
+            request["tools"] = tools::openai_tool_definitions(&tools);
+        }
 
-        let prompt = format!(
-            r#"Based on this webpage the user is viewing, generate a creative puzzle for a mystery game.
+        tracing::debug!("Ollama tool chat request to {} with model {}", url, model);
 
-URL: {}
-Title: {}
-Content snippet: {}
+        let response = self
+            .client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to connect to Ollama server")?;
 
-Recent Browsing History:
-{}
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Ollama API error {}: {}", status, body));
+        }
 
-Generate a JSON object with these fields:
-- "clue": A mysterious, cryptic clue that relates to this page's topic but leads to a DIFFERENT related page (max 100 chars)
-- "target_description": What the player should find (a related but different topic/page)
-- "target_url_pattern": A regex pattern that would match the solution URL
-- "hints": An array of 3 progressive hints
+        let result: OllamaChatResponse = response
+            .json()
+            .await
+            .context("Failed to parse Ollama chat response")?;
+        track_usage(&model, result.prompt_eval_count, result.eval_count);
 
-Respond ONLY with valid JSON, no markdown."#,
-            url,
-            page_title,
-            &page_content.chars().take(500).collect::<String>(),
-            history_context
-        );
+        let content = result.message.content.trim().to_string();
+        Ok(ToolCompletion {
+            text: if content.is_empty() {
+                None
+            } else {
+                Some(content)
+            },
+            tool_calls: result
+                .message
+                .tool_calls
+                .into_iter()
+                .map(|call| {
+                    tools::tool_call(
+                        tools::resolve_name(&call.function.name, &tools),
+                        call.function.arguments,
+                        None,
+                    )
+                })
+                .collect(),
+        })
+    }
+}
+
+// ============================================================================
+// Provider Trait Implementation
+// ============================================================================
 
-        let text = self.generate_json(&prompt).await?;
+#[async_trait]
+impl Provider for OllamaClient {
+    fn name(&self) -> &str {
+        "ollama"
+    }
 
-        let puzzle: crate::ai::gemini_client::DynamicPuzzle = serde_json::from_str(&text)
-            .context(format!("Failed to parse puzzle JSON. Raw: {}", text))?;
+    /// The pinned model, or the default text model when the model is
+    /// resolved from RuntimeConfig per request (see `info()` for the live value)
+    fn model(&self) -> &str {
+        self.model.as_deref().unwrap_or(DEFAULT_OLLAMA_TEXT_MODEL)
+    }
 
-        tracing::info!("Ollama: Successfully generated puzzle: {:?}", puzzle.clue);
+    async fn is_available(&self) -> bool {
+        OllamaClient::is_available(self).await
+    }
 
-        Ok(puzzle)
+    async fn complete(&self, prompt: &str) -> Result<String, ProviderError> {
+        self.complete_with_options(prompt, CompletionOptions::default())
+            .await
     }
 
-    /// Verify if a screenshot contains the solution to a puzzle
-    pub async fn verify_screenshot_clue(
+    async fn complete_with_options(
         &self,
-        base64_image: &str,
-        clue_description: &str,
-    ) -> Result<crate::ai::gemini_client::VerificationResult> {
-        let prompt = format!(
-            r#"Analyze this screenshot. Does it contain content matching this description: '{}'?
-            
-Respond with a JSON object:
-{{
-    "found": boolean,
-    "confidence": number (0.0-1.0),
-    "explanation": "Short explanation of what was found or missing"
-}}
-
-Be strict and accurate. Only return true if the visual proof CLEARLY matches the specific target description."#,
-            clue_description
-        );
+        prompt: &str,
+        options: CompletionOptions,
+    ) -> Result<String, ProviderError> {
+        let temperature = options.temperature.map(|t| t as f32);
+        let max_tokens = options.max_tokens.map(|t| t as u32);
+
+        self.generate_internal(
+            prompt,
+            None,
+            temperature,
+            max_tokens,
+            options.response_schema,
+        )
+        .await
+        .map_err(|e| ProviderError::APIError(e.to_string()))
+    }
 
-        let text = self
-            .generate_with_image(&prompt, base64_image, Some(0.2), Some(200))
-            .await?;
-        let clean_text = clean_json_response(&text);
+    async fn complete_stream(
+        &self,
+        prompt: &str,
+        options: CompletionOptions,
+        cancel: CancellationToken,
+    ) -> Result<CompletionStream, ProviderError> {
+        let temperature = options.temperature.map(|t| t as f32);
+        let max_tokens = options.max_tokens.map(|t| t as u32);
 
-        let result: crate::ai::gemini_client::VerificationResult =
-            serde_json::from_str(clean_text)?;
-        Ok(result)
+        self.generate_stream(prompt, temperature, max_tokens, cancel)
+            .await
+            .map_err(|e| ProviderError::APIError(e.to_string()))
     }
 
-    /// Generate adaptive puzzle based on user activities (simplified version for local LLM)
-    pub async fn generate_adaptive_puzzle(
+    async fn complete_chat(
         &self,
-        activities: &[crate::ai::gemini_client::ActivityContext],
-        current_app: Option<&str>,
-        _current_content: Option<&str>,
-    ) -> Result<crate::ai::gemini_client::AdaptivePuzzle> {
-        // Build activity summary
-        let activity_summary = activities
-            .iter()
-            .take(5)
-            .map(|a| format!("- {} ({}): {}", a.app_name, a.app_category, a.description))
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        let app_context = current_app.unwrap_or("desktop");
-
-        let prompt = format!(
-            r#"Based on the user's recent activities, generate a puzzle for a mystery game.
-
-Recent Activities:
-{}
-
-Current App: {}
-
-Generate a JSON object with these fields:
-- "puzzle_type": One of "navigation", "discovery", "knowledge"
-- "clue": A mysterious clue related to their activities (max 80 chars)
-- "target_description": What they should find
-- "hints": Array of 2 progressive hints
-- "difficulty": 1-5 (based on how related to their activities)
-
-Respond ONLY with valid JSON."#,
-            activity_summary, app_context
-        );
+        messages: &[ChatMessage],
+        options: CompletionOptions,
+    ) -> Result<String, ProviderError> {
+        self.chat_messages(messages, options)
+            .await
+            .map_err(|e| ProviderError::APIError(e.to_string()))
+    }
 
-        let text = self.generate_json(&prompt).await?;
+    /// Requires a tool-capable model (e.g. llama3.1+, qwen2.5); others reply with an API error
+    fn supports_tools(&self) -> bool {
+        true
+    }
 
-        let puzzle: crate::ai::gemini_client::AdaptivePuzzle = serde_json::from_str(&text)
-            .context(format!(
-                "Failed to parse adaptive puzzle JSON. Raw: {}",
-                text
-            ))?;
+    async fn complete_with_tools(
+        &self,
+        turns: &[ToolTurn],
+        options: CompletionOptions,
+    ) -> Result<ToolCompletion, ProviderError> {
+        self.chat_with_tools(turns, options)
+            .await
+            .map_err(|e| ProviderError::APIError(e.to_string()))
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        true
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.embed_texts(texts)
+            .await
+            .map_err(|e| ProviderError::APIError(e.to_string()))
+    }
 
-        Ok(puzzle)
+    fn supports_vision(&self) -> bool {
+        true
     }
 
-    /// Generate contextual dialogue based on activities
-    pub async fn generate_contextual_dialogue(
+    async fn analyze_image(
         &self,
-        activities: &[crate::ai::gemini_client::ActivityContext],
-        current_context: &str,
-        ghost_mood: &str,
-    ) -> Result<String> {
-        let activity_summary = activities
-            .iter()
-            .take(3)
-            .map(|a| format!("{}: {}", a.app_name, a.description))
-            .collect::<Vec<_>>()
-            .join("; ");
-
-        let prompt = format!(
-            r#"You are a mysterious desktop companion ghost. Your mood is: {}.
-
-Recent user activity: {}
-Current context: {}
-
-Generate a short, in-character comment (max 60 chars). Be cryptic if mysterious, helpful if friendly."#,
-            ghost_mood,
-            if activity_summary.is_empty() {
-                "watching the user".to_string()
+        base64_image: &str,
+        prompt: &str,
+    ) -> Result<String, ProviderError> {
+        OllamaClient::analyze_image(self, base64_image, prompt)
+            .await
+            .map_err(|e| ProviderError::APIError(e.to_string()))
+    }
+
+    fn info(&self) -> ProviderInfo {
+        ProviderInfo {
+            name: self.name().to_string(),
+            model: self.text_model(),
+            supports_vision: true,
+            supports_streaming: true,
+            context_window: 8192,
+            max_tokens: 2048,
+        }
+    }
+}
+
+/// Parse one NDJSON line of a streamed generation
+fn parse_stream_chunk(payload: &str) -> StreamEvent {
+    match serde_json::from_str::<OllamaStreamChunk>(payload) {
+        Ok(chunk) => {
+            if let Some(error) = chunk.error {
+                StreamEvent::Error(ProviderError::APIError(error))
+            } else if !chunk.response.is_empty() {
+                StreamEvent::Delta(chunk.response)
+            } else if chunk.done {
+                StreamEvent::Done
             } else {
-                activity_summary
-            },
-            current_context
-        );
+                StreamEvent::Skip
+            }
+        }
+        Err(e) => StreamEvent::Error(ProviderError::APIError(format!(
+            "Invalid stream chunk: {}",
+            e
+        ))),
+    }
+}
 
-        self.generate_internal(&prompt, None, Some(0.9), Some(40), false)
-            .await
+/// Token counts of the final line of a streamed generation
+fn stream_usage(payload: &str) -> Option<TokenUsage> {
+    let chunk: OllamaStreamChunk = serde_json::from_str(payload).ok()?;
+    chunk
+        .done
+        .then(|| TokenUsage::new(chunk.prompt_eval_count, chunk.eval_count))
+}
+
+/// Count tokens of a local call (local models are free, but usage is still shown)
+fn track_usage(model: &str, prompt_eval_count: usize, eval_count: usize) {
+    usage::record_usage(
+        "ollama",
+        model,
+        TokenUsage::new(prompt_eval_count, eval_count),
+    );
+}
+
+/// Parse one /api/pull line, failing on an `error` line
+fn handle_pull_line(line: &[u8], on_progress: &mut impl FnMut(&PullProgress)) -> Result<()> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(());
+    }
+    let progress: PullProgress =
+        serde_json::from_str(line).context("Failed to parse pull progress")?;
+    if let Some(error) = progress.error {
+        anyhow::bail!("Ollama pull failed: {}", error);
+    }
+    on_progress(&progress);
+    Ok(())
+}
+
+fn model_details(name: &str, show: OllamaShowResponse) -> ModelDetails {
+    // Context length is keyed by architecture, e.g. "llama.context_length"
+    let context_length = show
+        .model_info
+        .iter()
+        .find(|(key, _)| key.ends_with(".context_length"))
+        .and_then(|(_, value)| value.as_u64());
+
+    ModelDetails {
+        name: name.to_string(),
+        family: show.details.family,
+        parameter_size: show.details.parameter_size,
+        quantization_level: show.details.quantization_level,
+        context_length,
+        capabilities: show.capabilities,
+        parameters: show.parameters,
     }
 }
 
@@ -505,4 +1016,39 @@ mod tests {
             crate::core::utils::DEFAULT_OLLAMA_TEXT_MODEL
         );
     }
+
+    #[test]
+    fn test_parse_stream_chunk() {
+        let delta = r#"{"model":"llama3.2","response":"Hel","done":false}"#;
+        assert!(matches!(parse_stream_chunk(delta), StreamEvent::Delta(t) if t == "Hel"));
+        let done = r#"{"model":"llama3.2","response":"","done":true}"#;
+        assert!(matches!(parse_stream_chunk(done), StreamEvent::Done));
+        assert!(stream_usage(delta).is_none());
+        let last = r#"{"response":"","done":true,"prompt_eval_count":26,"eval_count":290}"#;
+        assert_eq!(stream_usage(last).map(|u| u.total_tokens), Some(316));
+        let error = r#"{"error":"model not found"}"#;
+        assert!(matches!(parse_stream_chunk(error), StreamEvent::Error(_)));
+    }
+
+    #[test]
+    fn test_pull_progress_and_model_details() {
+        let mut seen = Vec::new();
+        let line = br#"{"status":"pulling 6a0746a1ec1a","digest":"sha256:6a07","total":100,"completed":40}"#;
+        handle_pull_line(line, &mut |p: &PullProgress| seen.push(p.clone())).unwrap();
+        assert_eq!(seen[0].completed, Some(40));
+        assert!(handle_pull_line(
+            br#"{"error":"pull model manifest: file does not exist"}"#,
+            &mut |_: &PullProgress| {}
+        )
+        .is_err());
+
+        let show: OllamaShowResponse = serde_json::from_str(
+            r#"{"parameters":"stop \"<|eot_id|>\"","details":{"family":"llama","parameter_size":"3.2B","quantization_level":"Q4_K_M"},"model_info":{"general.architecture":"llama","llama.context_length":131072},"capabilities":["completion","tools"]}"#,
+        )
+        .unwrap();
+        let details = model_details("llama3.2", show);
+        assert_eq!(details.context_length, Some(131072));
+        assert_eq!(details.parameter_size.as_deref(), Some("3.2B"));
+        assert_eq!(details.capabilities, vec!["completion", "tools"]);
+    }
 }
@@ -0,0 +1,483 @@
+//! Prompt template registry
+//!
+//! Every agent and router prompt is a named, versioned template with typed
+//! `{{variable}}` placeholders. Defaults ship in `prompts/defaults/*.toml`; users
+//! override one by dropping `<name>.toml` (with `version` and `template`) into
+//! the `prompts/` folder next to `TOOLS.md` and `AGENTS.md`.
+//!
+//! Rendered templates are recorded for the current orchestrator invocation (see
+//! `track`), so `InvocationMetrics` can tell which prompt produced a result.
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Folder of user overrides inside the workspace data directory
+pub const PROMPTS_DIR: &str = "prompts";
+
+const BUILTIN_TEMPLATES: &[&str] = &[
+    include_str!("defaults/narrator.dialogue.toml"),
+    include_str!("defaults/narrator.success.toml"),
+    include_str!("defaults/planner.plan.toml"),
+    include_str!("defaults/planner.revise.toml"),
+    include_str!("defaults/planner.work.toml"),
+    include_str!("defaults/critic.review.toml"),
+    include_str!("defaults/critic.improve.toml"),
+    include_str!("defaults/critic.compare.toml"),
+    include_str!("defaults/guardrail.safety.toml"),
+    include_str!("defaults/guardrail.pii.toml"),
+    include_str!("defaults/watchdog.semantic.toml"),
+    include_str!("defaults/puzzle.dynamic.toml"),
+    include_str!("defaults/puzzle.adaptive.toml"),
+    include_str!("defaults/puzzle.verify.toml"),
+    include_str!("defaults/compaction.silent_memory.toml"),
+    include_str!("defaults/compaction.summarize.toml"),
+    include_str!("defaults/agent.user.toml"),
+];
+
+lazy_static::lazy_static! {
+    static ref REGISTRY: RwLock<PromptRegistry> = RwLock::new(PromptRegistry::builtin());
+}
+
+tokio::task_local! {
+    static RENDERED: RefCell<Vec<PromptRef>>;
+}
+
+/// Type of a template variable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VarKind {
+    Text,
+    Integer,
+    /// Rendered as a numbered list, one item per line
+    List,
+}
+
+/// Value bound to a template variable
+#[derive(Debug, Clone)]
+pub enum PromptValue {
+    Text(String),
+    Integer(i64),
+    List(Vec<String>),
+}
+
+impl PromptValue {
+    /// A 0.0-1.0 ratio as a whole percentage
+    pub fn percent(ratio: f32) -> Self {
+        PromptValue::Integer((ratio * 100.0).round() as i64)
+    }
+
+    fn kind(&self) -> VarKind {
+        match self {
+            PromptValue::Text(_) => VarKind::Text,
+            PromptValue::Integer(_) => VarKind::Integer,
+            PromptValue::List(_) => VarKind::List,
+        }
+    }
+
+    fn render(&self) -> String {
+        match self {
+            PromptValue::Text(text) => text.clone(),
+            PromptValue::Integer(n) => n.to_string(),
+            PromptValue::List(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| format!("{}. {}", i + 1, item))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+impl From<&str> for PromptValue {
+    fn from(value: &str) -> Self {
+        PromptValue::Text(value.to_string())
+    }
+}
+
+impl From<String> for PromptValue {
+    fn from(value: String) -> Self {
+        PromptValue::Text(value)
+    }
+}
+
+impl From<i64> for PromptValue {
+    fn from(value: i64) -> Self {
+        PromptValue::Integer(value)
+    }
+}
+
+impl From<usize> for PromptValue {
+    fn from(value: usize) -> Self {
+        PromptValue::Integer(value as i64)
+    }
+}
+
+impl From<Vec<String>> for PromptValue {
+    fn from(value: Vec<String>) -> Self {
+        PromptValue::List(value)
+    }
+}
+
+/// Template name and version used to build a prompt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptRef {
+    pub name: String,
+    pub version: u32,
+    /// Loaded from the user's workspace instead of the shipped default
+    pub overridden: bool,
+}
+
+/// A named, versioned prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub version: u32,
+    #[serde(default)]
+    pub description: String,
+    pub template: String,
+    #[serde(default)]
+    pub variables: BTreeMap<String, VarKind>,
+    #[serde(default)]
+    pub overridden: bool,
+}
+
+/// User override read from `prompts/<name>.toml`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PromptOverride {
+    version: u32,
+    template: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+impl PromptTemplate {
+    /// Substitute `vars`, checking them against the declared variable types
+    pub fn render(&self, vars: &[(&str, PromptValue)]) -> Result<String, String> {
+        for (name, value) in vars {
+            match self.variables.get(*name) {
+                Some(kind) if *kind == value.kind() => {}
+                Some(kind) => {
+                    return Err(format!(
+                        "Prompt '{}' variable '{}' expects {:?}, got {:?}",
+                        self.name,
+                        name,
+                        kind,
+                        value.kind()
+                    ))
+                }
+                None => return Err(format!("Prompt '{}' has no variable '{}'", self.name, name)),
+            }
+        }
+
+        substitute(&self.template, |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.render())
+                .ok_or_else(|| format!("Prompt '{}' is missing variable '{}'", self.name, name))
+        })
+    }
+
+    fn prompt_ref(&self) -> PromptRef {
+        PromptRef {
+            name: self.name.clone(),
+            version: self.version,
+            overridden: self.overridden,
+        }
+    }
+
+    /// Placeholders that are not declared variables
+    fn undeclared_placeholders(&self) -> Vec<String> {
+        let mut undeclared = Vec::new();
+        let _ = substitute(&self.template, |name| {
+            if !self.variables.contains_key(name) {
+                undeclared.push(name.to_string());
+            }
+            Ok(String::new())
+        });
+        undeclared
+    }
+}
+
+/// Shipped templates with any workspace overrides applied
+#[derive(Debug, Clone)]
+pub struct PromptRegistry {
+    templates: HashMap<String, PromptTemplate>,
+}
+
+impl PromptRegistry {
+    /// Registry of the shipped defaults
+    pub fn builtin() -> Self {
+        let templates = BUILTIN_TEMPLATES
+            .iter()
+            .map(|source| {
+                let template: PromptTemplate =
+                    toml::from_str(source).expect("built-in prompt template is valid TOML");
+                (template.name.clone(), template)
+            })
+            .collect();
+        Self { templates }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PromptTemplate> {
+        self.templates.get(name)
+    }
+
+    /// Apply `<name>.toml` overrides from `dir`, returning one message per rejected file
+    ///
+    /// An override may only use variables the default declares, so code that
+    /// renders the template keeps working.
+    pub fn load_overrides(&mut self, dir: &Path) -> Vec<String> {
+        let mut errors = Vec::new();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return errors;
+        };
+
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let Some(default) = self.templates.get(name) else {
+                errors.push(format!(
+                    "{}: no built-in prompt named '{}'",
+                    path.display(),
+                    name
+                ));
+                continue;
+            };
+
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|s| toml::from_str::<PromptOverride>(&s).map_err(|e| e.to_string()));
+            let custom = match parsed {
+                Ok(custom) => custom,
+                Err(e) => {
+                    errors.push(format!("{}: {}", path.display(), e));
+                    continue;
+                }
+            };
+
+            let template = PromptTemplate {
+                name: name.to_string(),
+                version: custom.version,
+                description: custom
+                    .description
+                    .unwrap_or_else(|| default.description.clone()),
+                template: custom.template,
+                variables: default.variables.clone(),
+                overridden: true,
+            };
+            let undeclared = template.undeclared_placeholders();
+            if !undeclared.is_empty() {
+                errors.push(format!(
+                    "{}: unknown variables {}",
+                    path.display(),
+                    undeclared.join(", ")
+                ));
+                continue;
+            }
+
+            tracing::info!("Using workspace prompt '{}' v{}", name, template.version);
+            self.templates.insert(name.to_string(), template);
+        }
+
+        errors
+    }
+
+    /// All templates, sorted by name
+    pub fn list(&self) -> Vec<PromptTemplate> {
+        let mut templates: Vec<_> = self.templates.values().cloned().collect();
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        templates
+    }
+}
+
+/// Replace each `{{name}}` with `lookup(name)`; other braces are left as-is
+fn substitute(
+    template: &str,
+    mut lookup: impl FnMut(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let placeholder = after.find("}}").map(|end| &after[..end]).filter(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        });
+        match placeholder {
+            Some(name) => {
+                out.push_str(&lookup(name)?);
+                rest = &after[name.len() + 2..];
+            }
+            None => {
+                out.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Directory holding user prompt overrides
+pub fn prompts_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(PROMPTS_DIR)
+}
+
+/// Rebuild the registry from the defaults plus overrides in `data_dir`
+pub fn reload_prompts(data_dir: &Path) {
+    let mut registry = PromptRegistry::builtin();
+    for error in registry.load_overrides(&prompts_dir(data_dir)) {
+        tracing::warn!("Ignoring prompt override {}", error);
+    }
+    if let Ok(mut r) = REGISTRY.write() {
+        *r = registry;
+    }
+}
+
+/// Render a registered template and record its use for the current invocation
+pub fn render(name: &str, vars: &[(&str, PromptValue)]) -> Result<String, String> {
+    let template = REGISTRY
+        .read()
+        .map_err(|_| "Prompt registry lock poisoned".to_string())?
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("Unknown prompt template '{}'", name))?;
+
+    let prompt = template.render(vars)?;
+    let _ = RENDERED.try_with(|rendered| {
+        let prompt_ref = template.prompt_ref();
+        let mut rendered = rendered.borrow_mut();
+        if !rendered.contains(&prompt_ref) {
+            rendered.push(prompt_ref);
+        }
+    });
+    Ok(prompt)
+}
+
+/// Run `future`, collecting the templates it renders for `rendered()`
+pub async fn track<F: Future>(future: F) -> F::Output {
+    RENDERED.scope(RefCell::new(Vec::new()), future).await
+}
+
+/// Templates rendered so far inside the enclosing `track`
+pub fn rendered() -> Vec<PromptRef> {
+    RENDERED
+        .try_with(|rendered| rendered.borrow().clone())
+        .unwrap_or_default()
+}
+
+/// Registered templates, sorted by name
+pub fn list_prompts() -> Vec<PromptTemplate> {
+    REGISTRY.read().map(|r| r.list()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_templates_declare_their_variables() {
+        let registry = PromptRegistry::builtin();
+        assert_eq!(registry.list().len(), BUILTIN_TEMPLATES.len());
+        for template in registry.list() {
+            assert!(
+                template.undeclared_placeholders().is_empty(),
+                "{} uses undeclared variables",
+                template.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_checks_variables() {
+        let registry = PromptRegistry::builtin();
+        let template = registry.get("planner.revise").unwrap();
+
+        let prompt = template
+            .render(&[
+                ("puzzle_clue", "The cipher".into()),
+                (
+                    "failed_approaches",
+                    vec!["enigma".to_string(), "turing".to_string()].into(),
+                ),
+            ])
+            .unwrap();
+        assert!(prompt.contains("PUZZLE CLUE: \"The cipher\""));
+        assert!(prompt.contains("1. enigma\n2. turing"));
+
+        assert!(template
+            .render(&[("puzzle_clue", 3usize.into())])
+            .unwrap_err()
+            .contains("expects Text"));
+        assert!(template
+            .render(&[("puzzle_clue", "x".into())])
+            .unwrap_err()
+            .contains("missing variable 'failed_approaches'"));
+
+        // JSON examples in templates are not placeholders
+        let plan = registry.get("planner.plan").unwrap();
+        assert!(plan.template.contains("{\"step\": 1"));
+    }
+
+    #[test]
+    fn test_workspace_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("watchdog.semantic.toml"),
+            "version = 2\ntemplate = \"Is {{url}} safe? {{content}}\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("guardrail.pii.toml"),
+            "version = 2\ntemplate = \"{{secret}}\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("unknown.toml"),
+            "version = 1\ntemplate = \"\"\n",
+        )
+        .unwrap();
+
+        let mut registry = PromptRegistry::builtin();
+        let errors = registry.load_overrides(dir.path());
+        assert_eq!(errors.len(), 2);
+
+        let watchdog = registry.get("watchdog.semantic").unwrap();
+        assert_eq!(watchdog.prompt_ref().version, 2);
+        assert!(watchdog.overridden);
+        assert_eq!(
+            watchdog
+                .render(&[("url", "a.com".into()), ("content", "hi".into())])
+                .unwrap(),
+            "Is a.com safe? hi"
+        );
+        assert!(!registry.get("guardrail.pii").unwrap().overridden);
+    }
+
+    #[tokio::test]
+    async fn test_track_records_rendered_templates() {
+        let vars = [("description", PromptValue::from("a cat"))];
+        let used = track(async {
+            render("puzzle.verify", &vars).unwrap();
+            render("puzzle.verify", &vars).unwrap();
+            rendered()
+        })
+        .await;
+        assert_eq!(used.len(), 1);
+        assert_eq!(used[0].name, "puzzle.verify");
+        assert!(rendered().is_empty());
+    }
+}
@@ -6,7 +6,17 @@ use async_trait::async_trait;
 use reqwest::Client;
 use serde::Deserialize;
 
-use super::{CompletionOptions, Provider, ProviderError, ProviderInfo};
+use super::chat::{self, ChatMessage, ChatRole};
+use super::streaming::{self, Framing, StreamEvent};
+use super::tools::{self, ToolCompletion, ToolTurn};
+use super::{CompletionOptions, CompletionStream, Provider, ProviderError, ProviderInfo};
+use crate::agents::callbacks::TokenUsage;
+use crate::ai::usage::{self, StreamUsage};
+use crate::mcp::types::ToolDescriptor;
+use serde_json::{json, Value};
+use tokio_util::sync::CancellationToken;
+
+const MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
 
 pub struct AnthropicClient {
     client: Client,
@@ -27,6 +37,77 @@ impl AnthropicClient {
         })
     }
 
+    fn message_body(&self, prompt: &str, options: CompletionOptions) -> serde_json::Value {
+        self.messages_body(
+            serde_json::json!([
+                {"role": "user", "content": prompt}
+            ]),
+            options,
+        )
+    }
+
+    fn messages_body(
+        &self,
+        messages: serde_json::Value,
+        options: CompletionOptions,
+    ) -> serde_json::Value {
+        let max_tokens = options.max_tokens.unwrap_or(self.max_tokens_for_model());
+
+        let mut body = serde_json::json!({
+            "model": self.model,
+            "messages": messages,
+            "max_tokens": max_tokens,
+        });
+
+        // Add optional parameters
+        if let Some(temp) = options.temperature {
+            body["temperature"] = serde_json::json!(temp);
+        }
+        if let Some(top_p) = options.top_p {
+            body["top_p"] = serde_json::json!(top_p);
+        }
+        if let Some(stop) = options.stop {
+            body["stop_sequences"] = serde_json::json!(stop);
+        }
+        if let Some(tools) = options.tools.filter(|t| !t.is_empty()) {
+            body["tools"] = tool_definitions(&tools);
+        }
+
+        body
+    }
+
+    async fn send_messages(
+        &self,
+        body: &serde_json::Value,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let response = self
+            .client
+            .post(MESSAGES_URL)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("Content-Type", "application/json")
+            .json(body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(ProviderError::APIError(format!(
+                "Status {}: {}",
+                status, text
+            )));
+        }
+
+        Ok(response)
+    }
+
+    fn track_usage(&self, wire: Option<&WireUsage>) {
+        if let Some(wire) = wire {
+            usage::record_usage(self.name(), &self.model, wire.token_usage());
+        }
+    }
+
     fn max_tokens_for_model(&self) -> usize {
         // All Claude models support 200k context
         if self.model.contains("claude-") {
@@ -49,7 +130,7 @@ impl Provider for AnthropicClient {
 
     async fn is_available(&self) -> bool {
         // Try a simple models list request
-        let url = "https://api.anthropic.com/v1/messages";
+        let url = MESSAGES_URL;
 
         let body = serde_json::json!({
             "model": self.model,
@@ -82,49 +163,11 @@ impl Provider for AnthropicClient {
         prompt: &str,
         options: CompletionOptions,
     ) -> Result<String, ProviderError> {
-        let url = "https://api.anthropic.com/v1/messages";
-
-        let max_tokens = options.max_tokens.unwrap_or(self.max_tokens_for_model());
-
-        let mut body = serde_json::json!({
-            "model": self.model,
-            "messages": [
-                {"role": "user", "content": prompt}
-            ],
-            "max_tokens": max_tokens,
-        });
-
-        // Add optional parameters
-        if let Some(temp) = options.temperature {
-            body["temperature"] = serde_json::json!(temp);
-        }
-        if let Some(top_p) = options.top_p {
-            body["top_p"] = serde_json::json!(top_p);
-        }
-        if let Some(stop) = options.stop {
-            body["stop_sequences"] = serde_json::json!(stop);
-        }
-
-        let response = self
-            .client
-            .post(url)
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(ProviderError::APIError(format!(
-                "Status {}: {}",
-                status, text
-            )));
-        }
+        let body = self.message_body(prompt, options);
+        let response = self.send_messages(&body).await?;
 
         let response: AnthropicResponse = response.json().await?;
+        self.track_usage(response.usage.as_ref());
 
         let content = response
             .content
@@ -135,6 +178,82 @@ impl Provider for AnthropicClient {
         Ok(content)
     }
 
+    async fn complete_chat(
+        &self,
+        messages: &[ChatMessage],
+        options: CompletionOptions,
+    ) -> Result<String, ProviderError> {
+        let (system, turns) = chat::split_system(messages);
+        let turns: Vec<Value> = turns
+            .into_iter()
+            .map(|(role, content)| {
+                let role = if role == ChatRole::Assistant {
+                    "assistant"
+                } else {
+                    "user"
+                };
+                json!({"role": role, "content": content})
+            })
+            .collect();
+
+        let mut body = self.messages_body(Value::Array(turns), options);
+        if let Some(system) = system {
+            body["system"] = json!(system);
+        }
+        let response = self.send_messages(&body).await?;
+
+        let response: AnthropicResponse = response.json().await?;
+        self.track_usage(response.usage.as_ref());
+
+        let text: Vec<&str> = response
+            .content
+            .iter()
+            .filter(|c| c.block_type == "text")
+            .map(|c| c.text.as_str())
+            .collect();
+        if text.is_empty() {
+            return Err(ProviderError::APIError(
+                "No content in response".to_string(),
+            ));
+        }
+        Ok(text.join("\n"))
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    async fn complete_with_tools(
+        &self,
+        turns: &[ToolTurn],
+        options: CompletionOptions,
+    ) -> Result<ToolCompletion, ProviderError> {
+        let tools = options.tools.clone().unwrap_or_default();
+        let body = self.messages_body(tool_messages(turns), options);
+        let response = self.send_messages(&body).await?;
+        let response: AnthropicResponse = response.json().await?;
+        self.track_usage(response.usage.as_ref());
+
+        let mut completion = ToolCompletion::default();
+        for block in response.content {
+            match block.block_type.as_str() {
+                "text" if !block.text.trim().is_empty() => {
+                    completion.text = Some(match completion.text.take() {
+                        Some(text) => format!("{}\n{}", text, block.text),
+                        None => block.text,
+                    });
+                }
+                "tool_use" => completion.tool_calls.push(tools::tool_call(
+                    tools::resolve_name(&block.name, &tools),
+                    block.input,
+                    Some(block.id),
+                )),
+                _ => {}
+            }
+        }
+        Ok(completion)
+    }
+
     fn supports_vision(&self) -> bool {
         // Claude 3 Sonnet and Opus support vision
         self.model.contains("claude-3")
@@ -197,6 +316,7 @@ impl Provider for AnthropicClient {
         }
 
         let response: AnthropicResponse = response.json().await?;
+        self.track_usage(response.usage.as_ref());
 
         let content = response
             .content
@@ -207,6 +327,25 @@ impl Provider for AnthropicClient {
         Ok(content)
     }
 
+    async fn complete_stream(
+        &self,
+        prompt: &str,
+        options: CompletionOptions,
+        cancel: CancellationToken,
+    ) -> Result<CompletionStream, ProviderError> {
+        let mut body = self.message_body(prompt, options);
+        body["stream"] = serde_json::json!(true);
+        let response = self.send_messages(&body).await?;
+
+        Ok(streaming::stream_response(
+            response,
+            cancel,
+            Framing::Sse,
+            StreamUsage::new(self.name(), &self.model, stream_usage),
+            parse_stream_event,
+        ))
+    }
+
     fn info(&self) -> ProviderInfo {
         let context_window = if self.model.contains("claude-") {
             200000
@@ -231,6 +370,22 @@ struct AnthropicResponse {
     content: Vec<ContentBlock>,
     #[serde(rename = "stop_reason")]
     stop_reason: Option<String>,
+    #[serde(default)]
+    usage: Option<WireUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WireUsage {
+    #[serde(default)]
+    input_tokens: usize,
+    #[serde(default)]
+    output_tokens: usize,
+}
+
+impl WireUsage {
+    fn token_usage(&self) -> TokenUsage {
+        TokenUsage::new(self.input_tokens, self.output_tokens)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -238,7 +393,146 @@ struct AnthropicResponse {
 struct ContentBlock {
     #[serde(rename = "type")]
     block_type: String,
+    #[serde(default)]
     text: String,
+    /// tool_use blocks only
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    input: Value,
+}
+
+/// Tool definitions in the Messages API format
+fn tool_definitions(tools: &[ToolDescriptor]) -> Value {
+    Value::Array(
+        tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "name": tools::wire_name(&tool.name),
+                    "description": tool.description,
+                    "input_schema": tools::parameters(tool),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Conversation in the Messages API format
+///
+/// Tool results are sent as user turns; consecutive results share one turn.
+fn tool_messages(turns: &[ToolTurn]) -> Value {
+    let mut messages: Vec<Value> = Vec::new();
+    for turn in turns {
+        match turn {
+            ToolTurn::User { content } => {
+                messages.push(json!({"role": "user", "content": content}));
+            }
+            ToolTurn::Assistant { text, tool_calls } => {
+                let mut blocks = Vec::new();
+                if let Some(text) = text {
+                    blocks.push(json!({"type": "text", "text": text}));
+                }
+                for call in tool_calls {
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.call_id,
+                        "name": tools::wire_name(&call.name),
+                        "input": call.arguments,
+                    }));
+                }
+                messages.push(json!({"role": "assistant", "content": blocks}));
+            }
+            ToolTurn::ToolResult {
+                call_id, content, ..
+            } => {
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": call_id,
+                    "content": tools::result_text(content),
+                });
+                let merged = messages.last_mut().and_then(|last| {
+                    let is_results = last["role"] == "user" && last["content"].is_array();
+                    if is_results {
+                        last["content"].as_array_mut()
+                    } else {
+                        None
+                    }
+                });
+                match merged {
+                    Some(blocks) => blocks.push(block),
+                    None => messages.push(json!({"role": "user", "content": [block]})),
+                }
+            }
+        }
+    }
+    Value::Array(messages)
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamEventPayload {
+    #[serde(rename = "type")]
+    event_type: String,
+    #[serde(default)]
+    delta: Option<StreamTextDelta>,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamTextDelta {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+/// Parse one SSE payload of a streamed Messages response
+fn parse_stream_event(payload: &str) -> StreamEvent {
+    let event: StreamEventPayload = match serde_json::from_str(payload) {
+        Ok(event) => event,
+        Err(e) => {
+            return StreamEvent::Error(ProviderError::APIError(format!(
+                "Invalid stream event: {}",
+                e
+            )))
+        }
+    };
+    match event.event_type.as_str() {
+        "content_block_delta" => match event.delta.and_then(|d| d.text) {
+            Some(text) if !text.is_empty() => StreamEvent::Delta(text),
+            _ => StreamEvent::Skip,
+        },
+        "message_stop" => StreamEvent::Done,
+        "error" => StreamEvent::Error(ProviderError::APIError(
+            event.error.map(|e| e.to_string()).unwrap_or_default(),
+        )),
+        _ => StreamEvent::Skip,
+    }
+}
+
+/// Usage carried by `message_start` (input) and `message_delta` (cumulative output)
+#[derive(Debug, Deserialize)]
+struct StreamUsagePayload {
+    #[serde(default)]
+    message: Option<StreamMessageUsage>,
+    #[serde(default)]
+    usage: Option<WireUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamMessageUsage {
+    #[serde(default)]
+    usage: Option<WireUsage>,
+}
+
+/// Token usage of a stream event, if it carries any
+fn stream_usage(payload: &str) -> Option<TokenUsage> {
+    let event: StreamUsagePayload = serde_json::from_str(payload).ok()?;
+    event
+        .usage
+        .or_else(|| event.message.and_then(|m| m.usage))
+        .map(|u| u.token_usage())
 }
 
 #[cfg(test)]
@@ -263,4 +557,58 @@ mod tests {
         let client = AnthropicClient::new("test-key", "claude-haiku-20240307").unwrap();
         assert!(client.max_tokens_for_model() >= 4096);
     }
+
+    #[test]
+    fn test_parse_stream_event() {
+        let delta =
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#;
+        assert!(matches!(parse_stream_event(delta), StreamEvent::Delta(t) if t == "Hi"));
+        assert!(matches!(
+            parse_stream_event(r#"{"type":"ping"}"#),
+            StreamEvent::Skip
+        ));
+        assert!(matches!(
+            parse_stream_event(r#"{"type":"message_stop"}"#),
+            StreamEvent::Done
+        ));
+
+        let start =
+            r#"{"type":"message_start","message":{"usage":{"input_tokens":25,"output_tokens":1}}}"#;
+        assert_eq!(stream_usage(start).map(|u| u.prompt_tokens), Some(25));
+        let end = r#"{"type":"message_delta","usage":{"output_tokens":15}}"#;
+        assert_eq!(stream_usage(end).map(|u| u.completion_tokens), Some(15));
+        assert!(stream_usage(delta).is_none());
+    }
+
+    #[test]
+    fn test_tool_results_share_user_turn() {
+        let call = |id: &str| {
+            tools::tool_call(
+                "browser.get_content".to_string(),
+                json!({}),
+                Some(id.to_string()),
+            )
+        };
+        let turns = vec![
+            ToolTurn::user("read the page"),
+            ToolTurn::Assistant {
+                text: None,
+                tool_calls: vec![call("a"), call("b")],
+            },
+            ToolTurn::ToolResult {
+                call_id: "a".to_string(),
+                name: "browser.get_content".to_string(),
+                content: json!("one"),
+            },
+            ToolTurn::ToolResult {
+                call_id: "b".to_string(),
+                name: "browser.get_content".to_string(),
+                content: json!("two"),
+            },
+        ];
+        let messages = tool_messages(&turns);
+        assert_eq!(messages.as_array().unwrap().len(), 3);
+        assert_eq!(messages[1]["content"][1]["name"], "browser__get_content");
+        assert_eq!(messages[2]["content"].as_array().unwrap().len(), 2);
+    }
 }
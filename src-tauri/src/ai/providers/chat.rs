@@ -0,0 +1,283 @@
+//! Multi-turn Chat Messages
+//!
+//! Role-tagged messages for conversations that span several turns. Clients map
+//! them onto their native chat formats; providers without one fall back to a
+//! flattened transcript (see `Provider::complete_chat`).
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Rough characters per token, used when no tokenizer is available
+const CHARS_PER_TOKEN: usize = 4;
+/// Per-message overhead for role markers and separators
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+    Tool,
+}
+
+impl ChatRole {
+    fn label(&self) -> &'static str {
+        match self {
+            ChatRole::System => "System",
+            ChatRole::User => "User",
+            ChatRole::Assistant => "Assistant",
+            ChatRole::Tool => "Tool",
+        }
+    }
+}
+
+/// One message of a conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+    /// Tool name for `Tool` messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            name: None,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::Assistant, content)
+    }
+
+    pub fn tool(name: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Self::new(ChatRole::Tool, content)
+        }
+    }
+
+    /// Content as the model should see it in a user turn
+    fn user_text(&self) -> String {
+        match (self.role, &self.name) {
+            (ChatRole::Tool, Some(name)) => format!("[Tool {}]\n{}", name, self.content),
+            (ChatRole::Tool, None) => format!("[Tool]\n{}", self.content),
+            _ => self.content.clone(),
+        }
+    }
+}
+
+// ============================================================================
+// Format Conversion
+// ============================================================================
+
+/// Conversation as a plain "Role: content" transcript
+pub fn transcript(messages: &[ChatMessage]) -> String {
+    messages
+        .iter()
+        .map(|m| match (m.role, &m.name) {
+            (ChatRole::Tool, Some(name)) => format!("Tool ({}): {}", name, m.content),
+            (role, _) => format!("{}: {}", role.label(), m.content),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Conversation as a single prompt for providers without a chat endpoint
+pub fn flatten(messages: &[ChatMessage]) -> String {
+    format!("{}\n\nAssistant:", transcript(messages))
+}
+
+/// Messages in the OpenAI chat format (also used by Ollama)
+///
+/// Tool output without a matching tool call id is sent as a user turn.
+pub fn openai_messages(messages: &[ChatMessage]) -> Vec<Value> {
+    messages
+        .iter()
+        .map(|m| match m.role {
+            ChatRole::System => json!({"role": "system", "content": m.content}),
+            ChatRole::Assistant => json!({"role": "assistant", "content": m.content}),
+            ChatRole::User | ChatRole::Tool => json!({"role": "user", "content": m.user_text()}),
+        })
+        .collect()
+}
+
+/// System prompt and strictly alternating user/assistant turns
+///
+/// Anthropic and Gemini take the system prompt separately and reject
+/// consecutive turns from the same role, so those are merged.
+pub fn split_system(messages: &[ChatMessage]) -> (Option<String>, Vec<(ChatRole, String)>) {
+    let mut system: Vec<&str> = Vec::new();
+    let mut turns: Vec<(ChatRole, String)> = Vec::new();
+
+    for message in messages {
+        let role = match message.role {
+            ChatRole::System => {
+                system.push(&message.content);
+                continue;
+            }
+            ChatRole::Assistant => ChatRole::Assistant,
+            ChatRole::User | ChatRole::Tool => ChatRole::User,
+        };
+        let text = message.user_text();
+        match turns.last_mut() {
+            Some((last, content)) if *last == role => {
+                content.push_str("\n\n");
+                content.push_str(&text);
+            }
+            _ => turns.push((role, text)),
+        }
+    }
+
+    // Both APIs require the conversation to open with a user turn
+    if turns
+        .first()
+        .is_some_and(|(role, _)| *role == ChatRole::Assistant)
+    {
+        turns.insert(0, (ChatRole::User, "(continued)".to_string()));
+    }
+
+    let system = (!system.is_empty()).then(|| system.join("\n\n"));
+    (system, turns)
+}
+
+// ============================================================================
+// Context Window
+// ============================================================================
+
+/// Approximate token count of a conversation
+pub fn estimate_tokens(messages: &[ChatMessage]) -> usize {
+    messages.iter().map(message_tokens).sum()
+}
+
+fn message_tokens(message: &ChatMessage) -> usize {
+    message.content.chars().count().div_ceil(CHARS_PER_TOKEN) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// Drop the oldest non-system messages until the conversation fits `max_tokens`
+///
+/// System messages are always kept, as is the newest message.
+pub fn truncate_to_window(messages: &[ChatMessage], max_tokens: usize) -> Vec<ChatMessage> {
+    let system_tokens: usize = messages
+        .iter()
+        .filter(|m| m.role == ChatRole::System)
+        .map(message_tokens)
+        .sum();
+
+    let mut budget = max_tokens.saturating_sub(system_tokens);
+    let mut keep = vec![false; messages.len()];
+    let mut kept_any = false;
+    for (i, message) in messages.iter().enumerate().rev() {
+        if message.role == ChatRole::System {
+            continue;
+        }
+        let tokens = message_tokens(message);
+        if tokens > budget && kept_any {
+            break;
+        }
+        budget = budget.saturating_sub(tokens);
+        keep[i] = true;
+        kept_any = true;
+    }
+
+    messages
+        .iter()
+        .zip(keep)
+        .filter(|(m, keep)| *keep || m.role == ChatRole::System)
+        .map(|(m, _)| m.clone())
+        .collect()
+}
+
+/// Split messages into consecutive chunks of at most `max_tokens` each
+///
+/// A message larger than `max_tokens` gets a chunk of its own.
+pub fn chunk_to_window(messages: &[ChatMessage], max_tokens: usize) -> Vec<&[ChatMessage]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut tokens = 0;
+    for (i, message) in messages.iter().enumerate() {
+        let size = message_tokens(message);
+        if i > start && tokens + size > max_tokens {
+            chunks.push(&messages[start..i]);
+            start = i;
+            tokens = 0;
+        }
+        tokens += size;
+    }
+    if start < messages.len() {
+        chunks.push(&messages[start..]);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_system_merges_turns() {
+        let messages = vec![
+            ChatMessage::system("Be brief."),
+            ChatMessage::assistant("Hello!"),
+            ChatMessage::user("What is on screen?"),
+            ChatMessage::tool("browser.get_content", "A news page"),
+            ChatMessage::assistant("A news page."),
+        ];
+        let (system, turns) = split_system(&messages);
+        assert_eq!(system.as_deref(), Some("Be brief."));
+        assert_eq!(turns.len(), 4);
+        assert_eq!(turns[0].0, ChatRole::User);
+        assert_eq!(turns[2].0, ChatRole::User);
+        assert!(turns[2].1.contains("[Tool browser.get_content]"));
+        assert_eq!(turns[3].0, ChatRole::Assistant);
+    }
+
+    #[test]
+    fn test_truncate_keeps_system_and_newest() {
+        let long = "x".repeat(400);
+        let messages = vec![
+            ChatMessage::system("Rules"),
+            ChatMessage::user(long.clone()),
+            ChatMessage::assistant(long.clone()),
+            ChatMessage::user("Latest question"),
+        ];
+        let kept = truncate_to_window(&messages, 120);
+        assert_eq!(kept.first(), Some(&ChatMessage::system("Rules")));
+        assert_eq!(kept.last(), Some(&ChatMessage::user("Latest question")));
+        assert_eq!(kept.len(), 3);
+        assert!(estimate_tokens(&kept) <= 120);
+
+        // The newest message survives even when it alone overflows
+        let kept = truncate_to_window(&messages[..2], 10);
+        assert_eq!(kept.len(), 2);
+    }
+
+    #[test]
+    fn test_chunks_cover_every_message() {
+        let long = "x".repeat(400);
+        let messages = vec![
+            ChatMessage::user("First"),
+            ChatMessage::assistant("Second"),
+            ChatMessage::user(long),
+            ChatMessage::assistant("Last"),
+        ];
+        let chunks = chunk_to_window(&messages, 50);
+        assert_eq!(chunks.concat(), messages);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].len(), 2);
+        assert!(chunk_to_window(&[], 50).is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+//! Local Inference Server Discovery
+//!
+//! Probes a configured list of localhost ports for an OpenAI-compatible
+//! `/v1/models` endpoint (LM Studio, llama.cpp `server`, vLLM, LocalAI, ...).
+//! Servers that answer are registered as `custom` providers named
+//! `<server>:<port>`, e.g. `lmstudio:1234`, so they can be used in the provider
+//! chain without configuring a base URL.
+
+use super::{ProviderFactory, ProviderKind};
+use crate::config::toml_config::{DiscoveryConfig, ProviderEntry};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use std::time::Duration;
+
+lazy_static::lazy_static! {
+    static ref DISCOVERED: RwLock<Vec<DiscoveredServer>> = RwLock::new(Vec::new());
+}
+
+/// A local server that answered `/v1/models`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiscoveredServer {
+    /// Provider name, `<server>:<port>`
+    pub name: String,
+    /// Best guess at the server software (lmstudio, llamacpp, vllm, localai, local)
+    pub server: String,
+    pub port: u16,
+    /// OpenAI-compatible base URL, ending in `/v1`
+    pub base_url: String,
+    /// Model ids served, in the order the server lists them
+    pub models: Vec<String>,
+}
+
+impl DiscoveredServer {
+    /// Chain entry for this server, using `model` or the first served model
+    pub fn provider_entry(&self, model: Option<&str>) -> ProviderEntry {
+        ProviderEntry {
+            provider: ProviderKind::Custom.to_string(),
+            model: model
+                .map(str::to_string)
+                .or_else(|| self.models.first().cloned()),
+            base_url: Some(self.base_url.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+    #[serde(default)]
+    owned_by: Option<String>,
+}
+
+/// Guess the server software from model owners, then from its default port
+fn server_label(port: u16, owners: &[String]) -> &'static str {
+    for owner in owners.iter().map(|o| o.to_lowercase()) {
+        match owner.as_str() {
+            "llamacpp" => return "llamacpp",
+            "vllm" => return "vllm",
+            "localai" => return "localai",
+            "organization_owner" | "lmstudio" => return "lmstudio",
+            _ => {}
+        }
+    }
+    match port {
+        1234 => "lmstudio",
+        8000 => "vllm",
+        _ => "local",
+    }
+}
+
+/// Whether `name` has the `<server>:<port>` form used for discovered servers
+pub fn is_discovered_name(name: &str) -> bool {
+    name.rsplit_once(':').is_some_and(|(server, port)| {
+        !server.is_empty()
+            && ProviderKind::from_str(server).is_none()
+            && port.parse::<u16>().is_ok()
+    })
+}
+
+/// Probe one port, returning the server if it lists at least one model
+async fn probe_port(client: &Client, host: &str, port: u16) -> Option<DiscoveredServer> {
+    let base_url = format!("http://{}:{}/v1", host, port);
+    let response = client
+        .get(format!("{}/models", base_url))
+        .send()
+        .await
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    let list: ModelList = response.json().await.ok()?;
+    if list.data.is_empty() {
+        tracing::debug!("Server on port {} lists no models", port);
+        return None;
+    }
+
+    let owners: Vec<String> = list
+        .data
+        .iter()
+        .filter_map(|m| m.owned_by.clone())
+        .collect();
+    let server = server_label(port, &owners);
+    Some(DiscoveredServer {
+        name: format!("{}:{}", server, port),
+        server: server.to_string(),
+        port,
+        base_url,
+        models: list.data.into_iter().map(|m| m.id).collect(),
+    })
+}
+
+/// Probe every configured port concurrently
+pub async fn discover(config: &DiscoveryConfig) -> Vec<DiscoveredServer> {
+    if !config.enabled || config.ports.is_empty() {
+        return Vec::new();
+    }
+    let client = match Client::builder()
+        .timeout(Duration::from_millis(config.timeout_ms))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::warn!("Failed to build discovery HTTP client: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let probes = config
+        .ports
+        .iter()
+        .map(|port| probe_port(&client, &config.host, *port));
+    futures::future::join_all(probes)
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Discover servers and register them, replacing the previous scan
+pub async fn refresh(config: &DiscoveryConfig) -> Vec<DiscoveredServer> {
+    let servers = discover(config).await;
+
+    let previous = match DISCOVERED.write() {
+        Ok(mut discovered) => std::mem::replace(&mut *discovered, servers.clone()),
+        Err(_) => Vec::new(),
+    };
+    for stale in previous
+        .iter()
+        .filter(|old| !servers.iter().any(|s| s.name == old.name))
+    {
+        super::unregister_provider(&stale.name);
+    }
+
+    for server in &servers {
+        let entry = server.provider_entry(None);
+        match ProviderFactory::create(
+            ProviderKind::Custom,
+            None,
+            entry.model.as_deref(),
+            entry.base_url.as_deref(),
+        ) {
+            Ok(provider) => {
+                tracing::info!(
+                    "Discovered local provider {} ({} model(s))",
+                    server.name,
+                    server.models.len()
+                );
+                super::register_provider(&server.name, provider);
+            }
+            Err(e) => tracing::warn!("Failed to register {}: {}", server.name, e),
+        }
+    }
+    servers
+}
+
+/// Servers found by the last scan
+pub fn discovered_servers() -> Vec<DiscoveredServer> {
+    DISCOVERED.read().map(|s| s.clone()).unwrap_or_default()
+}
+
+/// A server from the last scan by provider name
+pub fn find_server(name: &str) -> Option<DiscoveredServer> {
+    discovered_servers().into_iter().find(|s| s.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Serve one canned `/v1/models` response on an ephemeral port
+    fn serve_models(body: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        port
+    }
+
+    fn closed_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    #[tokio::test]
+    async fn test_discovers_servers_on_open_ports() {
+        let port = serve_models(
+            r#"{"object":"list","data":[{"id":"qwen2.5-7b","owned_by":"llamacpp"},{"id":"phi-3"}]}"#,
+        );
+        let config = DiscoveryConfig {
+            ports: vec![closed_port(), port],
+            ..DiscoveryConfig::default()
+        };
+
+        let servers = discover(&config).await;
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].name, format!("llamacpp:{}", port));
+        assert_eq!(servers[0].models, vec!["qwen2.5-7b", "phi-3"]);
+
+        let entry = servers[0].provider_entry(None);
+        assert_eq!(entry.provider, "custom");
+        assert_eq!(entry.model.as_deref(), Some("qwen2.5-7b"));
+        assert_eq!(
+            entry.base_url,
+            Some(format!("http://127.0.0.1:{}/v1", port))
+        );
+    }
+
+    #[test]
+    fn test_server_labels_and_names() {
+        assert_eq!(server_label(1234, &[]), "lmstudio");
+        assert_eq!(server_label(8080, &["vllm".to_string()]), "vllm");
+        assert_eq!(server_label(8080, &[]), "local");
+
+        assert!(is_discovered_name("lmstudio:1234"));
+        assert!(!is_discovered_name("ollama:11434"));
+        assert!(!is_discovered_name("custom"));
+        assert!(!is_discovered_name("lmstudio:port"));
+    }
+}
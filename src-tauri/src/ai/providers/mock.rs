@@ -0,0 +1,145 @@
+//! Scriptable provider shared by tests
+//!
+//! Replies `"{name}: {prompt}"`, or a fixed text, streamed word by word. It can
+//! fail every call, reject requests containing a marker text, and produce fixed
+//! embeddings.
+
+use super::{CompletionOptions, CompletionStream, Provider, ProviderError, ProviderInfo};
+use async_trait::async_trait;
+use tokio_util::sync::CancellationToken;
+
+pub struct MockProvider {
+    name: &'static str,
+    fail: bool,
+    reject: Option<&'static str>,
+    fixed_reply: Option<&'static str>,
+    embedding_dim: Option<usize>,
+}
+
+impl MockProvider {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            fail: false,
+            reject: None,
+            fixed_reply: None,
+            embedding_dim: None,
+        }
+    }
+
+    /// A provider whose every call fails
+    pub fn failing(name: &'static str) -> Self {
+        Self {
+            fail: true,
+            ..Self::new(name)
+        }
+    }
+
+    /// Reject requests containing `marker`
+    pub fn rejecting(mut self, marker: &'static str) -> Self {
+        self.reject = Some(marker);
+        self
+    }
+
+    /// Answer every prompt with `reply`
+    pub fn replying(mut self, reply: &'static str) -> Self {
+        self.fixed_reply = Some(reply);
+        self
+    }
+
+    /// Embed every text as a constant vector of `dim` dimensions
+    pub fn with_embeddings(mut self, dim: usize) -> Self {
+        self.embedding_dim = Some(dim);
+        self
+    }
+
+    fn check<'a>(&self, mut texts: impl Iterator<Item = &'a str>) -> Result<(), ProviderError> {
+        if self.fail {
+            return Err(ProviderError::APIError("boom".to_string()));
+        }
+        match self.reject {
+            Some(marker) if texts.any(|text| text.contains(marker)) => {
+                Err(ProviderError::InvalidRequest("rejected".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn reply(&self, prompt: &str) -> Result<String, ProviderError> {
+        self.check(std::iter::once(prompt))?;
+        Ok(match self.fixed_reply {
+            Some(reply) => reply.to_string(),
+            None => format!("{}: {}", self.name, prompt),
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn model(&self) -> &str {
+        "mock"
+    }
+
+    async fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String, ProviderError> {
+        self.reply(prompt)
+    }
+
+    async fn complete_with_options(
+        &self,
+        prompt: &str,
+        _options: CompletionOptions,
+    ) -> Result<String, ProviderError> {
+        self.reply(prompt)
+    }
+
+    async fn complete_stream(
+        &self,
+        prompt: &str,
+        _options: CompletionOptions,
+        _cancel: CancellationToken,
+    ) -> Result<CompletionStream, ProviderError> {
+        let deltas: Vec<Result<String, ProviderError>> = self
+            .reply(prompt)?
+            .split_inclusive(' ')
+            .map(|delta| Ok(delta.to_string()))
+            .collect();
+        Ok(Box::pin(futures::stream::iter(deltas)))
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.embedding_dim.is_some()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let Some(dim) = self.embedding_dim else {
+            return Err(ProviderError::InvalidRequest(
+                "embeddings not enabled".to_string(),
+            ));
+        };
+        self.check(texts.iter().map(String::as_str))?;
+        Ok(texts.iter().map(|_| vec![0.1; dim]).collect())
+    }
+
+    fn supports_vision(&self) -> bool {
+        false
+    }
+
+    fn info(&self) -> ProviderInfo {
+        ProviderInfo {
+            name: self.name.to_string(),
+            model: "mock".to_string(),
+            supports_vision: false,
+            supports_streaming: true,
+            context_window: 4096,
+            max_tokens: 1024,
+        }
+    }
+}
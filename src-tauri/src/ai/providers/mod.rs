@@ -8,17 +8,31 @@
 //! - Ollama (local)
 //! - OpenAI (GPT-4, GPT-4o)
 //! - Anthropic (Claude)
-//! - OpenAI-compatible APIs
+//! - OpenAI-compatible APIs (including local servers found by `discovery`)
 
+use crate::mcp::types::ToolDescriptor;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
 
 pub mod anthropic_client;
+pub mod chat;
+pub mod discovery;
+pub mod fixture;
+#[cfg(test)]
+pub mod mock;
 pub mod openai_client;
+pub mod streaming;
+pub mod tools;
 
 pub use anthropic_client::AnthropicClient;
+pub use chat::{ChatMessage, ChatRole};
+pub use discovery::DiscoveredServer;
+pub use fixture::{Cassette, FixtureMatch, FixtureMode, FixtureProvider};
 pub use openai_client::OpenAIClient;
+pub use streaming::{cancel_stream, CompletionStream, StreamDelta};
+pub use tools::{ToolCompletion, ToolTurn};
 
 // ============================================================================
 // Provider Trait
@@ -45,6 +59,84 @@ pub trait Provider: Send + Sync {
         options: CompletionOptions,
     ) -> Result<String, ProviderError>;
 
+    /// Stream a completion as text deltas
+    ///
+    /// Providers without native streaming yield the full completion as a single delta.
+    async fn complete_stream(
+        &self,
+        prompt: &str,
+        options: CompletionOptions,
+        cancel: CancellationToken,
+    ) -> Result<CompletionStream, ProviderError> {
+        let text = tokio::select! {
+            _ = cancel.cancelled() => String::new(),
+            result = self.complete_with_options(prompt, options) => result?,
+        };
+        Ok(Box::pin(futures::stream::once(async move { Ok(text) })))
+    }
+
+    /// Continue a multi-turn conversation
+    ///
+    /// Providers without a native chat endpoint get the conversation as a transcript.
+    async fn complete_chat(
+        &self,
+        messages: &[ChatMessage],
+        options: CompletionOptions,
+    ) -> Result<String, ProviderError> {
+        self.complete_with_options(&chat::flatten(messages), options)
+            .await
+    }
+
+    /// Stream a multi-turn conversation as text deltas
+    ///
+    /// Providers without native chat streaming stream the conversation as a transcript.
+    async fn complete_chat_stream(
+        &self,
+        messages: &[ChatMessage],
+        options: CompletionOptions,
+        cancel: CancellationToken,
+    ) -> Result<CompletionStream, ProviderError> {
+        self.complete_stream(&chat::flatten(messages), options, cancel)
+            .await
+    }
+
+    /// Check if this provider supports native tool calling
+    fn supports_tools(&self) -> bool {
+        false
+    }
+
+    /// Continue a tool-calling conversation with the tools in `options.tools`
+    async fn complete_with_tools(
+        &self,
+        _turns: &[ToolTurn],
+        _options: CompletionOptions,
+    ) -> Result<ToolCompletion, ProviderError> {
+        Err(ProviderError::InvalidRequest(format!(
+            "Provider {} does not support tool calling",
+            self.name()
+        )))
+    }
+
+    /// Check if this provider can ground completions in live web search
+    ///
+    /// Providers without it ignore `CompletionOptions::grounded`.
+    fn supports_grounding(&self) -> bool {
+        false
+    }
+
+    /// Check if this provider can produce embeddings
+    fn supports_embeddings(&self) -> bool {
+        false
+    }
+
+    /// Embed a batch of texts, one vector per input in order
+    async fn embed(&self, _texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        Err(ProviderError::InvalidRequest(format!(
+            "Provider {} does not support embeddings",
+            self.name()
+        )))
+    }
+
     /// Check if this provider supports vision
     fn supports_vision(&self) -> bool;
 
@@ -81,6 +173,15 @@ pub struct CompletionOptions {
     pub max_tokens: Option<usize>,
     pub top_p: Option<f64>,
     pub stop: Option<Vec<String>>,
+    /// Tools the model may call (see `complete_with_tools`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<ToolDescriptor>>,
+    /// JSON Schema the reply must follow, for providers with a native JSON mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<serde_json::Value>,
+    /// Ground the completion in web search, for providers that support it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub grounded: bool,
 }
 
 impl Default for CompletionOptions {
@@ -90,6 +191,9 @@ impl Default for CompletionOptions {
             max_tokens: Some(4096),
             top_p: None,
             stop: None,
+            tools: None,
+            response_schema: None,
+            grounded: false,
         }
     }
 }
@@ -179,6 +283,11 @@ impl ProviderKind {
             _ => None,
         }
     }
+
+    /// Whether this provider runs on the local machine (no API cost)
+    pub fn is_local(&self) -> bool {
+        matches!(self, ProviderKind::Ollama)
+    }
 }
 
 // ============================================================================
@@ -197,19 +306,23 @@ impl ProviderFactory {
     ) -> Result<Arc<dyn Provider>, ProviderError> {
         match kind {
             ProviderKind::Gemini => {
-                let _api_key =
+                let api_key =
                     api_key.ok_or_else(|| ProviderError::NotConfigured("gemini".to_string()))?;
-                // Use the existing Gemini client wrapper
-                // For now, return an error - the existing SmartAiRouter handles Gemini
-                Err(ProviderError::NotConfigured(
-                    "Use SmartAiRouter for Gemini".to_string(),
+                if api_key.is_empty() {
+                    return Err(ProviderError::NotConfigured("gemini".to_string()));
+                }
+                let model = model.unwrap_or(crate::ai::gemini_client::DEFAULT_GEMINI_MODEL);
+                Ok(Arc::new(
+                    crate::ai::gemini_client::GeminiClient::with_model(api_key.to_string(), model),
                 ))
             }
             ProviderKind::Ollama => {
-                // Use the existing Ollama client wrapper
-                Err(ProviderError::NotConfigured(
-                    "Use SmartAiRouter for Ollama".to_string(),
-                ))
+                // Base URL and vision model come from RuntimeConfig
+                let client = match model {
+                    Some(model) => crate::ai::ollama_client::OllamaClient::with_model(model),
+                    None => crate::ai::ollama_client::OllamaClient::new(),
+                };
+                Ok(Arc::new(client))
             }
             ProviderKind::OpenAI => {
                 let api_key =
@@ -224,8 +337,8 @@ impl ProviderFactory {
                 Ok(Arc::new(AnthropicClient::new(api_key, model)?))
             }
             ProviderKind::Custom => {
-                let api_key =
-                    api_key.ok_or_else(|| ProviderError::NotConfigured("custom".to_string()))?;
+                // Local OpenAI-compatible servers usually don't need a key
+                let api_key = api_key.unwrap_or("");
                 let model = model.unwrap_or("gpt-4o");
                 let base_url =
                     base_url.ok_or_else(|| ProviderError::NotConfigured("custom".to_string()))?;
@@ -262,6 +375,12 @@ pub fn register_provider(name: &str, provider: Arc<dyn Provider>) {
     }
 }
 
+pub fn unregister_provider(name: &str) {
+    if let Ok(mut registry) = PROVIDER_REGISTRY.write() {
+        registry.remove(name);
+    }
+}
+
 pub fn get_provider(name: &str) -> Option<Arc<dyn Provider>> {
     if let Ok(registry) = PROVIDER_REGISTRY.read() {
         registry.get(name).cloned()
@@ -282,14 +401,30 @@ pub fn list_providers() -> Vec<String> {
 // Tauri Commands
 // ============================================================================
 
+/// Provider kinds followed by local servers found by the last discovery scan
 #[tauri::command]
 pub fn get_available_providers() -> Vec<String> {
     ProviderFactory::available_providers()
         .iter()
         .map(|p| p.to_string())
+        .chain(discovery::discovered_servers().into_iter().map(|s| s.name))
         .collect()
 }
 
+/// Rescan the configured ports for local OpenAI-compatible servers
+///
+/// The router's chain is rebuilt so entries naming a server that appeared or
+/// went away take effect immediately.
+#[tauri::command]
+pub async fn discover_local_providers(
+    ai_router: tauri::State<'_, Arc<crate::ai::ai_provider::SmartAiRouter>>,
+) -> Result<Vec<DiscoveredServer>, String> {
+    let config = crate::config::toml_config::load_toml_config();
+    let servers = discovery::refresh(&config.discovery).await;
+    ai_router.reload_chain(&config.core);
+    Ok(servers)
+}
+
 #[tauri::command]
 pub fn get_registered_providers() -> Vec<String> {
     list_providers()
@@ -300,6 +435,11 @@ pub fn get_provider_info(name: String) -> Option<ProviderInfo> {
     get_provider(&name).map(|p| p.info())
 }
 
+#[tauri::command]
+pub fn cancel_ai_stream(request_id: String) -> bool {
+    cancel_stream(&request_id)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -311,6 +451,21 @@ mod tests {
         assert_eq!(ProviderKind::from_str("unknown"), None);
     }
 
+    #[test]
+    fn test_factory_custom_without_key() {
+        let provider = ProviderFactory::create(
+            ProviderKind::Custom,
+            None,
+            Some("local-model"),
+            Some("http://localhost:1234/v1"),
+        );
+        assert!(provider.is_ok());
+        assert_eq!(provider.unwrap().model(), "local-model");
+
+        assert!(ProviderFactory::create(ProviderKind::Gemini, None, None, None).is_err());
+        assert!(ProviderFactory::create(ProviderKind::Ollama, None, None, None).is_ok());
+    }
+
     #[test]
     fn test_completion_options_default() {
         let options = CompletionOptions::default();
@@ -6,7 +6,15 @@ use async_trait::async_trait;
 use reqwest::Client;
 use serde::Deserialize;
 
-use super::{CompletionOptions, Provider, ProviderError, ProviderInfo};
+use super::chat::{self, ChatMessage};
+use super::streaming::{self, Framing, StreamEvent};
+use super::tools::{self, ToolCompletion, ToolTurn};
+use super::{CompletionOptions, CompletionStream, Provider, ProviderError, ProviderInfo};
+use crate::agents::callbacks::TokenUsage;
+use crate::ai::usage::{self, StreamUsage};
+use tokio_util::sync::CancellationToken;
+
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
 
 pub struct OpenAIClient {
     client: Client,
@@ -17,7 +25,7 @@ pub struct OpenAIClient {
 
 impl OpenAIClient {
     pub fn new(api_key: &str, model: &str, base_url: Option<&str>) -> Result<Self, ProviderError> {
-        let base_url = base_url.unwrap_or("https://api.openai.com/v1");
+        let base_url = base_url.unwrap_or(OPENAI_BASE_URL);
 
         let client = Client::builder()
             .timeout(std::time::Duration::from_secs(120))
@@ -30,6 +38,21 @@ impl OpenAIClient {
             base_url: base_url.to_string(),
         })
     }
+
+    /// Attach the bearer token (skipped for keyless local endpoints)
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.api_key.is_empty() {
+            request
+        } else {
+            request.header("Authorization", format!("Bearer {}", self.api_key))
+        }
+    }
+
+    fn track_usage(&self, wire: Option<&WireUsage>) {
+        if let Some(wire) = wire {
+            usage::record_usage(self.name(), &self.model, wire.token_usage());
+        }
+    }
 }
 
 #[async_trait]
@@ -46,13 +69,7 @@ impl Provider for OpenAIClient {
         // Try a simple models list request
         let url = format!("{}/models", self.base_url);
 
-        match self
-            .client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
-            .await
-        {
+        match self.authorize(self.client.get(&url)).send().await {
             Ok(resp) => resp.status().is_success(),
             Err(_) => false,
         }
@@ -67,24 +84,32 @@ impl Provider for OpenAIClient {
         &self,
         prompt: &str,
         options: CompletionOptions,
+    ) -> Result<String, ProviderError> {
+        self.complete_chat(&[ChatMessage::user(prompt)], options)
+            .await
+    }
+
+    async fn complete_chat(
+        &self,
+        messages: &[ChatMessage],
+        options: CompletionOptions,
     ) -> Result<String, ProviderError> {
         let url = format!("{}/chat/completions", self.base_url);
 
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "model": self.model,
-            "messages": [
-                {"role": "user", "content": prompt}
-            ],
+            "messages": chat::openai_messages(messages),
             "temperature": options.temperature.unwrap_or(0.7),
             "max_tokens": options.max_tokens.unwrap_or(4096),
             "top_p": options.top_p,
             "stop": options.stop,
         });
+        if let Some(schema) = options.response_schema {
+            body["response_format"] = response_format(schema);
+        }
 
         let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .authorize(self.client.post(&url))
             .header("Content-Type", "application/json")
             .json(&body)
             .send()
@@ -100,6 +125,7 @@ impl Provider for OpenAIClient {
         }
 
         let response: OpenAIResponse = response.json().await?;
+        self.track_usage(response.usage.as_ref());
 
         let content = response
             .choices
@@ -110,6 +136,126 @@ impl Provider for OpenAIClient {
         Ok(content)
     }
 
+    async fn complete_stream(
+        &self,
+        prompt: &str,
+        options: CompletionOptions,
+        cancel: CancellationToken,
+    ) -> Result<CompletionStream, ProviderError> {
+        self.complete_chat_stream(&[ChatMessage::user(prompt)], options, cancel)
+            .await
+    }
+
+    async fn complete_chat_stream(
+        &self,
+        messages: &[ChatMessage],
+        options: CompletionOptions,
+        cancel: CancellationToken,
+    ) -> Result<CompletionStream, ProviderError> {
+        let url = format!("{}/chat/completions", self.base_url);
+
+        let mut body = serde_json::json!({
+            "model": self.model,
+            "messages": chat::openai_messages(messages),
+            "temperature": options.temperature.unwrap_or(0.7),
+            "max_tokens": options.max_tokens.unwrap_or(4096),
+            "top_p": options.top_p,
+            "stop": options.stop,
+            "stream": true,
+        });
+        // Compatible servers don't all accept stream_options
+        if self.base_url == OPENAI_BASE_URL {
+            body["stream_options"] = serde_json::json!({"include_usage": true});
+        }
+
+        let response = self
+            .authorize(self.client.post(&url))
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(ProviderError::APIError(format!(
+                "Status {}: {}",
+                status, text
+            )));
+        }
+
+        Ok(streaming::stream_response(
+            response,
+            cancel,
+            Framing::Sse,
+            StreamUsage::new(self.name(), &self.model, stream_usage),
+            parse_stream_chunk,
+        ))
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    async fn complete_with_tools(
+        &self,
+        turns: &[ToolTurn],
+        options: CompletionOptions,
+    ) -> Result<ToolCompletion, ProviderError> {
+        let url = format!("{}/chat/completions", self.base_url);
+        let tools = options.tools.unwrap_or_default();
+
+        let mut body = serde_json::json!({
+            "model": self.model,
+            "messages": tools::openai_messages(turns, true),
+            "temperature": options.temperature.unwrap_or(0.7),
+            "max_tokens": options.max_tokens.unwrap_or(4096),
+        });
+        if !tools.is_empty() {
+            body["tools"] = tools::openai_tool_definitions(&tools);
+        }
+
+        let response = self
+            .authorize(self.client.post(&url))
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(ProviderError::APIError(format!(
+                "Status {}: {}",
+                status, text
+            )));
+        }
+
+        let response: OpenAIResponse = response.json().await?;
+        self.track_usage(response.usage.as_ref());
+        let message = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|c| c.message)
+            .ok_or_else(|| ProviderError::APIError("No choices in response".to_string()))?;
+
+        Ok(ToolCompletion {
+            text: message.content.filter(|t| !t.trim().is_empty()),
+            tool_calls: message
+                .tool_calls
+                .into_iter()
+                .map(|call| {
+                    tools::tool_call(
+                        tools::resolve_name(&call.function.name, &tools),
+                        serde_json::Value::String(call.function.arguments),
+                        Some(call.id),
+                    )
+                })
+                .collect(),
+        })
+    }
+
     fn supports_vision(&self) -> bool {
         // GPT-4o supports vision
         self.model.contains("gpt-4o") || self.model.contains("4o")
@@ -146,9 +292,7 @@ impl Provider for OpenAIClient {
         });
 
         let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .authorize(self.client.post(&url))
             .header("Content-Type", "application/json")
             .json(&body)
             .send()
@@ -164,6 +308,7 @@ impl Provider for OpenAIClient {
         }
 
         let response: OpenAIResponse = response.json().await?;
+        self.track_usage(response.usage.as_ref());
 
         let content = response
             .choices
@@ -189,6 +334,22 @@ impl Provider for OpenAIClient {
 #[derive(Debug, Deserialize)]
 struct OpenAIResponse {
     choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<WireUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WireUsage {
+    #[serde(default)]
+    prompt_tokens: usize,
+    #[serde(default)]
+    completion_tokens: usize,
+}
+
+impl WireUsage {
+    fn token_usage(&self) -> TokenUsage {
+        TokenUsage::new(self.prompt_tokens, self.completion_tokens)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -199,6 +360,82 @@ struct Choice {
 #[derive(Debug, Deserialize)]
 struct Message {
     content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<WireToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WireToolCall {
+    #[serde(default)]
+    id: String,
+    function: WireFunction,
+}
+
+#[derive(Debug, Deserialize)]
+struct WireFunction {
+    #[serde(default)]
+    name: String,
+    /// JSON-encoded arguments
+    #[serde(default)]
+    arguments: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    /// Final chunk only, with `stream_options.include_usage`
+    #[serde(default)]
+    usage: Option<WireUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: Option<Message>,
+}
+
+/// Parse one SSE payload of a streamed chat completion
+fn parse_stream_chunk(payload: &str) -> StreamEvent {
+    if payload == "[DONE]" {
+        return StreamEvent::Done;
+    }
+    match serde_json::from_str::<StreamChunk>(payload) {
+        Ok(chunk) => match chunk
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|c| c.delta)
+            .and_then(|d| d.content)
+        {
+            Some(text) if !text.is_empty() => StreamEvent::Delta(text),
+            _ => StreamEvent::Skip,
+        },
+        Err(e) => StreamEvent::Error(ProviderError::APIError(format!(
+            "Invalid stream chunk: {}",
+            e
+        ))),
+    }
+}
+
+/// `response_format` constraining the reply to a JSON Schema
+fn response_format(schema: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "type": "json_schema",
+        "json_schema": {
+            "name": "response",
+            "schema": schema,
+            "strict": false,
+        },
+    })
+}
+
+/// Token usage of a streamed chunk, if it carries any
+fn stream_usage(payload: &str) -> Option<TokenUsage> {
+    serde_json::from_str::<StreamChunk>(payload)
+        .ok()?
+        .usage
+        .map(|u| u.token_usage())
 }
 
 #[cfg(test)]
@@ -223,4 +460,28 @@ mod tests {
         let client = client.unwrap();
         assert!(client.base_url.contains("localhost:8080"));
     }
+
+    #[test]
+    fn test_parse_stream_chunk() {
+        let delta = r#"{"choices":[{"delta":{"content":"Hel"}}]}"#;
+        assert!(matches!(parse_stream_chunk(delta), StreamEvent::Delta(t) if t == "Hel"));
+        let role_only = r#"{"choices":[{"delta":{"role":"assistant"}}]}"#;
+        assert!(matches!(parse_stream_chunk(role_only), StreamEvent::Skip));
+        assert!(matches!(parse_stream_chunk("[DONE]"), StreamEvent::Done));
+
+        let usage = r#"{"choices":[],"usage":{"prompt_tokens":9,"completion_tokens":3}}"#;
+        assert_eq!(stream_usage(usage).map(|u| u.total_tokens), Some(12));
+        assert!(stream_usage(delta).is_none());
+    }
+
+    #[test]
+    fn test_parse_tool_call_response() {
+        let raw = r#"{"choices":[{"message":{"content":null,"tool_calls":[
+            {"id":"call_1","type":"function","function":{"name":"browser__navigate","arguments":"{\"url\":\"https://a.b\"}"}}
+        ]}}]}"#;
+        let response: OpenAIResponse = serde_json::from_str(raw).unwrap();
+        let message = &response.choices[0].message;
+        assert!(message.content.is_none());
+        assert_eq!(message.tool_calls[0].function.name, "browser__navigate");
+    }
 }
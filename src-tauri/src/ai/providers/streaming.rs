@@ -0,0 +1,385 @@
+//! Streaming Completions
+//!
+//! Providers stream completions as text deltas over SSE (OpenAI, Anthropic, Gemini)
+//! or NDJSON (Ollama). Every stream is keyed by a request id: its CancellationToken
+//! aborts the underlying HTTP request, and its deltas are published on a process-wide
+//! broadcast channel that the Tauri frontend and WebSocket clients subscribe to.
+
+use super::ProviderError;
+use crate::ai::usage::StreamUsage;
+use futures::stream::{BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+/// Stream of completion text deltas
+pub type CompletionStream = BoxStream<'static, Result<String, ProviderError>>;
+
+/// Event name used for deltas emitted to the frontend
+pub const STREAM_DELTA_EVENT: &str = "ai_stream_delta";
+
+/// A single streamed chunk, as seen by the frontend and WebSocket clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamDelta {
+    pub request_id: String,
+    pub delta: String,
+    pub done: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A registered stream: its cancellation token plus the registry generation
+/// that tells it apart from later streams re-using the same request id
+#[derive(Debug, Clone)]
+pub struct StreamRegistration {
+    pub token: CancellationToken,
+    generation: u64,
+}
+
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+lazy_static::lazy_static! {
+    static ref ACTIVE_STREAMS: RwLock<HashMap<String, StreamRegistration>> = RwLock::new(HashMap::new());
+    static ref STREAM_EVENTS: broadcast::Sender<StreamDelta> = broadcast::channel(256).0;
+}
+
+// ============================================================================
+// Stream Registry
+// ============================================================================
+
+/// Register a stream and get its cancellation token
+///
+/// Re-using a request id cancels the stream previously registered under it.
+pub fn register_stream(request_id: &str) -> StreamRegistration {
+    let registration = StreamRegistration {
+        token: CancellationToken::new(),
+        generation: NEXT_GENERATION.fetch_add(1, Ordering::Relaxed),
+    };
+    if let Ok(mut streams) = ACTIVE_STREAMS.write() {
+        if let Some(previous) = streams.insert(request_id.to_string(), registration.clone()) {
+            previous.token.cancel();
+        }
+    }
+    registration
+}
+
+/// Cancel an in-flight stream. Returns false if no such stream is active.
+pub fn cancel_stream(request_id: &str) -> bool {
+    let registration = ACTIVE_STREAMS
+        .write()
+        .ok()
+        .and_then(|mut streams| streams.remove(request_id));
+    match registration {
+        Some(registration) => {
+            registration.token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Drop a stream from the registry once it has finished
+///
+/// Leaves the entry alone if the request id has since been re-registered.
+pub fn finish_stream(request_id: &str, registration: &StreamRegistration) {
+    if let Ok(mut streams) = ACTIVE_STREAMS.write() {
+        if streams
+            .get(request_id)
+            .is_some_and(|current| current.generation == registration.generation)
+        {
+            streams.remove(request_id);
+        }
+    }
+}
+
+/// Subscribe to deltas of all streams
+pub fn subscribe_deltas() -> broadcast::Receiver<StreamDelta> {
+    STREAM_EVENTS.subscribe()
+}
+
+fn publish(delta: StreamDelta) {
+    // No subscribers is not an error
+    let _ = STREAM_EVENTS.send(delta);
+}
+
+/// Removes the registry entry when a published stream is dropped
+struct StreamGuard(String, StreamRegistration);
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        finish_stream(&self.0, &self.1);
+    }
+}
+
+/// Wrap a provider stream so every delta is published under `request_id`
+///
+/// A final `done` delta is published when the stream ends, errors or is cancelled.
+pub fn publish_stream(
+    request_id: &str,
+    registration: &StreamRegistration,
+    stream: CompletionStream,
+) -> CompletionStream {
+    let guard = StreamGuard(request_id.to_string(), registration.clone());
+    futures::stream::unfold(
+        (stream, guard, false),
+        |(mut stream, guard, finished)| async move {
+            if finished {
+                return None;
+            }
+            let request_id = guard.0.clone();
+            match stream.next().await {
+                Some(Ok(delta)) => {
+                    publish(StreamDelta {
+                        request_id,
+                        delta: delta.clone(),
+                        done: false,
+                        error: None,
+                    });
+                    Some((Ok(delta), (stream, guard, false)))
+                }
+                Some(Err(e)) => {
+                    publish(StreamDelta {
+                        request_id,
+                        delta: String::new(),
+                        done: true,
+                        error: Some(e.to_string()),
+                    });
+                    Some((Err(e), (stream, guard, true)))
+                }
+                None => {
+                    publish(StreamDelta {
+                        request_id,
+                        delta: String::new(),
+                        done: true,
+                        error: None,
+                    });
+                    None
+                }
+            }
+        },
+    )
+    .boxed()
+}
+
+/// Publish a terminal error for a stream that could not be opened
+pub fn publish_failure(request_id: &str, registration: &StreamRegistration, error: &str) {
+    finish_stream(request_id, registration);
+    publish(StreamDelta {
+        request_id: request_id.to_string(),
+        delta: String::new(),
+        done: true,
+        error: Some(error.to_string()),
+    });
+}
+
+/// Drain a stream into the full completion text
+pub async fn collect_stream(mut stream: CompletionStream) -> Result<String, ProviderError> {
+    let mut text = String::new();
+    while let Some(delta) = stream.next().await {
+        text.push_str(&delta?);
+    }
+    Ok(text)
+}
+
+// ============================================================================
+// Response Framing
+// ============================================================================
+
+/// Wire framing of a streaming response body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Framing {
+    /// Server-sent events, payload in `data:` lines
+    Sse,
+    /// One JSON object per line
+    Ndjson,
+}
+
+/// Outcome of parsing one framed payload
+pub(crate) enum StreamEvent {
+    Delta(String),
+    Skip,
+    Done,
+    Error(ProviderError),
+}
+
+/// Pop one complete line off the front of the buffer
+fn take_line(buffer: &mut Vec<u8>) -> Option<String> {
+    let pos = buffer.iter().position(|&b| b == b'\n')?;
+    let line: Vec<u8> = buffer.drain(..=pos).collect();
+    Some(
+        String::from_utf8_lossy(&line)
+            .trim_end_matches(['\r', '\n'])
+            .to_string(),
+    )
+}
+
+/// Extract the payload of a line (None for blank lines, comments and event names)
+fn frame_payload(line: &str, framing: Framing) -> Option<&str> {
+    let payload = match framing {
+        Framing::Sse => line.strip_prefix("data:")?.trim(),
+        Framing::Ndjson => line.trim(),
+    };
+    if payload.is_empty() {
+        None
+    } else {
+        Some(payload)
+    }
+}
+
+struct ResponseState<F> {
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+    eof: bool,
+    finished: bool,
+    cancel: CancellationToken,
+    framing: Framing,
+    usage: StreamUsage,
+    parse: F,
+}
+
+/// Turn a streaming HTTP response into a delta stream
+///
+/// Cancelling the token (or dropping the stream) drops the response, closing the connection.
+/// Token usage seen in payloads is recorded when the stream is dropped.
+pub(crate) fn stream_response<F>(
+    response: reqwest::Response,
+    cancel: CancellationToken,
+    framing: Framing,
+    usage: StreamUsage,
+    parse: F,
+) -> CompletionStream
+where
+    F: Fn(&str) -> StreamEvent + Send + 'static,
+{
+    let state = ResponseState {
+        response,
+        buffer: Vec::new(),
+        eof: false,
+        finished: false,
+        cancel,
+        framing,
+        usage,
+        parse,
+    };
+
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if state.finished || state.cancel.is_cancelled() {
+                return None;
+            }
+
+            if let Some(line) = take_line(&mut state.buffer) {
+                let Some(payload) = frame_payload(&line, state.framing) else {
+                    continue;
+                };
+                state.usage.observe(payload);
+                match (state.parse)(payload) {
+                    StreamEvent::Delta(text) => return Some((Ok(text), state)),
+                    StreamEvent::Skip => continue,
+                    StreamEvent::Done => return None,
+                    StreamEvent::Error(e) => {
+                        state.finished = true;
+                        return Some((Err(e), state));
+                    }
+                }
+            }
+
+            if state.eof {
+                return None;
+            }
+
+            let chunk = tokio::select! {
+                _ = state.cancel.cancelled() => return None,
+                chunk = state.response.chunk() => chunk,
+            };
+
+            match chunk {
+                Ok(Some(bytes)) => state.buffer.extend_from_slice(&bytes),
+                Ok(None) => {
+                    state.eof = true;
+                    // Flush a trailing line without newline
+                    if !state.buffer.is_empty() {
+                        state.buffer.push(b'\n');
+                    }
+                }
+                Err(e) => {
+                    state.finished = true;
+                    return Some((Err(ProviderError::from(e)), state));
+                }
+            }
+        }
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_line_keeps_partial_tail() {
+        let mut buffer = b"data: one\r\ndata: tw".to_vec();
+        assert_eq!(take_line(&mut buffer).as_deref(), Some("data: one"));
+        assert_eq!(take_line(&mut buffer), None);
+        assert_eq!(buffer, b"data: tw".to_vec());
+    }
+
+    #[test]
+    fn test_frame_payload() {
+        assert_eq!(
+            frame_payload("data: {\"a\":1}", Framing::Sse),
+            Some("{\"a\":1}")
+        );
+        assert_eq!(frame_payload("event: ping", Framing::Sse), None);
+        assert_eq!(frame_payload(": keep-alive", Framing::Sse), None);
+        assert_eq!(
+            frame_payload("{\"done\":true}", Framing::Ndjson),
+            Some("{\"done\":true}")
+        );
+        assert_eq!(frame_payload("", Framing::Ndjson), None);
+    }
+
+    #[test]
+    fn test_cancel_stream() {
+        let registration = register_stream("test-cancel");
+        assert!(cancel_stream("test-cancel"));
+        assert!(registration.token.is_cancelled());
+        assert!(!cancel_stream("test-cancel"));
+    }
+
+    #[test]
+    fn test_finished_stream_keeps_newer_registration() {
+        let first = register_stream("test-reuse");
+        let second = register_stream("test-reuse");
+        assert!(first.token.is_cancelled());
+
+        // The superseded stream finishing must not unregister its replacement
+        finish_stream("test-reuse", &first);
+        assert!(cancel_stream("test-reuse"));
+        assert!(second.token.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_publish_stream_emits_done() {
+        let mut rx = subscribe_deltas();
+        let source: CompletionStream =
+            futures::stream::iter(vec![Ok("a".to_string()), Ok("b".to_string())]).boxed();
+        let registration = register_stream("test-publish");
+        let text = collect_stream(publish_stream("test-publish", &registration, source))
+            .await
+            .unwrap();
+        assert_eq!(text, "ab");
+
+        let mut deltas = Vec::new();
+        while let Ok(delta) = rx.try_recv() {
+            if delta.request_id == "test-publish" {
+                deltas.push(delta);
+            }
+        }
+        assert_eq!(deltas.len(), 3);
+        assert!(deltas[2].done);
+    }
+}
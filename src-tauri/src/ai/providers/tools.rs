@@ -0,0 +1,235 @@
+//! Native Tool Calling
+//!
+//! Shared types for function/tool calling across providers. Tools are described
+//! with MCP `ToolDescriptor`s and converted to each vendor's schema by the client;
+//! tool calls come back as structured `ToolCall`s so the orchestrator can run a
+//! multi-step loop instead of parsing free text.
+
+use crate::agents::callbacks::ToolCall;
+use crate::mcp::types::ToolDescriptor;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// One turn of a tool-calling conversation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "role", rename_all = "snake_case")]
+pub enum ToolTurn {
+    /// User instruction
+    User { content: String },
+    /// Model reply, possibly requesting tool calls
+    Assistant {
+        text: Option<String>,
+        tool_calls: Vec<ToolCall>,
+    },
+    /// Result of executing a tool call
+    ToolResult {
+        call_id: String,
+        name: String,
+        content: Value,
+    },
+}
+
+impl ToolTurn {
+    pub fn user(content: impl Into<String>) -> Self {
+        ToolTurn::User {
+            content: content.into(),
+        }
+    }
+}
+
+/// Model reply to a tool-calling request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolCompletion {
+    /// Text content (final answer, or commentary alongside tool calls)
+    pub text: Option<String>,
+    /// Requested tool calls, in order
+    pub tool_calls: Vec<ToolCall>,
+}
+
+impl ToolCompletion {
+    /// The model answered without requesting tools
+    pub fn is_final(&self) -> bool {
+        self.tool_calls.is_empty()
+    }
+}
+
+// ============================================================================
+// Schema Conversion Helpers
+// ============================================================================
+
+/// Vendor-safe tool name
+///
+/// OpenAI and Anthropic only accept `[a-zA-Z0-9_-]`, so MCP names such as
+/// `browser.navigate` are sent as `browser__navigate`.
+pub fn wire_name(name: &str) -> String {
+    name.replace('.', "__")
+}
+
+/// Map a vendor tool name back to the MCP tool name
+pub fn resolve_name(wire: &str, tools: &[ToolDescriptor]) -> String {
+    tools
+        .iter()
+        .find(|t| wire_name(&t.name) == wire || t.name == wire)
+        .map(|t| t.name.clone())
+        .unwrap_or_else(|| wire.replace("__", "."))
+}
+
+/// JSON Schema for a tool's parameters
+pub fn parameters(tool: &ToolDescriptor) -> Value {
+    serde_json::to_value(&tool.input_schema).unwrap_or_else(|_| json!({"type": "object"}))
+}
+
+/// Tool definitions in the OpenAI `tools` format (also used by Ollama)
+pub fn openai_tool_definitions(tools: &[ToolDescriptor]) -> Value {
+    Value::Array(
+        tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "type": "function",
+                    "function": {
+                        "name": wire_name(&tool.name),
+                        "description": tool.description,
+                        "parameters": parameters(tool),
+                    }
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Conversation in the OpenAI chat format (also used by Ollama)
+///
+/// OpenAI expects tool call arguments as a JSON string, Ollama as an object.
+pub fn openai_messages(turns: &[ToolTurn], arguments_as_string: bool) -> Vec<Value> {
+    turns
+        .iter()
+        .map(|turn| match turn {
+            ToolTurn::User { content } => json!({"role": "user", "content": content}),
+            ToolTurn::Assistant { text, tool_calls } => {
+                let calls: Vec<Value> = tool_calls
+                    .iter()
+                    .map(|call| {
+                        let arguments = json!(call.arguments);
+                        json!({
+                            "id": call.call_id,
+                            "type": "function",
+                            "function": {
+                                "name": wire_name(&call.name),
+                                "arguments": if arguments_as_string {
+                                    Value::String(arguments.to_string())
+                                } else {
+                                    arguments
+                                },
+                            }
+                        })
+                    })
+                    .collect();
+                let mut message = json!({"role": "assistant", "content": text});
+                if !calls.is_empty() {
+                    message["tool_calls"] = Value::Array(calls);
+                }
+                message
+            }
+            ToolTurn::ToolResult {
+                call_id,
+                name,
+                content,
+            } => json!({
+                "role": "tool",
+                "tool_call_id": call_id,
+                "name": wire_name(name),
+                "content": result_text(content),
+            }),
+        })
+        .collect()
+}
+
+/// Tool arguments from a vendor payload (object, or JSON-encoded string)
+pub fn arguments_from_value(value: Value) -> HashMap<String, Value> {
+    match value {
+        Value::Object(map) => map.into_iter().collect(),
+        Value::String(raw) => serde_json::from_str::<Value>(&raw)
+            .ok()
+            .filter(Value::is_object)
+            .map(arguments_from_value)
+            .unwrap_or_default(),
+        _ => HashMap::new(),
+    }
+}
+
+/// Tool result as plain text for vendors that take string content
+pub fn result_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Build a ToolCall, generating an id when the vendor doesn't supply one
+pub fn tool_call(name: String, arguments: Value, call_id: Option<String>) -> ToolCall {
+    ToolCall {
+        name,
+        arguments: arguments_from_value(arguments),
+        call_id: call_id
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4().simple())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::types::JsonSchema;
+
+    fn descriptor(name: &str) -> ToolDescriptor {
+        ToolDescriptor {
+            name: name.to_string(),
+            description: "test tool".to_string(),
+            input_schema: JsonSchema {
+                schema_type: "object".to_string(),
+                properties: None,
+                required: None,
+                description: None,
+            },
+            is_side_effect: false,
+            category: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_wire_name_round_trip() {
+        let tools = vec![descriptor("browser.get_content")];
+        let wire = wire_name("browser.get_content");
+        assert_eq!(wire, "browser__get_content");
+        assert_eq!(resolve_name(&wire, &tools), "browser.get_content");
+    }
+
+    #[test]
+    fn test_arguments_from_string() {
+        let args = arguments_from_value(Value::String(r#"{"url":"https://a.b"}"#.to_string()));
+        assert_eq!(args.get("url"), Some(&json!("https://a.b")));
+        assert!(arguments_from_value(Value::String("not json".to_string())).is_empty());
+    }
+
+    #[test]
+    fn test_openai_messages_encode_arguments() {
+        let call = tool_call(
+            "browser.navigate".to_string(),
+            json!({"url": "https://a.b"}),
+            Some("call_1".to_string()),
+        );
+        let turns = vec![
+            ToolTurn::user("go"),
+            ToolTurn::Assistant {
+                text: None,
+                tool_calls: vec![call],
+            },
+        ];
+        let messages = openai_messages(&turns, true);
+        let function = &messages[1]["tool_calls"][0]["function"];
+        assert_eq!(function["name"], "browser__navigate");
+        assert!(function["arguments"].is_string());
+    }
+}
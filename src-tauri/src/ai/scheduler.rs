@@ -0,0 +1,471 @@
+//! Priority-aware scheduling of LLM calls
+//!
+//! Every router call takes a token from a shared bucket. Callers are tagged
+//! with a `Priority` (see `with_priority`); lower classes wait while a higher
+//! class is queued and must leave part of the bucket for them, so background
+//! observers can't starve user-initiated requests. Per-provider concurrency
+//! caps keep one slot free for interactive calls, and background calls are
+//! deferred (then dropped) while `ResourceMonitor::should_pause` reports the
+//! system as busy.
+
+use crate::config::toml_config::SchedulerConfig;
+use crate::resources::monitor::ResourceMonitor;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Re-check interval while a higher class is queued or the system is busy
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const BUSY_RECHECK: Duration = Duration::from_secs(1);
+/// How long a system load reading is reused
+const LOAD_CACHE_TTL: Duration = Duration::from_secs(5);
+
+lazy_static::lazy_static! {
+    static ref ACTIVE_SCHEDULER: RwLock<Option<Arc<RequestScheduler>>> = RwLock::new(None);
+}
+
+tokio::task_local! {
+    static PRIORITY: Priority;
+}
+
+/// Priority class of an LLM call, highest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// The user is waiting on the answer (quick ask, chat)
+    Interactive,
+    /// Agent work started by the app on the user's behalf
+    Autonomous,
+    /// Observers and monitors that can run later or not at all
+    Background,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 3] = [
+        Priority::Interactive,
+        Priority::Autonomous,
+        Priority::Background,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Interactive => "interactive",
+            Priority::Autonomous => "autonomous",
+            Priority::Background => "background",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Share of the bucket this class leaves for higher classes
+    fn reserve(self) -> f64 {
+        match self {
+            Priority::Interactive => 0.0,
+            Priority::Autonomous => 0.1,
+            Priority::Background => 0.3,
+        }
+    }
+}
+
+/// Run `future` with its LLM calls scheduled at `priority`
+pub async fn with_priority<F: Future>(priority: Priority, future: F) -> F::Output {
+    PRIORITY.scope(priority, future).await
+}
+
+/// Priority of the current task (autonomous unless set with `with_priority`)
+pub fn current_priority() -> Priority {
+    PRIORITY.try_with(|p| *p).unwrap_or(Priority::Autonomous)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchedulerError {
+    #[error("Rate limit exceeded. Try again in {:.1}s", .0.as_secs_f32())]
+    RateLimited(Duration),
+    #[error("Background request dropped: system is busy")]
+    SystemBusy,
+}
+
+/// Queue and admission counters of one priority class
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorityStats {
+    pub priority: Priority,
+    /// Calls currently waiting to be admitted
+    pub queued: usize,
+    pub admitted: u64,
+    pub dropped: u64,
+}
+
+/// Point-in-time view of the scheduler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerStats {
+    pub tokens_available: f64,
+    pub capacity: f64,
+    pub priorities: Vec<PriorityStats>,
+    /// Calls in flight per provider
+    pub in_flight: BTreeMap<String, usize>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Cached `ResourceMonitor` reading
+struct SystemLoad {
+    monitor: Mutex<Option<ResourceMonitor>>,
+    last: Mutex<Option<(Instant, bool)>>,
+}
+
+impl SystemLoad {
+    fn new() -> Self {
+        Self {
+            monitor: Mutex::new(None),
+            last: Mutex::new(None),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        if let Ok(last) = self.last.lock() {
+            if let Some((at, busy)) = *last {
+                if at.elapsed() < LOAD_CACHE_TTL {
+                    return busy;
+                }
+            }
+        }
+
+        let mode = crate::config::system_settings::SystemSettings::load().performance_mode;
+        let busy = match self.monitor.lock() {
+            Ok(mut monitor) => monitor
+                .get_or_insert_with(ResourceMonitor::new)
+                .should_pause(mode),
+            Err(_) => false,
+        };
+        if let Ok(mut last) = self.last.lock() {
+            *last = Some((Instant::now(), busy));
+        }
+        busy
+    }
+}
+
+/// Token-bucket scheduler with priority classes and per-provider concurrency caps
+pub struct RequestScheduler {
+    capacity: f64,
+    refill_per_sec: f64,
+    bucket: Mutex<Bucket>,
+    waiting: [AtomicUsize; 3],
+    admitted: [AtomicU64; 3],
+    dropped: [AtomicU64; 3],
+    local_concurrency: usize,
+    remote_concurrency: usize,
+    /// Concurrency caps keyed by provider name
+    concurrency: HashMap<String, usize>,
+    slots: Arc<Slots>,
+    pause_background: bool,
+    /// Longest a background call is deferred before it is dropped
+    background_max_wait: Duration,
+    busy_check: Arc<dyn Fn() -> bool + Send + Sync>,
+}
+
+impl RequestScheduler {
+    /// Scheduler admitting `calls_per_minute` calls, with default caps
+    pub fn new(calls_per_minute: u32) -> Self {
+        Self::from_config(&SchedulerConfig {
+            calls_per_minute,
+            ..SchedulerConfig::default()
+        })
+    }
+
+    pub fn from_config(config: &SchedulerConfig) -> Self {
+        let capacity = config.calls_per_minute.max(1) as f64;
+        let load = SystemLoad::new();
+        Self {
+            capacity,
+            refill_per_sec: capacity / 60.0,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+            waiting: Default::default(),
+            admitted: Default::default(),
+            dropped: Default::default(),
+            local_concurrency: config.local_concurrency.max(1),
+            remote_concurrency: config.remote_concurrency.max(1),
+            concurrency: config.concurrency.clone(),
+            slots: Arc::new(Slots::default()),
+            pause_background: config.pause_background_when_busy,
+            background_max_wait: Duration::from_secs(config.background_defer_secs),
+            busy_check: Arc::new(move || load.is_busy()),
+        }
+    }
+
+    /// Replace the system load check (defaults to `ResourceMonitor`)
+    pub fn with_busy_check(mut self, check: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        self.busy_check = Arc::new(check);
+        self
+    }
+
+    /// Wait until a call of `priority` may proceed
+    ///
+    /// Fails once the wait would exceed the class's limit. Background calls
+    /// wait while the system is busy and are dropped if it stays busy.
+    pub async fn admit(&self, priority: Priority) -> Result<(), SchedulerError> {
+        let index = priority.index();
+        let deadline = Instant::now() + self.max_wait(priority);
+        let _queued = QueueGuard::new(&self.waiting[index]);
+
+        loop {
+            let busy =
+                priority == Priority::Background && self.pause_background && (self.busy_check)();
+            let wait = if busy {
+                BUSY_RECHECK
+            } else {
+                match self.try_take(priority) {
+                    Ok(()) => {
+                        self.admitted[index].fetch_add(1, Ordering::Relaxed);
+                        return Ok(());
+                    }
+                    Err(wait) => wait,
+                }
+            };
+
+            if Instant::now() + wait > deadline {
+                self.dropped[index].fetch_add(1, Ordering::Relaxed);
+                return Err(if busy {
+                    SchedulerError::SystemBusy
+                } else {
+                    SchedulerError::RateLimited(wait)
+                });
+            }
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Longest a call of `priority` waits to be admitted before it fails
+    fn max_wait(&self, priority: Priority) -> Duration {
+        match priority {
+            Priority::Interactive => Duration::from_secs(20),
+            Priority::Autonomous => Duration::from_secs(30),
+            Priority::Background => self.background_max_wait,
+        }
+    }
+
+    /// Take a token, or return how long to wait before trying again
+    fn try_take(&self, priority: Priority) -> Result<(), Duration> {
+        let higher_queued = Priority::ALL[..priority.index()]
+            .iter()
+            .any(|p| self.waiting[p.index()].load(Ordering::SeqCst) > 0);
+
+        let Ok(mut bucket) = self.bucket.lock() else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        bucket.last_refill = now;
+
+        let floor = (1.0 + self.capacity * priority.reserve()).min(self.capacity);
+        if higher_queued {
+            return Err(POLL_INTERVAL);
+        }
+        if bucket.tokens >= floor {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        let missing = floor - bucket.tokens;
+        Err(Duration::from_secs_f64(missing / self.refill_per_sec).max(POLL_INTERVAL))
+    }
+
+    /// Concurrency cap of a provider
+    pub fn concurrency_cap(&self, provider: &str, local: bool) -> usize {
+        match self.concurrency.get(provider) {
+            Some(cap) => (*cap).max(1),
+            None if local => self.local_concurrency,
+            None => self.remote_concurrency,
+        }
+    }
+
+    /// Wait for a free slot on `provider`, held until the permit is dropped
+    ///
+    /// Non-interactive calls leave the last slot free when the cap allows it.
+    pub async fn acquire_slot(
+        &self,
+        provider: &str,
+        local: bool,
+        priority: Priority,
+    ) -> SlotPermit {
+        let cap = self.concurrency_cap(provider, local);
+        let limit = if priority == Priority::Interactive || cap == 1 {
+            cap
+        } else {
+            cap - 1
+        };
+
+        loop {
+            // Created before checking so a release in between isn't missed
+            let freed = self.slots.freed.notified();
+            if let Ok(mut in_flight) = self.slots.in_flight.lock() {
+                let count = in_flight.entry(provider.to_string()).or_insert(0);
+                if *count < limit {
+                    *count += 1;
+                    return SlotPermit {
+                        slots: self.slots.clone(),
+                        provider: provider.to_string(),
+                    };
+                }
+            }
+            freed.await;
+        }
+    }
+
+    pub fn stats(&self) -> SchedulerStats {
+        let tokens_available = self.bucket.lock().map(|b| b.tokens).unwrap_or(0.0);
+        let priorities = Priority::ALL
+            .iter()
+            .map(|p| PriorityStats {
+                priority: *p,
+                queued: self.waiting[p.index()].load(Ordering::Relaxed),
+                admitted: self.admitted[p.index()].load(Ordering::Relaxed),
+                dropped: self.dropped[p.index()].load(Ordering::Relaxed),
+            })
+            .collect();
+        let in_flight = self
+            .slots
+            .in_flight
+            .lock()
+            .map(|m| m.iter().map(|(k, v)| (k.clone(), *v)).collect())
+            .unwrap_or_default();
+        SchedulerStats {
+            tokens_available,
+            capacity: self.capacity,
+            priorities,
+            in_flight,
+        }
+    }
+}
+
+/// Calls in flight per provider
+#[derive(Default)]
+struct Slots {
+    in_flight: Mutex<HashMap<String, usize>>,
+    freed: Notify,
+}
+
+/// A concurrency slot on one provider
+///
+/// Owned, so a streamed reply can hold it until the stream ends.
+pub struct SlotPermit {
+    slots: Arc<Slots>,
+    provider: String,
+}
+
+impl Drop for SlotPermit {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.slots.in_flight.lock() {
+            if let Some(count) = in_flight.get_mut(&self.provider) {
+                *count = count.saturating_sub(1);
+            }
+        }
+        self.slots.freed.notify_waiters();
+    }
+}
+
+/// Counts a caller as queued for as long as it waits
+struct QueueGuard<'a>(&'a AtomicUsize);
+
+impl<'a> QueueGuard<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter)
+    }
+}
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Report `scheduler` in the Prometheus metrics
+pub fn set_active_scheduler(scheduler: Arc<RequestScheduler>) {
+    if let Ok(mut active) = ACTIVE_SCHEDULER.write() {
+        *active = Some(scheduler);
+    }
+}
+
+/// Stats of the scheduler used by the app's router, if one is set
+pub fn scheduler_stats() -> Option<SchedulerStats> {
+    ACTIVE_SCHEDULER
+        .read()
+        .ok()
+        .and_then(|s| s.as_ref().map(|s| s.stats()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_background_leaves_reserve_for_interactive() {
+        let scheduler = RequestScheduler::new(10).with_busy_check(|| false);
+        // Background stops while 30% of the bucket plus one token is left
+        for _ in 0..7 {
+            scheduler.admit(Priority::Background).await.unwrap();
+        }
+        assert!(scheduler.try_take(Priority::Background).is_err());
+        assert!(scheduler.try_take(Priority::Autonomous).is_ok());
+        for _ in 0..2 {
+            scheduler.admit(Priority::Interactive).await.unwrap();
+        }
+
+        let stats = scheduler.stats();
+        assert_eq!(stats.priorities[2].admitted, 7);
+        assert_eq!(stats.priorities[0].admitted, 2);
+    }
+
+    #[tokio::test]
+    async fn test_busy_system_drops_background_only() {
+        let config = SchedulerConfig {
+            background_defer_secs: 0,
+            ..SchedulerConfig::default()
+        };
+        let scheduler = RequestScheduler::from_config(&config).with_busy_check(|| true);
+
+        let dropped = scheduler.admit(Priority::Background).await;
+        assert!(matches!(dropped, Err(SchedulerError::SystemBusy)));
+        scheduler.admit(Priority::Interactive).await.unwrap();
+        assert_eq!(scheduler.stats().priorities[2].dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_cap_keeps_a_slot_for_interactive() {
+        let scheduler = RequestScheduler::new(60);
+        assert_eq!(scheduler.concurrency_cap("ollama", true), 2);
+
+        let _background = scheduler
+            .acquire_slot("ollama", true, Priority::Background)
+            .await;
+        let blocked = tokio::time::timeout(
+            Duration::from_millis(20),
+            scheduler.acquire_slot("ollama", true, Priority::Autonomous),
+        )
+        .await;
+        assert!(blocked.is_err());
+
+        let _interactive = scheduler
+            .acquire_slot("ollama", true, Priority::Interactive)
+            .await;
+        assert_eq!(scheduler.stats().in_flight["ollama"], 2);
+
+        assert_eq!(
+            with_priority(Priority::Interactive, async { current_priority() }).await,
+            Priority::Interactive
+        );
+        assert_eq!(current_priority(), Priority::Autonomous);
+    }
+}
@@ -0,0 +1,419 @@
+//! Schema-validated structured output
+//!
+//! Callers ask for a Rust type instead of hand-parsing JSON. The type's JSON
+//! Schema (derived with `schemars`) is appended to the prompt and handed to
+//! providers with a native JSON mode via `CompletionOptions::response_schema`.
+//! Replies are validated against the schema; on failure the model is re-prompted
+//! with the validation errors (see `SmartAiRouter::generate_structured`).
+
+use crate::core::utils::clean_json_response;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::future::Future;
+
+/// Re-prompts after the first attempt before giving up
+pub const DEFAULT_REPAIR_ATTEMPTS: usize = 2;
+/// Validation errors reported back to the model per attempt
+const MAX_REPORTED_ERRORS: usize = 8;
+/// Characters of a rejected reply echoed in the repair prompt
+const MAX_ECHOED_REPLY: usize = 2000;
+
+/// Every attempt returned output that failed validation
+#[derive(Debug, thiserror::Error)]
+#[error("No valid structured output after {attempts} attempts: {reason}")]
+pub struct InvalidOutput {
+    pub attempts: usize,
+    /// Validation errors of the last reply
+    pub reason: String,
+}
+
+/// JSON Schema of `T`
+pub fn schema_for<T: JsonSchema>() -> Value {
+    serde_json::to_value(schemars::schema_for!(T)).unwrap_or(Value::Bool(true))
+}
+
+/// Append the schema and JSON-only instructions to a prompt
+pub fn structured_prompt(prompt: &str, schema: &Value) -> String {
+    format!(
+        "{}\n\nRespond ONLY with JSON (no markdown, no commentary) that validates against this JSON Schema:\n{}",
+        prompt, schema
+    )
+}
+
+/// Ask the model to correct a reply that failed validation
+pub fn repair_prompt(prompt: &str, reply: &str, error: &str) -> String {
+    let reply: String = reply.chars().take(MAX_ECHOED_REPLY).collect();
+    format!(
+        "{}\n\nYour previous response was:\n{}\n\nIt was rejected because:\n{}\n\nRespond again with ONLY the corrected JSON.",
+        prompt, reply, error
+    )
+}
+
+/// Parse a model reply as `T`, checking it against `schema` first
+pub fn parse_structured<T: DeserializeOwned>(reply: &str, schema: &Value) -> Result<T, String> {
+    let mut value: Value = serde_json::from_str(extract_json(reply))
+        .map_err(|e| format!("Response is not valid JSON: {}", e))?;
+
+    let errors = validate(&value, schema);
+    if !errors.is_empty() {
+        return Err(errors
+            .into_iter()
+            .take(MAX_REPORTED_ERRORS)
+            .collect::<Vec<_>>()
+            .join("\n"));
+    }
+
+    // Models often write whole numbers as `1.0`; serde won't read those as integers
+    integral_floats_to_integers(&mut value);
+    serde_json::from_value(value).map_err(|e| format!("Response has the wrong shape: {}", e))
+}
+
+/// Whole number written as a float, e.g. `1.0`
+fn as_integral(n: &serde_json::Number) -> Option<i64> {
+    let f = n.as_f64().filter(|_| n.is_f64())?;
+    (f.fract() == 0.0 && f >= i64::MIN as f64 && f <= i64::MAX as f64).then_some(f as i64)
+}
+
+fn integral_floats_to_integers(value: &mut Value) {
+    match value {
+        Value::Number(n) => {
+            if let Some(i) = as_integral(n) {
+                *value = Value::from(i);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(integral_floats_to_integers),
+        Value::Object(map) => map.values_mut().for_each(integral_floats_to_integers),
+        _ => {}
+    }
+}
+
+/// Request `T` until a reply validates, re-prompting with the errors
+///
+/// `call` sends a prompt to a model and returns its raw reply. It is called at
+/// most `repair_attempts + 1` times; errors from `call` are returned as-is, and
+/// running out of attempts fails with `InvalidOutput`.
+pub async fn request_with_repair<T, F, Fut>(
+    prompt: &str,
+    schema: &Value,
+    repair_attempts: usize,
+    mut call: F,
+) -> anyhow::Result<T>
+where
+    T: DeserializeOwned,
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<String>>,
+{
+    let base = structured_prompt(prompt, schema);
+    let mut request = base.clone();
+    let mut last_error = String::new();
+
+    for attempt in 1..=repair_attempts + 1 {
+        let reply = call(request).await?;
+        match parse_structured(&reply, schema) {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                tracing::warn!("Structured output rejected (attempt {}): {}", attempt, e);
+                request = repair_prompt(&base, &reply, &e);
+                last_error = e;
+            }
+        }
+    }
+
+    Err(InvalidOutput {
+        attempts: repair_attempts + 1,
+        reason: last_error,
+    }
+    .into())
+}
+
+/// The JSON part of a reply, without code fences or surrounding prose
+fn extract_json(reply: &str) -> &str {
+    let text = clean_json_response(reply);
+    if text.starts_with('{') || text.starts_with('[') {
+        return text;
+    }
+    let start = text.find(['{', '[']);
+    let end = text.rfind(['}', ']']);
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => text,
+    }
+}
+
+/// Check `value` against a JSON Schema, returning one message per violation
+///
+/// Covers what `schemars` emits: `$ref` into `definitions`, `allOf`/`anyOf`/`oneOf`,
+/// `type`, `enum`, `const`, `properties`/`required`, `items` and numeric bounds.
+pub fn validate(value: &Value, schema: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    check(value, schema, schema, "$", &mut errors);
+    errors
+}
+
+fn check(value: &Value, schema: &Value, root: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(rules) = schema.as_object() else {
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{}: no value is allowed here", path));
+        }
+        return;
+    };
+
+    if let Some(reference) = rules.get("$ref").and_then(Value::as_str) {
+        match reference
+            .strip_prefix('#')
+            .and_then(|ptr| root.pointer(ptr))
+        {
+            Some(target) => check(value, target, root, path, errors),
+            None => errors.push(format!("{}: unknown schema reference {}", path, reference)),
+        }
+        return;
+    }
+
+    if let Some(all) = rules.get("allOf").and_then(Value::as_array) {
+        for sub in all {
+            check(value, sub, root, path, errors);
+        }
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(options) = rules.get(key).and_then(Value::as_array) {
+            check_alternatives(value, options, root, path, errors);
+        }
+    }
+
+    if let Some(types) = rules.get("type") {
+        let allowed: Vec<&str> = match types {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| type_matches(value, t)) {
+            errors.push(format!(
+                "{}: expected {}, got {}",
+                path,
+                allowed.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(options) = rules.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            errors.push(format!(
+                "{}: must be one of {}",
+                path,
+                Value::from(options.clone())
+            ));
+        }
+    }
+    if let Some(expected) = rules.get("const") {
+        if value != expected {
+            errors.push(format!("{}: must be {}", path, expected));
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            if let Some(required) = rules.get("required").and_then(Value::as_array) {
+                for field in required.iter().filter_map(Value::as_str) {
+                    if !map.contains_key(field) {
+                        errors.push(format!("{}: missing required field \"{}\"", path, field));
+                    }
+                }
+            }
+            if let Some(properties) = rules.get("properties").and_then(Value::as_object) {
+                for (field, sub) in properties {
+                    if let Some(item) = map.get(field) {
+                        check(item, sub, root, &format!("{}.{}", path, field), errors);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => match rules.get("items") {
+            Some(Value::Array(tuple)) => {
+                for (i, (item, sub)) in items.iter().zip(tuple).enumerate() {
+                    check(item, sub, root, &format!("{}[{}]", path, i), errors);
+                }
+            }
+            Some(sub) => {
+                for (i, item) in items.iter().enumerate() {
+                    check(item, sub, root, &format!("{}[{}]", path, i), errors);
+                }
+            }
+            None => {}
+        },
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = rules.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    errors.push(format!("{}: must be at least {}", path, min));
+                }
+            }
+            if let Some(max) = rules.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    errors.push(format!("{}: must be at most {}", path, max));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Accept the value if any alternative matches, otherwise report the closest one
+fn check_alternatives(
+    value: &Value,
+    options: &[Value],
+    root: &Value,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    let mut closest: Option<Vec<String>> = None;
+    for sub in options {
+        let mut sub_errors = Vec::new();
+        check(value, sub, root, path, &mut sub_errors);
+        if sub_errors.is_empty() {
+            return;
+        }
+        // "expected null" is never the helpful explanation for an Option
+        let is_null_branch = sub.get("type").and_then(Value::as_str) == Some("null");
+        let fewer = match &closest {
+            Some(c) => sub_errors.len() < c.len(),
+            None => true,
+        };
+        if !is_null_branch && fewer {
+            closest = Some(sub_errors);
+        }
+    }
+    match closest {
+        Some(closest) => errors.extend(closest),
+        None => errors.push(format!("{}: does not match any allowed shape", path)),
+    }
+}
+
+fn type_matches(value: &Value, expected: &str) -> bool {
+    match expected {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => match value {
+            Value::Number(n) => n.is_i64() || n.is_u64() || as_integral(n).is_some(),
+            _ => false,
+        },
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct Plan {
+        steps: Vec<Step>,
+        difficulty: f32,
+        #[serde(default)]
+        note: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct Step {
+        step: u32,
+        description: String,
+    }
+
+    #[test]
+    fn test_parse_structured_accepts_fenced_json() {
+        let schema = schema_for::<Plan>();
+        let reply = "Here you go:\n```json\n{\"steps\": [{\"step\": 1, \"description\": \"Search\"}], \"difficulty\": 0.5}\n```";
+        let plan: Plan = parse_structured(reply, &schema).unwrap();
+        assert_eq!(plan.steps[0].step, 1);
+        assert!(plan.note.is_none());
+    }
+
+    #[test]
+    fn test_validation_reports_paths() {
+        let schema = schema_for::<Plan>();
+        let value = serde_json::json!({
+            "steps": [{"step": "one", "description": "Search"}, {"step": 2}],
+            "note": 3
+        });
+        let errors = validate(&value, &schema);
+        assert!(errors
+            .iter()
+            .any(|e| e.contains("missing required field \"difficulty\"")));
+        assert!(errors
+            .iter()
+            .any(|e| e.starts_with("$.steps[0].step: expected integer")));
+        assert!(errors
+            .iter()
+            .any(|e| e.contains("$.steps[1]: missing required field \"description\"")));
+        assert!(errors
+            .iter()
+            .any(|e| e.starts_with("$.note: expected string or null")));
+
+        let err = parse_structured::<Plan>("not json", &schema).unwrap_err();
+        assert!(err.contains("not valid JSON"));
+    }
+
+    #[test]
+    fn test_whole_floats_are_integers() {
+        let schema = schema_for::<Step>();
+        let step: Step =
+            parse_structured(r#"{"step": 2.0, "description": "Search"}"#, &schema).unwrap();
+        assert_eq!(step.step, 2);
+
+        let errors = validate(
+            &serde_json::json!({"step": 2.5, "description": "x"}),
+            &schema,
+        );
+        assert!(errors
+            .iter()
+            .any(|e| e.starts_with("$.step: expected integer")));
+    }
+
+    #[tokio::test]
+    async fn test_request_with_repair_reprompts_with_errors() {
+        let schema = schema_for::<Step>();
+        let mut prompts = Vec::new();
+        let step: Step = request_with_repair("Plan one step", &schema, 2, |prompt| {
+            let reply = if prompts.is_empty() {
+                r#"{"step": 1}"#
+            } else {
+                r#"{"step": 1, "description": "Search"}"#
+            };
+            prompts.push(prompt);
+            async move { Ok(reply.to_string()) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(step.description, "Search");
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[1].contains("missing required field \"description\""));
+
+        let failed = request_with_repair::<Step, _, _>("Plan one step", &schema, 1, |_| async {
+            Ok("{}".to_string())
+        })
+        .await;
+        assert!(failed.unwrap_err().is::<InvalidOutput>());
+    }
+}
@@ -0,0 +1,516 @@
+//! Token Usage, Cost Accounting and Spend Budgets
+//!
+//! Clients report the token counts returned by every API call. Calls are priced
+//! with the `[usage.prices]` table (USD per million tokens, on top of built-in
+//! defaults) and accumulated per provider into daily and monthly totals in sled.
+//! Calls the router makes are accounted to the chain entry that served them
+//! (its configured provider name, e.g. `openai` or `lmstudio:1234`), so
+//! providers speaking the same API keep separate totals and budgets.
+//!
+//! Budgets in `[usage.budgets]` raise a notification once spend crosses
+//! `soft_limit_ratio` of a budget; an exceeded budget takes the provider out of
+//! the router's chain (falling back to local models) until the period rolls over.
+
+use crate::agents::callbacks::TokenUsage;
+use crate::ai::providers::{discovery, ProviderKind};
+use crate::config::toml_config::{BudgetConfig, ModelPrice, UsageConfig};
+use crate::memory::MemoryStore;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Sled tree holding daily/monthly totals
+const USAGE_TREE: &str = "token_usage";
+
+/// Built-in prices as (model prefix, input, output) in USD per million tokens
+const DEFAULT_PRICES: &[(&str, f64, f64)] = &[
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gemini-1.5-pro", 1.25, 5.0),
+    ("gemini-1.5-flash", 0.075, 0.30),
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.0),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.0, 8.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-5-haiku", 0.80, 4.0),
+];
+
+/// Accumulated usage for one provider over one period
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    fn add(mut self, usage: &TokenUsage, cost_usd: f64) -> Self {
+        self.calls += 1;
+        self.input_tokens += usage.prompt_tokens as u64;
+        self.output_tokens += usage.completion_tokens as u64;
+        self.cost_usd += cost_usd;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetState {
+    Ok,
+    /// Past the soft limit of a budget
+    Warning,
+    /// A budget is used up; the provider is skipped
+    Exceeded,
+}
+
+/// Usage and budget status of a provider, as shown in the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderUsage {
+    pub provider: String,
+    pub today: UsageTotals,
+    pub month: UsageTotals,
+    pub daily_budget_usd: Option<f64>,
+    pub monthly_budget_usd: Option<f64>,
+    pub state: BudgetState,
+}
+
+/// Price of a model: user overrides first, then built-in defaults, by longest prefix
+pub fn price_for(model: &str, overrides: &HashMap<String, ModelPrice>) -> Option<ModelPrice> {
+    let model = model.to_lowercase();
+    let user = overrides
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(&prefix.to_lowercase()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, price)| price.clone());
+
+    user.or_else(|| {
+        DEFAULT_PRICES
+            .iter()
+            .filter(|(prefix, _, _)| model.starts_with(prefix))
+            .max_by_key(|(prefix, _, _)| prefix.len())
+            .map(|&(_, input, output)| ModelPrice { input, output })
+    })
+}
+
+/// Cost of one call in USD (local providers, discovered local servers and
+/// unpriced models are free)
+pub fn cost_usd(
+    provider: &str,
+    model: &str,
+    usage: &TokenUsage,
+    prices: &HashMap<String, ModelPrice>,
+) -> f64 {
+    if ProviderKind::from_str(provider).is_some_and(|kind| kind.is_local())
+        || discovery::is_discovered_name(provider)
+    {
+        return 0.0;
+    }
+    match price_for(model, prices) {
+        Some(price) => {
+            (usage.prompt_tokens as f64 * price.input
+                + usage.completion_tokens as f64 * price.output)
+                / 1_000_000.0
+        }
+        None => {
+            tracing::debug!("No price for model {}, counting tokens only", model);
+            0.0
+        }
+    }
+}
+
+fn day_key(provider: &str, date: NaiveDate) -> String {
+    format!("day:{}:{}", date.format("%Y-%m-%d"), provider)
+}
+
+fn month_key(provider: &str, date: NaiveDate) -> String {
+    format!("month:{}:{}", date.format("%Y-%m"), provider)
+}
+
+fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+/// Persistent usage totals with budget enforcement
+pub struct UsageLedger {
+    store: MemoryStore,
+    config: UsageConfig,
+    /// Notifications already sent, keyed by state and period
+    notified: Mutex<HashSet<String>>,
+}
+
+impl UsageLedger {
+    pub fn new(store: MemoryStore, config: UsageConfig) -> Self {
+        Self {
+            store,
+            config,
+            notified: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Record one call and return the provider's updated status
+    pub fn record(
+        &self,
+        provider: &str,
+        model: &str,
+        usage: &TokenUsage,
+    ) -> Result<ProviderUsage, String> {
+        self.record_on(provider, model, usage, today())
+    }
+
+    fn record_on(
+        &self,
+        provider: &str,
+        model: &str,
+        usage: &TokenUsage,
+        date: NaiveDate,
+    ) -> Result<ProviderUsage, String> {
+        let cost = cost_usd(provider, model, usage, &self.config.prices);
+        for key in [day_key(provider, date), month_key(provider, date)] {
+            self.store
+                .update(USAGE_TREE, &key, |totals: Option<UsageTotals>| {
+                    Some(totals.unwrap_or_default().add(usage, cost))
+                })
+                .map_err(|e| e.to_string())?;
+        }
+
+        let status = self.usage_on(provider, date);
+        self.notify(&status, date);
+        Ok(status)
+    }
+
+    /// Usage and budget status of a provider for the current day and month
+    pub fn provider_usage(&self, provider: &str) -> ProviderUsage {
+        self.usage_on(provider, today())
+    }
+
+    fn usage_on(&self, provider: &str, date: NaiveDate) -> ProviderUsage {
+        let totals = |key: String| -> UsageTotals {
+            self.store
+                .get(USAGE_TREE, &key)
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        };
+        let today = totals(day_key(provider, date));
+        let month = totals(month_key(provider, date));
+        let budget = self.config.budgets.get(provider);
+
+        ProviderUsage {
+            provider: provider.to_string(),
+            state: self.budget_state(&today, &month, budget),
+            daily_budget_usd: budget.and_then(|b| b.daily_usd),
+            monthly_budget_usd: budget.and_then(|b| b.monthly_usd),
+            today,
+            month,
+        }
+    }
+
+    fn budget_state(
+        &self,
+        today: &UsageTotals,
+        month: &UsageTotals,
+        budget: Option<&BudgetConfig>,
+    ) -> BudgetState {
+        let Some(budget) = budget else {
+            return BudgetState::Ok;
+        };
+        let limits = [
+            (today.cost_usd, budget.daily_usd),
+            (month.cost_usd, budget.monthly_usd),
+        ];
+
+        let mut state = BudgetState::Ok;
+        for (spent, limit) in limits {
+            let Some(limit) = limit else { continue };
+            if spent >= limit {
+                return BudgetState::Exceeded;
+            }
+            if spent >= limit * self.config.soft_limit_ratio {
+                state = BudgetState::Warning;
+            }
+        }
+        state
+    }
+
+    /// Whether a hard budget of the provider is used up
+    pub fn is_exceeded(&self, provider: &str) -> bool {
+        self.config.budgets.contains_key(provider)
+            && self.provider_usage(provider).state == BudgetState::Exceeded
+    }
+
+    /// Status of every provider used this month or with a budget
+    pub fn all_usage(&self) -> Vec<ProviderUsage> {
+        let prefix = format!("month:{}:", today().format("%Y-%m"));
+        let mut providers: BTreeSet<String> = self
+            .store
+            .list_keys(USAGE_TREE)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&prefix).map(str::to_string))
+            .collect();
+        providers.extend(self.config.budgets.keys().cloned());
+
+        providers
+            .iter()
+            .map(|provider| self.provider_usage(provider))
+            .collect()
+    }
+
+    /// Notify once per period when a provider reaches a soft or hard limit
+    fn notify(&self, usage: &ProviderUsage, date: NaiveDate) {
+        let (title, level) = match usage.state {
+            BudgetState::Ok => return,
+            BudgetState::Warning => ("AI budget warning", "warning"),
+            BudgetState::Exceeded => ("AI budget exceeded", "error"),
+        };
+
+        let marker = format!("{:?}:{}", usage.state, day_key(&usage.provider, date));
+        match self.notified.lock() {
+            Ok(mut notified) if notified.insert(marker) => {}
+            _ => return,
+        }
+
+        let spend = |spent: f64, limit: Option<f64>, period: &str| {
+            limit.map(|limit| format!("${:.2} of ${:.2} {}", spent, limit, period))
+        };
+        let detail = [
+            spend(usage.today.cost_usd, usage.daily_budget_usd, "today"),
+            spend(usage.month.cost_usd, usage.monthly_budget_usd, "this month"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", ");
+
+        let body = if usage.state == BudgetState::Exceeded {
+            format!(
+                "{} has used {}. Falling back to local models until the budget resets.",
+                usage.provider, detail
+            )
+        } else {
+            format!("{} has used {}.", usage.provider, detail)
+        };
+
+        tracing::warn!("{}: {}", title, body);
+        crate::core::notifications::push_notification_internal(
+            title.to_string(),
+            body,
+            level.to_string(),
+        );
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref USAGE_LEDGER: RwLock<Option<Arc<UsageLedger>>> = RwLock::new(None);
+}
+
+tokio::task_local! {
+    static ACCOUNT: String;
+}
+
+/// Run `future` with usage recorded under `account` instead of the name the
+/// reporting client gives itself
+pub async fn accounted_to<F: Future>(account: &str, future: F) -> F::Output {
+    ACCOUNT.scope(account.to_string(), future).await
+}
+
+/// Account usage reported by `provider` is recorded under
+fn account_for(provider: &str) -> String {
+    ACCOUNT
+        .try_with(|account| account.clone())
+        .unwrap_or_else(|_| provider.to_string())
+}
+
+/// Install the process-wide ledger
+pub fn init_usage_ledger(store: MemoryStore, config: UsageConfig) {
+    if let Ok(mut ledger) = USAGE_LEDGER.write() {
+        *ledger = Some(Arc::new(UsageLedger::new(store, config)));
+    }
+}
+
+fn ledger() -> Option<Arc<UsageLedger>> {
+    USAGE_LEDGER.read().ok().and_then(|l| l.clone())
+}
+
+/// Record token usage reported by a provider (no-op before the ledger is set up)
+pub fn record_usage(provider: &str, model: &str, usage: TokenUsage) {
+    if usage.prompt_tokens == 0 && usage.completion_tokens == 0 {
+        return;
+    }
+    if let Some(ledger) = ledger() {
+        if let Err(e) = ledger.record(&account_for(provider), model, &usage) {
+            tracing::warn!("Failed to record token usage: {}", e);
+        }
+    }
+}
+
+/// Whether the provider's hard budget is used up
+pub fn is_over_budget(provider: &str) -> bool {
+    ledger().is_some_and(|ledger| ledger.is_exceeded(provider))
+}
+
+/// Usage of all providers (empty before the ledger is set up)
+pub fn usage_summary() -> Vec<ProviderUsage> {
+    ledger()
+        .map(|ledger| ledger.all_usage())
+        .unwrap_or_default()
+}
+
+/// Token usage reported across a streamed response
+///
+/// Streaming APIs report usage in some chunks (often cumulatively), so the
+/// largest counts seen are kept and recorded once the stream is dropped.
+pub struct StreamUsage {
+    provider: String,
+    model: String,
+    extract: fn(&str) -> Option<TokenUsage>,
+    usage: Option<TokenUsage>,
+}
+
+impl StreamUsage {
+    pub fn new(provider: &str, model: &str, extract: fn(&str) -> Option<TokenUsage>) -> Self {
+        Self {
+            // Resolved now: the stream is usually dropped outside the caller's scope
+            provider: account_for(provider),
+            model: model.to_string(),
+            extract,
+            usage: None,
+        }
+    }
+
+    /// Inspect one stream payload for usage counts
+    pub fn observe(&mut self, payload: &str) {
+        let Some(seen) = (self.extract)(payload) else {
+            return;
+        };
+        let usage = self.usage.get_or_insert_with(TokenUsage::default);
+        usage.prompt_tokens = usage.prompt_tokens.max(seen.prompt_tokens);
+        usage.completion_tokens = usage.completion_tokens.max(seen.completion_tokens);
+        usage.total_tokens = usage.prompt_tokens + usage.completion_tokens;
+    }
+}
+
+impl Drop for StreamUsage {
+    fn drop(&mut self) {
+        if let Some(usage) = self.usage.take() {
+            record_usage(&self.provider, &self.model, usage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn ledger_with_budget(daily_usd: f64) -> (tempfile::TempDir, UsageLedger) {
+        let dir = tempdir().unwrap();
+        let store = MemoryStore::open(dir.path().join("usage.db")).unwrap();
+        let mut config = UsageConfig::default();
+        config.budgets.insert(
+            "openai".to_string(),
+            BudgetConfig {
+                daily_usd: Some(daily_usd),
+                monthly_usd: None,
+            },
+        );
+        (dir, UsageLedger::new(store, config))
+    }
+
+    #[test]
+    fn test_price_lookup_prefers_longest_prefix() {
+        let mut overrides = HashMap::new();
+        assert_eq!(
+            price_for("gpt-4o-mini-2024-07-18", &overrides)
+                .unwrap()
+                .input,
+            0.15
+        );
+        assert_eq!(
+            price_for("gpt-4o-2024-08-06", &overrides).unwrap().input,
+            2.50
+        );
+        assert!(price_for("llama3.2", &overrides).is_none());
+
+        overrides.insert(
+            "gpt-4o".to_string(),
+            ModelPrice {
+                input: 1.0,
+                output: 2.0,
+            },
+        );
+        assert_eq!(price_for("gpt-4o-mini", &overrides).unwrap().input, 1.0);
+    }
+
+    #[test]
+    fn test_local_providers_are_free() {
+        let usage = TokenUsage::new(1_000_000, 1_000_000);
+        let prices = HashMap::new();
+        assert_eq!(cost_usd("ollama", "gpt-4o", &usage, &prices), 0.0);
+        assert_eq!(cost_usd("lmstudio:1234", "gpt-4o", &usage, &prices), 0.0);
+        assert_eq!(cost_usd("openai", "gpt-4o", &usage, &prices), 12.5);
+    }
+
+    #[test]
+    fn test_budget_states() {
+        let (_dir, ledger) = ledger_with_budget(10.0);
+        let date = NaiveDate::from_ymd_opt(2026, 3, 14).unwrap();
+
+        // gpt-4o: 1M input tokens = $2.50
+        let million = TokenUsage::new(1_000_000, 0);
+        for _ in 0..3 {
+            ledger
+                .record_on("openai", "gpt-4o", &million, date)
+                .unwrap();
+        }
+        assert_eq!(ledger.usage_on("openai", date).state, BudgetState::Ok);
+
+        let status = ledger
+            .record_on("openai", "gpt-4o", &million, date)
+            .unwrap();
+        assert_eq!(status.state, BudgetState::Exceeded);
+        assert_eq!(status.today.calls, 4);
+        assert_eq!(status.today.input_tokens, 4_000_000);
+
+        // A new day starts with a fresh daily budget
+        let next_day = date.succ_opt().unwrap();
+        assert_eq!(ledger.usage_on("openai", next_day).state, BudgetState::Ok);
+        assert_eq!(ledger.usage_on("openai", next_day).month.calls, 4);
+    }
+
+    #[test]
+    fn test_stream_usage_keeps_largest_counts() {
+        fn extract(payload: &str) -> Option<TokenUsage> {
+            let (input, output) = payload.split_once('/')?;
+            Some(TokenUsage::new(input.parse().ok()?, output.parse().ok()?))
+        }
+
+        let mut usage = StreamUsage::new("anthropic", "claude", extract);
+        usage.observe("12/1");
+        usage.observe("text");
+        usage.observe("0/40");
+        let seen = usage.usage.take().unwrap();
+        assert_eq!((seen.prompt_tokens, seen.completion_tokens), (12, 40));
+    }
+
+    #[tokio::test]
+    async fn test_usage_is_accounted_to_the_chain_entry() {
+        assert_eq!(account_for("openai"), "openai");
+
+        // Streams keep the account they were opened under
+        let usage = accounted_to("lmstudio:1234", async {
+            StreamUsage::new("openai", "qwen", |_| None)
+        })
+        .await;
+        assert_eq!(usage.provider, "lmstudio:1234");
+        assert_eq!(account_for("openai"), "openai");
+    }
+}
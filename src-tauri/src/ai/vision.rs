@@ -4,9 +4,14 @@
 //! Provides element detection, interaction analysis, and visual verification
 //! for browser automation and puzzle solving.
 
+use crate::ai::ai_provider::breaker_config;
+use crate::ai::circuit_breaker::{BreakerRegistry, CircuitBreaker};
+use crate::ai::providers::{Provider, ProviderKind};
+use crate::ai::structured;
+use crate::ai::vision_cache::{vision_cache, ImageHash};
 use anyhow::{anyhow, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -126,83 +131,75 @@ impl std::fmt::Display for VisionProvider {
 pub struct VisionAnalyzer {
     gemini_client: Option<Arc<crate::ai::gemini_client::GeminiClient>>,
     ollama_client: Option<Arc<crate::ai::ollama_client::OllamaClient>>,
-    /// Track if Gemini is currently experiencing issues
-    gemini_failing: AtomicBool,
-    /// Timestamp when Gemini started failing
-    gemini_fail_time: AtomicU64,
-    /// Cache recent analyses to avoid repeated calls
-    analysis_cache: std::sync::Mutex<std::collections::HashMap<String, VisionAnalysis>>,
+    /// Breakers from the process-wide registry, shared with the router
+    gemini_breaker: Option<Arc<CircuitBreaker>>,
+    ollama_breaker: Option<Arc<CircuitBreaker>>,
 }
 
-/// Cache entry expiration (5 minutes)
-const CACHE_EXPIRY_SECS: u64 = 300;
-
 impl VisionAnalyzer {
     /// Create a new vision analyzer with optional providers
     pub fn new(
         gemini: Option<Arc<crate::ai::gemini_client::GeminiClient>>,
         ollama: Option<Arc<crate::ai::ollama_client::OllamaClient>>,
     ) -> Self {
+        let breakers = BreakerRegistry::global();
+        let gemini_breaker = gemini.as_ref().map(|client| {
+            breakers.get(
+                client.name(),
+                client.model(),
+                breaker_config(ProviderKind::Gemini),
+            )
+        });
+        let ollama_breaker = ollama.as_ref().map(|client| {
+            breakers.get(
+                client.name(),
+                &client.vision_model(),
+                breaker_config(ProviderKind::Ollama),
+            )
+        });
         Self {
             gemini_client: gemini,
             ollama_client: ollama,
-            gemini_failing: AtomicBool::new(false),
-            gemini_fail_time: AtomicU64::new(0),
-            analysis_cache: std::sync::Mutex::new(std::collections::HashMap::new()),
+            gemini_breaker,
+            ollama_breaker,
         }
     }
 
     /// Analyze screenshot and return detected elements
     /// Uses Gemini if available, falls back to Ollama
     pub async fn analyze_screenshot(&self, image_bytes: &[u8]) -> Result<VisionAnalysis> {
-        // Check cache first
-        let cache_key = self.compute_image_hash(image_bytes);
-        {
-            let cache = self.analysis_cache.lock().unwrap();
-            if let Some(cached) = cache.get(&cache_key) {
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                if now - cached.timestamp < CACHE_EXPIRY_SECS {
-                    tracing::debug!("Using cached vision analysis");
-                    return Ok(cached.clone());
-                }
-            }
+        // Check cache first; near-identical screenshots share an analysis
+        let image_hash = Self::compute_image_hash(image_bytes).await;
+        if let Some(cached) = image_hash.as_ref().and_then(|h| vision_cache().get(h)) {
+            return Ok(cached);
         }
 
-        // Try Gemini first if available and not failing
+        // Try Gemini first if available and its circuit allows it
         if let Some(gemini) = &self.gemini_client {
-            if !self.gemini_failing.load(Ordering::Relaxed) {
-                match self.analyze_with_gemini(gemini, image_bytes).await {
+            if Self::allow(&self.gemini_breaker) {
+                let result = self.analyze_with_gemini(gemini, image_bytes).await;
+                Self::record(&self.gemini_breaker, &result);
+                match result {
                     Ok(analysis) => {
-                        self.cache_analysis(&cache_key, &analysis);
+                        Self::cache_analysis(image_hash, &analysis);
                         return Ok(analysis);
                     }
-                    Err(e) => {
-                        tracing::warn!("Gemini vision failed, marking as failing: {}", e);
-                        self.gemini_failing.store(true, Ordering::Relaxed);
-                        self.gemini_fail_time.store(
-                            SystemTime::now()
-                                .duration_since(UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs(),
-                            Ordering::Relaxed,
-                        );
-                    }
+                    Err(e) => tracing::warn!("Gemini vision failed: {}", e),
                 }
             }
         }
 
         // Fallback to Ollama
         if let Some(ollama) = &self.ollama_client {
-            match self.analyze_with_ollama(ollama, image_bytes).await {
-                Ok(analysis) => {
-                    self.cache_analysis(&cache_key, &analysis);
-                    return Ok(analysis);
-                }
-                Err(e) => {
-                    tracing::error!("Ollama vision also failed: {}", e);
+            if Self::allow(&self.ollama_breaker) {
+                let result = self.analyze_with_ollama(ollama, image_bytes).await;
+                Self::record(&self.ollama_breaker, &result);
+                match result {
+                    Ok(analysis) => {
+                        Self::cache_analysis(image_hash, &analysis);
+                        return Ok(analysis);
+                    }
+                    Err(e) => tracing::error!("Ollama vision also failed: {}", e),
                 }
             }
         }
@@ -280,14 +277,17 @@ Return a JSON object with:
 
 Focus on elements users would want to click or interact with. Be precise with coordinates."#;
 
-        // Call Gemini API
-        let response = client
-            .analyze_image(&base64_image, prompt)
-            .await
-            .map_err(|e| anyhow!("Gemini vision API error: {}", e))?;
+        // Call Gemini API, re-prompting on malformed output
+        let image = base64_image.as_str();
+        let parsed = request_vision_response(prompt, |request| async move {
+            client
+                .analyze_image(image, &request)
+                .await
+                .map_err(|e| anyhow!("Gemini vision API error: {}", e))
+        })
+        .await?;
 
-        // Parse response
-        self.parse_vision_response(&response, VisionProvider::Gemini)
+        Ok(self.build_analysis(parsed, VisionProvider::Gemini))
     }
 
     /// Analyze with Ollama VLM
@@ -317,53 +317,21 @@ Respond with JSON:
   ]
 }"#;
 
-        // Call Ollama
-        let response = client
-            .analyze_image(&base64_image, prompt)
-            .await
-            .map_err(|e| anyhow!("Ollama vision error: {}", e))?;
+        // Call Ollama, re-prompting on malformed output
+        let image = base64_image.as_str();
+        let parsed = request_vision_response(prompt, |request| async move {
+            client
+                .analyze_image(image, &request)
+                .await
+                .map_err(|e| anyhow!("Ollama vision error: {}", e))
+        })
+        .await?;
 
-        // Parse response
-        self.parse_vision_response(&response, VisionProvider::Ollama)
+        Ok(self.build_analysis(parsed, VisionProvider::Ollama))
     }
 
-    /// Parse vision model response
-    fn parse_vision_response(
-        &self,
-        response: &str,
-        provider: VisionProvider,
-    ) -> Result<VisionAnalysis> {
-        // Extract JSON from response (models sometimes wrap in markdown)
-        let json_str = self.extract_json(response)?;
-
-        #[derive(Deserialize)]
-        struct VisionResponse {
-            page_description: String,
-            elements: Vec<ElementResponse>,
-        }
-
-        #[derive(Deserialize)]
-        struct ElementResponse {
-            element_type: String,
-            description: String,
-            coordinates: CoordsResponse,
-            #[serde(default)]
-            text_content: Option<String>,
-            #[serde(default)]
-            confidence: f32,
-            #[serde(default)]
-            is_interactive: bool,
-        }
-
-        #[derive(Deserialize)]
-        struct CoordsResponse {
-            x: f32,
-            y: f32,
-        }
-
-        let parsed: VisionResponse = serde_json::from_str(&json_str)
-            .map_err(|e| anyhow!("Failed to parse vision response: {}", e))?;
-
+    /// Convert a validated model response into an analysis
+    fn build_analysis(&self, parsed: VisionResponse, provider: VisionProvider) -> VisionAnalysis {
         let elements: Vec<VisualElement> = parsed
             .elements
             .into_iter()
@@ -382,7 +350,7 @@ Respond with JSON:
             .filter(|e| e.coordinates.is_valid())
             .collect();
 
-        Ok(VisionAnalysis {
+        VisionAnalysis {
             elements,
             page_description: parsed.page_description,
             timestamp: SystemTime::now()
@@ -390,26 +358,7 @@ Respond with JSON:
                 .unwrap()
                 .as_secs(),
             provider,
-        })
-    }
-
-    /// Extract JSON from response text
-    fn extract_json(&self, text: &str) -> Result<String> {
-        // Try to find JSON in markdown code blocks
-        if let Some(start) = text.find("```json") {
-            if let Some(end) = text[start..].find("```") {
-                return Ok(text[start + 7..start + end].trim().to_string());
-            }
-        }
-
-        // Try to find JSON object directly
-        if let Some(start) = text.find('{') {
-            if let Some(end) = text.rfind('}') {
-                return Ok(text[start..=end].to_string());
-            }
         }
-
-        Err(anyhow!("Could not extract JSON from response"))
     }
 
     /// Parse element type string
@@ -432,47 +381,82 @@ Respond with JSON:
         }
     }
 
-    /// Compute simple hash for image caching
-    fn compute_image_hash(&self, image_bytes: &[u8]) -> String {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
-        let mut hasher = DefaultHasher::new();
-        image_bytes.hash(&mut hasher);
-        format!("{:x}", hasher.finish())
+    /// Perceptual hash for image caching (decoding runs off the async runtime)
+    async fn compute_image_hash(image_bytes: &[u8]) -> Option<ImageHash> {
+        let bytes = image_bytes.to_vec();
+        tokio::task::spawn_blocking(move || ImageHash::from_bytes(&bytes))
+            .await
+            .ok()
+            .flatten()
     }
 
     /// Cache analysis result
-    fn cache_analysis(&self, key: &str, analysis: &VisionAnalysis) {
-        let mut cache = self.analysis_cache.lock().unwrap();
-        cache.insert(key.to_string(), analysis.clone());
+    fn cache_analysis(image_hash: Option<ImageHash>, analysis: &VisionAnalysis) {
+        if let Some(image_hash) = image_hash {
+            vision_cache().insert(image_hash, analysis.clone());
+        }
+    }
 
-        // Clean old entries if cache too large
-        if cache.len() > 100 {
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
-            cache.retain(|_, v| now - v.timestamp < CACHE_EXPIRY_SECS);
+    /// Whether a provider's breaker lets a call through
+    fn allow(breaker: &Option<Arc<CircuitBreaker>>) -> bool {
+        match breaker {
+            Some(breaker) => breaker.allow_request(),
+            None => true,
         }
     }
 
-    /// Reset Gemini failure state (call periodically to retry)
-    pub fn reset_gemini_status(&self) {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let fail_time = self.gemini_fail_time.load(Ordering::Relaxed);
-
-        // Reset after 5 minutes
-        if now - fail_time > 300 {
-            self.gemini_failing.store(false, Ordering::Relaxed);
-            tracing::info!("Resetting Gemini vision status");
+    /// Record a provider call in its breaker
+    fn record<T>(breaker: &Option<Arc<CircuitBreaker>>, result: &Result<T>) {
+        if let Some(breaker) = breaker {
+            match result {
+                Ok(_) => breaker.record_success(),
+                Err(e) => breaker.record_failure(&e.to_string()),
+            }
         }
     }
 }
 
+/// Element detection result as returned by a vision model
+#[derive(Debug, Deserialize, JsonSchema)]
+struct VisionResponse {
+    /// Brief description of the page (1-2 sentences)
+    page_description: String,
+    elements: Vec<ElementResponse>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ElementResponse {
+    /// button, input, link, text, image, dropdown, checkbox, radio, text_area,
+    /// label, navigation, search, submit or other
+    element_type: String,
+    description: String,
+    /// Normalized center of the element
+    coordinates: CoordsResponse,
+    #[serde(default)]
+    text_content: Option<String>,
+    #[serde(default)]
+    confidence: f32,
+    #[serde(default)]
+    is_interactive: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct CoordsResponse {
+    x: f32,
+    y: f32,
+}
+
+/// Request element detection, re-prompting while the reply fails validation
+async fn request_vision_response<F, Fut>(prompt: &str, call: F) -> Result<VisionResponse>
+where
+    F: FnMut(String) -> Fut,
+    Fut: std::future::Future<Output = Result<String>>,
+{
+    let schema = structured::schema_for::<VisionResponse>();
+    structured::request_with_repair(prompt, &schema, structured::DEFAULT_REPAIR_ATTEMPTS, call)
+        .await
+}
+
 /// Helper function for base64 encoding
 fn base64_encode(input: &[u8]) -> String {
     use base64::Engine;
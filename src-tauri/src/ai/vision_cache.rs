@@ -0,0 +1,330 @@
+//! Perceptual-hash cache for vision analyses
+//!
+//! Screenshots are keyed by a difference hash (dHash) of a downscaled greyscale
+//! copy, so frames that differ only by a blinking cursor or a ticking clock reuse
+//! the previous analysis instead of paying for a new vision call. Lookups pick
+//! the closest entry within `MAX_HAMMING_DISTANCE` bits. Element coordinates go
+//! stale as soon as the screen moves, so near matches are only served while they
+//! are seconds old; identical hashes are reused for the full TTL. The cache is a
+//! bounded LRU persisted in the `vision_cache` sled tree so it survives restarts.
+
+use crate::ai::vision::VisionAnalysis;
+use crate::memory::MemoryStore;
+use crate::observability::Metrics;
+use screenshots::image::{self, imageops::FilterType};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const VISION_CACHE_TREE: &str = "vision_cache";
+
+/// Hash grid width and height (the image is sampled at (SIZE + 1) x SIZE)
+const HASH_SIZE: u32 = 16;
+const HASH_WORDS: usize = (HASH_SIZE * HASH_SIZE / 64) as usize;
+/// Differing bits (of 256) at which two screenshots still count as the same screen
+pub const MAX_HAMMING_DISTANCE: u32 = 3;
+/// Entries kept before the least recently used is evicted
+pub const DEFAULT_CAPACITY: usize = 256;
+/// Analyses older than this are not reused
+pub const CACHE_TTL_SECS: u64 = 3600;
+/// Near (non-identical) matches older than this are not reused
+pub const NEAR_MATCH_TTL_SECS: u64 = 30;
+
+lazy_static::lazy_static! {
+    static ref VISION_CACHE: VisionCache = VisionCache::new(DEFAULT_CAPACITY);
+}
+
+/// Perceptual hash of a screenshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageHash {
+    /// Source dimensions; screenshots of different sizes never match
+    pub width: u32,
+    pub height: u32,
+    bits: [u64; HASH_WORDS],
+}
+
+impl ImageHash {
+    /// dHash of an encoded image, or `None` if it can't be decoded
+    pub fn from_bytes(image_bytes: &[u8]) -> Option<Self> {
+        let decoded = image::load_from_memory(image_bytes).ok()?;
+        let (width, height) = (decoded.width(), decoded.height());
+        let grey = decoded
+            .resize_exact(HASH_SIZE + 1, HASH_SIZE, FilterType::Triangle)
+            .to_luma8();
+
+        let mut bits = [0u64; HASH_WORDS];
+        for y in 0..HASH_SIZE {
+            for x in 0..HASH_SIZE {
+                if grey.get_pixel(x, y)[0] < grey.get_pixel(x + 1, y)[0] {
+                    let bit = (y * HASH_SIZE + x) as usize;
+                    bits[bit / 64] |= 1 << (bit % 64);
+                }
+            }
+        }
+        Some(Self {
+            width,
+            height,
+            bits,
+        })
+    }
+
+    /// Number of differing bits, or `None` for different image sizes
+    pub fn distance(&self, other: &ImageHash) -> Option<u32> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return None;
+        }
+        Some(
+            self.bits
+                .iter()
+                .zip(other.bits.iter())
+                .map(|(a, b)| (a ^ b).count_ones())
+                .sum(),
+        )
+    }
+
+    /// Storage key
+    fn key(&self) -> String {
+        let bits: String = self.bits.iter().map(|w| format!("{:016x}", w)).collect();
+        format!("{}x{}-{}", self.width, self.height, bits)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    hash: ImageHash,
+    analysis: VisionAnalysis,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: Vec<CacheEntry>,
+    store: Option<MemoryStore>,
+}
+
+/// Bounded LRU of vision analyses keyed by perceptual hash
+pub struct VisionCache {
+    state: Mutex<CacheState>,
+    capacity: usize,
+}
+
+impl VisionCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(CacheState::default()),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Persist entries in `store`, loading what an earlier run saved
+    pub fn attach_store(&self, store: MemoryStore) {
+        let mut saved: Vec<CacheEntry> = match store.get_all(VISION_CACHE_TREE) {
+            Ok(saved) => saved,
+            Err(e) => {
+                tracing::warn!("Failed to load vision cache: {}", e);
+                Vec::new()
+            }
+        };
+        let now = now_secs();
+        saved.retain(|entry| now.saturating_sub(entry.analysis.timestamp) < CACHE_TTL_SECS);
+        saved.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+        saved.truncate(self.capacity);
+
+        // Drop expired and overflow entries from disk
+        if let Err(e) = store.clear_tree(VISION_CACHE_TREE) {
+            tracing::warn!("Failed to prune vision cache: {}", e);
+        }
+        for entry in &saved {
+            if let Err(e) = store.set(VISION_CACHE_TREE, &entry.hash.key(), entry) {
+                tracing::warn!("Failed to persist vision cache entry: {}", e);
+            }
+        }
+
+        tracing::info!("Loaded {} cached vision analyses", saved.len());
+        if let Ok(mut state) = self.state.lock() {
+            state.entries = saved;
+            state.store = Some(store);
+        }
+    }
+
+    /// Closest fresh analysis within `MAX_HAMMING_DISTANCE`, recording a hit or miss
+    ///
+    /// Only identical hashes are served after `NEAR_MATCH_TTL_SECS`.
+    pub fn get(&self, hash: &ImageHash) -> Option<VisionAnalysis> {
+        let found = self.lookup(hash);
+        Metrics::new().increment_vision_cache(found.is_some());
+        found
+    }
+
+    fn lookup(&self, hash: &ImageHash) -> Option<VisionAnalysis> {
+        let mut state = self.state.lock().ok()?;
+        let now = now_secs();
+        state
+            .entries
+            .retain(|entry| now.saturating_sub(entry.analysis.timestamp) < CACHE_TTL_SECS);
+
+        let (index, distance) = state
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                let distance = entry.hash.distance(hash)?;
+                let age = now.saturating_sub(entry.analysis.timestamp);
+                let fresh = distance == 0 || age < NEAR_MATCH_TTL_SECS;
+                (distance <= MAX_HAMMING_DISTANCE && fresh).then_some((i, distance))
+            })
+            .min_by_key(|(_, distance)| *distance)?;
+
+        let entry = &mut state.entries[index];
+        entry.last_used = now;
+        tracing::debug!("Using cached vision analysis (hash distance {})", distance);
+        Some(entry.analysis.clone())
+    }
+
+    /// Cache an analysis, evicting the least recently used entry when full
+    pub fn insert(&self, hash: ImageHash, analysis: VisionAnalysis) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let entry = CacheEntry {
+            hash,
+            analysis,
+            last_used: now_secs(),
+        };
+
+        state.entries.retain(|e| e.hash != hash);
+        let mut evicted = None;
+        if state.entries.len() >= self.capacity {
+            if let Some(oldest) = state
+                .entries
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(i, _)| i)
+            {
+                evicted = Some(state.entries.swap_remove(oldest).hash);
+            }
+        }
+
+        if let Some(store) = &state.store {
+            if let Some(evicted) = evicted {
+                let _ = store.delete(VISION_CACHE_TREE, &evicted.key());
+            }
+            if let Err(e) = store.set(VISION_CACHE_TREE, &hash.key(), &entry) {
+                tracing::warn!("Failed to persist vision cache entry: {}", e);
+            }
+        }
+        state.entries.push(entry);
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().map(|s| s.entries.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Process-wide cache shared by every `VisionAnalyzer`
+pub fn vision_cache() -> &'static VisionCache {
+    &VISION_CACHE
+}
+
+/// Persist the shared cache in `store`
+pub fn init_vision_cache(store: MemoryStore) {
+    VISION_CACHE.attach_store(store);
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::vision::VisionProvider;
+    use screenshots::image::{ImageFormat, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    /// A window-like screenshot: a gradient with a dark block at `block_x`
+    fn screenshot(block_x: u32, cursor_on: bool) -> Vec<u8> {
+        let mut img = RgbaImage::from_fn(320, 200, |x, y| {
+            let shade = ((x + y) % 256) as u8;
+            Rgba([shade, shade, 200, 255])
+        });
+        for y in 40..120 {
+            for x in block_x..block_x + 80 {
+                img.put_pixel(x, y, Rgba([20, 20, 20, 255]));
+            }
+        }
+        if cursor_on {
+            for y in 150..160 {
+                img.put_pixel(300, y, Rgba([0, 0, 0, 255]));
+            }
+        }
+        let mut bytes = Vec::new();
+        img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn analysis(description: &str) -> VisionAnalysis {
+        VisionAnalysis {
+            elements: Vec::new(),
+            page_description: description.to_string(),
+            timestamp: now_secs(),
+            provider: VisionProvider::Gemini,
+        }
+    }
+
+    #[test]
+    fn test_near_matches_expire_before_exact_ones() {
+        let cache = VisionCache::new(4);
+        let base = ImageHash::from_bytes(&screenshot(40, false)).unwrap();
+        let mut near = base;
+        near.bits[0] ^= 1;
+
+        let mut older = analysis("older");
+        older.timestamp -= NEAR_MATCH_TTL_SECS + 60;
+        cache.insert(base, older);
+
+        assert!(cache.get(&near).is_none());
+        assert_eq!(cache.get(&base).unwrap().page_description, "older");
+    }
+
+    #[test]
+    fn test_small_changes_keep_the_hash_close() {
+        let base = ImageHash::from_bytes(&screenshot(40, false)).unwrap();
+        let cursor = ImageHash::from_bytes(&screenshot(40, true)).unwrap();
+        let moved = ImageHash::from_bytes(&screenshot(200, false)).unwrap();
+
+        assert!(base.distance(&cursor).unwrap() <= MAX_HAMMING_DISTANCE);
+        assert!(base.distance(&moved).unwrap() > MAX_HAMMING_DISTANCE);
+        assert!(ImageHash::from_bytes(b"not an image").is_none());
+    }
+
+    #[test]
+    fn test_lru_eviction_and_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MemoryStore::open(dir.path().join("test.db")).unwrap();
+        let cache = VisionCache::new(1);
+        cache.attach_store(store.clone());
+
+        let first = ImageHash::from_bytes(&screenshot(40, false)).unwrap();
+        let second = ImageHash::from_bytes(&screenshot(200, false)).unwrap();
+        cache.insert(first, analysis("first"));
+        cache.insert(second, analysis("second"));
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(&first).is_none());
+
+        let cursor = ImageHash::from_bytes(&screenshot(200, true)).unwrap();
+        let reloaded = VisionCache::new(1);
+        reloaded.attach_store(store);
+        let hit = reloaded.get(&cursor).unwrap();
+        assert_eq!(hit.page_description, "second");
+    }
+}
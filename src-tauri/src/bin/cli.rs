@@ -3,11 +3,15 @@
 //! Command-line interface for interacting with the OS-Ghost server
 //! or running tasks directly. Provides similar capabilities to UI-TARS CLI.
 
+use std::path::PathBuf;
 use std::process;
 
 use clap::{Parser, Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
 
+use os_ghost_lib::ai::ollama_client::{ModelDetails, ModelStatus, OllamaClient, PullProgress};
+use os_ghost_lib::memory::hybrid::{HybridMemory, IntegrityReport};
+
 const DEFAULT_SERVER_URL: &str = "http://127.0.0.1:7842";
 
 #[derive(Parser)]
@@ -108,6 +112,26 @@ enum Commands {
     /// Show memory statistics
     Memory,
 
+    /// Check or repair the local memory database (runs without the server)
+    MemoryDb {
+        #[command(subcommand)]
+        action: MemoryDbAction,
+
+        /// Path to memory.db (defaults to the app data directory)
+        #[arg(long)]
+        db: Option<PathBuf>,
+    },
+
+    /// Manage local Ollama models (talks to Ollama directly, not the server)
+    Models {
+        #[command(subcommand)]
+        action: ModelsAction,
+
+        /// Ollama URL (defaults to OLLAMA_URL or http://localhost:11434)
+        #[arg(long)]
+        ollama_url: Option<String>,
+    },
+
     /// Interactive mode
     Interactive,
 
@@ -115,6 +139,42 @@ enum Commands {
     Watch,
 }
 
+#[derive(Subcommand)]
+enum MemoryDbAction {
+    /// Verify SQLite pages and the full-text index
+    IntegrityCheck,
+
+    /// Rebuild the full-text and vector indexes from stored entries
+    Rebuild,
+}
+
+#[derive(Subcommand)]
+enum ModelsAction {
+    /// List installed models
+    List,
+
+    /// Check that the configured text, vision and embedding models are installed
+    Check,
+
+    /// Download a model
+    Pull {
+        /// Model name, e.g. llama3.2-vision
+        model: String,
+    },
+
+    /// Delete an installed model
+    Delete {
+        /// Model name
+        model: String,
+    },
+
+    /// Show parameters, context length and capabilities of a model
+    Show {
+        /// Model name
+        model: String,
+    },
+}
+
 #[derive(ValueEnum, Clone, Debug, Serialize, Deserialize)]
 enum AutonomyLevel {
     Observer,
@@ -297,6 +357,26 @@ async fn run_command(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
             print_memory(&memory, cli.format)?;
         }
 
+        Commands::MemoryDb { action, db } => {
+            let config = os_ghost_lib::config::toml_config::load_toml_config();
+            let memory = HybridMemory::new(db, config.memory.vector_dim)?;
+            let report = match action {
+                MemoryDbAction::IntegrityCheck => memory.integrity_check()?,
+                MemoryDbAction::Rebuild => memory.rebuild()?,
+            };
+            print_integrity_report(&report, cli.format)?;
+            if !report.ok {
+                return Err("memory database has problems (try `memory-db rebuild`)".into());
+            }
+        }
+
+        Commands::Models { action, ollama_url } => {
+            if let Some(url) = ollama_url {
+                os_ghost_lib::core::utils::runtime_config().set_ollama_url(url);
+            }
+            run_models_command(action, cli.format).await?;
+        }
+
         Commands::Interactive => {
             run_interactive_mode(&client, base_url).await?;
         }
@@ -493,6 +573,183 @@ fn print_memory(
     Ok(())
 }
 
+fn print_integrity_report(
+    report: &IntegrityReport,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(report)?);
+        }
+        _ => {
+            println!("Memory Database");
+            println!("===============");
+            println!("Schema Version: {}", report.schema_version);
+            println!("Total Entries: {}", report.total_entries);
+            println!("Indexed Entries: {}", report.indexed_entries);
+            if report.ok {
+                println!("Status: ok");
+            } else {
+                println!("Problems:");
+                for problem in &report.problems {
+                    println!("  - {}", problem);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_models_command(
+    action: ModelsAction,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut ollama = OllamaClient::new();
+    let memory = os_ghost_lib::config::toml_config::load_toml_config().memory;
+    if memory.embedding_provider.as_deref() == Some("ollama") {
+        ollama = ollama.with_embedding_model(
+            memory
+                .embedding_model
+                .as_deref()
+                .unwrap_or(os_ghost_lib::core::utils::DEFAULT_OLLAMA_EMBEDDING_MODEL),
+        );
+    }
+
+    match action {
+        ModelsAction::List => {
+            let models = ollama.list_models().await?;
+            match format {
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&models)?),
+                _ => {
+                    for model in models {
+                        println!("{}", model);
+                    }
+                }
+            }
+        }
+        ModelsAction::Check => {
+            let statuses = ollama.check_models().await?;
+            print_model_statuses(&statuses, format)?;
+            if statuses.iter().any(|s| !s.installed) {
+                return Err(
+                    "some configured models are missing (try `models pull <model>`)".into(),
+                );
+            }
+        }
+        ModelsAction::Pull { model } => {
+            let mut last_status = String::new();
+            ollama
+                .pull_model(&model, |progress| {
+                    print_pull_progress(progress, &mut last_status, &format)
+                })
+                .await?;
+            if !matches!(format, OutputFormat::Json) {
+                println!();
+                println!("Pulled {}", model);
+            }
+        }
+        ModelsAction::Delete { model } => {
+            ollama.delete_model(&model).await?;
+            println!("Deleted {}", model);
+        }
+        ModelsAction::Show { model } => {
+            let details = ollama.show_model(&model).await?;
+            print_model_details(&details, format)?;
+        }
+    }
+    Ok(())
+}
+
+fn print_pull_progress(progress: &PullProgress, last_status: &mut String, format: &OutputFormat) {
+    if matches!(format, OutputFormat::Json) {
+        if let Ok(line) = serde_json::to_string(progress) {
+            println!("{}", line);
+        }
+        return;
+    }
+
+    if progress.status != *last_status {
+        if !last_status.is_empty() {
+            println!();
+        }
+        *last_status = progress.status.clone();
+    }
+    match (progress.completed, progress.total) {
+        (Some(completed), Some(total)) if total > 0 => print!(
+            "\r{} {:>5.1}% ({} / {} MB)",
+            progress.status,
+            completed as f64 * 100.0 / total as f64,
+            completed / 1_000_000,
+            total / 1_000_000
+        ),
+        _ => print!("\r{}", progress.status),
+    }
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+}
+
+fn print_model_statuses(
+    statuses: &[ModelStatus],
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(statuses)?);
+        }
+        _ => {
+            println!(
+                "{:<10} {:<30} {:<10} Recommended",
+                "Role", "Model", "Installed"
+            );
+            println!("{}", "-".repeat(70));
+            for status in statuses {
+                println!(
+                    "{:<10} {:<30} {:<10} {}",
+                    format!("{:?}", status.role).to_lowercase(),
+                    status.model,
+                    if status.installed { "yes" } else { "no" },
+                    status.recommended
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_model_details(
+    details: &ModelDetails,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(details)?);
+        }
+        _ => {
+            println!("Model: {}", details.name);
+            println!("Family: {}", details.family.as_deref().unwrap_or("unknown"));
+            println!(
+                "Parameters: {}",
+                details.parameter_size.as_deref().unwrap_or("unknown")
+            );
+            println!(
+                "Quantization: {}",
+                details.quantization_level.as_deref().unwrap_or("unknown")
+            );
+            match details.context_length {
+                Some(length) => println!("Context Length: {}", length),
+                None => println!("Context Length: unknown"),
+            }
+            println!("Capabilities: {}", details.capabilities.join(", "));
+            if let Some(ref parameters) = details.parameters {
+                println!("Modelfile Parameters:");
+                for line in parameters.lines() {
+                    println!("  {}", line);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 async fn run_interactive_mode(
     _client: &reqwest::Client,
     _base_url: &str,
@@ -8,6 +8,7 @@
 //! Reference: https://github.com/theonlyhennygod/zeroclaw
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::RwLock;
@@ -36,6 +37,16 @@ pub struct TomlConfig {
     pub browser: BrowserConfig,
     #[serde(default)]
     pub identity: IdentityConfig,
+    #[serde(default)]
+    pub fixtures: FixtureConfig,
+    #[serde(default)]
+    pub usage: UsageConfig,
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+    #[serde(default)]
+    pub discovery: DiscoveryConfig,
+    #[serde(default)]
+    pub blocklists: BlocklistConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,12 +59,22 @@ pub struct CoreConfig {
     pub default_model: Option<String>,
     #[serde(default = "default_temperature")]
     pub default_temperature: f64,
+    /// Base URL for the default provider (OpenAI-compatible endpoints)
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Providers tried in order when the default provider fails
+    #[serde(default = "default_fallback_providers")]
+    pub fallback_providers: Vec<ProviderEntry>,
 }
 
 fn default_temperature() -> f64 {
     0.7
 }
 
+fn default_fallback_providers() -> Vec<ProviderEntry> {
+    vec![ProviderEntry::new("ollama")]
+}
+
 impl Default for CoreConfig {
     fn default() -> Self {
         Self {
@@ -61,10 +82,56 @@ impl Default for CoreConfig {
             default_provider: Some("gemini".to_string()),
             default_model: None,
             default_temperature: 0.7,
+            base_url: None,
+            fallback_providers: default_fallback_providers(),
+        }
+    }
+}
+
+/// One entry in the provider fallback chain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProviderEntry {
+    /// Provider kind (gemini, ollama, openai, anthropic, custom)
+    pub provider: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+impl ProviderEntry {
+    pub fn new(provider: &str) -> Self {
+        Self {
+            provider: provider.to_string(),
+            model: None,
+            base_url: None,
         }
     }
 }
 
+impl CoreConfig {
+    /// Full provider chain: the default provider followed by the fallbacks
+    pub fn provider_chain(&self) -> Vec<ProviderEntry> {
+        let mut chain = Vec::new();
+
+        if let Some(ref provider) = self.default_provider {
+            chain.push(ProviderEntry {
+                provider: provider.clone(),
+                model: self.default_model.clone(),
+                base_url: self.base_url.clone(),
+            });
+        }
+
+        for entry in &self.fallback_providers {
+            if !chain.contains(entry) {
+                chain.push(entry.clone());
+            }
+        }
+
+        chain
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryConfig {
     #[serde(default = "default_memory_backend")]
@@ -73,10 +140,19 @@ pub struct MemoryConfig {
     pub auto_save: bool,
     #[serde(default)]
     pub embedding_provider: Option<String>,
+    /// Embedding model (provider default when unset)
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    /// Expected embedding dimension; mismatching vectors are rejected
+    #[serde(default = "default_vector_dim")]
+    pub vector_dim: usize,
     #[serde(default = "default_vector_weight")]
     pub vector_weight: f64,
     #[serde(default = "default_keyword_weight")]
     pub keyword_weight: f64,
+    /// How vector and keyword results are merged: "weighted" or "rrf"
+    #[serde(default = "default_fusion")]
+    pub fusion: String,
 }
 
 fn default_memory_backend() -> String {
@@ -85,12 +161,18 @@ fn default_memory_backend() -> String {
 fn default_true() -> bool {
     true
 }
+fn default_vector_dim() -> usize {
+    768
+}
 fn default_vector_weight() -> f64 {
     0.7
 }
 fn default_keyword_weight() -> f64 {
     0.3
 }
+fn default_fusion() -> String {
+    "weighted".to_string()
+}
 
 impl Default for MemoryConfig {
     fn default() -> Self {
@@ -98,8 +180,190 @@ impl Default for MemoryConfig {
             backend: "sled".to_string(),
             auto_save: true,
             embedding_provider: None,
+            embedding_model: None,
+            vector_dim: 768,
             vector_weight: 0.7,
             keyword_weight: 0.3,
+            fusion: default_fusion(),
+        }
+    }
+}
+
+/// Token pricing and spend budgets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageConfig {
+    /// Fraction of a budget at which a warning is shown
+    #[serde(default = "default_soft_limit_ratio")]
+    pub soft_limit_ratio: f64,
+    /// Budgets keyed by provider (gemini, openai, anthropic, ...)
+    #[serde(default)]
+    pub budgets: HashMap<String, BudgetConfig>,
+    /// Price overrides keyed by model name prefix
+    #[serde(default)]
+    pub prices: HashMap<String, ModelPrice>,
+}
+
+fn default_soft_limit_ratio() -> f64 {
+    0.8
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            soft_limit_ratio: default_soft_limit_ratio(),
+            budgets: HashMap::new(),
+            prices: HashMap::new(),
+        }
+    }
+}
+
+/// LLM request scheduling (see `ai::scheduler`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerConfig {
+    /// Calls admitted per minute across all providers
+    #[serde(default = "default_calls_per_minute")]
+    pub calls_per_minute: u32,
+    /// Concurrent calls per local server (Ollama, LM Studio, ...)
+    #[serde(default = "default_local_concurrency")]
+    pub local_concurrency: usize,
+    /// Concurrent calls per remote provider
+    #[serde(default = "default_remote_concurrency")]
+    pub remote_concurrency: usize,
+    /// Caps keyed by provider name, overriding the two above
+    #[serde(default)]
+    pub concurrency: HashMap<String, usize>,
+    /// Defer background calls while the system is under load
+    #[serde(default = "default_true")]
+    pub pause_background_when_busy: bool,
+    /// Seconds a background call is deferred before it is dropped
+    #[serde(default = "default_background_defer_secs")]
+    pub background_defer_secs: u64,
+}
+
+fn default_calls_per_minute() -> u32 {
+    60
+}
+fn default_local_concurrency() -> usize {
+    2
+}
+fn default_remote_concurrency() -> usize {
+    4
+}
+fn default_background_defer_secs() -> u64 {
+    10
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            calls_per_minute: default_calls_per_minute(),
+            local_concurrency: default_local_concurrency(),
+            remote_concurrency: default_remote_concurrency(),
+            concurrency: HashMap::new(),
+            pause_background_when_busy: true,
+            background_defer_secs: default_background_defer_secs(),
+        }
+    }
+}
+
+/// Local OpenAI-compatible server discovery (see `ai::providers::discovery`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveryConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_discovery_host")]
+    pub host: String,
+    /// Ports probed for `/v1/models` (LM Studio, vLLM, llama.cpp/LocalAI)
+    #[serde(default = "default_discovery_ports")]
+    pub ports: Vec<u16>,
+    /// Per-port probe timeout
+    #[serde(default = "default_discovery_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_discovery_host() -> String {
+    "127.0.0.1".to_string()
+}
+fn default_discovery_ports() -> Vec<u16> {
+    vec![1234, 8000, 8080]
+}
+fn default_discovery_timeout_ms() -> u64 {
+    300
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            host: default_discovery_host(),
+            ports: default_discovery_ports(),
+            timeout_ms: default_discovery_timeout_ms(),
+        }
+    }
+}
+
+/// Offline threat-intelligence blocklists (see `security::blocklist`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlocklistConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Directory of hosts/domain/CSV lists (defaults to blocklists/ in the data directory)
+    #[serde(default)]
+    pub dir: Option<String>,
+}
+
+impl Default for BlocklistConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: None,
+        }
+    }
+}
+
+/// Spend limits for one provider; exceeding one stops using the provider
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BudgetConfig {
+    #[serde(default)]
+    pub daily_usd: Option<f64>,
+    #[serde(default)]
+    pub monthly_usd: Option<f64>,
+}
+
+/// USD per million tokens
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+/// Record/replay of provider calls, for running agents offline (tests, CI)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureConfig {
+    /// "off", "record" or "replay"
+    #[serde(default = "default_fixture_mode")]
+    pub mode: String,
+    /// JSONL cassette path (defaults to fixtures.jsonl in the data directory)
+    #[serde(default)]
+    pub cassette: Option<String>,
+    /// "strict" (prompt and options) or "lenient" (prompt only)
+    #[serde(default = "default_fixture_matching")]
+    pub matching: String,
+}
+
+fn default_fixture_mode() -> String {
+    "off".to_string()
+}
+fn default_fixture_matching() -> String {
+    "strict".to_string()
+}
+
+impl Default for FixtureConfig {
+    fn default() -> Self {
+        Self {
+            mode: default_fixture_mode(),
+            cassette: None,
+            matching: default_fixture_matching(),
         }
     }
 }
@@ -354,6 +618,12 @@ fn apply_env_overrides(mut config: TomlConfig) -> TomlConfig {
         }
     }
 
+    if let Ok(base_url) = std::env::var("OS_GHOST_BASE_URL") {
+        if !base_url.is_empty() {
+            config.core.base_url = Some(base_url);
+        }
+    }
+
     if let Ok(temp) = std::env::var("OS_GHOST_TEMPERATURE") {
         if let Ok(t) = temp.parse::<f64>() {
             config.core.default_temperature = t;
@@ -388,6 +658,30 @@ fn apply_env_overrides(mut config: TomlConfig) -> TomlConfig {
         config.gateway.allow_public_bind = allow_public == "true";
     }
 
+    if let Ok(mode) = std::env::var("OS_GHOST_FIXTURE_MODE") {
+        if !mode.is_empty() {
+            config.fixtures.mode = mode;
+        }
+    }
+
+    if let Ok(cassette) = std::env::var("OS_GHOST_FIXTURE_CASSETTE") {
+        if !cassette.is_empty() {
+            config.fixtures.cassette = Some(cassette);
+        }
+    }
+
+    if let Ok(matching) = std::env::var("OS_GHOST_FIXTURE_MATCHING") {
+        if !matching.is_empty() {
+            config.fixtures.matching = matching;
+        }
+    }
+
+    if let Ok(dir) = std::env::var("OS_GHOST_BLOCKLIST_DIR") {
+        if !dir.is_empty() {
+            config.blocklists.dir = Some(dir);
+        }
+    }
+
     config
 }
 
@@ -446,11 +740,16 @@ fn get_known_fields() -> Vec<&'static str> {
         "core.default_provider",
         "core.default_model",
         "core.default_temperature",
+        "core.base_url",
+        "core.fallback_providers",
         "memory.backend",
         "memory.auto_save",
         "memory.embedding_provider",
+        "memory.embedding_model",
+        "memory.vector_dim",
         "memory.vector_weight",
         "memory.keyword_weight",
+        "memory.fusion",
         "gateway.require_pairing",
         "gateway.allow_public_bind",
         "gateway.port",
@@ -467,6 +766,24 @@ fn get_known_fields() -> Vec<&'static str> {
         "identity.format",
         "identity.path",
         "identity.inline",
+        "fixtures.mode",
+        "fixtures.cassette",
+        "fixtures.matching",
+        "usage.soft_limit_ratio",
+        "usage.budgets",
+        "usage.prices",
+        "scheduler.calls_per_minute",
+        "scheduler.local_concurrency",
+        "scheduler.remote_concurrency",
+        "scheduler.concurrency",
+        "scheduler.pause_background_when_busy",
+        "scheduler.background_defer_secs",
+        "discovery.enabled",
+        "discovery.host",
+        "discovery.ports",
+        "discovery.timeout_ms",
+        "blocklists.enabled",
+        "blocklists.dir",
     ]
 }
 
@@ -515,6 +832,99 @@ pub fn validate_toml_config(config: &TomlConfig) -> ConfigValidationResult {
         result.valid = false;
     }
 
+    // Check provider chain (discovered servers are named `<server>:<port>`)
+    for entry in config.core.provider_chain() {
+        if crate::ai::ProviderKind::from_str(&entry.provider).is_none()
+            && !crate::ai::providers::discovery::is_discovered_name(&entry.provider)
+        {
+            result
+                .errors
+                .push(format!("Unknown provider in chain: {}", entry.provider));
+            result.valid = false;
+        }
+    }
+
+    if config.memory.vector_dim == 0 {
+        result
+            .errors
+            .push("memory.vector_dim must be greater than 0".to_string());
+        result.valid = false;
+    }
+
+    if !matches!(config.memory.fusion.as_str(), "weighted" | "rrf") {
+        result.errors.push(format!(
+            "memory.fusion must be \"weighted\" or \"rrf\", got \"{}\"",
+            config.memory.fusion
+        ));
+        result.valid = false;
+    }
+
+    if !(0.0..=1.0).contains(&config.usage.soft_limit_ratio) {
+        result
+            .errors
+            .push("usage.soft_limit_ratio must be between 0 and 1".to_string());
+        result.valid = false;
+    }
+
+    for (provider, budget) in &config.usage.budgets {
+        let limits = [budget.daily_usd, budget.monthly_usd];
+        if limits.iter().flatten().any(|limit| *limit < 0.0) {
+            result
+                .errors
+                .push(format!("usage.budgets.{} must not be negative", provider));
+            result.valid = false;
+        }
+    }
+
+    if config.scheduler.calls_per_minute == 0 {
+        result
+            .errors
+            .push("scheduler.calls_per_minute must be greater than 0".to_string());
+        result.valid = false;
+    }
+
+    let caps = [
+        ("local_concurrency", config.scheduler.local_concurrency),
+        ("remote_concurrency", config.scheduler.remote_concurrency),
+    ];
+    let overrides = config
+        .scheduler
+        .concurrency
+        .iter()
+        .map(|(provider, cap)| (provider.as_str(), *cap));
+    for (name, cap) in caps.into_iter().chain(overrides) {
+        if cap == 0 {
+            result.errors.push(format!(
+                "scheduler concurrency for {} must be greater than 0",
+                name
+            ));
+            result.valid = false;
+        }
+    }
+
+    if config.discovery.enabled && config.discovery.timeout_ms == 0 {
+        result
+            .errors
+            .push("discovery.timeout_ms must be greater than 0".to_string());
+        result.valid = false;
+    }
+
+    if crate::ai::providers::FixtureMode::from_str(&config.fixtures.mode).is_none() {
+        result.errors.push(format!(
+            "fixtures.mode must be \"off\", \"record\" or \"replay\", got \"{}\"",
+            config.fixtures.mode
+        ));
+        result.valid = false;
+    }
+
+    if crate::ai::providers::FixtureMatch::from_str(&config.fixtures.matching).is_none() {
+        result.errors.push(format!(
+            "fixtures.matching must be \"strict\" or \"lenient\", got \"{}\"",
+            config.fixtures.matching
+        ));
+        result.valid = false;
+    }
+
     // Suggestions
     if config.memory.embedding_provider.is_none() {
         result
@@ -557,6 +967,19 @@ mod tests {
         assert_eq!(config.autonomy.level, "supervised");
     }
 
+    #[test]
+    fn test_provider_chain() {
+        let mut core = CoreConfig::default();
+        core.default_provider = Some("openai".to_string());
+        core.default_model = Some("gpt-4o-mini".to_string());
+        core.fallback_providers = vec![ProviderEntry::new("gemini"), ProviderEntry::new("ollama")];
+
+        let chain = core.provider_chain();
+        let names: Vec<&str> = chain.iter().map(|e| e.provider.as_str()).collect();
+        assert_eq!(names, vec!["openai", "gemini", "ollama"]);
+        assert_eq!(chain[0].model.as_deref(), Some("gpt-4o-mini"));
+    }
+
     #[test]
     fn test_env_override() {
         std::env::set_var("OS_GHOST_AUTONOMY_LEVEL", "full");
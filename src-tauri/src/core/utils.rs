@@ -8,6 +8,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
 pub const DEFAULT_OLLAMA_VISION_MODEL: &str = "llama3.2-vision";
 pub const DEFAULT_OLLAMA_TEXT_MODEL: &str = "llama3.2";
+pub const DEFAULT_OLLAMA_EMBEDDING_MODEL: &str = "nomic-embed-text";
 
 /// Thread-safe runtime configuration store
 /// Used instead of env::set_var which is not thread-safe
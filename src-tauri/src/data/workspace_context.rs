@@ -2,11 +2,14 @@
 //!
 //! Supports loading and injecting context from workspace files:
 //! - TOOLS.md: Tool notes and policies injected into system prompt
-//! - AGENTS.md: Workspace-level agent instructions
+//! - AGENTS.md: Workspace-level agent instructions, optionally starting with a
+//!   `+++` TOML front-matter block of user-defined agents (see `agents::declarative`)
 //! - BOOT.md: Tasks to execute on startup
+//! - prompts/: Overrides of the built-in prompt templates (see `ai::prompts`)
 //!
 //! These files allow users to customize agent behavior without code changes.
 
+use crate::ai::prompts::{self, PromptTemplate};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::sync::RwLock;
@@ -16,7 +19,7 @@ lazy_static::lazy_static! {
 }
 
 const TOOLS_MD: &str = "TOOLS.md";
-const AGENTS_MD: &str = "AGENTS.md";
+pub const AGENTS_MD: &str = "AGENTS.md";
 const BOOT_MD: &str = "BOOT.md";
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -40,12 +43,13 @@ impl WorkspaceContext {
             }
         }
 
-        // Load AGENTS.md
+        // Load AGENTS.md (agent declarations in its front matter aren't instructions)
         let agents_path = data_dir.join(AGENTS_MD);
         if agents_path.exists() {
             if let Ok(content) = std::fs::read_to_string(&agents_path) {
-                if !content.trim().is_empty() {
-                    context.agents_md = Some(content);
+                let (_, body) = split_front_matter(&content);
+                if !body.trim().is_empty() {
+                    context.agents_md = Some(body.to_string());
                 }
             }
         }
@@ -85,7 +89,29 @@ impl WorkspaceContext {
     }
 }
 
-fn get_data_dir() -> PathBuf {
+/// Split a `+++`-delimited TOML front-matter block from the rest of a file
+pub fn split_front_matter(content: &str) -> (Option<&str>, &str) {
+    let trimmed = content.trim_start();
+    let Some(rest) = trimmed.strip_prefix("+++") else {
+        return (None, content);
+    };
+    let Some(rest) = rest
+        .strip_prefix('\n')
+        .or_else(|| rest.strip_prefix("\r\n"))
+    else {
+        return (None, content);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim() == "+++" {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, content)
+}
+
+pub fn get_data_dir() -> PathBuf {
     dirs::data_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join("os-ghost")
@@ -97,6 +123,7 @@ pub fn init_workspace_context() {
     if let Ok(mut ctx) = WORKSPACE_CONTEXT.write() {
         *ctx = context;
     }
+    prompts::reload_prompts(&data_dir);
 }
 
 pub fn reload_workspace_context() {
@@ -167,3 +194,15 @@ pub fn get_agents_md_path() -> String {
 pub fn get_boot_md_path() -> String {
     get_data_dir().join(BOOT_MD).to_string_lossy().to_string()
 }
+
+#[tauri::command]
+pub fn get_prompts_dir_path() -> String {
+    prompts::prompts_dir(&get_data_dir())
+        .to_string_lossy()
+        .to_string()
+}
+
+#[tauri::command]
+pub fn get_prompt_templates() -> Vec<PromptTemplate> {
+    prompts::list_prompts()
+}
@@ -597,6 +597,11 @@ pub fn start_native_messaging_server(app: AppHandle) {
         // Register MCP server as managed state for orchestrator access
         app.manage(mcp_server.clone());
 
+        // Let the operator verify URL expectations against the live page
+        if let Some(operator) = app.try_state::<Arc<crate::agents::OperatorAgent>>() {
+            operator.set_browser_state(browser_state.clone());
+        }
+
         // Create shared MCP context for connection handlers
         let mcp_ctx = Arc::new(McpBridgeContext {
             mcp_server: mcp_server.clone(),
@@ -684,6 +689,24 @@ pub async fn read_current_page(mcp_server: &BrowserMcpServer) -> Option<serde_js
     }
 }
 
+/// Read the user's most visited sites from MCP resource (convenience function)
+pub async fn read_top_sites(mcp_server: &BrowserMcpServer) -> Option<serde_json::Value> {
+    use crate::mcp::ResourceRequest;
+
+    let request = ResourceRequest {
+        uri: "browser://top-sites".to_string(),
+        request_id: Uuid::new_v4().to_string(),
+        query: None,
+    };
+
+    let response = mcp_server.read_resource(request).await;
+    if response.success {
+        Some(response.content)
+    } else {
+        None
+    }
+}
+
 /// Read browsing history from MCP resource (convenience function)
 pub async fn read_browsing_history(
     mcp_server: &BrowserMcpServer,
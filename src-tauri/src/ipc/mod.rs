@@ -3,6 +3,8 @@
 
 use crate::actions::action_preview;
 use crate::ai::ai_provider::SmartAiRouter;
+use crate::ai::ollama_client::{ModelDetails, ModelStatus, OllamaClient};
+use crate::ai::scheduler::{with_priority, Priority};
 use crate::capture::capture;
 use crate::core::game_state::{EffectMessage, EffectQueue};
 use crate::core::utils::current_timestamp_millis;
@@ -672,12 +674,23 @@ pub async fn generate_contextual_dialogue(
         .map_err(|e| e.to_string())
 }
 
+/// Instruction shared by one-off and conversational quick asks
+const QUICK_ASK_INSTRUCTIONS: &str =
+    "You are a fast desktop assistant. Answer succinctly (1-4 sentences).";
+
 /// Quick ask - minimal prompt/response for fast assistance
+/// When `request_id` is set, the answer is also streamed as `ai_stream_delta` events.
+/// When `conversation_id` is set, the question and answer are appended to that
+/// conversation and earlier turns are sent along (summarised once they outgrow the
+/// model's context window).
 #[tauri::command]
 pub async fn quick_ask(
     prompt: String,
     include_context: Option<bool>,
+    request_id: Option<String>,
+    conversation_id: Option<String>,
     session: State<'_, Arc<crate::memory::SessionMemory>>,
+    conversations: State<'_, Arc<crate::memory::ConversationStore>>,
     ai_router: State<'_, Arc<SmartAiRouter>>,
 ) -> Result<String, String> {
     let trimmed = prompt.trim();
@@ -686,7 +699,7 @@ pub async fn quick_ask(
     }
 
     let include_context = include_context.unwrap_or(true);
-    let full_prompt = if include_context {
+    let question = if include_context {
         let state = session.load().unwrap_or_default();
         let redacted_url = crate::config::privacy::redact_with_settings(&state.current_url);
         let redacted_title = crate::config::privacy::redact_with_settings(&state.current_title);
@@ -700,22 +713,186 @@ pub async fn quick_ask(
             .join("\n");
 
         format!(
-            "You are a fast desktop assistant. Answer succinctly (1-4 sentences).\n\n\
-            User question: {}\n\n\
+            "{}\n\n\
             Context (if relevant):\n\
             - Current URL: {}\n- Page title: {}\n\n\
             Recent Activity:\n{}",
             trimmed, redacted_url, redacted_title, timeline_str
         )
     } else {
-        format!(
-            "You are a fast desktop assistant. Answer succinctly (1-4 sentences).\n\nUser question: {}",
-            trimmed
-        )
+        trimmed.to_string()
     };
 
-    ai_router
-        .generate_text(&full_prompt)
+    // The user is waiting: schedule ahead of agents and background observers
+    with_priority(Priority::Interactive, async {
+        if let Some(conversation_id) = conversation_id {
+            return continue_conversation(
+                &conversation_id,
+                trimmed,
+                question,
+                request_id.as_deref(),
+                &conversations,
+                &ai_router,
+            )
+            .await;
+        }
+
+        let full_prompt = format!("{}\n\nUser question: {}", QUICK_ASK_INSTRUCTIONS, question);
+        let result = match request_id {
+            Some(request_id) => {
+                ai_router
+                    .generate_text_streamed(&request_id, &full_prompt)
+                    .await
+            }
+            None => ai_router.generate_text(&full_prompt).await,
+        };
+        result.map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// Append a question to a stored conversation and answer it with the full history
+///
+/// Only the user's own text is stored; `question` (the text plus any page
+/// context) is what the model sees for this turn.
+async fn continue_conversation(
+    conversation_id: &str,
+    text: &str,
+    question: String,
+    request_id: Option<&str>,
+    conversations: &crate::memory::ConversationStore,
+    ai_router: &Arc<SmartAiRouter>,
+) -> Result<String, String> {
+    let mut conversation = conversations
+        .get(conversation_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Conversation not found: {}", conversation_id))?;
+    conversation.push(crate::ai::providers::ChatMessage::user(text));
+
+    let context_window = ai_router.context_window();
+    // Truncation below still keeps the request in bounds if summarising fails
+    if let Err(e) =
+        crate::memory::summarize_conversation(&mut conversation, ai_router, context_window).await
+    {
+        tracing::warn!("{}", e);
+    }
+
+    let mut messages = conversation.context_messages(context_window);
+    if let Some(last) = messages.last_mut() {
+        last.content = question;
+    }
+
+    let answer = match request_id {
+        Some(request_id) => ai_router.chat_streamed(request_id, &messages).await,
+        None => ai_router.chat(&messages).await,
+    }
+    .map_err(|e| e.to_string())?;
+
+    conversation.push(crate::ai::providers::ChatMessage::assistant(answer.clone()));
+    conversations.save(&conversation).map_err(|e| e.to_string())?;
+    Ok(answer)
+}
+
+/// Start a conversation for multi-turn quick asks
+#[tauri::command]
+pub fn create_conversation(
+    system_prompt: Option<String>,
+    conversations: State<'_, Arc<crate::memory::ConversationStore>>,
+) -> Result<crate::memory::Conversation, String> {
+    conversations
+        .create(Some(system_prompt.unwrap_or_else(|| QUICK_ASK_INSTRUCTIONS.to_string())))
+        .map_err(|e| e.to_string())
+}
+
+/// Get a conversation with its messages and summary
+#[tauri::command]
+pub fn get_conversation(
+    conversation_id: String,
+    conversations: State<'_, Arc<crate::memory::ConversationStore>>,
+) -> Result<Option<crate::memory::Conversation>, String> {
+    conversations.get(&conversation_id).map_err(|e| e.to_string())
+}
+
+/// List conversations, most recent first
+#[tauri::command]
+pub fn list_conversations(
+    conversations: State<'_, Arc<crate::memory::ConversationStore>>,
+) -> Result<Vec<crate::memory::ConversationInfo>, String> {
+    conversations.list().map_err(|e| e.to_string())
+}
+
+/// Delete a conversation
+#[tauri::command]
+pub fn delete_conversation(
+    conversation_id: String,
+    conversations: State<'_, Arc<crate::memory::ConversationStore>>,
+) -> Result<(), String> {
+    conversations.delete(&conversation_id).map_err(|e| e.to_string())
+}
+
+/// Transcript store of the orchestrator
+fn transcript_store(
+    orchestrator: &crate::agents::AgentOrchestrator,
+) -> Result<&Arc<crate::agents::TranscriptStore>, String> {
+    orchestrator
+        .transcripts()
+        .ok_or_else(|| "Transcripts are not enabled".to_string())
+}
+
+/// List saved agent invocations, most recent first
+#[tauri::command]
+pub fn list_transcripts(
+    orchestrator: State<'_, Arc<crate::agents::AgentOrchestrator>>,
+) -> Result<Vec<crate::agents::TranscriptInfo>, String> {
+    transcript_store(&orchestrator)?
+        .list()
+        .map_err(|e| e.to_string())
+}
+
+/// Get a saved invocation with its events, tool calls and LLM exchanges
+#[tauri::command]
+pub fn get_transcript(
+    invocation_id: String,
+    orchestrator: State<'_, Arc<crate::agents::AgentOrchestrator>>,
+) -> Result<Option<crate::agents::Transcript>, String> {
+    transcript_store(&orchestrator)?
+        .get(&invocation_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Export a saved invocation as "json" or as a replay "cassette" (JSONL)
+#[tauri::command]
+pub fn export_transcript(
+    invocation_id: String,
+    format: Option<String>,
+    orchestrator: State<'_, Arc<crate::agents::AgentOrchestrator>>,
+) -> Result<String, String> {
+    let format: crate::agents::transcript::ExportFormat =
+        format.as_deref().unwrap_or("json").parse()?;
+    transcript_store(&orchestrator)?
+        .export(&invocation_id, format)
+        .map_err(|e| e.to_string())
+}
+
+/// Delete a saved invocation
+#[tauri::command]
+pub fn delete_transcript(
+    invocation_id: String,
+    orchestrator: State<'_, Arc<crate::agents::AgentOrchestrator>>,
+) -> Result<(), String> {
+    transcript_store(&orchestrator)?
+        .delete(&invocation_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Re-run a saved invocation against its recorded LLM outputs
+#[tauri::command]
+pub async fn replay_transcript(
+    invocation_id: String,
+    orchestrator: State<'_, Arc<crate::agents::AgentOrchestrator>>,
+) -> Result<crate::agents::Transcript, String> {
+    orchestrator
+        .replay_transcript(&invocation_id)
         .await
         .map_err(|e| e.to_string())
 }
@@ -1126,6 +1303,7 @@ pub fn reset_ollama_config() -> Result<OllamaConfig, String> {
 }
 
 /// Check if Ollama is running and return status
+/// Includes which configured models are missing so the UI can offer to pull them
 #[tauri::command]
 pub async fn get_ollama_status(
     ai_router: State<'_, Arc<SmartAiRouter>>,
@@ -1137,13 +1315,115 @@ pub async fn get_ollama_status(
     let has_gemini = ai_router.has_gemini();
     let active = ai_router.active_provider().to_string();
 
+    let models = if available {
+        configured_ollama_client()
+            .check_models()
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to check Ollama models: {}", e);
+                Vec::new()
+            })
+    } else {
+        Vec::new()
+    };
+    let mut missing_models: Vec<String> = Vec::new();
+    for status in models.iter().filter(|m| !m.installed) {
+        if !missing_models.contains(&status.model) {
+            missing_models.push(status.model.clone());
+        }
+    }
+
     Ok(serde_json::json!({
         "ollama_available": available,
         "gemini_configured": has_gemini,
-        "active_provider": active
+        "active_provider": active,
+        "models": models,
+        "missing_models": missing_models,
+        "can_pull": available && !missing_models.is_empty()
     }))
 }
 
+/// Ollama client for the configured text, vision and (if used) embedding models
+fn configured_ollama_client() -> OllamaClient {
+    let memory = crate::config::toml_config::load_toml_config().memory;
+    let client = OllamaClient::new();
+    match memory.embedding_provider.as_deref() {
+        Some("ollama") => client.with_embedding_model(
+            memory
+                .embedding_model
+                .as_deref()
+                .unwrap_or(crate::core::utils::DEFAULT_OLLAMA_EMBEDDING_MODEL),
+        ),
+        _ => client,
+    }
+}
+
+fn validate_model_name(model: &str) -> Result<&str, String> {
+    let model = model.trim();
+    if model.is_empty() {
+        return Err("Model name cannot be empty".to_string());
+    }
+    Ok(model)
+}
+
+/// Download an Ollama model, emitting `ollama_pull_progress` events
+#[tauri::command]
+pub async fn pull_ollama_model(
+    model: String,
+    ai_router: State<'_, Arc<SmartAiRouter>>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let model = validate_model_name(&model)?;
+    OllamaClient::new()
+        .pull_model(model, |progress| {
+            let _ = app_handle.emit(
+                "ollama_pull_progress",
+                serde_json::json!({
+                    "model": model,
+                    "status": progress.status,
+                    "digest": progress.digest,
+                    "total": progress.total,
+                    "completed": progress.completed,
+                }),
+            );
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // A newly installed model may make Ollama usable for the router
+    ai_router.refresh_ollama_status().await;
+    Ok(())
+}
+
+/// Delete an installed Ollama model
+#[tauri::command]
+pub async fn delete_ollama_model(model: String) -> Result<(), String> {
+    let model = validate_model_name(&model)?;
+    OllamaClient::new()
+        .delete_model(model)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Show parameters, context length and capabilities of an Ollama model
+#[tauri::command]
+pub async fn show_ollama_model(model: String) -> Result<ModelDetails, String> {
+    let model = validate_model_name(&model)?;
+    OllamaClient::new()
+        .show_model(model)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Check the configured models against what Ollama has installed
+#[tauri::command]
+pub async fn check_ollama_models() -> Result<Vec<ModelStatus>, String> {
+    configured_ollama_client()
+        .check_models()
+        .await
+        .map_err(|e| e.to_string())
+}
+
 // ============================================================================
 // Shared Helper Functions & Constants
 // ============================================================================
@@ -1252,6 +1532,31 @@ pub async fn process_agent_cycle(
     Ok(result)
 }
 
+/// Work toward a goal with native tool calling over the browser MCP tools
+#[tauri::command]
+pub async fn run_tool_task(
+    goal: String,
+    max_steps: Option<usize>,
+    orchestrator: State<'_, Arc<crate::agents::AgentOrchestrator>>,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::agents::orchestrator::ToolLoopResult, String> {
+    let goal = goal.trim();
+    if goal.is_empty() {
+        return Err("Goal cannot be empty".to_string());
+    }
+
+    use tauri::Manager;
+    let mcp_server = app_handle
+        .try_state::<Arc<crate::mcp::BrowserMcpServer>>()
+        .ok_or_else(|| "Browser MCP server not available".to_string())?;
+
+    let context = crate::agents::traits::AgentContext::default();
+    orchestrator
+        .run_tool_loop(&context, &mcp_server, goal, max_steps)
+        .await
+        .map_err(|e| format!("Tool task failed: {}", e))
+}
+
 /// Trigger background analysis (Parallel Workflow)
 #[tauri::command]
 pub async fn start_background_checks(
@@ -1300,6 +1605,56 @@ pub async fn start_background_checks(
     Ok(format!("Completed {} background checks", results.len()))
 }
 
+/// Plan the current puzzle and execute its sub-goals as a dependency graph
+#[tauri::command]
+pub async fn execute_puzzle_plan(
+    context: PageContext,
+    orchestrator: State<'_, Arc<crate::agents::AgentOrchestrator>>,
+    puzzles: State<'_, std::sync::RwLock<Vec<Puzzle>>>,
+    app_handle: tauri::AppHandle,
+) -> Result<crate::workflow::PlanExecution, String> {
+    let privacy = crate::config::privacy::PrivacySettings::load();
+    if privacy.read_only_mode {
+        return Err("Read-only mode enabled".to_string());
+    }
+
+    use tauri::Manager;
+    let mcp_server = app_handle
+        .try_state::<Arc<crate::mcp::BrowserMcpServer>>()
+        .ok_or_else(|| "Browser MCP server not available".to_string())?;
+    let target_url_pattern = {
+        let puzzles = puzzles.read().map_err(|e| format!("Lock error: {}", e))?;
+        let puzzle = puzzles
+            .iter()
+            .find(|p| p.id == context.puzzle_id)
+            .ok_or_else(|| format!("Puzzle {} not found", context.puzzle_id))?;
+        puzzle.target_url_pattern.clone()
+    };
+
+    let agent_context = crate::agents::traits::AgentContext {
+        current_url: context.url,
+        current_title: context.title,
+        page_content: context.content,
+        puzzle_id: context.puzzle_id,
+        puzzle_clue: context.puzzle_clue,
+        target_pattern: target_url_pattern,
+        hints: context.hints,
+        hints_revealed: context.hints_revealed,
+        proximity: 0.0,
+        ghost_mood: "analytical".to_string(),
+        metadata: std::collections::HashMap::new(),
+        planning: Default::default(),
+        last_reflection: None,
+        reflection_iterations: 0,
+        previous_outputs: Vec::new(),
+    };
+
+    orchestrator
+        .execute_plan(&agent_context, &mcp_server)
+        .await
+        .map_err(|e| format!("Plan execution failed: {}", e))
+}
+
 /// Autonomous mode progress event payload
 #[derive(Clone, Serialize)]
 pub struct AutonomousProgress {
@@ -1622,6 +1977,12 @@ pub async fn get_model_capabilities(
             }
             (vision_available, false, 8192) // Most Ollama models have smaller context
         }
+        crate::ai::ai_provider::ProviderType::OpenAI
+        | crate::ai::ai_provider::ProviderType::Anthropic
+        | crate::ai::ai_provider::ProviderType::Custom => match ai_router.active_provider_info() {
+            Some(info) => (info.supports_vision, true, info.context_window),
+            None => (false, false, 0),
+        },
         crate::ai::ai_provider::ProviderType::None => {
             warnings.push(
                 "No AI provider available. Configure Gemini API key or start Ollama.".to_string(),
@@ -1663,33 +2024,51 @@ pub struct TokenUsage {
     pub gemini_calls: u64,
     /// Ollama API calls this session
     pub ollama_calls: u64,
-    /// Estimated Gemini tokens (rough: 1 call ≈ 500 tokens avg)
+    /// Gemini tokens today (estimated at ~500 tokens per call until usage is recorded)
     pub estimated_gemini_tokens: u64,
-    /// Estimated cost in USD (Gemini 2.0 Flash: ~$0.075/1M input, $0.30/1M output)
+    /// Spend today across providers in USD (estimated until usage is recorded)
     pub estimated_cost_usd: f64,
+    /// Calls this session keyed by provider kind (gemini, ollama, openai, ...)
+    pub calls_by_provider: std::collections::HashMap<String, u64>,
+    /// Recorded token usage, spend and budget state per provider
+    pub providers: Vec<crate::ai::usage::ProviderUsage>,
 }
 
-/// Get token usage for cost visibility (P2)
-#[tauri::command]
-pub fn get_token_usage(ai_router: State<'_, Arc<SmartAiRouter>>) -> TokenUsage {
+/// Build token usage from recorded totals, falling back to call-count estimates
+fn current_token_usage(ai_router: &SmartAiRouter) -> TokenUsage {
     let (gemini_calls, ollama_calls) = ai_router.get_call_counts();
+    let providers = crate::ai::usage::usage_summary();
 
-    // Rough estimate: average 500 tokens per call (250 input + 250 output)
-    let estimated_gemini_tokens = gemini_calls * 500;
-
-    // Gemini 2.0 Flash pricing (as of 2024):
-    // Input: $0.075 per 1M tokens, Output: $0.30 per 1M tokens
-    // Blended average: ~$0.19 per 1M tokens
-    let estimated_cost_usd = (estimated_gemini_tokens as f64 / 1_000_000.0) * 0.19;
+    let (estimated_gemini_tokens, estimated_cost_usd) = if providers.is_empty() {
+        // Rough estimate: average 500 tokens per call (250 input + 250 output)
+        // at a blended Gemini 2.0 Flash price of ~$0.19 per 1M tokens
+        let tokens = gemini_calls * 500;
+        (tokens, (tokens as f64 / 1_000_000.0) * 0.19)
+    } else {
+        let gemini_tokens = providers
+            .iter()
+            .filter(|p| p.provider == "gemini")
+            .map(|p| p.today.input_tokens + p.today.output_tokens)
+            .sum();
+        (gemini_tokens, providers.iter().map(|p| p.today.cost_usd).sum())
+    };
 
     TokenUsage {
         gemini_calls,
         ollama_calls,
         estimated_gemini_tokens,
         estimated_cost_usd,
+        calls_by_provider: ai_router.provider_call_counts(),
+        providers,
     }
 }
 
+/// Get token usage for cost visibility (P2)
+#[tauri::command]
+pub fn get_token_usage(ai_router: State<'_, Arc<SmartAiRouter>>) -> TokenUsage {
+    current_token_usage(&ai_router)
+}
+
 /// Reset token usage counters (start of new session)
 #[tauri::command]
 pub fn reset_token_usage(ai_router: State<'_, Arc<SmartAiRouter>>) {
@@ -1713,6 +2092,8 @@ pub struct AgentPollStatus {
     pub rollback_status: crate::actions::rollback::RollbackStatus,
     /// Token usage stats
     pub token_usage: TokenUsage,
+    /// Circuit breaker state of every AI backend
+    pub circuit_breakers: Vec<crate::ai::circuit_breaker::BreakerSnapshot>,
     /// Timestamp of poll (for staleness detection)
     pub timestamp_ms: u64,
 }
@@ -1741,15 +2122,8 @@ pub fn poll_agent_status(ai_router: State<'_, Arc<SmartAiRouter>>) -> AgentPollS
         });
 
     // Get token usage
-    let (gemini_calls, ollama_calls) = ai_router.get_call_counts();
-    let estimated_gemini_tokens = gemini_calls * 500;
-    let estimated_cost_usd = (estimated_gemini_tokens as f64 / 1_000_000.0) * 0.19;
-    let token_usage = TokenUsage {
-        gemini_calls,
-        ollama_calls,
-        estimated_gemini_tokens,
-        estimated_cost_usd,
-    };
+    let token_usage = current_token_usage(&ai_router);
+    let circuit_breakers = ai_router.breaker_snapshots();
 
     // Get timestamp
     let timestamp_ms = std::time::SystemTime::now()
@@ -1762,6 +2136,7 @@ pub fn poll_agent_status(ai_router: State<'_, Arc<SmartAiRouter>>) -> AgentPollS
         action_preview,
         rollback_status,
         token_usage,
+        circuit_breakers,
         timestamp_ms,
     }
 }
@@ -1781,7 +2156,8 @@ use std::sync::Mutex;
 static WORKFLOW_STORE: Mutex<Option<WorkflowStore>> = Mutex::new(None);
 
 fn get_workflow_store() -> WorkflowStore {
-    WORKFLOW_STORE.lock()
+    WORKFLOW_STORE
+        .lock()
         .ok()
         .and_then(|guard| guard.clone())
         .unwrap_or_default()
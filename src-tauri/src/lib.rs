@@ -810,8 +810,45 @@ pub fn run() {
             // Create Ollama client (always available, will check server at runtime)
             let ollama_client = Arc::new(OllamaClient::new());
 
-            // Create SmartAiRouter with both providers
-            let ai_router = Arc::new(SmartAiRouter::new(gemini_client.clone(), ollama_client.clone()));
+            // Create SmartAiRouter over the configured provider chain
+            // ([core] default_provider/default_model + fallback_providers)
+            let toml_config = config::toml_config::load_toml_config();
+            // Register local OpenAI-compatible servers so the chain can name them
+            tauri::async_runtime::block_on(ai::providers::discovery::refresh(
+                &toml_config.discovery,
+            ));
+            let ai_router = Arc::new(
+                SmartAiRouter::from_config(
+                    &toml_config.core,
+                    gemini_client.clone(),
+                    ollama_client.clone(),
+                )
+                .with_fixtures(&toml_config.fixtures)
+                .with_scheduler(&toml_config.scheduler),
+            );
+
+            // User-defined agents from AGENTS.md / agents.toml (reloaded on change)
+            crate::agents::declarative::init_user_agents(
+                ai_router.clone(),
+                toml_config.core.clone(),
+            );
+
+            // Guardrail policy rules from guardrails.toml (reloaded on change)
+            crate::agents::policy::init_guardrail_policies();
+
+            // Offline threat-intelligence blocklists (reloaded on change)
+            crate::security::blocklist::init_blocklists();
+
+            // Hybrid memory (SQLite + FTS5 + vectors) and embedding backfill
+            if let Err(e) = memory::hybrid::init_hybrid_memory(&toml_config.memory) {
+                tracing::warn!("Failed to initialize hybrid memory: {}", e);
+            } else if let Some(embedder) =
+                memory::embeddings::Embedder::from_config(&toml_config.memory)
+            {
+                let embedder = Arc::new(embedder);
+                memory::embeddings::set_embedder(embedder.clone());
+                memory::embeddings::start_backfill_job(embedder);
+            }
 
             // Initialize router (check Ollama availability)
             let router_init = ai_router.clone();
@@ -837,6 +874,8 @@ pub fn run() {
                 tracing::error!("Failed to create memory store: {}", e);
                 e
             })?;
+            crate::ai::usage::init_usage_ledger(store.clone(), toml_config.usage.clone());
+            crate::ai::vision_cache::init_vision_cache(store.clone());
 
             let shared_ltm = Arc::new(Mutex::new(LongTermMemory::new(store.clone())));
             let shared_session = Arc::new(Mutex::new(memory::SessionMemory::new(store.clone())));
@@ -846,6 +885,8 @@ pub fn run() {
             // Create a separate Arc for SessionMemory to be used directly by bridge
             let session_for_ipc = Arc::new(memory::SessionMemory::new(store.clone()));
             app.manage(session_for_ipc.clone());
+            app.manage(Arc::new(memory::ConversationStore::new(store.clone())));
+            let transcripts = Arc::new(crate::agents::TranscriptStore::new(store.clone()));
 
             // Register LongTermMemory as managed state for IPC commands (HITL feedback)
             let ltm_for_ipc = Arc::new(memory::LongTermMemory::new(store));
@@ -884,7 +925,7 @@ pub fn run() {
                 operator.clone(),
             ) {
                 Ok(orchestrator) => {
-                    app.manage(Arc::new(orchestrator));
+                    app.manage(Arc::new(orchestrator.with_transcripts(transcripts)));
                     tracing::info!("Agent Orchestrator initialized with shared memory");
                 }
                 Err(e) => tracing::error!("Failed to initialize orchestrator: {}", e),
@@ -923,15 +964,40 @@ pub fn run() {
             let monitor_session = shared_session.clone();
 
             tauri::async_runtime::spawn(async move {
-                crate::monitoring::monitor::start_monitor_loop(
-                    monitor_handle,
-                    monitor_router,
-                    monitor_ltm,
-                    monitor_session,
+                // Screen observations yield to user requests
+                ai::scheduler::with_priority(
+                    ai::scheduler::Priority::Background,
+                    crate::monitoring::monitor::start_monitor_loop(
+                        monitor_handle,
+                        monitor_router,
+                        monitor_ltm,
+                        monitor_session,
+                    ),
                 )
                 .await;
             });
 
+            // Forward streamed completion deltas to the frontend
+            let stream_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut deltas = ai::providers::streaming::subscribe_deltas();
+                loop {
+                    match deltas.recv().await {
+                        Ok(delta) => {
+                            if let Err(e) = stream_handle
+                                .emit(ai::providers::streaming::STREAM_DELTA_EVENT, delta)
+                            {
+                                tracing::error!("Failed to emit stream delta: {}", e);
+                            }
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::debug!("Stream delta forwarder skipped {} deltas", skipped);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
             // Start Hint Checker Loop (Background Task)
             let hint_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -976,6 +1042,7 @@ pub fn run() {
 
                 loop {
                     interval.tick().await;
+                    status_router.probe_health().await;
                     let now = crate::core::utils::current_timestamp();
                     if let Some(store) = crate::config::system_status::get_system_status_store() {
                         if let Ok(mut guard) = store.write() {
@@ -1023,6 +1090,7 @@ pub fn run() {
             ipc::puzzles::generate_puzzle_from_history,
             ipc::process_agent_cycle,
             ipc::start_background_checks,
+            ipc::execute_puzzle_plan,
             ipc::enable_autonomous_mode,
             ipc::trigger_browser_effect,
             ipc::request_extension_ping,
@@ -1051,7 +1119,20 @@ pub fn run() {
             ipc::generate_adaptive_puzzle,
             ipc::generate_contextual_dialogue,
             ipc::quick_ask,
+            ipc::create_conversation,
+            ipc::get_conversation,
+            ipc::list_conversations,
+            ipc::delete_conversation,
+            ipc::list_transcripts,
+            ipc::get_transcript,
+            ipc::export_transcript,
+            ipc::delete_transcript,
+            ipc::replay_transcript,
             ipc::request_assistance,
+            ipc::run_tool_task,
+            ai::providers::cancel_ai_stream,
+            ai::providers::get_available_providers,
+            ai::providers::discover_local_providers,
             // Integrations: calendar + notes + email
             integrations::integrations::get_calendar_settings,
             integrations::integrations::update_calendar_settings,
@@ -1124,6 +1205,10 @@ pub fn run() {
             ipc::set_ollama_config,
             ipc::reset_ollama_config,
             ipc::get_ollama_status,
+            ipc::pull_ollama_model,
+            ipc::delete_ollama_model,
+            ipc::show_ollama_model,
+            ipc::check_ollama_models,
             // HITL Feedback commands (Chapter 13)
             ipc::submit_feedback,
             ipc::submit_escalation,
@@ -1214,6 +1299,13 @@ pub fn run() {
             data::workspace_context::get_tools_md_path,
             data::workspace_context::get_agents_md_path,
             data::workspace_context::get_boot_md_path,
+            data::workspace_context::get_prompts_dir_path,
+            data::workspace_context::get_prompt_templates,
+            agents::declarative::list_user_agents,
+            agents::declarative::reload_user_agents_cmd,
+            agents::policy::reload_guardrail_policies,
+            agents::policy::get_guardrail_decisions,
+            security::blocklist::reload_threat_blocklists,
             // TOML config validation (Moltis-inspired)
             config::toml_config::validate_toml_settings,
             // Identity commands (Moltis-inspired)
@@ -1262,6 +1354,8 @@ pub fn run() {
             memory::hybrid::memory_delete,
             memory::hybrid::memory_search,
             memory::hybrid::memory_stats,
+            memory::hybrid::memory_integrity_check,
+            memory::hybrid::memory_rebuild,
             // Observability commands (ZeroClaw-inspired)
             observability::get_current_metrics,
             observability::get_prometheus_metrics,
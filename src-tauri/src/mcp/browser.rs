@@ -13,6 +13,7 @@
 //! - browser.get_content - Get page content
 //! - browser.inject_effect - Apply visual effect
 //! - browser.highlight_text - Highlight specific text
+//! - security.check_url - Check a URL against offline blocklists and lookalikes
 
 use super::traits::*;
 use super::types::*;
@@ -522,6 +523,60 @@ impl McpTool for GetContentTool {
     }
 }
 
+/// Tool: Check a URL against the offline blocklists and lookalike detection
+pub struct CheckUrlTool;
+
+#[async_trait]
+impl McpTool for CheckUrlTool {
+    fn descriptor(&self) -> ToolDescriptor {
+        let mut props = HashMap::new();
+        props.insert(
+            "url".to_string(),
+            PropertySchema {
+                prop_type: "string".to_string(),
+                description: Some("The URL to check".to_string()),
+                default: None,
+                enum_values: None,
+            },
+        );
+
+        ToolDescriptor {
+            name: "security.check_url".to_string(),
+            description: "Check whether a URL is on a local threat blocklist or imitates a known site (works offline)"
+                .to_string(),
+            input_schema: JsonSchema {
+                schema_type: "object".to_string(),
+                properties: Some(props),
+                required: Some(vec!["url".to_string()]),
+                description: None,
+            },
+            is_side_effect: false,
+            category: "security".to_string(),
+        }
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<serde_json::Value, McpError> {
+        let url = arguments
+            .get("url")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::InvalidArguments("Missing 'url' parameter".to_string()))?;
+        let host = url::Url::parse(url)
+            .map_err(|e| McpError::InvalidArguments(format!("Invalid URL: {}", e)))?
+            .host_str()
+            .map(str::to_string);
+
+        let hits = crate::security::blocklist::check_url(url);
+        let lookalike = host.and_then(|h| crate::agents::lookalike::find_lookalike(&h, &[]));
+
+        Ok(json!({
+            "url": url,
+            "blocklisted": !hits.is_empty(),
+            "blocklist_hits": hits,
+            "lookalike": lookalike
+        }))
+    }
+}
+
 // ============================================================================
 // Browser Prompts
 // ============================================================================
@@ -614,6 +669,7 @@ impl BrowserMcpServer {
             Box::new(InjectEffectTool::new(effect_sender.clone())),
             Box::new(HighlightTextTool::new(effect_sender.clone())),
             Box::new(GetContentTool::new(effect_sender.clone(), state.clone())),
+            Box::new(CheckUrlTool),
         ];
 
         // Add visual tools if vision is available
@@ -7,18 +7,65 @@
 //! This ensures durable memories survive compaction.
 
 use crate::ai::ai_provider::SmartAiRouter;
+use crate::ai::prompts;
+use crate::ai::providers::chat;
 use crate::data::workspace_context;
+use crate::memory::conversation::Conversation;
 use crate::memory::session::SessionMemory;
 use crate::memory::LongTermMemory;
 use std::sync::Arc;
 
-/// System prompt for the silent memory turn
-const SILENT_MEMORY_PROMPT: &str = "You are a memory consolidation agent. Your task is to review the conversation history and save important information to long-term memory.\n\nAnalyze the conversation and extract:\n1. User preferences and working style\n2. Key decisions and their reasoning\n3. Project context, architecture choices, and conventions\n4. Important facts, names, dates, and relationships\n5. Technical setup details (tools, languages, frameworks)\n\nWrite a concise summary (2-4 sentences) of the most important information that should be remembered for future sessions.\n\nFormat your response as markdown that can be saved to a memory file.\nIf there is nothing important to remember, respond with just NO_MEMORY.";
+/// Words the conversation summary may grow to
+const SUMMARY_MAX_WORDS: i64 = 300;
 
-/// Check if session needs compaction based on message count
-pub fn should_compact(message_count: usize, context_window: usize) -> bool {
+/// Check if a prompt of `used_tokens` needs compaction to fit `context_window`
+pub fn should_compact(used_tokens: usize, context_window: usize) -> bool {
     // Trigger compaction at 95% of context window
-    message_count >= (context_window as f64 * 0.95) as usize
+    used_tokens >= (context_window as f64 * 0.95) as usize
+}
+
+/// Fold older conversation messages into its summary once it nears the context window
+/// Returns whether the conversation was compacted
+pub async fn summarize_conversation(
+    conversation: &mut Conversation,
+    router: &Arc<SmartAiRouter>,
+    context_window: usize,
+) -> Result<bool, String> {
+    if !conversation.needs_compaction(context_window) {
+        return Ok(false);
+    }
+
+    tracing::info!(
+        "Compacting conversation {} ({} messages)",
+        conversation.id,
+        conversation.messages.len()
+    );
+
+    // Summarise in chunks that fit the summariser's window, carrying the
+    // summary forward so every message being folded is covered
+    let mut summary = conversation.summary.clone();
+    for chunk in chat::chunk_to_window(conversation.compactable_messages(), context_window / 2) {
+        let prompt = prompts::render(
+            "compaction.summarize",
+            &[
+                (
+                    "previous_summary",
+                    summary.as_deref().unwrap_or("(none)").into(),
+                ),
+                ("transcript", chat::transcript(chunk).into()),
+                ("max_words", SUMMARY_MAX_WORDS.into()),
+            ],
+        )?;
+
+        let updated = router
+            .generate_text_light(&prompt)
+            .await
+            .map_err(|e| format!("Conversation summary failed: {}", e))?;
+        summary = Some(updated.trim().to_string());
+    }
+
+    conversation.compact(summary.unwrap_or_default());
+    Ok(true)
 }
 
 /// Run silent memory turn before compaction
@@ -44,11 +91,10 @@ pub async fn run_silent_memory_turn(
     );
 
     // Call LLM with silent memory prompt
-    let prompt = format!(
-        "{}\n\nRecent session context:\n{}\n\nWhat important information should be saved to memory?",
-        SILENT_MEMORY_PROMPT,
-        recent_summary
-    );
+    let prompt = prompts::render(
+        "compaction.silent_memory",
+        &[("session_context", recent_summary.into())],
+    )?;
 
     match router.generate_text(&prompt).await {
         Ok(response) => {
@@ -0,0 +1,241 @@
+//! Persisted multi-turn conversations
+//! Stores chat history per conversation ID and keeps it within the model's
+//! context window by folding older messages into a running summary
+
+use super::compaction::should_compact;
+use super::store::MemoryStore;
+use crate::ai::providers::chat::{self, ChatMessage, ChatRole};
+use crate::core::utils::current_timestamp;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+const CONVERSATION_TREE: &str = "conversations";
+
+/// Tokens left free for the model's reply
+pub const REPLY_RESERVE_TOKENS: usize = 1024;
+/// Most recent messages kept verbatim when compacting
+pub const KEEP_RECENT_MESSAGES: usize = 6;
+/// Characters of the first user message's first line used as the title
+const TITLE_CHARS: usize = 60;
+
+/// A chat conversation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: String,
+    pub title: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    /// System prompt sent ahead of every request
+    #[serde(default)]
+    pub system: Option<String>,
+    /// Messages not yet folded into `summary`, oldest first
+    #[serde(default)]
+    pub messages: Vec<ChatMessage>,
+    /// Running summary of compacted messages
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// How many messages the summary covers
+    #[serde(default)]
+    pub summarized_count: usize,
+}
+
+/// Listing entry for a conversation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationInfo {
+    pub id: String,
+    pub title: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub message_count: usize,
+}
+
+impl Conversation {
+    pub fn new(system: Option<String>) -> Self {
+        let now = current_timestamp();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: String::new(),
+            created_at: now,
+            updated_at: now,
+            system: system.filter(|s| !s.trim().is_empty()),
+            messages: Vec::new(),
+            summary: None,
+            summarized_count: 0,
+        }
+    }
+
+    /// Append a message, titling the conversation after the first user message
+    pub fn push(&mut self, message: ChatMessage) {
+        if self.title.is_empty() && message.role == ChatRole::User {
+            let first_line = message.content.lines().next().unwrap_or_default();
+            self.title = first_line
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .chars()
+                .take(TITLE_CHARS)
+                .collect();
+        }
+        self.messages.push(message);
+        self.updated_at = current_timestamp();
+    }
+
+    /// Total messages, including those folded into the summary
+    pub fn message_count(&self) -> usize {
+        self.summarized_count + self.messages.len()
+    }
+
+    /// Everything the model should see: system prompt, summary, then messages
+    pub fn prompt_messages(&self) -> Vec<ChatMessage> {
+        let mut messages = Vec::with_capacity(self.messages.len() + 2);
+        if let Some(system) = &self.system {
+            messages.push(ChatMessage::system(system.clone()));
+        }
+        if let Some(summary) = &self.summary {
+            messages.push(ChatMessage::system(format!(
+                "Summary of the earlier conversation:\n{}",
+                summary
+            )));
+        }
+        messages.extend(self.messages.iter().cloned());
+        messages
+    }
+
+    /// Prompt messages truncated to fit a context window
+    pub fn context_messages(&self, context_window: usize) -> Vec<ChatMessage> {
+        chat::truncate_to_window(&self.prompt_messages(), prompt_budget(context_window))
+    }
+
+    /// Whether the conversation has outgrown the context window
+    pub fn needs_compaction(&self, context_window: usize) -> bool {
+        self.messages.len() > KEEP_RECENT_MESSAGES
+            && should_compact(
+                chat::estimate_tokens(&self.prompt_messages()),
+                prompt_budget(context_window),
+            )
+    }
+
+    /// Messages that the next compaction would fold into the summary
+    pub fn compactable_messages(&self) -> &[ChatMessage] {
+        let end = self.messages.len().saturating_sub(KEEP_RECENT_MESSAGES);
+        &self.messages[..end]
+    }
+
+    /// Replace the compactable messages with an updated summary
+    pub fn compact(&mut self, summary: String) {
+        let folded = self.compactable_messages().len();
+        self.messages.drain(..folded);
+        self.summarized_count += folded;
+        self.summary = Some(summary);
+        self.updated_at = current_timestamp();
+    }
+
+    pub fn info(&self) -> ConversationInfo {
+        ConversationInfo {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            message_count: self.message_count(),
+        }
+    }
+}
+
+/// Tokens available for the prompt once the reply is reserved
+fn prompt_budget(context_window: usize) -> usize {
+    context_window
+        .saturating_sub(REPLY_RESERVE_TOKENS)
+        .max(context_window / 2)
+}
+
+/// Conversation storage
+pub struct ConversationStore {
+    store: MemoryStore,
+}
+
+impl ConversationStore {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+
+    /// Start and persist a new conversation
+    pub fn create(&self, system: Option<String>) -> Result<Conversation> {
+        let conversation = Conversation::new(system);
+        self.save(&conversation)?;
+        Ok(conversation)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<Conversation>> {
+        self.store.get(CONVERSATION_TREE, id)
+    }
+
+    pub fn save(&self, conversation: &Conversation) -> Result<()> {
+        self.store
+            .set(CONVERSATION_TREE, &conversation.id, conversation)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        self.store.delete(CONVERSATION_TREE, id)
+    }
+
+    /// All conversations, most recently updated first
+    pub fn list(&self) -> Result<Vec<ConversationInfo>> {
+        let mut conversations: Vec<ConversationInfo> = self
+            .store
+            .get_all::<Conversation>(CONVERSATION_TREE)?
+            .iter()
+            .map(Conversation::info)
+            .collect();
+        conversations.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(conversations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_store_round_trip() {
+        let dir = tempdir().unwrap();
+        let store = ConversationStore::new(MemoryStore::open(dir.path().join("test.db")).unwrap());
+
+        let mut conversation = store.create(Some("Be brief.".to_string())).unwrap();
+        conversation.push(ChatMessage::user("What   is this page about?"));
+        conversation.push(ChatMessage::assistant("A recipe."));
+        store.save(&conversation).unwrap();
+
+        let loaded = store.get(&conversation.id).unwrap().unwrap();
+        assert_eq!(loaded.title, "What is this page about?");
+        assert_eq!(loaded.messages.len(), 2);
+        assert_eq!(store.list().unwrap()[0].message_count, 2);
+
+        store.delete(&conversation.id).unwrap();
+        assert!(store.get(&conversation.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_compaction_folds_older_messages() {
+        let mut conversation = Conversation::new(None);
+        for i in 0..20 {
+            conversation.push(ChatMessage::user(format!(
+                "question {} {}",
+                i,
+                "x".repeat(400)
+            )));
+            conversation.push(ChatMessage::assistant("answer"));
+        }
+        assert!(conversation.needs_compaction(2048));
+        assert!(!conversation.needs_compaction(128_000));
+
+        conversation.compact("The user asked twenty questions.".to_string());
+        assert_eq!(conversation.messages.len(), KEEP_RECENT_MESSAGES);
+        assert_eq!(conversation.message_count(), 40);
+
+        let prompt = conversation.prompt_messages();
+        assert_eq!(prompt[0].role, ChatRole::System);
+        assert!(prompt[0].content.contains("twenty questions"));
+        assert!(!conversation.needs_compaction(2048));
+    }
+}
@@ -0,0 +1,251 @@
+//! Embedding generation for hybrid memory
+//!
+//! Turns memory entries into vectors through a `Provider` with embedding support
+//! (currently Ollama). A background job backfills rows stored without an embedding
+//! so vector search has something to search.
+
+use super::hybrid::with_hybrid_memory;
+use crate::ai::ollama_client::OllamaClient;
+use crate::ai::providers::Provider;
+use crate::config::toml_config::MemoryConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Texts sent to the provider per request batch
+const DEFAULT_BATCH_SIZE: usize = 16;
+/// Interval between backfill passes
+const BACKFILL_INTERVAL_SECS: u64 = 300;
+/// Upper bound of rows embedded per pass
+const BACKFILL_MAX_ROWS: usize = 512;
+
+lazy_static::lazy_static! {
+    static ref EMBEDDER: RwLock<Option<Arc<Embedder>>> = RwLock::new(None);
+}
+
+/// Register the embedder used for search queries
+pub fn set_embedder(embedder: Arc<Embedder>) {
+    if let Ok(mut e) = EMBEDDER.write() {
+        *e = Some(embedder);
+    }
+}
+
+/// The configured embedder, if any
+pub fn embedder() -> Option<Arc<Embedder>> {
+    EMBEDDER.read().ok().and_then(|e| e.clone())
+}
+
+/// Result of a backfill pass
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackfillReport {
+    pub embedded: usize,
+    /// Rows left unembedded this pass because the provider rejected them
+    #[serde(default)]
+    pub skipped: usize,
+    pub remaining: usize,
+}
+
+/// Produces embeddings of a fixed dimension
+pub struct Embedder {
+    provider: Arc<dyn Provider>,
+    vector_dim: usize,
+    batch_size: usize,
+}
+
+impl Embedder {
+    pub fn new(provider: Arc<dyn Provider>, vector_dim: usize) -> Self {
+        Self {
+            provider,
+            vector_dim,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Build the embedder configured in `[memory]`, if any
+    pub fn from_config(config: &MemoryConfig) -> Option<Self> {
+        let provider: Arc<dyn Provider> = match config.embedding_provider.as_deref()? {
+            "ollama" => {
+                let client = OllamaClient::new();
+                Arc::new(match config.embedding_model.as_deref() {
+                    Some(model) => client.with_embedding_model(model),
+                    None => client,
+                })
+            }
+            other => {
+                tracing::warn!("Embedding provider '{}' does not support embeddings", other);
+                return None;
+            }
+        };
+        Some(Self::new(provider, config.vector_dim))
+    }
+
+    /// Embed texts in batches, rejecting vectors of the wrong dimension
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            let vectors = self
+                .provider
+                .embed(batch)
+                .await
+                .map_err(|e| e.to_string())?;
+            if vectors.len() != batch.len() {
+                return Err(format!(
+                    "Provider returned {} embeddings for {} texts",
+                    vectors.len(),
+                    batch.len()
+                ));
+            }
+            for vector in &vectors {
+                if vector.len() != self.vector_dim {
+                    return Err(format!(
+                        "Embedding dimension mismatch: expected {}, got {} (check memory.vector_dim)",
+                        self.vector_dim,
+                        vector.len()
+                    ));
+                }
+            }
+            embeddings.extend(vectors);
+        }
+        Ok(embeddings)
+    }
+
+    /// Embed a single search query
+    pub async fn embed_query(&self, query: &str) -> Result<Vec<f32>, String> {
+        self.embed(&[query.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| "Provider returned no embedding".to_string())
+    }
+
+    /// Embed a batch of texts, falling back to one request per text if the
+    /// batch is rejected; texts the provider still rejects come back as `None`
+    ///
+    /// Fails only if no text at all could be embedded, so a misconfigured
+    /// provider surfaces as an error instead of a pass of skipped rows.
+    async fn embed_each(&self, texts: &[String]) -> Result<Vec<Option<Vec<f32>>>, String> {
+        let batch_error = match self.embed(texts).await {
+            Ok(vectors) => return Ok(vectors.into_iter().map(Some).collect()),
+            Err(e) => e,
+        };
+
+        let mut vectors = Vec::with_capacity(texts.len());
+        for text in texts {
+            vectors.push(self.embed_query(text).await.ok());
+        }
+        if vectors.iter().all(Option::is_none) {
+            return Err(batch_error);
+        }
+        Ok(vectors)
+    }
+
+    /// Embed entries stored without a vector, up to `max_rows`
+    ///
+    /// Rows the provider rejects are skipped for the rest of the pass; a
+    /// batch where nothing can be embedded stops it so a misconfigured model
+    /// doesn't spin.
+    pub async fn backfill(&self, max_rows: usize) -> Result<BackfillReport, String> {
+        let mut report = BackfillReport::default();
+        let mut skipped: HashSet<String> = HashSet::new();
+
+        while report.embedded < max_rows {
+            let limit = self.batch_size.min(max_rows - report.embedded);
+            let rows: Vec<(String, String)> =
+                with_hybrid_memory(|mem| mem.missing_embeddings(limit + skipped.len()))?
+                    .into_iter()
+                    .filter(|(key, _)| !skipped.contains(key))
+                    .take(limit)
+                    .collect();
+            if rows.is_empty() {
+                break;
+            }
+
+            let texts: Vec<String> = rows.iter().map(|(_, content)| content.clone()).collect();
+            let vectors = self.embed_each(&texts).await?;
+
+            with_hybrid_memory(|mem| {
+                for ((key, _), vector) in rows.iter().zip(&vectors) {
+                    let stored = match vector {
+                        Some(vector) => mem.set_embedding(key, vector),
+                        None => Err("provider rejected the entry".to_string()),
+                    };
+                    match stored {
+                        Ok(()) => report.embedded += 1,
+                        Err(e) => {
+                            tracing::warn!("Skipping embedding for memory '{}': {}", key, e);
+                            skipped.insert(key.clone());
+                        }
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        report.skipped = skipped.len();
+
+        report.remaining = with_hybrid_memory(|mem| {
+            mem.stats()
+                .map(|s| s.total_entries.saturating_sub(s.entries_with_embeddings))
+        })?;
+        Ok(report)
+    }
+}
+
+/// Periodically backfill missing embeddings in the background
+pub fn start_backfill_job(embedder: Arc<Embedder>) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(BACKFILL_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            match embedder.backfill(BACKFILL_MAX_ROWS).await {
+                Ok(report) if report.embedded > 0 => tracing::info!(
+                    "Embedded {} memory entries ({} remaining)",
+                    report.embedded,
+                    report.remaining
+                ),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Embedding backfill failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::providers::mock::MockProvider;
+
+    #[tokio::test]
+    async fn test_embed_batches_and_checks_dimension() {
+        let texts: Vec<String> = (0..5).map(|i| format!("text {}", i)).collect();
+
+        let embedder = Embedder::new(Arc::new(MockProvider::new("mock").with_embeddings(4)), 4)
+            .with_batch_size(2);
+        assert_eq!(embedder.embed(&texts).await.unwrap().len(), 5);
+
+        let mismatched = Embedder::new(Arc::new(MockProvider::new("mock").with_embeddings(3)), 4);
+        assert!(mismatched.embed(&texts).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_texts_are_skipped() {
+        let provider = MockProvider::new("mock")
+            .with_embeddings(2)
+            .rejecting("bad");
+        let embedder = Embedder::new(Arc::new(provider), 2);
+
+        let texts = vec!["good".to_string(), "bad".to_string(), "fine".to_string()];
+        let vectors = embedder.embed_each(&texts).await.unwrap();
+        assert!(vectors[0].is_some());
+        assert!(vectors[1].is_none());
+        assert!(vectors[2].is_some());
+
+        // Nothing embeddable means the provider itself is broken
+        let texts = vec!["bad".to_string(), "also bad".to_string()];
+        assert!(embedder.embed_each(&texts).await.is_err());
+    }
+}
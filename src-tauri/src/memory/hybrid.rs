@@ -1,16 +1,62 @@
 //! Hybrid Memory Search - SQLite + FTS5 + Vector
 //!
 //! Reference: ZeroClaw memory implementation
+//!
+//! Embeddings are kept in an in-memory index (unit-normalised, contiguous) loaded
+//! at startup, so vector search is a single linear scan without touching SQLite.
+//! Vector and BM25 results are fused by weighted score or reciprocal rank.
 
+use crate::config::toml_config::MemoryConfig;
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
+
+/// Rank constant for reciprocal-rank fusion
+const RRF_K: f32 = 60.0;
+/// Minimum candidates fetched from each side before fusion
+const MIN_CANDIDATES: usize = 20;
 
 pub struct HybridMemory {
     conn: Connection,
-    #[allow(dead_code)]
     vector_dim: usize,
+    index: RwLock<VectorIndex>,
+    fusion: Fusion,
+}
+
+/// How vector and keyword results are combined
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Fusion {
+    /// Weighted sum of cosine similarity and min-max normalised BM25
+    Weighted {
+        vector_weight: f32,
+        keyword_weight: f32,
+    },
+    /// Reciprocal-rank fusion, `1 / (k + rank)` summed over both lists
+    Rrf { k: f32 },
+}
+
+impl Default for Fusion {
+    fn default() -> Self {
+        Fusion::Weighted {
+            vector_weight: 0.7,
+            keyword_weight: 0.3,
+        }
+    }
+}
+
+impl Fusion {
+    pub fn from_config(config: &MemoryConfig) -> Self {
+        match config.fusion.as_str() {
+            "rrf" => Fusion::Rrf { k: RRF_K },
+            _ => Fusion::Weighted {
+                vector_weight: config.vector_weight as f32,
+                keyword_weight: config.keyword_weight as f32,
+            },
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,79 +73,240 @@ pub struct MemoryStats {
     pub entries_with_embeddings: usize,
 }
 
+/// Result of checking (or rebuilding) the memory database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    pub schema_version: i64,
+    pub total_entries: usize,
+    pub indexed_entries: usize,
+    pub problems: Vec<String>,
+}
+
+/// Latest schema version; bump together with a new `MIGRATIONS` entry
+const SCHEMA_VERSION: i64 = 2;
+
+/// Ordered schema migrations as (version, SQL batch)
+///
+/// v1 is the original schema (idempotent, so pre-versioning databases pass
+/// through it). v2 adds triggers keeping the external-content FTS table in sync
+/// and rebuilds it from whatever was stored before.
+const MIGRATIONS: &[(i64, &str)] = &[
+    (
+        1,
+        "CREATE TABLE IF NOT EXISTS memories (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            key TEXT NOT NULL UNIQUE,
+            content TEXT NOT NULL,
+            embedding BLOB,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE VIRTUAL TABLE IF NOT EXISTS memories_fts USING fts5(
+            key, content, content=memories, content_rowid=id
+        );
+        CREATE INDEX IF NOT EXISTS idx_memories_key ON memories(key);",
+    ),
+    (
+        2,
+        "CREATE TRIGGER IF NOT EXISTS memories_ai AFTER INSERT ON memories BEGIN
+            INSERT INTO memories_fts(rowid, key, content) VALUES (new.id, new.key, new.content);
+        END;
+        CREATE TRIGGER IF NOT EXISTS memories_ad AFTER DELETE ON memories BEGIN
+            INSERT INTO memories_fts(memories_fts, rowid, key, content)
+            VALUES ('delete', old.id, old.key, old.content);
+        END;
+        CREATE TRIGGER IF NOT EXISTS memories_au AFTER UPDATE OF key, content ON memories BEGIN
+            INSERT INTO memories_fts(memories_fts, rowid, key, content)
+            VALUES ('delete', old.id, old.key, old.content);
+            INSERT INTO memories_fts(rowid, key, content) VALUES (new.id, new.key, new.content);
+        END;
+        INSERT INTO memories_fts(memories_fts) VALUES ('rebuild');",
+    ),
+];
+
+/// Default location of memory.db
+pub fn default_db_path() -> PathBuf {
+    let mut p = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("."));
+    p.push("os-ghost");
+    p.push("memory.db");
+    p
+}
+
 impl HybridMemory {
     pub fn new(db_path: Option<PathBuf>, vector_dim: usize) -> Result<Self, String> {
-        let path = db_path.unwrap_or_else(|| {
-            let mut p = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("."));
-            p.push("os-ghost");
-            p.push("memory.db");
-            p
-        });
+        let path = db_path.unwrap_or_else(default_db_path);
 
         if let Some(parent) = path.parent() {
             let _ = std::fs::create_dir_all(parent);
         }
 
         let conn = Connection::open(&path).map_err(|e| e.to_string())?;
-        let memory = Self { conn, vector_dim };
-        memory.init_schema()?;
+        let memory = Self {
+            conn,
+            vector_dim,
+            index: RwLock::new(VectorIndex::new(vector_dim)),
+            fusion: Fusion::default(),
+        };
+        memory.migrate()?;
+        memory.load_index()?;
 
         tracing::info!("Hybrid memory initialized at {:?}", path);
         Ok(memory)
     }
 
-    fn init_schema(&self) -> Result<(), String> {
-        self.conn
-            .execute(
-                "CREATE TABLE IF NOT EXISTS memories (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                key TEXT NOT NULL UNIQUE,
-                content TEXT NOT NULL,
-                embedding BLOB,
-                created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL
-            )",
-                [],
-            )
-            .map_err(|e| e.to_string())?;
+    pub fn with_fusion(mut self, fusion: Fusion) -> Self {
+        self.fusion = fusion;
+        self
+    }
 
+    /// Apply pending schema migrations, tracked in `PRAGMA user_version`
+    fn migrate(&self) -> Result<(), String> {
+        let current = self.schema_version()?;
+        for (version, sql) in MIGRATIONS.iter().filter(|(v, _)| *v > current) {
+            let tx = self
+                .conn
+                .unchecked_transaction()
+                .map_err(|e| e.to_string())?;
+            tx.execute_batch(sql)
+                .map_err(|e| format!("Memory migration {} failed: {}", version, e))?;
+            tx.pragma_update(None, "user_version", version)
+                .map_err(|e| e.to_string())?;
+            tx.commit().map_err(|e| e.to_string())?;
+            tracing::info!("Migrated memory database to schema v{}", version);
+        }
+        Ok(())
+    }
+
+    pub fn schema_version(&self) -> Result<i64, String> {
         self.conn
-            .execute(
-                "CREATE VIRTUAL TABLE IF NOT EXISTS memories_fts USING fts5(
-                key, content, content=memories, content_rowid=id
-            )",
-                [],
-            )
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Load stored embeddings into the in-memory vector index
+    fn load_index(&self) -> Result<(), String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key, embedding FROM memories WHERE embedding IS NOT NULL")
             .map_err(|e| e.to_string())?;
 
-        self.conn
-            .execute(
-                "CREATE INDEX IF NOT EXISTS idx_memories_key ON memories(key)",
-                [],
-            )
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })
             .map_err(|e| e.to_string())?;
 
+        let mut index = self.index.write().map_err(|e| e.to_string())?;
+        let mut skipped = 0;
+        for (key, blob) in rows.filter_map(|r| r.ok()) {
+            match decode_embedding(&blob) {
+                Some(embedding) if embedding.len() == self.vector_dim => {
+                    index.upsert(&key, &embedding)
+                }
+                _ => skipped += 1,
+            }
+        }
+
+        if skipped > 0 {
+            tracing::warn!(
+                "Skipped {} stored embeddings that don't match vector_dim {}",
+                skipped,
+                self.vector_dim
+            );
+        }
+        tracing::debug!("Loaded {} embeddings into vector index", index.len());
+        Ok(())
+    }
+
+    pub fn vector_dim(&self) -> usize {
+        self.vector_dim
+    }
+
+    fn check_dim(&self, embedding: &[f32]) -> Result<(), String> {
+        if embedding.len() != self.vector_dim {
+            return Err(format!(
+                "Embedding dimension mismatch: expected {}, got {}",
+                self.vector_dim,
+                embedding.len()
+            ));
+        }
         Ok(())
     }
 
     pub fn store(&self, key: &str, content: &str, embedding: Option<&[f32]>) -> Result<(), String> {
+        if let Some(embedding) = embedding {
+            self.check_dim(embedding)?;
+        }
+
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .map(|d| d.as_secs() as i64)
             .unwrap_or(0);
 
-        let embedding_blob =
-            embedding.map(|e| e.iter().flat_map(|f| f.to_le_bytes()).collect::<Vec<u8>>());
+        let embedding_blob = embedding.map(encode_embedding);
 
-        self.conn.execute(
-            "INSERT OR REPLACE INTO memories (key, content, embedding, created_at, updated_at)
-             VALUES (?1, ?2, ?3, COALESCE((SELECT created_at FROM memories WHERE key = ?1), ?4), ?4)",
-            params![key, content, embedding_blob, now],
-        ).map_err(|e| e.to_string())?;
+        // Upsert rather than INSERT OR REPLACE: REPLACE deletes the old row without
+        // firing delete triggers, which would leave stale FTS entries behind
+        self.conn
+            .execute(
+                "INSERT INTO memories (key, content, embedding, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?4)
+                 ON CONFLICT(key) DO UPDATE SET
+                     content = excluded.content,
+                     embedding = excluded.embedding,
+                     updated_at = excluded.updated_at",
+                params![key, content, embedding_blob, now],
+            )
+            .map_err(|e| e.to_string())?;
+
+        let mut index = self.index.write().map_err(|e| e.to_string())?;
+        match embedding {
+            Some(embedding) => index.upsert(key, embedding),
+            None => index.remove(key),
+        }
 
         Ok(())
     }
 
+    /// Attach an embedding to an existing entry without touching its content
+    pub fn set_embedding(&self, key: &str, embedding: &[f32]) -> Result<(), String> {
+        self.check_dim(embedding)?;
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE memories SET embedding = ?1 WHERE key = ?2",
+                params![encode_embedding(embedding), key],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated > 0 {
+            self.index
+                .write()
+                .map_err(|e| e.to_string())?
+                .upsert(key, embedding);
+        }
+        Ok(())
+    }
+
+    /// Entries still waiting for an embedding, oldest first, as (key, content)
+    pub fn missing_embeddings(&self, limit: usize) -> Result<Vec<(String, String)>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT key, content FROM memories WHERE embedding IS NULL
+                 ORDER BY updated_at ASC LIMIT ?1",
+            )
+            .map_err(|e| e.to_string())?;
+
+        let rows = stmt
+            .query_map([limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(rows)
+    }
+
     pub fn recall(&self, key: &str) -> Result<Option<String>, String> {
         let mut stmt = self
             .conn
@@ -117,6 +324,7 @@ impl HybridMemory {
         self.conn
             .execute("DELETE FROM memories WHERE key = ?1", [key])
             .map_err(|e| e.to_string())?;
+        self.index.write().map_err(|e| e.to_string())?.remove(key);
         Ok(())
     }
 
@@ -164,6 +372,128 @@ impl HybridMemory {
         Ok(results)
     }
 
+    /// Nearest entries by cosine similarity, best first
+    pub fn vector_search(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+    ) -> Result<Vec<ScoredResult>, String> {
+        self.check_dim(embedding)?;
+        let hits = self
+            .index
+            .read()
+            .map_err(|e| e.to_string())?
+            .search(embedding, limit);
+
+        let mut results = Vec::with_capacity(hits.len());
+        for (key, score) in hits {
+            if let Some(content) = self.recall(&key)? {
+                results.push(ScoredResult {
+                    key,
+                    content,
+                    score,
+                    source: "vector".to_string(),
+                });
+            }
+        }
+        Ok(results)
+    }
+
+    /// Vector + BM25 search fused with the configured strategy
+    ///
+    /// `query` is free text (not FTS5 syntax). Without an embedding this is a
+    /// keyword search with normalised scores.
+    pub fn hybrid_search(
+        &self,
+        query: &str,
+        embedding: Option<&[f32]>,
+        limit: usize,
+    ) -> Result<Vec<ScoredResult>, String> {
+        let candidates = (limit * 4).max(MIN_CANDIDATES);
+
+        let keyword = match fts_query(query) {
+            Some(fts) => self.keyword_search(&fts, candidates)?,
+            None => Vec::new(),
+        };
+        let vector = match embedding {
+            Some(embedding) => self.vector_search(embedding, candidates)?,
+            None => Vec::new(),
+        };
+
+        Ok(fuse(keyword, vector, self.fusion, limit))
+    }
+
+    /// Check SQLite page integrity and that the FTS index matches its content
+    pub fn integrity_check(&self) -> Result<IntegrityReport, String> {
+        let mut problems = Vec::new();
+
+        let mut stmt = self
+            .conn
+            .prepare("PRAGMA integrity_check")
+            .map_err(|e| e.to_string())?;
+        let messages: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .filter_map(|r| r.ok())
+            .collect();
+        problems.extend(messages.into_iter().filter(|m| m != "ok"));
+
+        if let Err(e) = self.conn.execute(
+            "INSERT INTO memories_fts(memories_fts) VALUES ('integrity-check')",
+            [],
+        ) {
+            problems.push(format!("FTS index out of sync: {}", e));
+        }
+
+        let schema_version = self.schema_version()?;
+        if schema_version < SCHEMA_VERSION {
+            problems.push(format!(
+                "Schema v{} is older than v{}",
+                schema_version, SCHEMA_VERSION
+            ));
+        }
+
+        let total_entries = self.stats()?.total_entries;
+        let indexed: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM memories_fts_docsize", [], |row| {
+                row.get(0)
+            })
+            .map_err(|e| e.to_string())?;
+        let indexed_entries = indexed as usize;
+        if indexed_entries != total_entries {
+            problems.push(format!(
+                "FTS index has {} of {} entries",
+                indexed_entries, total_entries
+            ));
+        }
+
+        Ok(IntegrityReport {
+            ok: problems.is_empty(),
+            schema_version,
+            total_entries,
+            indexed_entries,
+            problems,
+        })
+    }
+
+    /// Rebuild the FTS index and the in-memory vector index from stored rows
+    pub fn rebuild(&self) -> Result<IntegrityReport, String> {
+        self.migrate()?;
+        self.conn
+            .execute(
+                "INSERT INTO memories_fts(memories_fts) VALUES ('rebuild')",
+                [],
+            )
+            .map_err(|e| e.to_string())?;
+
+        *self.index.write().map_err(|e| e.to_string())? = VectorIndex::new(self.vector_dim);
+        self.load_index()?;
+
+        tracing::info!("Rebuilt memory search indexes");
+        self.integrity_check()
+    }
+
     pub fn stats(&self) -> Result<MemoryStats, String> {
         let total: i64 = self
             .conn
@@ -186,6 +516,202 @@ impl HybridMemory {
     }
 }
 
+/// Embeddings are stored as little-endian f32 BLOBs
+fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+fn decode_embedding(blob: &[u8]) -> Option<Vec<f32>> {
+    if blob.len() % 4 != 0 {
+        return None;
+    }
+    Some(
+        blob.chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    )
+}
+
+fn normalized(embedding: &[f32]) -> Option<Vec<f32>> {
+    let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 || !norm.is_finite() {
+        return None;
+    }
+    Some(embedding.iter().map(|x| x / norm).collect())
+}
+
+/// Free text to an FTS5 query: quoted terms joined with OR
+fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| format!("\"{}\"", t))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" OR "))
+    }
+}
+
+/// Flat in-memory index of unit-normalised embeddings
+///
+/// Rows live in one contiguous buffer so a search is a cache-friendly dot product
+/// scan; removal swaps the last row into the hole.
+struct VectorIndex {
+    dim: usize,
+    keys: Vec<String>,
+    data: Vec<f32>,
+    positions: HashMap<String, usize>,
+}
+
+impl VectorIndex {
+    fn new(dim: usize) -> Self {
+        Self {
+            dim,
+            keys: Vec::new(),
+            data: Vec::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn upsert(&mut self, key: &str, embedding: &[f32]) {
+        let Some(vector) = normalized(embedding).filter(|v| v.len() == self.dim) else {
+            // Zero vectors can't be ranked by cosine
+            self.remove(key);
+            return;
+        };
+        match self.positions.get(key) {
+            Some(&pos) => self.data[pos * self.dim..(pos + 1) * self.dim].copy_from_slice(&vector),
+            None => {
+                self.positions.insert(key.to_string(), self.keys.len());
+                self.keys.push(key.to_string());
+                self.data.extend_from_slice(&vector);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        let Some(pos) = self.positions.remove(key) else {
+            return;
+        };
+        let last = self.keys.len() - 1;
+        if pos != last {
+            self.data
+                .copy_within(last * self.dim..(last + 1) * self.dim, pos * self.dim);
+            self.keys.swap(pos, last);
+            self.positions.insert(self.keys[pos].clone(), pos);
+        }
+        self.keys.pop();
+        self.data.truncate(last * self.dim);
+    }
+
+    /// Top `limit` keys by cosine similarity, best first
+    fn search(&self, query: &[f32], limit: usize) -> Vec<(String, f32)> {
+        if limit == 0 || self.keys.is_empty() {
+            return Vec::new();
+        }
+        let Some(query) = normalized(query).filter(|q| q.len() == self.dim) else {
+            return Vec::new();
+        };
+
+        let mut scored: Vec<(usize, f32)> = self
+            .data
+            .chunks_exact(self.dim)
+            .map(|row| row.iter().zip(&query).map(|(a, b)| a * b).sum())
+            .enumerate()
+            .collect();
+
+        let by_score = |a: &(usize, f32), b: &(usize, f32)| b.1.total_cmp(&a.1);
+        if scored.len() > limit {
+            scored.select_nth_unstable_by(limit - 1, by_score);
+            scored.truncate(limit);
+        }
+        scored.sort_by(by_score);
+
+        scored
+            .into_iter()
+            .map(|(pos, score)| (self.keys[pos].clone(), score))
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct Candidate {
+    content: String,
+    keyword: Option<(usize, f32)>,
+    vector: Option<(usize, f32)>,
+}
+
+/// Merge keyword (raw BM25, best first) and vector (cosine, best first) results
+fn fuse(
+    keyword: Vec<ScoredResult>,
+    vector: Vec<ScoredResult>,
+    fusion: Fusion,
+    limit: usize,
+) -> Vec<ScoredResult> {
+    // bm25() is lower-is-better; flip and min-max normalise to [0, 1]
+    let relevance: Vec<f32> = keyword.iter().map(|r| -r.score).collect();
+    let max = relevance.iter().cloned().fold(f32::MIN, f32::max);
+    let min = relevance.iter().cloned().fold(f32::MAX, f32::min);
+
+    let mut candidates: HashMap<String, Candidate> = HashMap::new();
+    for (rank, (result, relevance)) in keyword.into_iter().zip(relevance).enumerate() {
+        let score = if max > min {
+            (relevance - min) / (max - min)
+        } else {
+            1.0
+        };
+        let entry = candidates.entry(result.key).or_default();
+        entry.content = result.content;
+        entry.keyword = Some((rank, score));
+    }
+    for (rank, result) in vector.into_iter().enumerate() {
+        let entry = candidates.entry(result.key).or_default();
+        entry.content = result.content;
+        entry.vector = Some((rank, result.score.clamp(0.0, 1.0)));
+    }
+
+    let mut results: Vec<ScoredResult> = candidates
+        .into_iter()
+        .map(|(key, c)| {
+            let score = match fusion {
+                Fusion::Weighted {
+                    vector_weight,
+                    keyword_weight,
+                } => {
+                    vector_weight * c.vector.map_or(0.0, |(_, s)| s)
+                        + keyword_weight * c.keyword.map_or(0.0, |(_, s)| s)
+                }
+                Fusion::Rrf { k } => [c.vector, c.keyword]
+                    .iter()
+                    .flatten()
+                    .map(|(rank, _)| 1.0 / (k + *rank as f32 + 1.0))
+                    .sum(),
+            };
+            let source = match (c.vector.is_some(), c.keyword.is_some()) {
+                (true, true) => "hybrid",
+                (true, false) => "vector",
+                _ => "keyword",
+            };
+            ScoredResult {
+                key,
+                content: c.content,
+                score,
+                source: source.to_string(),
+            }
+        })
+        .collect();
+
+    results.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.key.cmp(&b.key)));
+    results.truncate(limit);
+    results
+}
+
 pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     if a.len() != b.len() || a.is_empty() {
         return 0.0;
@@ -205,14 +731,26 @@ lazy_static::lazy_static! {
     static ref HYBRID_MEMORY: Mutex<Option<HybridMemory>> = Mutex::new(None);
 }
 
-pub fn init_hybrid_memory(vector_dim: usize) -> Result<(), String> {
-    let memory = HybridMemory::new(None, vector_dim)?;
+pub fn init_hybrid_memory(config: &MemoryConfig) -> Result<(), String> {
+    let memory =
+        HybridMemory::new(None, config.vector_dim)?.with_fusion(Fusion::from_config(config));
     if let Ok(mut m) = HYBRID_MEMORY.lock() {
         *m = Some(memory);
     }
     Ok(())
 }
 
+/// Run a closure against the global hybrid memory
+pub fn with_hybrid_memory<T>(
+    f: impl FnOnce(&HybridMemory) -> Result<T, String>,
+) -> Result<T, String> {
+    let guard = HYBRID_MEMORY.lock().map_err(|e| e.to_string())?;
+    match guard.as_ref() {
+        Some(mem) => f(mem),
+        None => Err("Memory not initialized".to_string()),
+    }
+}
+
 #[tauri::command]
 pub fn memory_store(
     key: String,
@@ -249,16 +787,34 @@ pub fn memory_delete(key: String) -> Result<(), String> {
     Err("Memory not initialized".to_string())
 }
 
+/// Hybrid search; the query is embedded when an embedding provider is configured
 #[tauri::command]
-pub fn memory_search(query: String, limit: Option<usize>) -> Result<Vec<ScoredResult>, String> {
-    if let Ok(m) = HYBRID_MEMORY.lock() {
-        if let Some(mem) = m.as_ref() {
-            return mem
-                .keyword_search(&query, limit.unwrap_or(10))
-                .map_err(|e| e.to_string());
-        }
-    }
-    Err("Memory not initialized".to_string())
+pub async fn memory_search(
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<ScoredResult>, String> {
+    let embedding = match super::embeddings::embedder() {
+        Some(embedder) => match embedder.embed_query(&query).await {
+            Ok(embedding) => Some(embedding),
+            Err(e) => {
+                tracing::warn!("Query embedding failed, searching keywords only: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    with_hybrid_memory(|mem| mem.hybrid_search(&query, embedding.as_deref(), limit.unwrap_or(10)))
+}
+
+#[tauri::command]
+pub fn memory_integrity_check() -> Result<IntegrityReport, String> {
+    with_hybrid_memory(|mem| mem.integrity_check())
+}
+
+#[tauri::command]
+pub fn memory_rebuild() -> Result<IntegrityReport, String> {
+    with_hybrid_memory(|mem| mem.rebuild())
 }
 
 #[tauri::command]
@@ -270,3 +826,149 @@ pub fn memory_stats() -> Result<MemoryStats, String> {
     }
     Err("Memory not initialized".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn temp_memory(dim: usize) -> (tempfile::TempDir, HybridMemory) {
+        let dir = tempdir().unwrap();
+        let memory = HybridMemory::new(Some(dir.path().join("memory.db")), dim).unwrap();
+        (dir, memory)
+    }
+
+    #[test]
+    fn test_embedding_dimension_is_checked() {
+        let (_dir, memory) = temp_memory(3);
+        assert!(memory.store("a", "alpha", Some(&[1.0, 0.0])).is_err());
+        assert!(memory.store("a", "alpha", Some(&[1.0, 0.0, 0.0])).is_ok());
+    }
+
+    #[test]
+    fn test_backfill_queue() {
+        let (_dir, memory) = temp_memory(2);
+        memory.store("a", "alpha", None).unwrap();
+        memory.store("b", "beta", Some(&[0.5, 0.5])).unwrap();
+
+        let missing = memory.missing_embeddings(10).unwrap();
+        assert_eq!(missing, vec![("a".to_string(), "alpha".to_string())]);
+
+        memory.set_embedding("a", &[1.0, 0.0]).unwrap();
+        assert!(memory.missing_embeddings(10).unwrap().is_empty());
+        assert_eq!(memory.stats().unwrap().entries_with_embeddings, 2);
+    }
+
+    #[test]
+    fn test_keyword_search_sees_updates() {
+        let (_dir, memory) = temp_memory(2);
+        memory.store("pet", "the cat sleeps", None).unwrap();
+        memory.store("pet", "the dog barks", None).unwrap();
+        memory.store("gone", "a cat naps", None).unwrap();
+        memory.delete("gone").unwrap();
+
+        assert!(memory.keyword_search("cat", 10).unwrap().is_empty());
+        let hits = memory.keyword_search("dog", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].key, "pet");
+        assert!(memory.integrity_check().unwrap().ok);
+    }
+
+    #[test]
+    fn test_migration_repairs_unindexed_database() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("memory.db");
+        {
+            // A pre-versioning database: v1 schema, rows never indexed
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(MIGRATIONS[0].1).unwrap();
+            conn.execute(
+                "INSERT INTO memories (key, content, created_at, updated_at)
+                 VALUES ('note', 'remember the milk', 0, 0)",
+                [],
+            )
+            .unwrap();
+        }
+
+        let memory = HybridMemory::new(Some(path), 2).unwrap();
+        assert_eq!(memory.schema_version().unwrap(), SCHEMA_VERSION);
+        assert_eq!(memory.keyword_search("milk", 10).unwrap().len(), 1);
+
+        let report = memory.rebuild().unwrap();
+        assert!(report.ok, "{:?}", report.problems);
+        assert_eq!(report.indexed_entries, 1);
+    }
+
+    fn result(key: &str, score: f32, source: &str) -> ScoredResult {
+        ScoredResult {
+            key: key.to_string(),
+            content: key.to_string(),
+            score,
+            source: source.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_vector_index_remove_keeps_positions() {
+        let mut index = VectorIndex::new(2);
+        index.upsert("a", &[1.0, 0.0]);
+        index.upsert("b", &[0.0, 1.0]);
+        index.upsert("c", &[1.0, 1.0]);
+        index.remove("a");
+
+        assert_eq!(index.len(), 2);
+        let hits = index.search(&[0.0, 2.0], 2);
+        assert_eq!(hits[0].0, "b");
+        assert!((hits[0].1 - 1.0).abs() < 1e-6);
+        assert!((hits[1].1 - cosine_similarity(&[1.0, 1.0], &[0.0, 2.0])).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_weighted_fusion_sources() {
+        let keyword = vec![
+            result("both", -3.0, "keyword"),
+            result("kw", -1.0, "keyword"),
+        ];
+        let vector = vec![result("both", 0.9, "vector"), result("vec", 0.8, "vector")];
+        let fusion = Fusion::Weighted {
+            vector_weight: 0.5,
+            keyword_weight: 0.5,
+        };
+
+        let fused = fuse(keyword, vector, fusion, 10);
+        assert_eq!(fused[0].key, "both");
+        assert_eq!(fused[0].source, "hybrid");
+        assert!((fused[0].score - 0.95).abs() < 1e-6);
+        assert_eq!(fused[1].source, "vector");
+        assert_eq!(fused[2].source, "keyword");
+        assert_eq!(fused[2].score, 0.0);
+    }
+
+    #[test]
+    fn test_rrf_fusion_rewards_agreement() {
+        let keyword = vec![result("a", -5.0, "keyword"), result("b", -4.0, "keyword")];
+        let vector = vec![result("c", 0.9, "vector"), result("b", 0.8, "vector")];
+
+        let fused = fuse(keyword, vector, Fusion::Rrf { k: RRF_K }, 2);
+        assert_eq!(fused.len(), 2);
+        assert_eq!(fused[0].key, "b");
+        assert_eq!(fused[0].source, "hybrid");
+    }
+
+    #[test]
+    fn test_hybrid_search_uses_index() {
+        let (_dir, memory) = temp_memory(2);
+        memory.store("north", "north", Some(&[0.0, 1.0])).unwrap();
+        memory.store("east", "east", Some(&[1.0, 0.0])).unwrap();
+        memory.store("plain", "plain", None).unwrap();
+
+        let results = memory.hybrid_search("", Some(&[0.1, 0.9]), 5).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].key, "north");
+        assert_eq!(results[0].source, "vector");
+
+        memory.delete("north").unwrap();
+        let results = memory.hybrid_search("", Some(&[0.1, 0.9]), 5).unwrap();
+        assert_eq!(results[0].key, "east");
+    }
+}
@@ -10,6 +10,8 @@
 
 pub mod advanced;
 pub mod compaction;
+pub mod conversation;
+pub mod embeddings;
 pub mod hybrid;
 pub mod long_term;
 pub mod scoped_state;
@@ -20,7 +22,11 @@ pub use advanced::{
     AdvancedMemory, ExportedWorkflow, FileDrop, MemoryEntry, MemoryKind, Mood,
     OperationalMode, PersonalityGenome, RetrievalConfig,
 };
-pub use compaction::{get_boot_tasks, inject_workspace_context, run_silent_memory_turn, should_compact};
+pub use compaction::{
+    get_boot_tasks, inject_workspace_context, run_silent_memory_turn, should_compact,
+    summarize_conversation,
+};
+pub use conversation::{Conversation, ConversationInfo, ConversationStore};
 pub use long_term::LongTermMemory;
 pub use scoped_state::{IntoScopedKey, ScopedState, StateScope};
 pub use session::{ActivityEntry, AppMode, SessionMemory};
@@ -25,6 +25,12 @@ impl MemoryStore {
         Ok(Self { db: Arc::new(db) })
     }
 
+    /// Open a throwaway in-memory store, deleted when dropped
+    pub fn temporary() -> Result<Self> {
+        let db = sled::Config::new().temporary(true).open()?;
+        Ok(Self { db: Arc::new(db) })
+    }
+
     /// Get the default database path
     fn default_path() -> Result<PathBuf> {
         let mut path =
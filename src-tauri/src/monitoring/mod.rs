@@ -8,8 +8,8 @@ pub mod types;
 // Re-export commonly used types
 pub use app_context::{AppCategory, AppContext, AppContextDetector, AppSwitchEvent};
 pub use types::{
-    with_retry, AggregateMetrics, InvocationMetrics, MetricsCollector, RetryConfig, RetryResult,
-    Span, SpanStatus, SpanType, ToolCallRecord,
+    with_retry, AggregateMetrics, CandidateRecord, InvocationMetrics, MetricsCollector,
+    RetryConfig, RetryResult, Span, SpanStatus, SpanType, ToolCallRecord,
 };
 
 // Activity tracker temporarily disabled - requires rdev dependency not in Cargo.toml
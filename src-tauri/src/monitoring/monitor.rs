@@ -10,11 +10,12 @@
 
 use crate::ai::ai_provider::SmartAiRouter;
 use crate::capture::capture;
-use crate::core::utils::{clean_json_response, current_timestamp};
+use crate::core::utils::current_timestamp;
 use crate::data::events_bus::{record_event, EventKind, EventPriority};
 use crate::memory::{ActivityEntry, LongTermMemory, SessionMemory};
 use crate::resources::monitor::ResourceMonitor;
 use base64::{engine::general_purpose, Engine as _};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
@@ -22,7 +23,7 @@ use tauri::{AppHandle, Emitter, Manager};
 use tokio::time::Duration;
 
 /// Detected application category
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum AppCategory {
     Browser,
@@ -38,7 +39,7 @@ pub enum AppCategory {
 }
 
 /// Enhanced observation result with app categorization
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ObservationResult {
     /// Main activity description
     pub activity: String,
@@ -410,156 +411,142 @@ Respond in JSON: {{"activity":"brief description","is_idle":bool,"new_fact":stri
                 "Calling AI analysis from Monitor Loop (Thread: {:?})",
                 std::thread::current().id()
             );
-            ai_router.analyze_image(&base64_image, &prompt).await
+            ai_router
+                .analyze_image_structured::<ObservationResult>(&base64_image, &prompt)
+                .await
         })
         .await;
 
         match analysis_result {
-            Ok(Ok(json_str)) => {
-                let clean_json = clean_json_response(&json_str);
+            Ok(Ok(observation)) => {
+                // Success! Reset backoff
+                state.reset_backoff();
 
-                match serde_json::from_str::<ObservationResult>(clean_json) {
-                    Ok(observation) => {
-                        // Success! Reset backoff
-                        state.reset_backoff();
+                tracing::debug!("Monitor observed: {:?}", observation);
 
-                        tracing::debug!("Monitor observed: {:?}", observation);
+                let now = current_timestamp();
 
-                        let now = current_timestamp();
-
-                        // Track idle patterns
-                        if observation.is_idle {
-                            state.consecutive_idle_count += 1;
-                        } else {
-                            state.consecutive_idle_count = 0;
-                        }
-
-                        // Track category patterns (using VecDeque for efficiency)
-                        state
-                            .recent_categories
-                            .push_back(observation.app_category.clone());
-                        if state.recent_categories.len() > settings.monitor_category_window {
-                            state.recent_categories.pop_front();
-                        }
+                // Track idle patterns
+                if observation.is_idle {
+                    state.consecutive_idle_count += 1;
+                } else {
+                    state.consecutive_idle_count = 0;
+                }
 
-                        // Store facts using try_lock (best effort)
-                        if let Some(ref fact) = observation.new_fact {
-                            if let Ok(ltm_guard) = long_term.try_lock() {
-                                let _ =
-                                    ltm_guard.record_fact("last_activity", &observation.activity);
-                                let _ = ltm_guard.record_fact("last_new_fact", fact);
-                                if let Some(ref app) = observation.app_name {
-                                    let _ = ltm_guard.record_fact("last_app", app);
-                                }
-                                tracing::info!("Recorded new fact: {}", fact);
-                            }
-                        }
+                // Track category patterns (using VecDeque for efficiency)
+                state
+                    .recent_categories
+                    .push_back(observation.app_category.clone());
+                if state.recent_categories.len() > settings.monitor_category_window {
+                    state.recent_categories.pop_front();
+                }
 
-                        // Update session using try_lock (best effort)
-                        if let Ok(sess_guard) = session.try_lock() {
-                            let _ = sess_guard.touch();
-                            let _ = sess_guard.record_analysis();
-
-                            if !observation.is_idle {
-                                let _ = sess_guard.add_activity(ActivityEntry {
-                                    activity_type: "observation".to_string(),
-                                    description: observation.activity.clone(),
-                                    timestamp: now,
-                                    metadata: Some(serde_json::json!({
-                                        "app_name": observation.app_name,
-                                        "app_category": observation.app_category,
-                                        "content_context": observation.content_context,
-                                        "puzzle_theme": observation.puzzle_theme,
-                                        "is_idle": observation.is_idle,
-                                    })),
-                                });
-                            }
+                // Store facts using try_lock (best effort)
+                if let Some(ref fact) = observation.new_fact {
+                    if let Ok(ltm_guard) = long_term.try_lock() {
+                        let _ = ltm_guard.record_fact("last_activity", &observation.activity);
+                        let _ = ltm_guard.record_fact("last_new_fact", fact);
+                        if let Some(ref app) = observation.app_name {
+                            let _ = ltm_guard.record_fact("last_app", app);
                         }
+                        tracing::info!("Recorded new fact: {}", fact);
+                    }
+                }
 
-                        // Generate companion behavior
-                        let behavior = generate_companion_behavior(
-                            &observation,
-                            &state.recent_categories.iter().collect::<Vec<_>>(),
-                            state.consecutive_idle_count,
-                            settings.monitor_idle_streak_threshold,
-                        );
-
-                        // Emit events
-                        if !observation.is_idle {
-                            let _ = app.emit("ghost_observation", &observation);
-                        }
+                // Update session using try_lock (best effort)
+                if let Ok(sess_guard) = session.try_lock() {
+                    let _ = sess_guard.touch();
+                    let _ = sess_guard.record_analysis();
+
+                    if !observation.is_idle {
+                        let _ = sess_guard.add_activity(ActivityEntry {
+                            activity_type: "observation".to_string(),
+                            description: observation.activity.clone(),
+                            timestamp: now,
+                            metadata: Some(serde_json::json!({
+                                "app_name": observation.app_name,
+                                "app_category": observation.app_category,
+                                "content_context": observation.content_context,
+                                "puzzle_theme": observation.puzzle_theme,
+                                "is_idle": observation.is_idle,
+                            })),
+                        });
+                    }
+                }
 
-                        if let Some(b) = behavior {
-                            tracing::info!(
-                                "Companion behavior triggered: {} - {}",
-                                b.behavior_type,
-                                b.suggestion
-                            );
-                            let _ = app.emit("companion_behavior", &b);
-
-                            let mut metadata = serde_json::Map::new();
-                            metadata.insert(
-                                "behavior_type".to_string(),
-                                serde_json::Value::String(b.behavior_type.clone()),
-                            );
-                            metadata.insert(
-                                "urgency".to_string(),
-                                serde_json::Value::Number(
-                                    serde_json::Number::from_f64(b.urgency as f64)
-                                        .unwrap_or_else(|| serde_json::Number::from(0)),
-                                ),
-                            );
-                            record_event(
-                                EventKind::Suggestion,
-                                b.suggestion.clone(),
-                                Some(b.trigger_context.clone()),
-                                metadata.into_iter().collect(),
-                                EventPriority::Normal,
-                                Some(format!("suggestion:{}", b.behavior_type)),
-                                Some(300),
-                                Some("monitor".to_string()),
-                            );
-                        }
+                // Generate companion behavior
+                let behavior = generate_companion_behavior(
+                    &observation,
+                    &state.recent_categories.iter().collect::<Vec<_>>(),
+                    state.consecutive_idle_count,
+                    settings.monitor_idle_streak_threshold,
+                );
+
+                // Emit events
+                if !observation.is_idle {
+                    let _ = app.emit("ghost_observation", &observation);
+                }
 
-                        // Record observation event
-                        let mut metadata = serde_json::Map::new();
-                        metadata.insert(
-                            "app_category".to_string(),
-                            serde_json::to_value(&observation.app_category).unwrap_or_default(),
-                        );
-                        metadata.insert(
-                            "app_name".to_string(),
-                            serde_json::to_value(&observation.app_name).unwrap_or_default(),
-                        );
-                        metadata.insert(
-                            "content_context".to_string(),
-                            serde_json::to_value(&observation.content_context).unwrap_or_default(),
-                        );
-                        record_event(
-                            EventKind::Observation,
-                            observation.activity.clone(),
-                            observation.new_fact.clone(),
-                            metadata.into_iter().collect(),
-                            if observation.is_idle {
-                                EventPriority::Low
-                            } else {
-                                EventPriority::Normal
-                            },
-                            Some("observation".to_string()),
-                            Some(120),
-                            Some("monitor".to_string()),
-                        );
-                    }
-                    Err(e) => {
-                        let snippet: String = clean_json.chars().take(200).collect();
-                        tracing::warn!(
-                            "Failed to parse observation JSON: {} - Raw: {}",
-                            e,
-                            snippet
-                        );
-                        state.increase_backoff();
-                    }
+                if let Some(b) = behavior {
+                    tracing::info!(
+                        "Companion behavior triggered: {} - {}",
+                        b.behavior_type,
+                        b.suggestion
+                    );
+                    let _ = app.emit("companion_behavior", &b);
+
+                    let mut metadata = serde_json::Map::new();
+                    metadata.insert(
+                        "behavior_type".to_string(),
+                        serde_json::Value::String(b.behavior_type.clone()),
+                    );
+                    metadata.insert(
+                        "urgency".to_string(),
+                        serde_json::Value::Number(
+                            serde_json::Number::from_f64(b.urgency as f64)
+                                .unwrap_or_else(|| serde_json::Number::from(0)),
+                        ),
+                    );
+                    record_event(
+                        EventKind::Suggestion,
+                        b.suggestion.clone(),
+                        Some(b.trigger_context.clone()),
+                        metadata.into_iter().collect(),
+                        EventPriority::Normal,
+                        Some(format!("suggestion:{}", b.behavior_type)),
+                        Some(300),
+                        Some("monitor".to_string()),
+                    );
                 }
+
+                // Record observation event
+                let mut metadata = serde_json::Map::new();
+                metadata.insert(
+                    "app_category".to_string(),
+                    serde_json::to_value(&observation.app_category).unwrap_or_default(),
+                );
+                metadata.insert(
+                    "app_name".to_string(),
+                    serde_json::to_value(&observation.app_name).unwrap_or_default(),
+                );
+                metadata.insert(
+                    "content_context".to_string(),
+                    serde_json::to_value(&observation.content_context).unwrap_or_default(),
+                );
+                record_event(
+                    EventKind::Observation,
+                    observation.activity.clone(),
+                    observation.new_fact.clone(),
+                    metadata.into_iter().collect(),
+                    if observation.is_idle {
+                        EventPriority::Low
+                    } else {
+                        EventPriority::Normal
+                    },
+                    Some("observation".to_string()),
+                    Some(120),
+                    Some("monitor".to_string()),
+                );
             }
             Ok(Err(e)) => {
                 tracing::warn!("Monitor analysis failed: {}", e);
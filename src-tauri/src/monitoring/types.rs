@@ -6,6 +6,7 @@
 //! - Quality evaluation criteria
 //! - Error recovery with retry logic
 
+use crate::ai::prompts::PromptRef;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -45,6 +46,12 @@ pub struct InvocationMetrics {
     pub started_at: u64,
     /// Confidence score from agent output
     pub confidence: f32,
+    /// Prompt templates (name and version) rendered during the invocation
+    #[serde(default)]
+    pub prompts: Vec<PromptRef>,
+    /// Best-of-N candidates the critic judged, if any
+    #[serde(default)]
+    pub candidates: Vec<CandidateRecord>,
 }
 
 impl InvocationMetrics {
@@ -64,9 +71,23 @@ impl InvocationMetrics {
             error: None,
             started_at: current_timestamp_ms(),
             confidence: 0.0,
+            prompts: Vec::new(),
+            candidates: Vec::new(),
         }
     }
 
+    /// Record the prompt templates that produced this result
+    pub fn with_prompts(mut self, prompts: Vec<PromptRef>) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// Record the best-of-N candidates behind this result
+    pub fn with_candidates(mut self, candidates: Vec<CandidateRecord>) -> Self {
+        self.candidates = candidates;
+        self
+    }
+
     /// Record a successful completion
     pub fn complete_success(mut self, latency_ms: u64, confidence: f32) -> Self {
         self.total_latency_ms = latency_ms;
@@ -112,6 +133,17 @@ pub struct ToolCallRecord {
     pub error: Option<String>,
 }
 
+/// A best-of-N candidate and how it fared in pairwise judging
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CandidateRecord {
+    pub text: String,
+    /// Shift applied to the generator's sampling temperature
+    pub temperature_offset: f64,
+    /// Share of pairwise comparisons won (ties count half)
+    pub score: f32,
+    pub selected: bool,
+}
+
 // =============================================================================
 // Aggregate Metrics
 // =============================================================================
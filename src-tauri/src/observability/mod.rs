@@ -3,7 +3,10 @@
 //! Provides metrics and tracing capabilities for production monitoring.
 //! Reference: ZeroClaw observability design
 
+use crate::ai::circuit_breaker::{self, BreakerSnapshot};
+use crate::ai::scheduler::{self, SchedulerStats};
 use serde::{Deserialize, Serialize};
+use std::fmt::Write;
 use std::sync::RwLock;
 
 lazy_static::lazy_static! {
@@ -25,6 +28,8 @@ pub struct Metrics {
     pub memory_recalls: u64,
     pub channel_messages_sent: u64,
     pub channel_messages_received: u64,
+    pub vision_cache_hits: u64,
+    pub vision_cache_misses: u64,
     pub start_time_secs: u64,
 }
 
@@ -110,6 +115,16 @@ impl Metrics {
         }
     }
 
+    pub fn increment_vision_cache(&self, hit: bool) {
+        if let Ok(mut m) = METRICS.write() {
+            if hit {
+                m.vision_cache_hits += 1;
+            } else {
+                m.vision_cache_misses += 1;
+            }
+        }
+    }
+
     pub fn get(&self) -> Metrics {
         METRICS.read().map(|m| m.clone()).unwrap_or_default()
     }
@@ -135,7 +150,7 @@ pub fn get_metrics_prometheus() -> String {
     let m = get_metrics();
     let uptime = Metrics::new().uptime_secs();
 
-    format!(
+    let mut output = format!(
         r#"# HELP os_ghost_requests_total Total HTTP requests
 # TYPE os_ghost_requests_total counter
 os_ghost_requests_total {}
@@ -188,6 +203,14 @@ os_ghost_messages_sent {}
 # TYPE os_ghost_messages_received counter
 os_ghost_messages_received {}
 
+# HELP os_ghost_vision_cache_hits Screenshot analyses served from the vision cache
+# TYPE os_ghost_vision_cache_hits counter
+os_ghost_vision_cache_hits {}
+
+# HELP os_ghost_vision_cache_misses Screenshot analyses that needed a vision call
+# TYPE os_ghost_vision_cache_misses counter
+os_ghost_vision_cache_misses {}
+
 # HELP os_ghost_uptime_seconds Server uptime in seconds
 # TYPE os_ghost_uptime_seconds gauge
 os_ghost_uptime_seconds {}
@@ -205,8 +228,131 @@ os_ghost_uptime_seconds {}
         m.memory_recalls,
         m.channel_messages_sent,
         m.channel_messages_received,
+        m.vision_cache_hits,
+        m.vision_cache_misses,
         uptime
-    )
+    );
+    output.push_str(&circuit_breaker_metrics(
+        &circuit_breaker::breaker_snapshots(),
+    ));
+    if let Some(stats) = scheduler::scheduler_stats() {
+        output.push_str(&scheduler_metrics(&stats));
+    }
+    output
+}
+
+/// LLM scheduler queue depth, admissions and in-flight calls
+fn scheduler_metrics(stats: &SchedulerStats) -> String {
+    let mut output = String::new();
+    let per_priority = [
+        (
+            "os_ghost_llm_queue_depth",
+            "gauge",
+            "LLM calls waiting to be scheduled",
+        ),
+        (
+            "os_ghost_llm_requests_admitted",
+            "counter",
+            "LLM calls admitted by the scheduler",
+        ),
+        (
+            "os_ghost_llm_requests_dropped",
+            "counter",
+            "LLM calls rejected by the scheduler",
+        ),
+    ];
+    for (i, (name, kind, help)) in per_priority.iter().enumerate() {
+        let _ = write!(
+            output,
+            "\n# HELP {} {}\n# TYPE {} {}\n",
+            name, help, name, kind
+        );
+        for p in &stats.priorities {
+            let value = match i {
+                0 => p.queued as u64,
+                1 => p.admitted,
+                _ => p.dropped,
+            };
+            let _ = writeln!(
+                output,
+                "{}{{priority=\"{}\"}} {}",
+                name,
+                p.priority.as_str(),
+                value
+            );
+        }
+    }
+
+    output.push_str(
+        "\n# HELP os_ghost_llm_tokens_available Tokens left in the LLM rate-limit bucket\n\
+         # TYPE os_ghost_llm_tokens_available gauge\n",
+    );
+    let _ = writeln!(
+        output,
+        "os_ghost_llm_tokens_available {:.2}",
+        stats.tokens_available
+    );
+
+    output.push_str(
+        "\n# HELP os_ghost_llm_in_flight LLM calls in flight per provider\n\
+         # TYPE os_ghost_llm_in_flight gauge\n",
+    );
+    for (provider, count) in &stats.in_flight {
+        let _ = writeln!(
+            output,
+            "os_ghost_llm_in_flight{{provider=\"{}\"}} {}",
+            escape_label(provider),
+            count
+        );
+    }
+    output
+}
+
+/// Per-backend breaker state and trip count
+fn circuit_breaker_metrics(breakers: &[BreakerSnapshot]) -> String {
+    if breakers.is_empty() {
+        return String::new();
+    }
+
+    let labels = |b: &BreakerSnapshot| {
+        format!(
+            "provider=\"{}\",model=\"{}\"",
+            escape_label(&b.provider),
+            escape_label(&b.model)
+        )
+    };
+    let mut output = String::from(
+        "\n# HELP os_ghost_circuit_breaker_state AI backend circuit state (0 closed, 1 half-open, 2 open)\n\
+         # TYPE os_ghost_circuit_breaker_state gauge\n",
+    );
+    for b in breakers {
+        let _ = writeln!(
+            output,
+            "os_ghost_circuit_breaker_state{{{}}} {}",
+            labels(b),
+            b.state.as_gauge()
+        );
+    }
+    output.push_str(
+        "\n# HELP os_ghost_circuit_breaker_trips Times an AI backend circuit has opened\n\
+         # TYPE os_ghost_circuit_breaker_trips counter\n",
+    );
+    for b in breakers {
+        let _ = writeln!(
+            output,
+            "os_ghost_circuit_breaker_trips{{{}}} {}",
+            labels(b),
+            b.trips
+        );
+    }
+    output
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
 }
 
 pub fn reset_metrics() {
@@ -360,6 +506,39 @@ mod tests {
         assert!(output.contains("os_ghost_requests_total"));
     }
 
+    #[test]
+    fn test_circuit_breaker_metrics() {
+        let breaker = circuit_breaker::CircuitBreaker::new(
+            "ollama",
+            "llama3.2",
+            circuit_breaker::BreakerConfig::local(),
+        );
+        breaker.record_failure("connection refused");
+
+        let output = circuit_breaker_metrics(&[breaker.snapshot()]);
+        assert!(output
+            .contains("os_ghost_circuit_breaker_state{provider=\"ollama\",model=\"llama3.2\"} 2"));
+        assert!(output
+            .contains("os_ghost_circuit_breaker_trips{provider=\"ollama\",model=\"llama3.2\"} 1"));
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_metrics() {
+        let scheduler = scheduler::RequestScheduler::new(60);
+        scheduler
+            .admit(scheduler::Priority::Interactive)
+            .await
+            .unwrap();
+        let _permit = scheduler
+            .acquire_slot("openai", false, scheduler::Priority::Interactive)
+            .await;
+
+        let output = scheduler_metrics(&scheduler.stats());
+        assert!(output.contains("os_ghost_llm_requests_admitted{priority=\"interactive\"} 1"));
+        assert!(output.contains("os_ghost_llm_queue_depth{priority=\"background\"} 0"));
+        assert!(output.contains("os_ghost_llm_in_flight{provider=\"openai\"} 1"));
+    }
+
     #[test]
     fn test_trace() {
         let id = otel::start_trace("test_operation");
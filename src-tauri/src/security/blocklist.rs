@@ -0,0 +1,568 @@
+//! Offline Threat-Intelligence Blocklists
+//!
+//! Blocklists are read from a local directory (`blocklists/` in the data
+//! directory, or `[blocklists] dir` in config.toml), so lookups work without
+//! network access. Each file is one list, named after its file stem:
+//! - hosts files (`0.0.0.0 evil.example`)
+//! - plain domain or IP lists (one per line; `*.` / `||...^` wrappers are
+//!   stripped)
+//! - CSV dumps (`.csv`, URLhaus or PhishTank style); the `url`, `domain` or
+//!   `host` column is used
+//!
+//! Entries are kept in sorted, deduplicated vectors and looked up by binary
+//! search. A listed domain also matches its subdomains; listed URLs match
+//! exactly. The directory is watched and reloaded once writes to it settle.
+
+use crate::data::workspace_context;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// Default directory name inside the data directory
+pub const BLOCKLIST_DIR: &str = "blocklists";
+
+/// File extensions read as blocklists (files without an extension are read too)
+const LIST_EXTENSIONS: &[&str] = &["txt", "hosts", "list", "domains", "csv"];
+
+/// Hostnames in hosts files that are not blocklist entries
+const HOSTS_RESERVED: &[&str] = &[
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "0.0.0.0",
+];
+
+/// Quiet period after a change before the directory is re-read, so a large
+/// dump being written is parsed once
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(500);
+
+static WATCH_STARTED: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    static ref BLOCKLISTS: RwLock<BlocklistSet> = RwLock::new(BlocklistSet::default());
+}
+
+/// What a blocklist entry matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HitKind {
+    /// The URL's host or a parent domain is listed
+    Domain,
+    /// The exact URL is listed
+    Url,
+}
+
+/// A URL found on a blocklist
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlocklistHit {
+    /// List name (file stem)
+    pub list: String,
+    /// The listed entry that matched
+    pub entry: String,
+    pub kind: HitKind,
+}
+
+/// Entry counts for one loaded list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlocklistSummary {
+    pub name: String,
+    pub domains: usize,
+    pub urls: usize,
+}
+
+/// Loaded lists and the files that failed to load
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlocklistStatus {
+    pub enabled: bool,
+    pub dir: Option<String>,
+    pub lists: Vec<BlocklistSummary>,
+    pub errors: Vec<String>,
+}
+
+/// One blocklist file
+#[derive(Debug, Clone, Default)]
+pub struct Blocklist {
+    name: String,
+    domains: Vec<Box<str>>,
+    urls: Vec<Box<str>>,
+}
+
+/// Unquote one CSV line (`"a","b ""c""",d`)
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Position of the `url` / `domain` / `host` column in a CSV header
+fn entry_column(fields: &[String]) -> Option<usize> {
+    fields.iter().position(|field| {
+        matches!(
+            field.trim().to_lowercase().as_str(),
+            "url" | "domain" | "host"
+        )
+    })
+}
+
+/// Normalized form used for URL entries and lookups
+fn normalize_url(url: &str) -> String {
+    match url::Url::parse(url.trim()) {
+        Ok(mut parsed) => {
+            parsed.set_fragment(None);
+            parsed.as_str().trim_end_matches('/').to_string()
+        }
+        Err(_) => url.trim().trim_end_matches('/').to_lowercase(),
+    }
+}
+
+/// Domain entry without adblock-style wrappers, or None if it isn't a domain
+fn normalize_domain(entry: &str) -> Option<String> {
+    let domain = entry
+        .trim()
+        .trim_start_matches("||")
+        .trim_start_matches("*.")
+        .trim_end_matches('^')
+        .trim_end_matches('.')
+        .to_lowercase();
+    let valid = !domain.is_empty()
+        && !HOSTS_RESERVED.contains(&domain.as_str())
+        && domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':'));
+    valid.then_some(domain)
+}
+
+fn sorted(entries: Vec<String>) -> Vec<Box<str>> {
+    let mut entries: Vec<Box<str>> = entries.into_iter().map(String::into_boxed_str).collect();
+    entries.sort_unstable();
+    entries.dedup();
+    entries.shrink_to_fit();
+    entries
+}
+
+impl Blocklist {
+    /// Parse a list; `csv` selects CSV dumps over hosts/plain-domain text
+    pub fn parse(name: &str, source: &str, csv: bool) -> Self {
+        let mut domains = Vec::new();
+        let mut urls = Vec::new();
+        let mut add = |entry: &str| {
+            if entry.contains("://") {
+                urls.push(normalize_url(entry));
+            } else if let Some(domain) = normalize_domain(entry) {
+                domains.push(domain);
+            }
+        };
+
+        if csv {
+            let mut column = None;
+            for line in source.lines() {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                // URLhaus puts its header in a comment
+                if let Some(comment) = line.strip_prefix('#') {
+                    if column.is_none() {
+                        column = entry_column(&split_csv(comment.trim()));
+                    }
+                    continue;
+                }
+                let fields = split_csv(line);
+                if column.is_none() {
+                    if let Some(found) = entry_column(&fields) {
+                        column = Some(found);
+                        continue;
+                    }
+                }
+                match column {
+                    Some(index) => {
+                        if let Some(field) = fields.get(index) {
+                            add(field);
+                        }
+                    }
+                    None => {
+                        if let Some(field) = fields.iter().find(|f| f.contains("://")) {
+                            add(field);
+                        }
+                    }
+                }
+            }
+        } else {
+            for line in source.lines() {
+                let line = line.split('#').next().unwrap_or_default().trim();
+                if line.is_empty() || line.starts_with('!') {
+                    continue;
+                }
+                let mut tokens = line.split_whitespace().peekable();
+                let first = tokens.next().unwrap_or_default();
+                if first.parse::<IpAddr>().is_ok() && tokens.peek().is_some() {
+                    // hosts file: address followed by hostnames
+                    for host in tokens {
+                        add(host);
+                    }
+                } else {
+                    add(first);
+                }
+            }
+        }
+
+        Self {
+            name: name.to_string(),
+            domains: sorted(domains),
+            urls: sorted(urls),
+        }
+    }
+
+    fn contains_domain(&self, domain: &str) -> bool {
+        self.domains
+            .binary_search_by(|entry| (**entry).cmp(domain))
+            .is_ok()
+    }
+
+    fn contains_url(&self, url: &str) -> bool {
+        self.urls
+            .binary_search_by(|entry| (**entry).cmp(url))
+            .is_ok()
+    }
+
+    pub fn summary(&self) -> BlocklistSummary {
+        BlocklistSummary {
+            name: self.name.clone(),
+            domains: self.domains.len(),
+            urls: self.urls.len(),
+        }
+    }
+}
+
+/// All loaded blocklists
+#[derive(Debug, Clone, Default)]
+pub struct BlocklistSet {
+    lists: Vec<Blocklist>,
+}
+
+impl BlocklistSet {
+    pub fn new(lists: Vec<Blocklist>) -> Self {
+        Self { lists }
+    }
+
+    /// Load every list file in `dir`
+    pub fn load(dir: &Path) -> (Self, Vec<String>) {
+        let mut lists = Vec::new();
+        let mut errors = Vec::new();
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| is_list_file(path))
+                .collect(),
+            Err(e) => return (Self::default(), vec![format!("{}: {}", dir.display(), e)]),
+        };
+        paths.sort();
+
+        for path in paths {
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let csv = path.extension().is_some_and(|ext| ext == "csv");
+            match std::fs::read(&path) {
+                Ok(bytes) => lists.push(Blocklist::parse(
+                    &name,
+                    &String::from_utf8_lossy(&bytes),
+                    csv,
+                )),
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        (Self { lists }, errors)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lists.is_empty()
+    }
+
+    pub fn summaries(&self) -> Vec<BlocklistSummary> {
+        self.lists.iter().map(Blocklist::summary).collect()
+    }
+
+    /// Lists containing the URL, its host or one of the host's parent domains
+    pub fn check(&self, url: &str) -> Vec<BlocklistHit> {
+        let parsed = url::Url::parse(url).ok();
+        let host = parsed
+            .as_ref()
+            .and_then(|p| p.host_str())
+            .map(|h| h.trim_matches(['[', ']']).to_lowercase());
+        let normalized = normalize_url(url);
+
+        // evil.example matches a.b.evil.example, but IPs only match exactly
+        let mut candidates = Vec::new();
+        if let Some(host) = host {
+            if host.parse::<IpAddr>().is_ok() {
+                candidates.push(host);
+            } else {
+                let labels: Vec<&str> = host.split('.').collect();
+                for start in 0..labels.len().saturating_sub(1).max(1) {
+                    candidates.push(labels[start..].join("."));
+                }
+            }
+        }
+
+        let mut hits = Vec::new();
+        for list in &self.lists {
+            if list.contains_url(&normalized) {
+                hits.push(BlocklistHit {
+                    list: list.name.clone(),
+                    entry: normalized.clone(),
+                    kind: HitKind::Url,
+                });
+            } else if let Some(domain) = candidates.iter().find(|d| list.contains_domain(d)) {
+                hits.push(BlocklistHit {
+                    list: list.name.clone(),
+                    entry: domain.clone(),
+                    kind: HitKind::Domain,
+                });
+            }
+        }
+        hits
+    }
+}
+
+fn is_list_file(path: &Path) -> bool {
+    let hidden = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    let listed = match path.extension() {
+        Some(ext) => LIST_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()),
+        None => true,
+    };
+    path.is_file() && !hidden && listed
+}
+
+/// Blocklist directory, or None when blocklists are disabled
+pub fn blocklist_dir() -> Option<PathBuf> {
+    let config = crate::config::toml_config::load_toml_config().blocklists;
+    if !config.enabled {
+        return None;
+    }
+    Some(
+        config
+            .dir
+            .map(PathBuf::from)
+            .unwrap_or_else(|| workspace_context::get_data_dir().join(BLOCKLIST_DIR)),
+    )
+}
+
+/// Lists containing `url` (see `BlocklistSet::check`)
+pub fn check_url(url: &str) -> Vec<BlocklistHit> {
+    BLOCKLISTS
+        .read()
+        .map(|lists| lists.check(url))
+        .unwrap_or_default()
+}
+
+/// Load the blocklists and reload them whenever the directory changes
+pub fn init_blocklists() {
+    let Some(dir) = blocklist_dir() else {
+        tracing::info!("Threat blocklists disabled");
+        return;
+    };
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        tracing::warn!("Failed to create blocklist directory {:?}: {}", dir, e);
+    }
+    reload_blocklists();
+    ensure_watcher(dir);
+}
+
+/// Re-read the blocklist directory, replacing the loaded lists
+pub fn reload_blocklists() -> BlocklistStatus {
+    let dir = blocklist_dir();
+    let (lists, errors) = match &dir {
+        Some(dir) => BlocklistSet::load(dir),
+        None => (BlocklistSet::default(), Vec::new()),
+    };
+    for error in &errors {
+        tracing::warn!("Skipping blocklist: {}", error);
+    }
+    let summaries = lists.summaries();
+    tracing::info!(
+        "Loaded {} blocklist(s) with {} entries",
+        summaries.len(),
+        summaries.iter().map(|s| s.domains + s.urls).sum::<usize>()
+    );
+    if let Ok(mut loaded) = BLOCKLISTS.write() {
+        *loaded = lists;
+    }
+    BlocklistStatus {
+        enabled: dir.is_some(),
+        dir: dir.map(|d| d.to_string_lossy().to_string()),
+        lists: summaries,
+        errors,
+    }
+}
+
+fn ensure_watcher(dir: PathBuf) {
+    if WATCH_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher: RecommendedWatcher = match notify::recommended_watcher(tx) {
+            Ok(w) => w,
+            Err(e) => {
+                tracing::warn!("Failed to watch blocklists: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            tracing::warn!("Failed to watch {:?}: {}", dir, e);
+            return;
+        }
+
+        let mut reload_at: Option<Instant> = None;
+        loop {
+            let event = match reload_at {
+                Some(at) => match rx.recv_timeout(at.saturating_duration_since(Instant::now())) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => {
+                        reload_at = None;
+                        reload_blocklists();
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => return,
+                },
+                None => match rx.recv() {
+                    Ok(event) => event,
+                    Err(_) => return,
+                },
+            };
+            if matches!(&event, Ok(event) if !event.kind.is_access()) {
+                reload_at = Some(Instant::now() + RELOAD_DEBOUNCE);
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn reload_threat_blocklists() -> BlocklistStatus {
+    reload_blocklists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS: &str = "\
+# Example hosts blocklist
+127.0.0.1 localhost
+0.0.0.0 evil.example tracker.example # trailing comment
+0.0.0.0 ads.example
+";
+
+    const URLHAUS: &str = "\
+################################################################
+# abuse.ch URLhaus Database Dump (CSV)
+################################################################
+# id,dateadded,url,url_status,last_online,threat,tags,urlhaus_link,reporter
+\"1\",\"2024-01-01 00:00:00\",\"http://203.0.113.7:8080/bins/payload\",\"online\",\"\",\"malware_download\",\"elf\",\"https://urlhaus.abuse.ch/url/1/\",\"someone\"
+\"2\",\"2024-01-01 00:00:00\",\"https://files.example/drop/x.exe\",\"offline\",\"\",\"malware_download\",\"exe\",\"https://urlhaus.abuse.ch/url/2/\",\"someone\"
+";
+
+    #[test]
+    fn test_parse_formats() {
+        let hosts = Blocklist::parse("hosts", HOSTS, false);
+        assert_eq!(hosts.summary().domains, 3);
+        assert!(hosts.contains_domain("evil.example"));
+        assert!(!hosts.contains_domain("localhost"));
+
+        let plain = Blocklist::parse(
+            "plain",
+            "! comment\n||phish.example^\n*.bad.example\n",
+            false,
+        );
+        assert!(plain.contains_domain("phish.example"));
+        assert!(plain.contains_domain("bad.example"));
+
+        let ips = Blocklist::parse("ips", "203.0.113.9\n2001:db8::1\n0.0.0.0\n", false);
+        assert_eq!(ips.summary().domains, 2);
+        assert!(ips.contains_domain("203.0.113.9"));
+        assert!(ips.contains_domain("2001:db8::1"));
+
+        let urlhaus = Blocklist::parse("urlhaus", URLHAUS, true);
+        assert_eq!(urlhaus.summary().urls, 2);
+        assert_eq!(urlhaus.summary().domains, 0);
+
+        let phishtank = Blocklist::parse(
+            "phishtank",
+            "phish_id,url,phish_detail_url\n42,http://login.phish.example/,https://phishtank.example/42\n",
+            true,
+        );
+        assert!(phishtank.contains_url("http://login.phish.example"));
+    }
+
+    #[test]
+    fn test_check_matches_subdomains_and_urls() {
+        let lists = BlocklistSet::new(vec![
+            Blocklist::parse("hosts", HOSTS, false),
+            Blocklist::parse("urlhaus", URLHAUS, true),
+        ]);
+
+        let hits = lists.check("https://cdn.evil.example/script.js");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].list, "hosts");
+        assert_eq!(hits[0].entry, "evil.example");
+        assert_eq!(hits[0].kind, HitKind::Domain);
+
+        let ips = BlocklistSet::new(vec![Blocklist::parse("ips", "203.0.113.9\n", false)]);
+        assert_eq!(
+            ips.check("http://203.0.113.9/login")[0].kind,
+            HitKind::Domain
+        );
+        assert!(ips.check("http://203.0.113.90/").is_empty());
+
+        let hits = lists.check("http://203.0.113.7:8080/bins/payload");
+        assert_eq!(hits[0].list, "urlhaus");
+        assert_eq!(hits[0].kind, HitKind::Url);
+
+        // Other paths on a listed URL's host are not blocked
+        assert!(lists.check("https://files.example/readme").is_empty());
+        assert!(lists.check("https://example.com/").is_empty());
+        assert!(lists.check("not a url").is_empty());
+    }
+
+    #[test]
+    fn test_load_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("malware.hosts"), HOSTS).unwrap();
+        std::fs::write(dir.path().join("urlhaus.csv"), URLHAUS).unwrap();
+        std::fs::write(dir.path().join("README.md"), "not a list").unwrap();
+
+        let (lists, errors) = BlocklistSet::load(dir.path());
+        assert!(errors.is_empty());
+        let names: Vec<String> = lists.summaries().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["malware", "urlhaus"]);
+    }
+}
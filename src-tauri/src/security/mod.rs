@@ -4,10 +4,13 @@
 //! - Leak detection for credential exfiltration
 //! - HTTP endpoint allowlisting
 //! - Tool output sanitization
+//! - Offline threat-intelligence blocklists
 
+pub mod blocklist;
 pub mod leak_detector;
 pub mod http_allowlist;
 
+pub use blocklist::{BlocklistHit, BlocklistStatus, HitKind};
 pub use leak_detector::{
     LeakMatch, LeakScanResult, LeakSeverity, LeakDetectionConfig, scan_for_leaks, 
     scan_request, scan_response, sanitize_content,
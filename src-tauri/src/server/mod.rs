@@ -137,15 +137,26 @@ async fn health_handler(
     axum::extract::State(state): axum::extract::State<Arc<RwLock<ServerState>>>,
 ) -> impl axum::response::IntoResponse {
     let state = state.read().await;
+    let breakers = crate::ai::circuit_breaker::breaker_snapshots();
+    // Degraded while any AI backend is tripped
+    let status = if breakers
+        .iter()
+        .any(|b| b.state != crate::ai::circuit_breaker::BreakerState::Closed)
+    {
+        "degraded"
+    } else {
+        "healthy"
+    };
 
     axum::Json(serde_json::json!({
-        "status": "healthy",
+        "status": status,
         "version": env!("CARGO_PKG_VERSION"),
         "connected": state.connected,
         "active_agents": state.active_agents.len(),
         "pending_actions": state.pending_actions.len(),
         "workflows_count": state.workflows.len(),
         "memory_entries": state.memory_entries,
+        "circuit_breakers": breakers,
         "timestamp": chrono::Utc::now().to_rfc3339(),
     }))
 }
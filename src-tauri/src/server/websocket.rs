@@ -5,7 +5,9 @@
 //! - Action approval notifications
 //! - Workflow recording progress
 //! - System events and logs
+//! - Streamed AI completion deltas
 
+use crate::ai::providers::streaming::{self, StreamDelta};
 use axum::{
     extract::ws::{Message, WebSocket, WebSocketUpgrade},
     response::IntoResponse,
@@ -38,6 +40,8 @@ pub enum WsEvent {
     LogMessage(LogData),
     /// Error occurred
     Error(ErrorData),
+    /// Streamed completion text
+    CompletionDelta(StreamDelta),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -142,9 +146,31 @@ async fn handle_socket(mut socket: WebSocket) {
         return;
     }
 
+    // Streamed completion deltas are forwarded to every connected client
+    let mut deltas = streaming::subscribe_deltas();
+
     // Main WebSocket loop
     loop {
-        match socket.recv().await {
+        let incoming = tokio::select! {
+            delta = deltas.recv() => {
+                match delta {
+                    Ok(delta) => {
+                        if let Err(e) = send_event(&mut socket, WsEvent::CompletionDelta(delta)).await {
+                            error!("Failed to forward completion delta: {}", e);
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("WebSocket client lagged, skipped {} deltas", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {}
+                }
+                continue;
+            }
+            msg = socket.recv() => msg,
+        };
+
+        match incoming {
             Some(Ok(msg)) => {
                 match msg {
                     Message::Text(text) => {
@@ -183,6 +209,11 @@ async fn handle_socket(mut socket: WebSocket) {
                                         info!("Client denied action: {}", action_id);
                                         // TODO: Trigger action denial
                                     }
+                                    ClientMessage::Cancel { request_id } => {
+                                        if !streaming::cancel_stream(&request_id) {
+                                            debug!("No active stream to cancel: {}", request_id);
+                                        }
+                                    }
                                 }
                             }
                             Err(e) => {
@@ -236,10 +267,22 @@ async fn send_event(socket: &mut WebSocket, event: WsEvent) -> Result<(), axum::
 #[serde(rename_all = "snake_case")]
 pub enum ClientMessage {
     Ping,
-    Subscribe { channel: String },
-    Execute { task: String },
-    Approve { action_id: String },
-    Deny { action_id: String },
+    Subscribe {
+        channel: String,
+    },
+    Execute {
+        task: String,
+    },
+    Approve {
+        action_id: String,
+    },
+    Deny {
+        action_id: String,
+    },
+    /// Cancel an in-flight completion stream
+    Cancel {
+        request_id: String,
+    },
 }
 
 #[cfg(test)]
@@ -274,4 +317,11 @@ mod tests {
         assert!(json.contains("execute"));
         assert!(json.contains("Book a flight"));
     }
+
+    #[test]
+    fn test_cancel_message_deserialization() {
+        let msg: ClientMessage =
+            serde_json::from_str(r#"{"type":"cancel","request_id":"abc"}"#).unwrap();
+        assert!(matches!(msg, ClientMessage::Cancel { request_id } if request_id == "abc"));
+    }
 }
@@ -0,0 +1,544 @@
+//! DAG Workflow - Execute a plan's sub-goals as a dependency graph
+//!
+//! Sub-goals declare the steps they depend on (`SubGoal::depends_on`). Every
+//! sub-goal whose prerequisites are achieved is ready; ready sub-goals run
+//! concurrently through a `ParallelWorkflow` bounded by `max_concurrency`. When
+//! a sub-goal fails the planner revises the plan (`PlannerAgent::revise_plan`)
+//! and the new graph is executed, up to `max_revisions` times. Each node's status
+//! is recorded on the activity timeline as soon as it is settled.
+
+use super::parallel::ParallelWorkflow;
+use super::Workflow;
+use crate::agents::planner::PlannerAgent;
+use crate::agents::traits::{
+    Agent, AgentContext, AgentOutput, AgentResult, NextAction, PlanningContext, SubGoal,
+};
+use crate::data::timeline::{record_timeline_event, TimelineEntryType, TimelineStatus};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Default sub-goals run at once
+const DEFAULT_MAX_CONCURRENCY: usize = 3;
+/// Default plan revisions after failures
+const DEFAULT_MAX_REVISIONS: usize = 2;
+
+/// Execution state of a sub-goal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeStatus {
+    Pending,
+    Done,
+    Failed,
+    /// A prerequisite failed, so the sub-goal never ran
+    Blocked,
+}
+
+/// Final status of one sub-goal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeReport {
+    pub step: usize,
+    pub description: String,
+    pub status: NodeStatus,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Plan revision the sub-goal belongs to (0 = original plan)
+    pub revision: usize,
+}
+
+/// Result of executing a plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanExecution {
+    /// The last plan executed, with achieved sub-goals marked
+    pub planning: PlanningContext,
+    /// Status of every sub-goal of every executed plan, in execution order
+    pub nodes: Vec<NodeReport>,
+    pub outputs: Vec<AgentOutput>,
+    pub revisions: usize,
+}
+
+impl PlanExecution {
+    /// Whether every sub-goal of the last plan was achieved
+    pub fn completed(&self) -> bool {
+        self.planning.sub_goals.iter().all(|goal| goal.achieved)
+    }
+}
+
+/// Check that dependencies name existing steps and contain no cycle
+pub fn validate_graph(plan: &PlanningContext) -> Result<(), String> {
+    let steps: HashSet<usize> = plan.sub_goals.iter().map(|goal| goal.step).collect();
+    if steps.len() != plan.sub_goals.len() {
+        return Err("Plan has duplicate step numbers".to_string());
+    }
+    for goal in &plan.sub_goals {
+        if let Some(missing) = goal.depends_on.iter().find(|dep| !steps.contains(dep)) {
+            return Err(format!(
+                "Step {} depends on unknown step {}",
+                goal.step, missing
+            ));
+        }
+    }
+
+    // Kahn's algorithm: whatever can't be ordered is part of a cycle
+    let mut remaining: Vec<&SubGoal> = plan.sub_goals.iter().collect();
+    let mut ordered: HashSet<usize> = HashSet::new();
+    while !remaining.is_empty() {
+        let before = remaining.len();
+        remaining.retain(|goal| {
+            if goal.depends_on.iter().all(|dep| ordered.contains(dep)) {
+                ordered.insert(goal.step);
+                false
+            } else {
+                true
+            }
+        });
+        if remaining.len() == before {
+            let steps: Vec<String> = remaining.iter().map(|g| g.step.to_string()).collect();
+            return Err(format!(
+                "Steps {} form a dependency cycle",
+                steps.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Runs the worker agent on a single sub-goal, never failing outright
+struct SubGoalAgent {
+    name: String,
+    goal: SubGoal,
+    worker: Arc<dyn Agent>,
+}
+
+impl SubGoalAgent {
+    /// Context focused on this sub-goal
+    fn focus(&self, context: &AgentContext) -> AgentContext {
+        let mut focused = context.clone();
+        focused.planning.sub_goals = vec![self.goal.clone()];
+        focused.planning.primary_keywords = self.goal.keywords.clone();
+        focused
+            .metadata
+            .insert("sub_goal".to_string(), self.goal.description.clone());
+        focused
+            .metadata
+            .insert("sub_goal_step".to_string(), self.goal.step.to_string());
+        if !self.goal.tools.is_empty() {
+            focused
+                .metadata
+                .insert("sub_goal_tools".to_string(), self.goal.tools.join(","));
+        }
+        focused
+    }
+}
+
+#[async_trait]
+impl Agent for SubGoalAgent {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.goal.description
+    }
+
+    fn can_handle(&self, context: &AgentContext) -> bool {
+        self.worker.can_handle(&self.focus(context))
+    }
+
+    async fn process(&self, context: &AgentContext) -> AgentResult<AgentOutput> {
+        let mut output = match self.worker.process(&self.focus(context)).await {
+            Ok(output) => output,
+            Err(e) => AgentOutput {
+                agent_name: self.worker.name().to_string(),
+                result: e.to_string(),
+                confidence: 0.0,
+                next_action: None,
+                data: HashMap::from([("sub_goal_failed".to_string(), serde_json::json!(true))]),
+            },
+        };
+        if matches!(output.next_action, Some(NextAction::Abort)) {
+            output
+                .data
+                .insert("sub_goal_failed".to_string(), serde_json::json!(true));
+        }
+        output.data.insert(
+            "sub_goal_step".to_string(),
+            serde_json::json!(self.goal.step),
+        );
+        Ok(output)
+    }
+}
+
+/// Workflow executing a plan's sub-goals in dependency order
+pub struct DagWorkflow {
+    name: String,
+    planner: Arc<PlannerAgent>,
+    /// Agent run on each sub-goal
+    worker: Arc<dyn Agent>,
+    max_concurrency: usize,
+    max_revisions: usize,
+    /// Record node status on the activity timeline
+    timeline: bool,
+}
+
+impl DagWorkflow {
+    pub fn new(name: &str, planner: Arc<PlannerAgent>, worker: Arc<dyn Agent>) -> Self {
+        Self {
+            name: name.to_string(),
+            planner,
+            worker,
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            max_revisions: DEFAULT_MAX_REVISIONS,
+            timeline: true,
+        }
+    }
+
+    /// Set the number of sub-goals run at once
+    pub fn with_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Set how often a failing plan is revised before giving up
+    pub fn with_max_revisions(mut self, max_revisions: usize) -> Self {
+        self.max_revisions = max_revisions;
+        self
+    }
+
+    /// Don't record node status on the timeline
+    pub fn without_timeline(mut self) -> Self {
+        self.timeline = false;
+        self
+    }
+
+    /// Plan (unless the context has a plan), execute, and revise on failure
+    pub async fn run(&self, context: &AgentContext) -> AgentResult<PlanExecution> {
+        let mut current = context.clone();
+        if current.planning.sub_goals.is_empty() {
+            current.planning = self.planner.analyze_puzzle(&current).await?;
+        }
+
+        let mut nodes = Vec::new();
+        let mut outputs = Vec::new();
+        let mut revisions = 0;
+        loop {
+            let failure = match validate_graph(&current.planning) {
+                Ok(()) => {
+                    let (reports, mut run_outputs) =
+                        self.execute_plan(&mut current, revisions).await?;
+                    outputs.append(&mut run_outputs);
+                    let failure = reports
+                        .iter()
+                        .find(|node| node.status == NodeStatus::Failed)
+                        .map(|node| {
+                            format!(
+                                "Sub-goal {} ({}) failed: {}",
+                                node.step,
+                                node.description,
+                                node.error.as_deref().unwrap_or("unknown error")
+                            )
+                        });
+                    nodes.extend(reports);
+                    failure
+                }
+                Err(e) => Some(format!("Invalid plan: {}", e)),
+            };
+
+            let Some(reason) = failure else { break };
+            if revisions >= self.max_revisions {
+                tracing::warn!(
+                    "{}: giving up after {} revisions: {}",
+                    self.name,
+                    revisions,
+                    reason
+                );
+                break;
+            }
+            revisions += 1;
+            tracing::info!("{}: revising plan ({})", self.name, reason);
+            current.planning = self.planner.revise_plan(&current, &reason).await?;
+        }
+
+        Ok(PlanExecution {
+            planning: current.planning,
+            nodes,
+            outputs,
+            revisions,
+        })
+    }
+
+    /// Run every reachable sub-goal of `context.planning`, marking achieved ones
+    async fn execute_plan(
+        &self,
+        context: &mut AgentContext,
+        revision: usize,
+    ) -> AgentResult<(Vec<NodeReport>, Vec<AgentOutput>)> {
+        let mut status: HashMap<usize, NodeStatus> = context
+            .planning
+            .sub_goals
+            .iter()
+            .map(|goal| {
+                let initial = if goal.achieved {
+                    NodeStatus::Done
+                } else {
+                    NodeStatus::Pending
+                };
+                (goal.step, initial)
+            })
+            .collect();
+        let mut errors: HashMap<usize, String> = HashMap::new();
+        let mut outputs = Vec::new();
+
+        loop {
+            // Sub-goals behind a failed prerequisite can never run
+            let mut blocked = true;
+            while blocked {
+                blocked = false;
+                for goal in &context.planning.sub_goals {
+                    if status[&goal.step] != NodeStatus::Pending {
+                        continue;
+                    }
+                    let Some(dep) = goal.depends_on.iter().find(|dep| {
+                        matches!(status[*dep], NodeStatus::Failed | NodeStatus::Blocked)
+                    }) else {
+                        continue;
+                    };
+                    let error = format!("Step {} did not complete", dep);
+                    self.report(goal, NodeStatus::Blocked, Some(&error));
+                    errors.insert(goal.step, error);
+                    status.insert(goal.step, NodeStatus::Blocked);
+                    blocked = true;
+                }
+            }
+
+            let ready: Vec<SubGoal> = context
+                .planning
+                .sub_goals
+                .iter()
+                .filter(|goal| {
+                    status[&goal.step] == NodeStatus::Pending
+                        && goal
+                            .depends_on
+                            .iter()
+                            .all(|dep| status[dep] == NodeStatus::Done)
+                })
+                .cloned()
+                .collect();
+            if ready.is_empty() {
+                break;
+            }
+
+            tracing::debug!(
+                "{}: running sub-goals {:?}",
+                self.name,
+                ready.iter().map(|g| g.step).collect::<Vec<_>>()
+            );
+            let mut batch = ParallelWorkflow::with_concurrency("SubGoals", self.max_concurrency);
+            for goal in &ready {
+                batch = batch.add_agent(Arc::new(SubGoalAgent {
+                    name: format!("{}#{}", self.worker.name(), goal.step),
+                    goal: goal.clone(),
+                    worker: self.worker.clone(),
+                }));
+            }
+            // Sub-goal agents report failures in their output rather than erring
+            let results = batch.execute(context).await?;
+
+            for goal in &ready {
+                let output = results.iter().find(|output| {
+                    output.data.get("sub_goal_step").and_then(|v| v.as_u64())
+                        == Some(goal.step as u64)
+                });
+                // No output means the worker declined the sub-goal
+                let failed = output.map_or(true, |output| {
+                    output.data.get("sub_goal_failed").and_then(|v| v.as_bool()) == Some(true)
+                });
+                if failed {
+                    let error = output
+                        .map(|output| output.result.clone())
+                        .unwrap_or_else(|| "worker can't handle this sub-goal".to_string());
+                    self.report(goal, NodeStatus::Failed, Some(&error));
+                    errors.insert(goal.step, error);
+                    status.insert(goal.step, NodeStatus::Failed);
+                } else {
+                    self.report(goal, NodeStatus::Done, None);
+                    status.insert(goal.step, NodeStatus::Done);
+                }
+            }
+            outputs.extend(results);
+        }
+
+        let mut reports = Vec::with_capacity(context.planning.sub_goals.len());
+        for goal in &mut context.planning.sub_goals {
+            let node_status = status[&goal.step];
+            if node_status == NodeStatus::Done {
+                goal.achieved = true;
+            }
+            reports.push(NodeReport {
+                step: goal.step,
+                description: goal.description.clone(),
+                status: node_status,
+                error: errors.remove(&goal.step),
+                revision,
+            });
+        }
+        Ok((reports, outputs))
+    }
+
+    /// Record a sub-goal's settled status on the timeline
+    fn report(&self, goal: &SubGoal, status: NodeStatus, error: Option<&str>) {
+        if !self.timeline {
+            return;
+        }
+        // A blocked sub-goal never ran; nothing denied it
+        let status = match status {
+            NodeStatus::Pending => TimelineStatus::Pending,
+            NodeStatus::Done => TimelineStatus::Executed,
+            NodeStatus::Failed => TimelineStatus::Failed,
+            NodeStatus::Blocked => TimelineStatus::Info,
+        };
+        record_timeline_event(
+            &format!("Sub-goal {}: {}", goal.step, goal.description),
+            error.map(str::to_string),
+            TimelineEntryType::System,
+            status,
+        );
+    }
+}
+
+#[async_trait]
+impl Workflow for DagWorkflow {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn execute(&self, context: &AgentContext) -> AgentResult<Vec<AgentOutput>> {
+        Ok(self.run(context).await?.outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::traits::AgentError;
+    use crate::ai::ai_provider::SmartAiRouter;
+    use crate::ai::providers::fixture::{Cassette, FixtureMatch};
+    use std::sync::Mutex;
+
+    fn goal(step: usize, depends_on: &[usize]) -> SubGoal {
+        SubGoal {
+            step,
+            description: format!("step {}", step),
+            keywords: Vec::new(),
+            achieved: false,
+            confidence: 0.0,
+            depends_on: depends_on.to_vec(),
+            tools: Vec::new(),
+        }
+    }
+
+    fn plan(goals: Vec<SubGoal>) -> PlanningContext {
+        PlanningContext {
+            sub_goals: goals,
+            ..Default::default()
+        }
+    }
+
+    /// Records the order sub-goals ran in and fails the given step
+    struct Worker {
+        fail_step: Option<usize>,
+        ran: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl Agent for Worker {
+        fn name(&self) -> &str {
+            "Worker"
+        }
+
+        fn description(&self) -> &str {
+            "test worker"
+        }
+
+        async fn process(&self, context: &AgentContext) -> AgentResult<AgentOutput> {
+            let step: usize = context.metadata["sub_goal_step"].parse().unwrap();
+            self.ran.lock().unwrap().push(step);
+            if self.fail_step == Some(step) {
+                return Err(AgentError::ProcessingError("not found".to_string()));
+            }
+            Ok(AgentOutput {
+                agent_name: "Worker".to_string(),
+                result: format!("did {}", step),
+                confidence: 0.5,
+                next_action: None,
+                data: HashMap::new(),
+            })
+        }
+    }
+
+    fn workflow(worker: Arc<Worker>) -> DagWorkflow {
+        // The planner answers from an empty cassette, so revisions use its fallback plan
+        let router = Arc::new(SmartAiRouter::replaying(
+            Arc::new(Cassette::from_entries(Vec::new())),
+            FixtureMatch::Lenient,
+        ));
+        DagWorkflow::new("Test", Arc::new(PlannerAgent::new(router)), worker)
+            .with_max_revisions(0)
+            .without_timeline()
+    }
+
+    #[test]
+    fn test_validate_graph() {
+        assert!(validate_graph(&plan(vec![goal(1, &[]), goal(2, &[1]), goal(3, &[1, 2])])).is_ok());
+        assert!(validate_graph(&plan(vec![goal(1, &[4])]))
+            .unwrap_err()
+            .contains("unknown step 4"));
+        assert!(
+            validate_graph(&plan(vec![goal(1, &[2]), goal(2, &[1]), goal(3, &[])]))
+                .unwrap_err()
+                .contains("cycle")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_runs_in_dependency_order() {
+        let worker = Arc::new(Worker {
+            fail_step: None,
+            ran: Mutex::new(Vec::new()),
+        });
+        let context = AgentContext {
+            planning: plan(vec![goal(3, &[1, 2]), goal(1, &[]), goal(2, &[])]),
+            ..Default::default()
+        };
+
+        let execution = workflow(worker.clone()).run(&context).await.unwrap();
+        assert!(execution.completed());
+        assert_eq!(execution.outputs.len(), 3);
+        let ran = worker.ran.lock().unwrap().clone();
+        assert_eq!(ran.last(), Some(&3));
+    }
+
+    #[tokio::test]
+    async fn test_failure_blocks_dependents() {
+        let worker = Arc::new(Worker {
+            fail_step: Some(1),
+            ran: Mutex::new(Vec::new()),
+        });
+        let context = AgentContext {
+            planning: plan(vec![goal(1, &[]), goal(2, &[]), goal(3, &[1])]),
+            ..Default::default()
+        };
+
+        let execution = workflow(worker.clone()).run(&context).await.unwrap();
+        assert!(!execution.completed());
+        let status: HashMap<usize, NodeStatus> =
+            execution.nodes.iter().map(|n| (n.step, n.status)).collect();
+        assert_eq!(status[&1], NodeStatus::Failed);
+        assert_eq!(status[&2], NodeStatus::Done);
+        assert_eq!(status[&3], NodeStatus::Blocked);
+        let blocked = execution.nodes.iter().find(|n| n.step == 3).unwrap();
+        assert_eq!(blocked.error.as_deref(), Some("Step 1 did not complete"));
+        assert!(!worker.ran.lock().unwrap().contains(&3));
+    }
+}
@@ -7,6 +7,7 @@
 //! - **Loop**: Repeat until condition met, with self-correction
 //! - **Reflection**: Generator-Critic loop for quality control
 //! - **Planning**: Dynamic goal decomposition and strategy adaptation
+//! - **DAG**: Execute a plan's sub-goals in dependency order, in parallel where possible
 //!
 //! ## Cancellation Support
 //! Workflows support graceful cancellation via `CancellationToken`:
@@ -23,6 +24,7 @@
 //! cancel_handle.cancel();
 //! ```
 
+pub mod dag;
 pub mod loop_agent;
 pub mod parallel;
 pub mod planning;
@@ -31,6 +33,7 @@ pub mod reflection;
 pub mod replay;
 pub mod sequential;
 
+pub use dag::{DagWorkflow, NodeReport, NodeStatus, PlanExecution};
 pub use loop_agent::{create_adaptive_loop, LoopWorkflow};
 pub use parallel::ParallelWorkflow;
 pub use planning::{create_intelligent_pipeline, PlanningWorkflow};
@@ -66,10 +69,37 @@ pub fn export_workflow_to_json(workflow: &RecordedWorkflow) -> String {
     exported.to_json()
 }
 
-use crate::agents::traits::{AgentContext, AgentError, AgentOutput, AgentResult};
+use crate::agents::traits::{Agent, AgentContext, AgentError, AgentOutput, AgentResult};
 use async_trait::async_trait;
 use tokio_util::sync::CancellationToken;
 
+/// Run the user agents declared to follow `agent_name` (see `agents::declarative`)
+///
+/// A failing user agent is logged and skipped. Returns true when one of them
+/// transferred control, which ends the pipeline.
+pub(crate) async fn run_user_agents_after(
+    agent_name: &str,
+    context: &AgentContext,
+    outputs: &mut Vec<AgentOutput>,
+) -> bool {
+    for agent in crate::agents::declarative::agents_after(agent_name) {
+        if !agent.can_handle(context) {
+            continue;
+        }
+        match agent.process(context).await {
+            Ok(output) => {
+                let transferred = output.transfer_target().is_some();
+                outputs.push(output);
+                if transferred {
+                    return true;
+                }
+            }
+            Err(e) => tracing::warn!("User agent {} failed: {}", agent.name(), e),
+        }
+    }
+    false
+}
+
 /// Workflow trait - defines a reusable execution pattern
 #[async_trait]
 pub trait Workflow: Send + Sync {
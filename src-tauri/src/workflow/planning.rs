@@ -7,7 +7,7 @@
 //! - Adapts workflow based on progress and difficulty
 //! - Integrates Planner, Observer, Verifier, and Narrator agents
 
-use super::Workflow;
+use super::{run_user_agents_after, Workflow};
 use crate::agents::planner::PlannerAgent;
 use crate::agents::traits::{
     Agent, AgentContext, AgentOutput, AgentResult, NextAction, SearchStrategy,
@@ -103,6 +103,9 @@ impl Workflow for PlanningWorkflow {
             current_context.planning.strategy,
             current_context.planning.sub_goals.len()
         );
+        if run_user_agents_after(self.planner.name(), &current_context, &mut outputs).await {
+            return Ok(outputs);
+        }
 
         // Step 2: Select agents based on strategy
         let agents = self.select_agents_for_strategy(&current_context.planning.strategy);
@@ -117,7 +120,7 @@ impl Workflow for PlanningWorkflow {
                 continue;
             }
 
-            let output = agent.process(&current_context).await?;
+            let mut output = agent.process(&current_context).await?;
 
             // Update context with output
             if let Some(proximity) = output.data.get("proximity") {
@@ -126,10 +129,22 @@ impl Workflow for PlanningWorkflow {
                 }
             }
 
-            // Check for puzzle solved
+            // Check for puzzle solved; the celebration below is the transfer
+            // to the narrator a solve asks for, so it isn't routed again
             let solved = matches!(output.next_action, Some(NextAction::PuzzleSolved));
+            if let (true, Some(narrator)) = (solved, &self.narrator) {
+                if output.transfer_target() == Some(narrator.name()) {
+                    output.data.remove("transfer_to");
+                }
+            }
 
-            outputs.push(output.clone());
+            // A transfer hands control to the target agent, skipping the rest
+            if output.transfer_target().is_some() {
+                outputs.push(output);
+                return Ok(outputs);
+            }
+
+            outputs.push(output);
 
             if solved {
                 // Run narrator for celebration if available
@@ -137,9 +152,13 @@ impl Workflow for PlanningWorkflow {
                     current_context.planning.strategy = SearchStrategy::Celebrate;
                     let celebration = narrator.process(&current_context).await?;
                     outputs.push(celebration);
+                    run_user_agents_after(narrator.name(), &current_context, &mut outputs).await;
                 }
                 break;
             }
+            if run_user_agents_after(agent.name(), &current_context, &mut outputs).await {
+                return Ok(outputs);
+            }
         }
 
         // Step 4: Run narrator if not already done
@@ -149,6 +168,7 @@ impl Workflow for PlanningWorkflow {
             {
                 let dialogue = narrator.process(&current_context).await?;
                 outputs.push(dialogue);
+                run_user_agents_after(narrator.name(), &current_context, &mut outputs).await;
             }
         }
 
@@ -6,13 +6,31 @@
 //! - Critic evaluates the output against criteria
 //! - If rejected, Generator refines based on feedback
 //! - Loop continues until approved or max iterations reached
+//!
+//! With `candidates > 1` it runs best-of-N instead: the generator produces N
+//! candidates concurrently at spread temperatures, the critic judges every pair,
+//! and the candidate winning the most comparisons is (optionally) refined.
 
 use super::Workflow;
 use crate::agents::critic::CriticAgent;
-use crate::agents::traits::{Agent, AgentContext, AgentOutput, AgentResult, NextAction};
+use crate::agents::traits::{
+    Agent, AgentContext, AgentError, AgentOutput, AgentResult, NextAction, PairwiseWinner,
+};
+use crate::ai::ai_provider::with_temperature_offset;
+use crate::monitoring::CandidateRecord;
 use async_trait::async_trait;
+use futures::future::join_all;
 use std::sync::Arc;
 
+/// Criteria the critic judges candidates by, most important first
+const DEFAULT_JUDGE_RUBRIC: &[&str] = &[
+    "Safe and appropriate for all audiences",
+    "Matches the expected mood",
+    "Guides the user without giving the answer away",
+    "Evocative and in character as a mysterious digital ghost",
+    "Concise",
+];
+
 /// Configuration for reflection workflow
 #[derive(Clone)]
 pub struct ReflectionConfig {
@@ -22,6 +40,15 @@ pub struct ReflectionConfig {
     pub delay_ms: u64,
     /// Whether to include all iterations in output
     pub include_history: bool,
+    /// Candidates generated for best-of-N selection (1 = serial loop)
+    pub candidates: usize,
+    /// Width of the temperature range candidates are spread across, centred
+    /// on the generator's own temperature
+    pub temperature_spread: f64,
+    /// Criteria the critic judges candidates by, most important first
+    pub judge_rubric: Vec<String>,
+    /// Run the critique-improve loop on the winning candidate
+    pub refine_winner: bool,
 }
 
 impl Default for ReflectionConfig {
@@ -30,7 +57,24 @@ impl Default for ReflectionConfig {
             max_iterations: 3,
             delay_ms: 100,
             include_history: false,
+            candidates: 1,
+            temperature_spread: 0.4,
+            judge_rubric: DEFAULT_JUDGE_RUBRIC.iter().map(|c| c.to_string()).collect(),
+            refine_winner: true,
+        }
+    }
+}
+
+impl ReflectionConfig {
+    /// Temperature offset for each candidate, evenly spaced across the spread
+    pub fn temperature_offsets(&self) -> Vec<f64> {
+        let n = self.candidates.max(1);
+        if n == 1 {
+            return vec![0.0];
         }
+        (0..n)
+            .map(|i| self.temperature_spread * (i as f64 / (n - 1) as f64 - 0.5))
+            .collect()
     }
 }
 
@@ -66,6 +110,178 @@ impl ReflectionWorkflow {
         self.config.max_iterations = max;
         self
     }
+
+    /// Select among `candidates` generations instead of refining serially
+    pub fn best_of(mut self, candidates: usize) -> Self {
+        self.config.candidates = candidates;
+        self
+    }
+
+    /// Generate candidates concurrently, keep the one winning most pairwise
+    /// comparisons, and optionally refine it
+    async fn execute_best_of_n(&self, context: &AgentContext) -> AgentResult<Vec<AgentOutput>> {
+        let offsets = self.config.temperature_offsets();
+        let generated = join_all(
+            offsets
+                .iter()
+                .map(|offset| with_temperature_offset(*offset, self.generator.process(context))),
+        )
+        .await;
+
+        let mut candidates = Vec::new();
+        let mut first_error = None;
+        for (offset, result) in offsets.iter().zip(generated) {
+            match result {
+                Ok(output) => candidates.push((*offset, output)),
+                Err(e) => {
+                    tracing::warn!("Best-of-N candidate failed: {}", e);
+                    first_error = first_error.or(Some(e));
+                }
+            }
+        }
+        if candidates.is_empty() {
+            return match first_error {
+                Some(AgentError::CircuitOpen(msg)) => {
+                    tracing::warn!("Circuit breaker open in best-of-N: {}", msg);
+                    Ok(Vec::new())
+                }
+                Some(e) => Err(e),
+                None => Ok(Vec::new()),
+            };
+        }
+
+        let scores = self.judge(&candidates, context).await?;
+        let winner =
+            scores.iter().enumerate().fold(
+                0,
+                |best, (i, score)| if *score > scores[best] { i } else { best },
+            );
+        let records: Vec<CandidateRecord> = candidates
+            .iter()
+            .zip(&scores)
+            .enumerate()
+            .map(|(i, ((offset, output), score))| CandidateRecord {
+                text: output.result.clone(),
+                temperature_offset: *offset,
+                score: *score,
+                selected: i == winner,
+            })
+            .collect();
+        tracing::info!(
+            "Best-of-{} selected candidate {} (score {:.2})",
+            candidates.len(),
+            winner + 1,
+            scores[winner]
+        );
+
+        let (_, mut output) = candidates.swap_remove(winner);
+        let mut approved = true;
+        if self.config.refine_winner {
+            let (refined, refined_approved, iterations) =
+                self.refine(&output.result, context).await?;
+            if refined != output.result {
+                output.result = refined;
+                output
+                    .data
+                    .insert("refined".to_string(), serde_json::Value::Bool(true));
+            }
+            output.data.insert(
+                "reflection_iterations".to_string(),
+                serde_json::Value::Number(iterations.into()),
+            );
+            approved = refined_approved;
+        }
+
+        output.data.insert(
+            "reflection_approved".to_string(),
+            serde_json::Value::Bool(approved),
+        );
+        output.data.insert(
+            "candidates".to_string(),
+            serde_json::to_value(&records).unwrap_or_default(),
+        );
+        Ok(vec![output])
+    }
+
+    /// Round-robin pairwise judging; each candidate's share of wins
+    ///
+    /// `CriticAgent::compare` judges each pair in both orders, so only
+    /// consistent winners score a full win.
+    async fn judge(
+        &self,
+        candidates: &[(f64, AgentOutput)],
+        context: &AgentContext,
+    ) -> AgentResult<Vec<f32>> {
+        let n = candidates.len();
+        if n == 1 {
+            return Ok(vec![1.0]);
+        }
+
+        let pairs: Vec<(usize, usize)> = (0..n)
+            .flat_map(|a| (a + 1..n).map(move |b| (a, b)))
+            .collect();
+        let judgments = join_all(pairs.iter().map(|(a, b)| {
+            self.critic.compare(
+                &candidates[*a].1.result,
+                &candidates[*b].1.result,
+                &self.config.judge_rubric,
+                context,
+            )
+        }))
+        .await;
+
+        let mut wins = vec![0.0f32; n];
+        for ((a, b), judgment) in pairs.iter().zip(judgments) {
+            match judgment {
+                Ok(judgment) => match judgment.winner {
+                    PairwiseWinner::A => wins[*a] += 1.0,
+                    PairwiseWinner::B => wins[*b] += 1.0,
+                    PairwiseWinner::Tie => {
+                        wins[*a] += 0.5;
+                        wins[*b] += 0.5;
+                    }
+                },
+                Err(AgentError::CircuitOpen(msg)) => {
+                    // Unjudged pairs count as ties so judging degrades gracefully
+                    tracing::warn!("Circuit breaker open during judging: {}", msg);
+                    wins[*a] += 0.5;
+                    wins[*b] += 0.5;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(wins.into_iter().map(|w| w / (n - 1) as f32).collect())
+    }
+
+    /// Critique-improve loop on a fixed text; returns the final text, whether
+    /// it was approved and the iterations run
+    async fn refine(
+        &self,
+        text: &str,
+        context: &AgentContext,
+    ) -> AgentResult<(String, bool, usize)> {
+        let mut text = text.to_string();
+        for iteration in 0..self.config.max_iterations {
+            let feedback = match self.critic.critique(&text, context).await {
+                Ok(feedback) => feedback,
+                Err(AgentError::CircuitOpen(msg)) => {
+                    tracing::warn!("Circuit breaker open during refinement: {}", msg);
+                    return Ok((text, false, iteration));
+                }
+                Err(e) => return Err(e),
+            };
+            if feedback.approved {
+                return Ok((text, true, iteration + 1));
+            }
+            if iteration + 1 < self.config.max_iterations {
+                text = self
+                    .critic
+                    .suggest_improvement(&text, &feedback, context)
+                    .await?;
+            }
+        }
+        Ok((text, false, self.config.max_iterations))
+    }
 }
 
 #[async_trait]
@@ -75,6 +291,10 @@ impl Workflow for ReflectionWorkflow {
     }
 
     async fn execute(&self, context: &AgentContext) -> AgentResult<Vec<AgentOutput>> {
+        if self.config.candidates > 1 {
+            return self.execute_best_of_n(context).await;
+        }
+
         let mut outputs = Vec::new();
         let mut current_context = context.clone();
         let mut approved = false;
@@ -247,5 +467,22 @@ mod tests {
         let config = ReflectionConfig::default();
         assert_eq!(config.max_iterations, 3);
         assert!(!config.include_history);
+        assert_eq!(config.candidates, 1);
+    }
+
+    #[test]
+    fn test_temperature_offsets_span_the_spread() {
+        let config = ReflectionConfig {
+            candidates: 3,
+            temperature_spread: 0.4,
+            ..Default::default()
+        };
+        let offsets = config.temperature_offsets();
+        assert_eq!(offsets.len(), 3);
+        assert!((offsets[0] + 0.2).abs() < 1e-9);
+        assert!(offsets[1].abs() < 1e-9);
+        assert!((offsets[2] - 0.2).abs() < 1e-9);
+
+        assert_eq!(ReflectionConfig::default().temperature_offsets(), vec![0.0]);
     }
 }
@@ -61,16 +61,20 @@ impl Workflow for SequentialWorkflow {
                 }
             }
 
-            // Check for stop conditions
-            let should_stop = match &output.next_action {
-                Some(NextAction::Stop) => true,
-                Some(NextAction::PuzzleSolved) if self.stop_on_solved => true,
-                _ => false,
-            };
+            // Check for stop conditions; a transfer hands the rest of the
+            // pipeline over to the target agent
+            let should_stop = output.transfer_target().is_some()
+                || match &output.next_action {
+                    Some(NextAction::Stop) => true,
+                    Some(NextAction::PuzzleSolved) if self.stop_on_solved => true,
+                    _ => false,
+                };
 
             outputs.push(output);
 
-            if should_stop {
+            if should_stop
+                || super::run_user_agents_after(agent.name(), &current_context, &mut outputs).await
+            {
                 break;
             }
         }
@@ -80,7 +84,8 @@ impl Workflow for SequentialWorkflow {
 }
 
 /// Helper to create a standard clue-validation pipeline
-/// Note: stop_on_solved is false so Narrator always runs (for success/failure dialogue)
+/// Note: stop_on_solved is false so Narrator always runs (for success/failure dialogue);
+/// on a solve the Verifier transfers to it instead
 pub fn create_puzzle_pipeline(
     observer: Arc<dyn Agent>,
     verifier: Arc<dyn Agent>,
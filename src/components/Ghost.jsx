@@ -238,6 +238,8 @@ const Ghost = () => {
 		isOpen: false,
 		includeContext: true,
 	});
+	// Quick asks share one conversation until the response is cleared
+	const quickAskConversationRef = useRef(null);
 	const [recentTimeline, setRecentTimeline] = useState([]);
 	const [showHistory, setShowHistory] = useState(false);
 	const [recentEvents, setRecentEvents] = useState([]);
@@ -752,10 +754,24 @@ const Ghost = () => {
 			event.preventDefault();
 			if (!quickAsk.prompt.trim()) return;
 			setQuickAsk((prev) => ({ ...prev, isLoading: true, error: "" }));
+			// Render the answer as it streams in
+			const requestId = `quick-ask-${Date.now()}`;
+			let streamed = "";
+			const unlisten = await listen("ai_stream_delta", ({ payload }) => {
+				if (payload?.request_id !== requestId || !payload.delta) return;
+				streamed += payload.delta;
+				setQuickAsk((prev) => ({ ...prev, response: streamed }));
+			});
 			try {
+				if (!quickAskConversationRef.current) {
+					const conversation = await invoke("create_conversation");
+					quickAskConversationRef.current = conversation.id;
+				}
 				const response = await invoke("quick_ask", {
 					prompt: quickAsk.prompt.trim(),
 					includeContext: quickAsk.includeContext,
+					requestId,
+					conversationId: quickAskConversationRef.current,
 				});
 				setQuickAsk({
 					prompt: "",
@@ -775,6 +791,8 @@ const Ghost = () => {
 					isLoading: false,
 					error: message,
 				}));
+			} finally {
+				unlisten();
 			}
 		},
 		[quickAsk.prompt, quickAsk.includeContext],
@@ -1222,7 +1240,8 @@ const Ghost = () => {
 							<button
 								type="button"
 								className="mini-btn subtle"
-								onClick={() =>
+								onClick={() => {
+									quickAskConversationRef.current = null;
 									setQuickAsk({
 										prompt: "",
 										response: "",
@@ -1230,8 +1249,8 @@ const Ghost = () => {
 										isLoading: false,
 										isOpen: false,
 										includeContext: true,
-									})
-								}
+									});
+								}}
 							>
 								Clear response
 							</button>
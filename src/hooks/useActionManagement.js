@@ -57,7 +57,8 @@ import { safeInvoke } from "../utils/data";
  * @typedef {Object} TokenUsage
  * @property {number} gemini_calls - Gemini API call count
  * @property {number} ollama_calls - Ollama call count
- * @property {number} estimated_cost_usd - Estimated cost in USD
+ * @property {number} estimated_cost_usd - Spend today in USD (estimated until usage is recorded)
+ * @property {Array<{provider: string, today: Object, month: Object, state: string}>} providers - Per-provider usage and budget state
  */
 
 /** Default rollback status */
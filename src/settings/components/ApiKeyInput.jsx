@@ -7,6 +7,7 @@
 import React, { useState, useCallback, useRef, useEffect } from "react";
 import PropTypes from "prop-types";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { safeInvoke } from "../../utils/data";
 
 /** Default Ollama status when unavailable */
@@ -26,8 +27,20 @@ const INITIAL_OLLAMA_STATE = Object.freeze({
 	error: "",
 	success: "",
 	saving: false,
+	pulling: null,
+	pullProgress: "",
 });
 
+/**
+ * Formats an `ollama_pull_progress` event for display.
+ * @param {Object} progress - Pull progress payload
+ * @returns {string} Status with percentage when known
+ */
+const formatPullProgress = ({ status, total, completed }) =>
+	total && completed
+		? `${status} ${Math.round((completed / total) * 100)}%`
+		: status;
+
 /**
  * Validates a URL string format.
  * @param {string} url - URL to validate
@@ -286,6 +299,39 @@ const ApiKeyInput = ({ onKeySet, apiKeySource = "none" }) => {
 		}
 	}, [updateOllamaMultiple]);
 
+	/**
+	 * Download a missing Ollama model, showing streamed progress.
+	 * @param {string} model - Model name to pull
+	 */
+	const handlePullModel = useCallback(
+		async (model) => {
+			updateOllamaMultiple({ pulling: model, pullProgress: "", error: "" });
+			const unlisten = await listen("ollama_pull_progress", (event) => {
+				if (isMountedRef.current && event.payload?.model === model) {
+					updateOllama("pullProgress", formatPullProgress(event.payload));
+				}
+			});
+
+			try {
+				await invoke("pull_ollama_model", { model });
+				await checkOllamaStatus();
+				if (isMountedRef.current) {
+					updateOllama("success", `Downloaded ${model}`);
+				}
+			} catch (err) {
+				if (isMountedRef.current) {
+					updateOllama("error", `Failed to download ${model}: ${err}`);
+				}
+			} finally {
+				unlisten();
+				if (isMountedRef.current) {
+					updateOllamaMultiple({ pulling: null, pullProgress: "" });
+				}
+			}
+		},
+		[checkOllamaStatus, updateOllama, updateOllamaMultiple]
+	);
+
 	/**
 	 * Handle Gemini API key input change.
 	 */
@@ -490,6 +536,28 @@ const ApiKeyInput = ({ onKeySet, apiKeySource = "none" }) => {
 						</div>
 					)}
 
+					{/* Configured models that Ollama does not have yet */}
+					{ollama.status?.missing_models?.length > 0 && (
+						<div className="ollama-missing-models" role="status">
+							{ollama.status.missing_models.map((model) => (
+								<div key={model} className="ollama-missing-model">
+									<span>⚠️ {model} is not installed</span>
+									<button
+										type="button"
+										className="ollama-pull-btn"
+										onClick={() => handlePullModel(model)}
+										onMouseDown={stopPropagation}
+										disabled={ollama.pulling !== null}
+									>
+										{ollama.pulling === model
+											? ollama.pullProgress || "Starting…"
+											: "Download"}
+									</button>
+								</div>
+							))}
+						</div>
+					)}
+
 					<div className="ollama-field">
 						<label htmlFor="ollama-url">Server URL</label>
 						<input